@@ -1,25 +1,52 @@
 use crate::{
+    config::{Hooks, Scene},
+    hooks::{HookEvent, HookRunner},
     icons::Icons,
+    ipc::{ShowTarget, DEFAULT_SINK_TARGET, DEFAULT_SOURCE_TARGET},
     menu::{
-        DeviceMenuOptions, InputDeviceMenuOptions, MainMenuOptions, Menu, OutputDeviceMenuOptions,
-        ProfileMenuOptions, SampleRateMenuOptions, SettingsMenuOptions, StreamMenuOptions,
-        VolumeMenuOptions,
+        format_latency, parse_object_id, port_mapping_to_str, AllDevicesMenuOptions,
+        ConnectionsMenuOptions, DeviceMenuOptions, DeviceQuickAction, InputDeviceMenuOptions,
+        LatencyMenuOptions, MainMenuOptions, ManagedObjectMenuOptions, ManagedObjectsMenuOptions,
+        Menu, OutputDeviceMenuOptions, ProfileMenuOptions, SampleRateMenuOptions,
+        SettingsMenuOptions, StreamMenuOptions, StreamPropertiesMenuOptions, VolumeMenuOptions,
+    },
+    notification::{NotificationManager, NotificationUrgency, OsdBackend},
+    pw::{
+        controller::Controller,
+        engine::DEFAULT_COMMAND_TIMEOUT,
+        nodes::{Node, NodeType},
+        PortDirection, PortMapping, Profile, PwError, Volume, VolumeScale,
     },
-    notification::NotificationManager,
-    pw::{controller::Controller, nodes::Node, Profile},
 };
 use anyhow::Result;
-use log::{debug, info};
 use rust_i18n::t;
-use std::sync::Arc;
-use tokio::time::{sleep, Duration};
+use std::{collections::BTreeMap, sync::Arc};
+use tokio::time::{sleep, Duration, Instant};
+use tracing::{debug, info, warn};
+
+const VOLUME_RAMP_INTERVAL: Duration = Duration::from_millis(30);
+const MIC_TEST_DURATION: Duration = Duration::from_secs(5);
+const LEVEL_REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+// Most starts finish before anyone would notice, so only report the slow ones
+const INIT_NOTICE_DELAY: Duration = Duration::from_millis(300);
 
 pub struct App {
     pub running: bool,
     pub interactive: bool,
-    controller: Controller,
+    controller: Arc<Controller>,
     notification_manager: Arc<NotificationManager>,
     volume_step: f32,
+    move_streams: bool,
+    allow_card_off: bool,
+    show_unavailable_profiles: bool,
+    scenes: BTreeMap<String, Scene>,
+    hooks: HookRunner,
+    notify_changes: bool,
+    repeat_count: Option<u8>,
+    volume_scale: VolumeScale,
+    max_input_volume: f32,
+    notify_mute: bool,
+    mute_at_zero: bool,
 }
 
 impl App {
@@ -28,27 +55,202 @@ impl App {
         icons: Arc<Icons>,
         volume_step: f32,
         interactive: bool,
+        remote: Option<String>,
     ) -> Result<Self> {
-        let controller = Controller::new().await?;
-        let notification_manager = Arc::new(NotificationManager::new(icons.clone()));
+        let controller = Arc::new(Controller::connect(remote, DEFAULT_COMMAND_TIMEOUT).await?);
 
         info!("{}", t!("notifications.pw.initialized"));
 
-        Ok(Self {
+        Ok(Self::with_controller(
+            controller,
+            icons,
+            volume_step,
+            interactive,
+        ))
+    }
+
+    /// Builds an app on top of an existing PipeWire connection, as the daemon does.
+    pub fn with_controller(
+        controller: Arc<Controller>,
+        icons: Arc<Icons>,
+        volume_step: f32,
+        interactive: bool,
+    ) -> Self {
+        let notification_manager = Arc::new(NotificationManager::new(icons));
+
+        Self {
             running: true,
             interactive,
             controller,
             notification_manager,
             volume_step,
-        })
+            move_streams: false,
+            allow_card_off: false,
+            show_unavailable_profiles: false,
+            scenes: BTreeMap::new(),
+            hooks: HookRunner::default(),
+            notify_changes: true,
+            repeat_count: None,
+            volume_scale: VolumeScale::default(),
+            max_input_volume: 1.0,
+            notify_mute: true,
+            mute_at_zero: false,
+        }
+    }
+
+    pub fn with_move_streams(mut self, move_streams: bool) -> Self {
+        self.move_streams = move_streams;
+        self
+    }
+
+    /// Offers turning cards off from the profile menu.
+    pub fn with_allow_card_off(mut self, allow_card_off: bool) -> Self {
+        self.allow_card_off = allow_card_off;
+        self
+    }
+
+    /// Lists unavailable profiles in profile menus instead of hiding them.
+    pub fn with_show_unavailable_profiles(mut self, show_unavailable_profiles: bool) -> Self {
+        self.show_unavailable_profiles = show_unavailable_profiles;
+        self
+    }
+
+    pub fn with_scenes(mut self, scenes: BTreeMap<String, Scene>) -> Self {
+        self.scenes = scenes;
+        self
+    }
+
+    pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = HookRunner::new(hooks);
+        self
+    }
+
+    pub fn with_osd_backend(mut self, osd_backend: OsdBackend) -> Self {
+        self.notification_manager = Arc::new(
+            NotificationManager::clone(&self.notification_manager).with_osd_backend(osd_backend),
+        );
+        self
+    }
+
+    pub fn with_volume_scale(mut self, volume_scale: VolumeScale) -> Self {
+        self.volume_scale = volume_scale;
+        self
+    }
+
+    /// Lets input volumes go past 100%, up to `percent`.
+    pub fn with_max_input_volume(mut self, percent: u8) -> Self {
+        self.max_input_volume = f32::from(percent) / 100.0;
+        self
+    }
+
+    /// Mutes devices lowered to zero and brings back their volume when raised again.
+    pub fn with_mute_at_zero(mut self, mute_at_zero: bool) -> Self {
+        self.mute_at_zero = mute_at_zero;
+        self
+    }
+
+    /// Adds a volume menu entry applying the last step `count` times at once.
+    pub fn with_repeat(mut self, count: Option<u8>) -> Self {
+        self.repeat_count = count;
+        self
+    }
+
+    /// Disables volume, mute and default device notifications, for when the daemon sends them.
+    pub fn with_change_notifications(mut self, notify_changes: bool) -> Self {
+        self.notify_changes = notify_changes;
+        self
+    }
+
+    /// Turns mute notifications off, or sets the urgency they are sent with.
+    pub fn with_mute_notifications(
+        mut self,
+        notify_mute: bool,
+        urgency: Option<NotificationUrgency>,
+    ) -> Self {
+        self.notify_mute = notify_mute;
+        self.notification_manager = Arc::new(
+            NotificationManager::clone(&self.notification_manager).with_mute_urgency(urgency),
+        );
+        self
     }
 
     pub fn quit(&mut self) {
         self.running = false;
     }
 
+    /// Waits for the graph like [`Controller::wait_for_initialization`], showing a notification
+    /// while connecting and reading device settings take longer than [`INIT_NOTICE_DELAY`].
     pub async fn wait_for_initialization(&self) -> Result<()> {
-        self.controller.wait_for_initialization().await
+        let initialization = self.controller.wait_for_initialization();
+        tokio::pin!(initialization);
+        let delay = sleep(INIT_NOTICE_DELAY);
+        tokio::pin!(delay);
+
+        let mut graph_rx = self.controller.subscribe();
+        // Whether the notice shown says the registry is listed, `None` before it is shown
+        let mut notice: Option<bool> = None;
+
+        let result = loop {
+            tokio::select! {
+                biased;
+                result = &mut initialization => break result,
+                _ = &mut delay, if notice.is_none() => {
+                    let connected = graph_rx.borrow().is_connected();
+                    self.show_init_progress(connected);
+                    notice = Some(connected);
+                }
+                changed = graph_rx.changed(), if notice == Some(false) => {
+                    if changed.is_ok() && graph_rx.borrow().is_connected() {
+                        self.show_init_progress(true);
+                        notice = Some(true);
+                    }
+                }
+            }
+        };
+
+        if notice.is_some() {
+            if let Err(e) = self.notification_manager.close_progress_notification() {
+                debug!("Failed to close the initialization notification: {e}");
+            }
+        }
+
+        result
+    }
+
+    fn show_init_progress(&self, connected: bool) {
+        let body = if connected {
+            t!("notifications.pw.reading_devices")
+        } else {
+            t!("notifications.pw.connecting")
+        };
+
+        if let Err(e) = self.notification_manager.send_progress_notification(&body) {
+            warn!("Notification failed: {e}");
+        }
+    }
+
+    pub async fn shutdown(&self) -> Result<()> {
+        self.notification_manager.flush_volume_notification();
+        self.controller.shutdown().await
+    }
+
+    /// See [`Controller::save_volumes`].
+    pub fn save_volumes(&self) -> Result<bool> {
+        self.controller.save_volumes()
+    }
+
+    pub async fn cleanup_managed_objects(&self) -> Result<usize> {
+        self.controller.wait_for_initialization().await?;
+        let count = self.controller.remove_all_managed_objects().await?;
+
+        if count > 0 {
+            info!(
+                "{}",
+                t!("notifications.pw.managed_objects_cleaned", count = count)
+            );
+        }
+
+        Ok(count)
     }
 
     pub async fn run(
@@ -59,7 +261,18 @@ impl App {
         spaces: usize,
     ) -> Result<Option<String>> {
         while self.running {
-            match menu.show_main_menu(menu_command, icon_type, spaces).await? {
+            let connection = self.controller.connection_status();
+            let default_output_muted = self.default_output().map(|node| node.volume.muted);
+            match menu
+                .show_main_menu(
+                    menu_command,
+                    icon_type,
+                    spaces,
+                    connection,
+                    default_output_muted,
+                )
+                .await?
+            {
                 Some(main_menu_option) => {
                     self.handle_main_options(
                         menu,
@@ -80,6 +293,39 @@ impl App {
         Ok(None)
     }
 
+    pub async fn run_show(
+        &mut self,
+        menu: &Menu,
+        menu_command: &Option<String>,
+        icon_type: &str,
+        spaces: usize,
+        target: &ShowTarget,
+    ) -> Result<Option<String>> {
+        match target {
+            ShowTarget::Device(key) => {
+                let node = resolve_target_node(&self.controller, key)?;
+                let is_output = self
+                    .controller
+                    .get_output_nodes()
+                    .iter()
+                    .any(|n| n.id == node.id);
+                self.handle_device_menu(menu, menu_command, &node, icon_type, spaces, is_output)
+                    .await?;
+            }
+            ShowTarget::Volume(key) => {
+                let node = resolve_target_node(&self.controller, key)?;
+                let is_output = self
+                    .controller
+                    .get_output_nodes()
+                    .iter()
+                    .any(|n| n.id == node.id);
+                self.handle_volume_menu(menu, menu_command, &node, icon_type, spaces, is_output)
+                    .await?;
+            }
+        }
+        Ok(None)
+    }
+
     pub async fn run_output_device_menu(
         &mut self,
         menu: &Menu,
@@ -104,6 +350,18 @@ impl App {
         Ok(None)
     }
 
+    pub async fn run_all_devices_menu(
+        &mut self,
+        menu: &Menu,
+        menu_command: &Option<String>,
+        icon_type: &str,
+        spaces: usize,
+    ) -> Result<Option<String>> {
+        self.handle_all_devices_menu(menu, menu_command, icon_type, spaces)
+            .await?;
+        Ok(None)
+    }
+
     pub async fn run_output_streams_menu(
         &mut self,
         menu: &Menu,
@@ -136,6 +394,15 @@ impl App {
         spaces: usize,
         main_menu_option: MainMenuOptions,
     ) -> Result<Option<String>> {
+        // Only devices are cached, everything else needs the live graph
+        let lists_devices = matches!(
+            main_menu_option,
+            MainMenuOptions::ShowOutputDeviceMenu | MainMenuOptions::ShowInputDeviceMenu
+        );
+        if !lists_devices && self.controller.is_showing_cache() {
+            self.wait_for_initialization().await?;
+        }
+
         match main_menu_option {
             MainMenuOptions::ShowOutputDeviceMenu => {
                 self.handle_output_device_menu(menu, menu_command, icon_type, spaces)
@@ -145,14 +412,38 @@ impl App {
                 self.handle_input_device_menu(menu, menu_command, icon_type, spaces)
                     .await?;
             }
-            MainMenuOptions::ShowOutputStreamsMenu => {
+            MainMenuOptions::ShowPlaybackMenu => {
                 self.handle_output_streams_menu(menu, menu_command, icon_type, spaces)
                     .await?;
             }
-            MainMenuOptions::ShowInputStreamsMenu => {
+            MainMenuOptions::ShowRecordingMenu => {
                 self.handle_input_streams_menu(menu, menu_command, icon_type, spaces)
                     .await?;
             }
+            MainMenuOptions::RaiseDefaultOutputVolume => {
+                if let Some(node) = self.default_output() {
+                    self.perform_volume_change(&node, self.volume_step).await?;
+                }
+            }
+            MainMenuOptions::LowerDefaultOutputVolume => {
+                if let Some(node) = self.default_output() {
+                    self.perform_volume_change(&node, -self.volume_step).await?;
+                }
+            }
+            MainMenuOptions::MuteDefaultOutput => {
+                if let Some(node) = self.default_output() {
+                    self.perform_mute_toggle(&node, true).await?;
+                }
+            }
+            MainMenuOptions::UnmuteDefaultOutput => {
+                if let Some(node) = self.default_output() {
+                    self.perform_mute_toggle(&node, false).await?;
+                }
+            }
+            MainMenuOptions::ShowScenesMenu => {
+                self.handle_scenes_menu(menu, menu_command, icon_type, spaces)
+                    .await?;
+            }
             MainMenuOptions::ShowSettingsMenu => {
                 self.handle_settings_menu(menu, menu_command, icon_type, spaces)
                     .await?;
@@ -161,6 +452,99 @@ impl App {
         Ok(None)
     }
 
+    fn default_output(&self) -> Option<Node> {
+        self.controller
+            .get_default_sink()
+            .and_then(|id| self.controller.get_node(id))
+    }
+
+    async fn handle_scenes_menu(
+        &mut self,
+        menu: &Menu,
+        menu_command: &Option<String>,
+        icon_type: &str,
+        spaces: usize,
+    ) -> Result<()> {
+        let mut stay_in_scenes_menu = true;
+
+        while stay_in_scenes_menu {
+            let should_stay = self
+                .handle_scenes_options(menu, menu_command, icon_type, spaces)
+                .await?;
+
+            if !should_stay {
+                stay_in_scenes_menu = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle_scenes_options(
+        &mut self,
+        menu: &Menu,
+        menu_command: &Option<String>,
+        icon_type: &str,
+        spaces: usize,
+    ) -> Result<bool> {
+        let scene_names: Vec<String> = self.scenes.keys().cloned().collect();
+        let devices: Vec<(u32, String)> = self
+            .controller
+            .get_devices_with_profiles()
+            .into_iter()
+            .map(|(id, name)| {
+                if self.controller.is_device_off(id) {
+                    let name = t!("menus.profile.card_off", device_name = name);
+                    (id, name.to_string())
+                } else {
+                    (id, name)
+                }
+            })
+            .collect();
+
+        let option = menu
+            .show_scenes_menu(
+                menu_command,
+                icon_type,
+                spaces,
+                &scene_names,
+                &devices,
+                self.interactive,
+            )
+            .await?;
+
+        match option {
+            Some(ScenesMenuOptions::Scene(name)) => {
+                self.perform_apply_scene(&name).await?;
+                if !self.interactive {
+                    self.running = false;
+                    return Ok(false);
+                }
+                Ok(true)
+            }
+            Some(ScenesMenuOptions::Device(device_id)) => {
+                self.handle_profile_menu(menu, menu_command, device_id, icon_type, spaces)
+                    .await?;
+                if !self.running {
+                    return Ok(false);
+                }
+                if !self.interactive {
+                    self.running = false;
+                    return Ok(false);
+                }
+                Ok(true)
+            }
+            Some(ScenesMenuOptions::Back) => Ok(false),
+            None => {
+                if !self.interactive {
+                    self.running = false;
+                }
+                debug!("Exited scenes menu");
+                Ok(false)
+            }
+        }
+    }
+
     async fn handle_settings_menu(
         &mut self,
         menu: &Menu,
@@ -204,6 +588,15 @@ impl App {
                 }
                 Ok(true)
             }
+            Some(SettingsMenuOptions::ManageObjects) => {
+                self.handle_managed_objects_menu(menu, menu_command, icon_type, spaces)
+                    .await?;
+                if !self.interactive {
+                    self.running = false;
+                    return Ok(false);
+                }
+                Ok(true)
+            }
             Some(SettingsMenuOptions::Back) => Ok(false),
             None => {
                 if !self.interactive {
@@ -276,72 +669,43 @@ impl App {
         }
     }
 
-    async fn handle_output_streams_menu(
-        &mut self,
-        menu: &Menu,
-        menu_command: &Option<String>,
-        icon_type: &str,
-        spaces: usize,
-    ) -> Result<()> {
-        let mut stay_in_streams_menu = true;
-
-        while stay_in_streams_menu {
-            let should_stay = self
-                .handle_stream_options(menu, menu_command, icon_type, spaces, true)
-                .await?;
-
-            if !should_stay {
-                stay_in_streams_menu = false;
-            }
-        }
-
-        Ok(())
-    }
-
-    async fn handle_input_streams_menu(
+    async fn handle_managed_objects_menu(
         &mut self,
         menu: &Menu,
         menu_command: &Option<String>,
         icon_type: &str,
         spaces: usize,
     ) -> Result<()> {
-        let mut stay_in_streams_menu = true;
+        let mut stay_in_managed_objects_menu = true;
 
-        while stay_in_streams_menu {
+        while stay_in_managed_objects_menu {
             let should_stay = self
-                .handle_stream_options(menu, menu_command, icon_type, spaces, false)
+                .handle_managed_objects_options(menu, menu_command, icon_type, spaces)
                 .await?;
 
             if !should_stay {
-                stay_in_streams_menu = false;
+                stay_in_managed_objects_menu = false;
             }
         }
 
         Ok(())
     }
 
-    async fn handle_stream_options(
+    async fn handle_managed_objects_options(
         &mut self,
         menu: &Menu,
         menu_command: &Option<String>,
         icon_type: &str,
         spaces: usize,
-        is_output: bool,
     ) -> Result<bool> {
-        let streams = if is_output {
-            self.controller.get_output_streams()
-        } else {
-            self.controller.get_input_streams()
-        };
-
+        let nodes = self.controller.get_managed_nodes();
         let menu_result = menu
-            .show_stream_menu(
+            .show_managed_objects_menu(
                 menu_command,
-                &streams,
+                &nodes,
                 &self.controller,
                 icon_type,
                 spaces,
-                is_output,
                 self.interactive,
             )
             .await?;
@@ -352,56 +716,213 @@ impl App {
                     return Ok(false);
                 }
 
-                let refresh_text = StreamMenuOptions::RefreshList.to_str();
-                if selection == refresh_text.as_ref() {
-                    Ok(true)
-                } else {
-                    if let Some(stream) = self.find_stream_by_name(&streams, &selection, menu) {
-                        self.handle_volume_menu(
-                            menu,
-                            menu_command,
-                            &stream,
-                            icon_type,
-                            spaces,
-                            is_output,
-                        )
-                        .await?;
-                        if !self.running {
-                            return Ok(false);
+                match ManagedObjectsMenuOptions::from_string(&selection) {
+                    Some(ManagedObjectsMenuOptions::CreateVirtualSink) => {
+                        let hint = t!("menus.managed_objects.name_hint");
+                        if let Some(name) =
+                            menu.prompt_for_name(menu_command, icon_type, &hint).await?
+                        {
+                            self.perform_create_virtual_sink(&name).await?;
                         }
-                        if !self.interactive {
-                            self.running = false;
-                            return Ok(false);
+                    }
+                    Some(ManagedObjectsMenuOptions::Object(_)) => {
+                        let selected_node = self
+                            .handle_device_selection(&nodes, &selection, menu, icon_type, spaces)?;
+                        if let Some(node) = selected_node {
+                            self.handle_managed_object_options(
+                                menu,
+                                menu_command,
+                                &node,
+                                icon_type,
+                                spaces,
+                            )
+                            .await?;
                         }
                     }
-                    Ok(true)
+                    None => {}
+                }
+
+                if !self.interactive {
+                    self.running = false;
+                    return Ok(false);
                 }
+                Ok(true)
             }
             None => {
                 if !self.interactive {
                     self.running = false;
                 }
-                let message = if is_output {
-                    t!("notifications.pw.output_streams_menu_exited")
-                } else {
-                    t!("notifications.pw.input_streams_menu_exited")
-                };
-                debug!("{message}");
+                debug!("Exited managed objects menu");
                 Ok(false)
             }
         }
     }
 
-    fn find_stream_by_name(&self, streams: &[Node], selection: &str, menu: &Menu) -> Option<Node> {
-        let base_selection = if let Some(pos) = selection.find(" [") {
-            &selection[..pos]
-        } else {
-            selection
-        };
-
-        for stream in streams {
-            let display_name = menu.format_stream_display_name(stream, &self.controller);
-            if display_name == base_selection {
+    async fn handle_managed_object_options(
+        &mut self,
+        menu: &Menu,
+        menu_command: &Option<String>,
+        node: &Node,
+        icon_type: &str,
+        spaces: usize,
+    ) -> Result<()> {
+        let display_name = self.controller.get_node_base_name(node);
+        let option = menu
+            .show_managed_object_menu(
+                menu_command,
+                icon_type,
+                spaces,
+                &display_name,
+                self.interactive,
+            )
+            .await?;
+
+        match option {
+            Some(ManagedObjectMenuOptions::Rename) => {
+                let hint = t!("menus.managed_objects.name_hint");
+                if let Some(name) = menu.prompt_for_name(menu_command, icon_type, &hint).await? {
+                    self.perform_rename_managed_object(node, &name).await?;
+                }
+            }
+            Some(ManagedObjectMenuOptions::Remove) => {
+                self.perform_remove_managed_object(node).await?;
+            }
+            Some(ManagedObjectMenuOptions::Back) | None => {}
+        }
+
+        Ok(())
+    }
+
+    async fn handle_output_streams_menu(
+        &mut self,
+        menu: &Menu,
+        menu_command: &Option<String>,
+        icon_type: &str,
+        spaces: usize,
+    ) -> Result<()> {
+        let mut stay_in_streams_menu = true;
+
+        while stay_in_streams_menu {
+            let should_stay = self
+                .handle_stream_options(menu, menu_command, icon_type, spaces, true)
+                .await?;
+
+            if !should_stay {
+                stay_in_streams_menu = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle_input_streams_menu(
+        &mut self,
+        menu: &Menu,
+        menu_command: &Option<String>,
+        icon_type: &str,
+        spaces: usize,
+    ) -> Result<()> {
+        let mut stay_in_streams_menu = true;
+
+        while stay_in_streams_menu {
+            let should_stay = self
+                .handle_stream_options(menu, menu_command, icon_type, spaces, false)
+                .await?;
+
+            if !should_stay {
+                stay_in_streams_menu = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle_stream_options(
+        &mut self,
+        menu: &Menu,
+        menu_command: &Option<String>,
+        icon_type: &str,
+        spaces: usize,
+        is_output: bool,
+    ) -> Result<bool> {
+        let streams = if is_output {
+            self.controller.get_output_streams()
+        } else {
+            self.controller.get_input_streams()
+        };
+
+        let menu_result = menu
+            .show_stream_menu(
+                menu_command,
+                &streams,
+                &self.controller,
+                icon_type,
+                spaces,
+                is_output,
+                self.interactive,
+            )
+            .await?;
+
+        match menu_result {
+            Some(selection) => {
+                if selection == t!("menus.common.back").as_ref() {
+                    return Ok(false);
+                }
+
+                let refresh_text = StreamMenuOptions::RefreshList.to_str();
+                if selection == refresh_text.as_ref() {
+                    Ok(true)
+                } else {
+                    if let Some(stream) = self.find_stream_by_name(&streams, &selection, menu) {
+                        self.handle_volume_menu(
+                            menu,
+                            menu_command,
+                            &stream,
+                            icon_type,
+                            spaces,
+                            is_output,
+                        )
+                        .await?;
+                        if !self.running {
+                            return Ok(false);
+                        }
+                        if !self.interactive {
+                            self.running = false;
+                            return Ok(false);
+                        }
+                    }
+                    Ok(true)
+                }
+            }
+            None => {
+                if !self.interactive {
+                    self.running = false;
+                }
+                let message = if is_output {
+                    t!("notifications.pw.output_streams_menu_exited")
+                } else {
+                    t!("notifications.pw.input_streams_menu_exited")
+                };
+                debug!("{message}");
+                Ok(false)
+            }
+        }
+    }
+
+    fn find_stream_by_name(&self, streams: &[Node], selection: &str, menu: &Menu) -> Option<Node> {
+        if let Some(id) = parse_object_id(selection).filter(|_| menu.show_ids) {
+            return streams.iter().find(|stream| stream.id == id).cloned();
+        }
+
+        let base_selection = if let Some(pos) = selection.find(" [") {
+            &selection[..pos]
+        } else {
+            selection
+        };
+
+        for stream in streams {
+            let display_name = menu.format_stream_display_name(stream, &self.controller);
+            if display_name == base_selection {
                 return Some(stream.clone());
             }
         }
@@ -437,6 +958,7 @@ impl App {
         icon_type: &str,
         spaces: usize,
     ) -> Result<bool> {
+        let from_cache = self.controller.is_showing_cache();
         let nodes = self.controller.get_output_nodes();
         let menu_result = menu
             .show_output_device_menu(
@@ -450,7 +972,7 @@ impl App {
             .await?;
 
         match menu_result {
-            Some(selection) => {
+            Some((selection, quick_action)) => {
                 if selection == t!("menus.common.back").as_ref() {
                     return Ok(false);
                 }
@@ -461,7 +983,12 @@ impl App {
                 } else {
                     let selected_node =
                         self.handle_device_selection(&nodes, &selection, menu, icon_type, spaces)?;
+                    let selected_node = self.live_node(selected_node, from_cache).await?;
+                    if let (Some(node), Some(action)) = (&selected_node, quick_action) {
+                        return self.handle_quick_action(node, action, true).await;
+                    }
                     if let Some(node) = selected_node {
+                        self.controller.record_node_selection(&node).await;
                         self.handle_device_menu(menu, menu_command, &node, icon_type, spaces, true)
                             .await?;
                         if !self.running {
@@ -510,6 +1037,7 @@ impl App {
         icon_type: &str,
         spaces: usize,
     ) -> Result<bool> {
+        let from_cache = self.controller.is_showing_cache();
         let nodes = self.controller.get_input_nodes();
         let menu_result = menu
             .show_input_device_menu(
@@ -523,7 +1051,7 @@ impl App {
             .await?;
 
         match menu_result {
-            Some(selection) => {
+            Some((selection, quick_action)) => {
                 if selection == t!("menus.common.back").as_ref() {
                     return Ok(false);
                 }
@@ -534,7 +1062,12 @@ impl App {
                 } else {
                     let selected_node =
                         self.handle_device_selection(&nodes, &selection, menu, icon_type, spaces)?;
+                    let selected_node = self.live_node(selected_node, from_cache).await?;
+                    if let (Some(node), Some(action)) = (&selected_node, quick_action) {
+                        return self.handle_quick_action(node, action, false).await;
+                    }
                     if let Some(node) = selected_node {
+                        self.controller.record_node_selection(&node).await;
                         self.handle_device_menu(
                             menu,
                             menu_command,
@@ -561,6 +1094,140 @@ impl App {
         }
     }
 
+    async fn handle_all_devices_menu(
+        &mut self,
+        menu: &Menu,
+        menu_command: &Option<String>,
+        icon_type: &str,
+        spaces: usize,
+    ) -> Result<()> {
+        let mut stay_in_all_devices_menu = true;
+
+        while stay_in_all_devices_menu {
+            let should_stay = self
+                .handle_all_devices_options(menu, menu_command, icon_type, spaces)
+                .await?;
+
+            if !should_stay {
+                stay_in_all_devices_menu = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle_all_devices_options(
+        &mut self,
+        menu: &Menu,
+        menu_command: &Option<String>,
+        icon_type: &str,
+        spaces: usize,
+    ) -> Result<bool> {
+        let from_cache = self.controller.is_showing_cache();
+        let output_nodes = self.controller.get_output_nodes();
+        let input_nodes = self.controller.get_input_nodes();
+        let menu_result = menu
+            .show_all_devices_menu(
+                menu_command,
+                &output_nodes,
+                &input_nodes,
+                &self.controller,
+                icon_type,
+                spaces,
+                self.interactive,
+            )
+            .await?;
+
+        match menu_result {
+            Some((selection, quick_action)) => {
+                if selection == t!("menus.common.back").as_ref() {
+                    return Ok(false);
+                }
+
+                match AllDevicesMenuOptions::from_string(&selection) {
+                    Some(AllDevicesMenuOptions::Device(_)) => {
+                        let output_match = self.handle_device_selection(
+                            &output_nodes,
+                            &selection,
+                            menu,
+                            icon_type,
+                            spaces,
+                        )?;
+                        let selected = match output_match {
+                            Some(node) => Some((node, true)),
+                            None => self
+                                .handle_device_selection(
+                                    &input_nodes,
+                                    &selection,
+                                    menu,
+                                    icon_type,
+                                    spaces,
+                                )?
+                                .map(|node| (node, false)),
+                        };
+                        let selected = match selected {
+                            Some((node, is_output)) => self
+                                .live_node(Some(node), from_cache)
+                                .await?
+                                .map(|node| (node, is_output)),
+                            None => None,
+                        };
+
+                        if let (Some((node, is_output)), Some(action)) = (&selected, quick_action) {
+                            return self.handle_quick_action(node, action, *is_output).await;
+                        }
+                        if let Some((node, is_output)) = selected {
+                            self.controller.record_node_selection(&node).await;
+                            self.handle_device_menu(
+                                menu,
+                                menu_command,
+                                &node,
+                                icon_type,
+                                spaces,
+                                is_output,
+                            )
+                            .await?;
+                            if !self.running {
+                                return Ok(false);
+                            }
+                        }
+                        Ok(true)
+                    }
+                    // Section headers are not actionable, just show the list again
+                    _ => Ok(true),
+                }
+            }
+            None => {
+                if !self.interactive {
+                    self.running = false;
+                }
+                debug!("Exited all devices menu");
+                Ok(false)
+            }
+        }
+    }
+
+    /// Applies a device list quick action, then shows the list again in interactive mode.
+    async fn handle_quick_action(
+        &mut self,
+        node: &Node,
+        action: DeviceQuickAction,
+        is_output: bool,
+    ) -> Result<bool> {
+        match action {
+            DeviceQuickAction::ToggleMute => {
+                self.perform_mute_toggle(node, !node.volume.muted).await?
+            }
+            DeviceQuickAction::SetDefault => self.perform_set_default(node, is_output).await?,
+        }
+
+        if !self.interactive {
+            self.running = false;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
     fn handle_device_selection(
         &self,
         nodes: &[Node],
@@ -569,6 +1236,10 @@ impl App {
         icon_type: &str,
         spaces: usize,
     ) -> Result<Option<Node>> {
+        if let Some(id) = parse_object_id(selection).filter(|_| menu.show_ids) {
+            return Ok(nodes.iter().find(|node| node.id == id).cloned());
+        }
+
         for node in nodes {
             let formatted = menu.format_node_display(node, &self.controller, icon_type, spaces);
             let cleaned_formatted = menu.clean_menu_output(&formatted, icon_type);
@@ -581,6 +1252,25 @@ impl App {
         Ok(None)
     }
 
+    /// Finds a node picked from a list of the cached graph in the live one, where its id can
+    /// differ after PipeWire restarted.
+    async fn live_node(&self, node: Option<Node>, from_cache: bool) -> Result<Option<Node>> {
+        if !from_cache {
+            return Ok(node);
+        }
+        let Some(node) = node else {
+            return Ok(None);
+        };
+
+        self.wait_for_initialization().await?;
+        let graph = self.controller.get_graph();
+        Ok(graph
+            .nodes
+            .values()
+            .find(|live| live.name == node.name && live.node_type == node.node_type)
+            .and_then(|live| self.controller.get_node(live.id)))
+    }
+
     fn find_replacement_node(&self, original_node: &Node, is_output: bool) -> Option<Node> {
         let device_id = original_node.device_id?;
 
@@ -673,6 +1363,7 @@ impl App {
                 spaces,
                 &device_name,
                 node.is_default,
+                self.controller.has_session_manager(),
                 is_output,
                 has_profiles,
                 self.interactive,
@@ -712,6 +1403,50 @@ impl App {
                 }
                 Ok(false)
             }
+            Some(DeviceMenuOptions::Connections) => {
+                self.handle_connections_menu(menu, menu_command, node, icon_type, spaces)
+                    .await?;
+                if !self.running {
+                    return Ok(false);
+                }
+                if !self.interactive {
+                    self.running = false;
+                }
+                Ok(false)
+            }
+            Some(DeviceMenuOptions::TestSpeakers) => {
+                self.perform_test_tone(node).await?;
+                // The tone plays from this process, so stay here even outside interactive mode
+                Ok(true)
+            }
+            Some(DeviceMenuOptions::TestMicrophone) => {
+                self.perform_mic_test(node).await?;
+                Ok(true)
+            }
+            Some(DeviceMenuOptions::Properties) => {
+                let properties = self.controller.get_node_properties(node);
+                let selection = menu
+                    .show_properties_menu(
+                        menu_command,
+                        icon_type,
+                        spaces,
+                        &device_name,
+                        &properties,
+                        self.interactive,
+                    )
+                    .await?;
+
+                // Rows are read-only, picking one just returns to the device menu
+                match selection {
+                    Some(_) => Ok(true),
+                    None => {
+                        if !self.interactive {
+                            self.running = false;
+                        }
+                        Ok(false)
+                    }
+                }
+            }
             Some(DeviceMenuOptions::Back) => Ok(false),
             None => {
                 if !self.interactive {
@@ -726,11 +1461,144 @@ impl App {
         }
     }
 
-    async fn handle_profile_menu(
+    async fn handle_connections_menu(
         &mut self,
         menu: &Menu,
         menu_command: &Option<String>,
-        device_id: u32,
+        node: &Node,
+        icon_type: &str,
+        spaces: usize,
+    ) -> Result<()> {
+        let mut stay_in_connections_menu = true;
+
+        while stay_in_connections_menu {
+            let should_stay = self
+                .handle_connections_options(menu, menu_command, node, icon_type, spaces)
+                .await?;
+
+            if !should_stay {
+                stay_in_connections_menu = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle_connections_options(
+        &mut self,
+        menu: &Menu,
+        menu_command: &Option<String>,
+        node: &Node,
+        icon_type: &str,
+        spaces: usize,
+    ) -> Result<bool> {
+        let linked_nodes = self.controller.get_linked_nodes(node.id);
+        let display_name = self.controller.get_node_base_name(node);
+
+        let menu_result = menu
+            .show_connections_menu(
+                menu_command,
+                &linked_nodes,
+                &self.controller,
+                icon_type,
+                spaces,
+                &display_name,
+                self.interactive,
+            )
+            .await?;
+
+        match menu_result {
+            Some(selection) => {
+                if selection == t!("menus.common.back").as_ref() {
+                    return Ok(false);
+                }
+
+                match ConnectionsMenuOptions::from_string(&selection) {
+                    Some(ConnectionsMenuOptions::LinkTo) => {
+                        self.handle_link_target_menu(menu, menu_command, node, icon_type, spaces)
+                            .await?;
+                    }
+                    Some(ConnectionsMenuOptions::Linked(_)) => {
+                        let selected_node = self.handle_device_selection(
+                            &linked_nodes,
+                            &selection,
+                            menu,
+                            icon_type,
+                            spaces,
+                        )?;
+                        if let Some(target) = selected_node {
+                            self.perform_remove_link(node, &target).await?;
+                        }
+                    }
+                    None => {}
+                }
+
+                if !self.interactive {
+                    self.running = false;
+                    return Ok(false);
+                }
+                Ok(true)
+            }
+            None => {
+                if !self.interactive {
+                    self.running = false;
+                }
+                debug!("Exited connections menu");
+                Ok(false)
+            }
+        }
+    }
+
+    async fn handle_link_target_menu(
+        &mut self,
+        menu: &Menu,
+        menu_command: &Option<String>,
+        node: &Node,
+        icon_type: &str,
+        spaces: usize,
+    ) -> Result<()> {
+        let targets = self.controller.get_link_targets(node.id);
+        let Some(selection) = menu
+            .show_link_target_menu(menu_command, &targets, &self.controller, icon_type, spaces)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let Some(target) =
+            self.handle_device_selection(&targets, &selection, menu, icon_type, spaces)?
+        else {
+            return Ok(());
+        };
+
+        let output_channels = self
+            .controller
+            .get_port_count(node.id, PortDirection::Output);
+        let input_channels = self
+            .controller
+            .get_port_count(target.id, PortDirection::Input);
+
+        if let Some(mapping) = menu
+            .show_port_mapping_menu(
+                menu_command,
+                icon_type,
+                spaces,
+                output_channels,
+                input_channels,
+            )
+            .await?
+        {
+            self.perform_create_link(node, &target, mapping).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn handle_profile_menu(
+        &mut self,
+        menu: &Menu,
+        menu_command: &Option<String>,
+        device_id: u32,
         icon_type: &str,
         spaces: usize,
     ) -> Result<()> {
@@ -757,8 +1625,15 @@ impl App {
         icon_type: &str,
         spaces: usize,
     ) -> Result<bool> {
-        let profiles = self.controller.get_device_profiles(device_id);
+        let mut profiles = self.controller.get_device_profiles(device_id);
+        if self.show_unavailable_profiles {
+            profiles.extend(self.controller.get_device_unavailable_profiles(device_id));
+        }
         let current_profile = self.controller.get_device_current_profile(device_id);
+        let off_profile = self
+            .controller
+            .get_device_off_profile(device_id)
+            .filter(|_| self.allow_card_off && !self.controller.is_device_off(device_id));
 
         let device_name = self.controller.get_device_name(device_id);
 
@@ -770,17 +1645,49 @@ impl App {
                 &device_name,
                 &profiles,
                 current_profile.as_ref().map(|p| p.index),
+                off_profile.is_some(),
                 self.interactive,
             )
             .await?;
 
         match option {
             Some(ProfileMenuOptions::SelectProfile(profile_index)) => {
-                let target_profile = profile_index;
                 self.perform_profile_switch(device_id, profile_index, &device_name, &profiles)
                     .await?;
-                self.wait_for_profile_change(device_id, target_profile)
+                if !self.interactive {
+                    self.running = false;
+                }
+                Ok(false)
+            }
+            Some(ProfileMenuOptions::Unavailable(profile_index)) => {
+                if let Some(profile) = profiles.iter().find(|p| p.index == profile_index) {
+                    let msg = t!(
+                        "notifications.pw.profile_unavailable",
+                        device_name = device_name,
+                        profile_name = &profile.description
+                    );
+                    info!("{msg}");
+                    try_send_notification!(
+                        self.notification_manager,
+                        None,
+                        Some(msg.to_string()),
+                        Some("switch_profile"),
+                        None
+                    );
+                }
+                // Offer the other profiles again rather than leaving
+                Ok(true)
+            }
+            Some(ProfileMenuOptions::TurnOff) => {
+                if let Some(off_profile) = off_profile {
+                    self.perform_profile_switch(
+                        device_id,
+                        off_profile.index,
+                        &device_name,
+                        &[off_profile.clone()],
+                    )
                     .await?;
+                }
                 if !self.interactive {
                     self.running = false;
                 }
@@ -797,23 +1704,6 @@ impl App {
         }
     }
 
-    async fn wait_for_profile_change(
-        &self,
-        device_id: u32,
-        target_profile_index: u32,
-    ) -> Result<()> {
-        for _ in 0..20 {
-            if let Some(current_profile) = self.controller.get_device_current_profile(device_id) {
-                if current_profile.index == target_profile_index {
-                    return Ok(());
-                }
-            }
-            sleep(Duration::from_millis(50)).await;
-        }
-
-        Ok(())
-    }
-
     async fn handle_volume_menu(
         &mut self,
         menu: &Menu,
@@ -874,7 +1764,7 @@ impl App {
         let volume_display = if node.volume.muted {
             t!("menus.volume.muted").to_string()
         } else {
-            format!("{}%", node.volume.percent())
+            format!("{}%", node.volume.percent_in(self.volume_scale))
         };
 
         let step_percent = (self.volume_step * 100.0).round() as u8;
@@ -889,6 +1779,7 @@ impl App {
                 &device_name,
                 &volume_display,
                 step_percent,
+                self.repeat_count,
                 self.interactive,
             )
             .await?;
@@ -902,6 +1793,31 @@ impl App {
                 self.perform_volume_change(node, -self.volume_step).await?;
                 Ok((true, Some(VolumeMenuOptions::Decrease)))
             }
+            Some(VolumeMenuOptions::Repeat) => {
+                let count = self.repeat_count.unwrap_or(1);
+                let delta = match last_action {
+                    Some(VolumeMenuOptions::Decrease) => -self.volume_step,
+                    _ => self.volume_step,
+                };
+                self.perform_volume_ramp(node, delta, count).await?;
+                Ok((true, last_action))
+            }
+            Some(VolumeMenuOptions::Adjust(percent)) => {
+                // Typed percentages follow the displayed scale, unlike steps which follow the curve
+                let curve = self.controller.volume_curve();
+                let current = self.volume_scale.to_display(curve, node.volume.linear);
+                let target = self
+                    .volume_scale
+                    .from_display(curve, (current + percent as f32 / 100.0).max(0.0));
+                self.perform_volume_change(node, target - node.volume.linear)
+                    .await?;
+                let action = if percent < 0 {
+                    VolumeMenuOptions::Decrease
+                } else {
+                    VolumeMenuOptions::Increase
+                };
+                Ok((true, Some(action)))
+            }
             Some(VolumeMenuOptions::Mute) => {
                 self.perform_mute_toggle(node, true).await?;
                 Ok((true, Some(VolumeMenuOptions::Mute)))
@@ -910,6 +1826,18 @@ impl App {
                 self.perform_mute_toggle(node, false).await?;
                 Ok((true, Some(VolumeMenuOptions::Unmute)))
             }
+            Some(VolumeMenuOptions::StreamProperties) => {
+                self.handle_stream_properties_menu(menu, menu_command, node, icon_type, spaces)
+                    .await?;
+                if !self.running {
+                    return Ok((false, None));
+                }
+                if !self.interactive {
+                    self.running = false;
+                    return Ok((false, None));
+                }
+                Ok((true, last_action))
+            }
             Some(VolumeMenuOptions::Back) => Ok((false, None)),
             None => {
                 if !self.interactive {
@@ -924,6 +1852,278 @@ impl App {
         }
     }
 
+    async fn handle_stream_properties_menu(
+        &mut self,
+        menu: &Menu,
+        menu_command: &Option<String>,
+        stream: &Node,
+        icon_type: &str,
+        spaces: usize,
+    ) -> Result<()> {
+        let mut stay_in_properties_menu = true;
+
+        while stay_in_properties_menu {
+            // The stream may have ended while the menu was open
+            let Some(current_stream) = self.controller.get_node(stream.id) else {
+                return Ok(());
+            };
+
+            let should_stay = self
+                .handle_stream_properties_options(
+                    menu,
+                    menu_command,
+                    &current_stream,
+                    icon_type,
+                    spaces,
+                )
+                .await?;
+
+            if !should_stay {
+                stay_in_properties_menu = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle_stream_properties_options(
+        &mut self,
+        menu: &Menu,
+        menu_command: &Option<String>,
+        stream: &Node,
+        icon_type: &str,
+        spaces: usize,
+    ) -> Result<bool> {
+        let stream_name = menu.format_stream_display_name(stream, &self.controller);
+        // A target that is not in the graph right now is shown as written in the metadata
+        let target_name = self
+            .controller
+            .get_stream_target(stream)
+            .map(|target| self.controller.get_node_base_name(&target))
+            .or_else(|| stream.target_object.clone());
+        let lock_target = self
+            .controller
+            .get_stream_lock(stream)
+            .map(|lock| self.node_display_name(&lock.target));
+        let rate = stream
+            .sample_spec
+            .as_ref()
+            .map_or(self.controller.get_graph().default_clock_rate, |spec| {
+                spec.rate
+            });
+
+        let option = menu
+            .show_stream_properties_menu(
+                menu_command,
+                icon_type,
+                spaces,
+                &stream_name,
+                target_name.as_deref(),
+                stream.follows_default(),
+                stream.latency.as_deref(),
+                stream.application_name.is_some(),
+                lock_target.as_deref(),
+                self.interactive,
+            )
+            .await?;
+
+        match option {
+            Some(StreamPropertiesMenuOptions::Target | StreamPropertiesMenuOptions::Lock) => {
+                let targets = if stream.node_type == NodeType::StreamInputAudio {
+                    self.controller.get_input_nodes()
+                } else {
+                    self.controller.get_output_nodes()
+                };
+                let selection = menu
+                    .show_stream_target_menu(
+                        menu_command,
+                        &targets,
+                        &self.controller,
+                        icon_type,
+                        spaces,
+                        &stream_name,
+                    )
+                    .await?;
+                if let Some(selection) = selection {
+                    if let Some(target) =
+                        self.handle_device_selection(&targets, &selection, menu, icon_type, spaces)?
+                    {
+                        if option == Some(StreamPropertiesMenuOptions::Lock) {
+                            self.perform_lock_stream(stream, &target).await?;
+                        } else {
+                            self.perform_set_stream_target(stream, Some(&target))
+                                .await?;
+                        }
+                    }
+                }
+            }
+            Some(StreamPropertiesMenuOptions::Unlock) => {
+                self.perform_unlock_stream(stream).await?;
+            }
+            Some(StreamPropertiesMenuOptions::FollowDefault) => {
+                self.perform_set_stream_target(stream, None).await?;
+            }
+            Some(StreamPropertiesMenuOptions::PinToCurrent) => {
+                if let Some(target) = self.find_stream_device(stream) {
+                    self.perform_set_stream_target(stream, Some(&target))
+                        .await?;
+                }
+            }
+            Some(StreamPropertiesMenuOptions::Latency) => {
+                let selection = menu
+                    .show_latency_menu(
+                        menu_command,
+                        icon_type,
+                        spaces,
+                        stream.latency.as_deref(),
+                        rate,
+                        self.interactive,
+                    )
+                    .await?;
+                if let Some(LatencyMenuOptions::SelectQuantum(quantum)) = selection {
+                    self.perform_set_stream_latency(stream, quantum, rate)
+                        .await?;
+                }
+            }
+            Some(StreamPropertiesMenuOptions::Back) => return Ok(false),
+            None => {
+                if !self.interactive {
+                    self.running = false;
+                }
+                debug!("Exited stream properties menu for {stream_name}");
+                return Ok(false);
+            }
+        }
+
+        if !self.interactive {
+            self.running = false;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// The sink or source a stream plays to or records from, or the default one when it is
+    /// not linked yet.
+    fn find_stream_device(&self, stream: &Node) -> Option<Node> {
+        let (device_type, default_id) = if stream.node_type == NodeType::StreamInputAudio {
+            (NodeType::AudioSource, self.controller.get_default_source())
+        } else {
+            (NodeType::AudioSink, self.controller.get_default_sink())
+        };
+
+        self.controller
+            .get_linked_nodes(stream.id)
+            .into_iter()
+            .find(|node| node.node_type == device_type)
+            .or_else(|| default_id.and_then(|id| self.controller.get_node(id)))
+    }
+
+    async fn perform_set_stream_target(&self, stream: &Node, target: Option<&Node>) -> Result<()> {
+        self.controller
+            .set_stream_target(stream.id, target.map(|target| target.id))
+            .await?;
+
+        let stream_name = self.controller.get_node_base_name(stream);
+        let msg = match target {
+            Some(target) => t!(
+                "notifications.pw.stream_target_set",
+                stream_name = stream_name,
+                target = self.controller.get_node_base_name(target)
+            ),
+            None => t!(
+                "notifications.pw.stream_follows_default",
+                stream_name = stream_name
+            ),
+        };
+        info!("{msg}");
+        try_send_notification!(
+            self.notification_manager,
+            Some("Stream Target".to_string()),
+            Some(msg.to_string()),
+            Some("stream"),
+            None
+        );
+
+        Ok(())
+    }
+
+    async fn perform_lock_stream(&self, stream: &Node, target: &Node) -> Result<()> {
+        self.controller.lock_stream_to(stream, target).await?;
+
+        let msg = t!(
+            "notifications.pw.stream_locked",
+            application = stream.application_name.as_deref().unwrap_or_default(),
+            target = self.controller.get_node_base_name(target)
+        );
+        info!("{msg}");
+        try_send_notification!(
+            self.notification_manager,
+            Some("Stream Locked".to_string()),
+            Some(msg.to_string()),
+            Some("lock"),
+            None
+        );
+
+        Ok(())
+    }
+
+    async fn perform_unlock_stream(&self, stream: &Node) -> Result<()> {
+        self.controller.unlock_stream(stream).await?;
+
+        let msg = t!(
+            "notifications.pw.stream_unlocked",
+            application = stream.application_name.as_deref().unwrap_or_default()
+        );
+        info!("{msg}");
+        try_send_notification!(
+            self.notification_manager,
+            Some("Stream Unlocked".to_string()),
+            Some(msg.to_string()),
+            Some("stream"),
+            None
+        );
+
+        Ok(())
+    }
+
+    /// Name of the node called `node_name` as menus show it, or the raw name while it is gone.
+    fn node_display_name(&self, node_name: &str) -> String {
+        self.controller
+            .get_graph()
+            .nodes
+            .values()
+            .find(|node| node.name == node_name)
+            .map_or_else(
+                || node_name.to_string(),
+                |node| self.controller.get_node_base_name(node),
+            )
+    }
+
+    async fn perform_set_stream_latency(
+        &self,
+        stream: &Node,
+        quantum: u32,
+        rate: u32,
+    ) -> Result<()> {
+        self.controller.set_node_latency(stream.id, quantum).await?;
+
+        let msg = t!(
+            "notifications.pw.stream_latency_set",
+            stream_name = self.controller.get_node_base_name(stream),
+            latency = format_latency(quantum, rate)
+        );
+        info!("{msg}");
+        try_send_notification!(
+            self.notification_manager,
+            Some("Stream Latency".to_string()),
+            Some(msg.to_string()),
+            Some("stream"),
+            None
+        );
+
+        Ok(())
+    }
+
     async fn perform_set_default(&self, node: &Node, is_output: bool) -> Result<()> {
         let device_type = if is_output { "output" } else { "input" };
 
@@ -943,8 +2143,20 @@ impl App {
                     device_name = display_name
                 );
                 info!("{msg}");
-                self.notification_manager
-                    .send_default_changed_notification(device_type, &display_name)?;
+
+                if self.move_streams {
+                    if let Err(e) = self.controller.move_streams_to(node.id).await {
+                        warn!("Failed to move streams to {display_name}: {e}");
+                    }
+                }
+
+                self.hooks
+                    .run(HookEvent::DefaultChanged, node, &display_name);
+
+                if self.notify_changes {
+                    self.notification_manager
+                        .send_default_changed_notification(device_type, &display_name)?;
+                }
             }
             Err(e) => {
                 let msg = e.to_string();
@@ -969,31 +2181,53 @@ impl App {
         device_name: &str,
         profiles: &[Profile],
     ) -> Result<()> {
-        match self
+        let profile_name = profiles
+            .iter()
+            .find(|p| p.index == profile_index)
+            .map(|profile| profile.description.clone())
+            .unwrap_or_else(|| profile_index.to_string());
+
+        // The switch is only confirmed once the device reports the new profile
+        let result = match self
             .controller
             .switch_device_profile(device_id, profile_index)
             .await
         {
             Ok(()) => {
-                if let Some(profile) = profiles.iter().find(|p| p.index == profile_index) {
-                    let msg = t!(
-                        "notifications.pw.profile_switched",
-                        device_name = device_name,
-                        profile_name = &profile.description
-                    );
-                    info!("{msg}");
-                    try_send_notification!(
-                        self.notification_manager,
-                        None,
-                        Some(msg.to_string()),
-                        Some("switch_profile"),
-                        None
-                    );
-                }
+                self.controller
+                    .wait_for_profile(device_id, profile_index)
+                    .await
             }
-            Err(e) => {
-                let msg = e.to_string();
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => {
+                let msg = t!(
+                    "notifications.pw.profile_switched",
+                    device_name = device_name,
+                    profile_name = &profile_name
+                );
                 info!("{msg}");
+                try_send_notification!(
+                    self.notification_manager,
+                    None,
+                    Some(msg.to_string()),
+                    Some("switch_profile"),
+                    None
+                );
+            }
+            Err(e) => {
+                let msg = match PwError::find(&e) {
+                    Some(PwError::ProfileNotApplied { .. }) => t!(
+                        "notifications.pw.profile_switch_failed",
+                        device_name = device_name,
+                        profile_name = &profile_name
+                    )
+                    .to_string(),
+                    _ => e.to_string(),
+                };
+                warn!("{msg}");
                 try_send_notification!(
                     self.notification_manager,
                     None,
@@ -1007,18 +2241,71 @@ impl App {
         Ok(())
     }
 
-    async fn perform_volume_change(&self, node: &Node, delta: f32) -> Result<()> {
-        let new_volume = (node.volume.linear + delta).clamp(0.0, 2.0);
+    // The daemon only watches devices, so stream changes are always notified from here
+    fn should_notify_change(&self, node: &Node) -> bool {
+        self.notify_changes
+            || !matches!(node.node_type, NodeType::AudioSink | NodeType::AudioSource)
+    }
+
+    /// Applies `count` steps one after another so the change is heard gradually, notifying
+    /// only for the final one.
+    async fn perform_volume_ramp(&self, node: &Node, delta: f32, count: u8) -> Result<()> {
+        // Raising a device muted at zero brings its volume back in one go
+        if self.mute_at_zero && delta > 0.0 && node.volume.muted {
+            return self.perform_volume_change(node, delta).await;
+        }
+
+        let mut current = node.clone();
 
-        if node.volume.muted {
-            self.controller.set_mute(node.id, false).await?;
+        for _ in 1..count {
+            let volume = (current.volume.linear + delta)
+                .clamp(0.0, current.volume_limit(self.max_input_volume));
+            // Zero is left to the last change, which keeps the level to come back to
+            if self.mute_at_zero && volume <= 0.0 {
+                break;
+            }
+            if current.volume.muted {
+                self.controller.set_mute(current.id, false).await?;
+            }
+            self.controller.set_volume(current.id, volume).await?;
+            current.volume = Volume::on_curve(self.controller.volume_curve(), volume, false);
+            sleep(VOLUME_RAMP_INTERVAL).await;
         }
 
-        self.controller.set_volume(node.id, new_volume).await?;
+        self.perform_volume_change(&current, delta).await
+    }
+
+    async fn perform_volume_change(&self, node: &Node, delta: f32) -> Result<()> {
+        let mut new_volume =
+            (node.volume.linear + delta).clamp(0.0, node.volume_limit(self.max_input_volume));
+        let mute = self.mute_at_zero && new_volume <= 0.0;
+
+        if self.mute_at_zero {
+            new_volume = self
+                .controller
+                .set_volume_muting_at_zero(
+                    node,
+                    new_volume,
+                    delta > 0.0,
+                    node.max_volume(self.max_input_volume),
+                )
+                .await?;
+        } else {
+            if node.volume.muted {
+                self.controller.set_mute(node.id, false).await?;
+            }
+            self.controller.set_volume(node.id, new_volume).await?;
+        }
 
-        let volume_percent = (new_volume * 100.0).round() as u8;
+        let new_volume = Volume::on_curve(self.controller.volume_curve(), new_volume, mute);
+        let volume_percent = new_volume.percent_in(self.volume_scale);
         let display_name = self.controller.get_node_base_name(node);
 
+        let mut updated_node = node.clone();
+        updated_node.volume = new_volume;
+        self.hooks
+            .run(HookEvent::VolumeChanged, &updated_node, &display_name);
+
         let msg = t!(
             "notifications.pw.volume_changed",
             device_name = display_name,
@@ -1026,12 +2313,14 @@ impl App {
         );
 
         info!("{msg}");
-        self.notification_manager.send_volume_notification(
-            &display_name,
-            volume_percent,
-            false,
-            &node.node_type,
-        )?;
+        if self.should_notify_change(node) {
+            self.notification_manager.send_volume_notification(
+                &display_name,
+                volume_percent,
+                mute,
+                &node.node_type,
+            )?;
+        }
 
         Ok(())
     }
@@ -1058,12 +2347,19 @@ impl App {
         };
 
         info!("{} {}", &summary, &display_name);
-        self.notification_manager.send_volume_notification(
-            &display_name,
-            node.volume.percent(),
-            mute,
-            &node.node_type,
-        )?;
+
+        let mut updated_node = node.clone();
+        updated_node.volume.muted = mute;
+        self.hooks
+            .run(HookEvent::MuteToggled, &updated_node, &display_name);
+        if self.notify_mute && self.should_notify_change(node) {
+            self.notification_manager.send_mute_notification(
+                &display_name,
+                node.volume.percent_in(self.volume_scale),
+                mute,
+                &node.node_type,
+            )?;
+        }
 
         Ok(())
     }
@@ -1088,4 +2384,205 @@ impl App {
 
         Ok(())
     }
+
+    async fn perform_create_virtual_sink(&self, name: &str) -> Result<()> {
+        self.controller.create_virtual_sink(name).await?;
+
+        let msg = t!("notifications.pw.virtual_sink_created", name = name);
+        info!("{msg}");
+        try_send_notification!(
+            self.notification_manager,
+            Some("Virtual Sink Created".to_string()),
+            Some(msg.to_string()),
+            Some("create_virtual_sink"),
+            None
+        );
+
+        Ok(())
+    }
+
+    async fn perform_rename_managed_object(&self, node: &Node, name: &str) -> Result<()> {
+        self.controller.rename_managed_node(node.id, name).await?;
+
+        let display_name = self.controller.get_node_base_name(node);
+        let msg = t!(
+            "notifications.pw.managed_object_renamed",
+            old_name = display_name,
+            new_name = name
+        );
+        info!("{msg}");
+        try_send_notification!(
+            self.notification_manager,
+            Some("Virtual Sink Renamed".to_string()),
+            Some(msg.to_string()),
+            Some("rename"),
+            None
+        );
+
+        Ok(())
+    }
+
+    async fn perform_remove_managed_object(&self, node: &Node) -> Result<()> {
+        self.controller.remove_managed_node(node.id).await?;
+
+        let display_name = self.controller.get_node_base_name(node);
+        let msg = t!(
+            "notifications.pw.managed_object_removed",
+            name = display_name
+        );
+        info!("{msg}");
+        try_send_notification!(
+            self.notification_manager,
+            Some("Virtual Sink Removed".to_string()),
+            Some(msg.to_string()),
+            Some("remove"),
+            None
+        );
+
+        Ok(())
+    }
+
+    async fn perform_create_link(
+        &self,
+        node: &Node,
+        target: &Node,
+        mapping: PortMapping,
+    ) -> Result<()> {
+        self.controller
+            .create_link(node.id, target.id, mapping)
+            .await?;
+
+        let msg = t!(
+            "notifications.pw.link_created",
+            source = self.controller.get_node_base_name(node),
+            target = self.controller.get_node_base_name(target),
+            mapping = port_mapping_to_str(mapping)
+        );
+        info!("{msg}");
+        try_send_notification!(
+            self.notification_manager,
+            Some("Link Created".to_string()),
+            Some(msg.to_string()),
+            Some("link"),
+            None
+        );
+
+        Ok(())
+    }
+
+    async fn perform_test_tone(&self, node: &Node) -> Result<()> {
+        if let Err(e) = self.controller.play_test_tone(node.id).await {
+            let msg = e.to_string();
+            info!("{msg}");
+            try_send_notification!(
+                self.notification_manager,
+                None,
+                Some(msg),
+                Some("output"),
+                None
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Shows the live input level of `node` for a few seconds.
+    async fn perform_mic_test(&self, node: &Node) -> Result<()> {
+        let level = match self.controller.start_level_meter(node.id).await {
+            Ok(level) => level,
+            Err(e) => {
+                let msg = e.to_string();
+                info!("{msg}");
+                try_send_notification!(
+                    self.notification_manager,
+                    None,
+                    Some(msg),
+                    Some("input"),
+                    None
+                );
+                return Ok(());
+            }
+        };
+
+        let device_name = self.controller.get_node_base_name(node);
+        let deadline = Instant::now() + MIC_TEST_DURATION;
+
+        while Instant::now() < deadline {
+            sleep(LEVEL_REFRESH_INTERVAL).await;
+            if let Err(e) = self.notification_manager.send_level_notification(
+                &device_name,
+                level.percent(),
+                level.peak_db(),
+            ) {
+                warn!("Failed to show input level: {e}");
+                break;
+            }
+        }
+
+        self.controller.stop_level_meter().await
+    }
+
+    async fn perform_remove_link(&self, node: &Node, target: &Node) -> Result<()> {
+        self.controller.remove_link(node.id, target.id).await?;
+
+        let msg = t!(
+            "notifications.pw.link_removed",
+            source = self.controller.get_node_base_name(node),
+            target = self.controller.get_node_base_name(target)
+        );
+        info!("{msg}");
+        try_send_notification!(
+            self.notification_manager,
+            Some("Link Removed".to_string()),
+            Some(msg.to_string()),
+            Some("remove"),
+            None
+        );
+
+        Ok(())
+    }
+
+    async fn perform_apply_scene(&self, name: &str) -> Result<()> {
+        let Some(scene) = self.scenes.get(name) else {
+            return Ok(());
+        };
+
+        let msg = match self.controller.apply_scene(scene).await {
+            Ok(()) => t!("notifications.pw.scene_applied", name = name),
+            Err(e) => {
+                warn!("Scene {name} applied partially: {e:#}");
+                t!(
+                    "notifications.pw.scene_partially_applied",
+                    name = name,
+                    error = format!("{e:#}")
+                )
+            }
+        };
+        info!("{msg}");
+        try_send_notification!(
+            self.notification_manager,
+            Some("Scene Applied".to_string()),
+            Some(msg.to_string()),
+            Some("scene"),
+            None
+        );
+
+        Ok(())
+    }
+}
+
+/// Finds a node by name or pattern (see [`Controller::find_node`]), or the default device for
+/// `@DEFAULT_SINK@` and `@DEFAULT_SOURCE@`.
+pub fn resolve_target_node(controller: &Controller, key: &str) -> Result<Node> {
+    let node = match key {
+        DEFAULT_SINK_TARGET => controller
+            .get_default_sink()
+            .and_then(|id| controller.get_node(id)),
+        DEFAULT_SOURCE_TARGET => controller
+            .get_default_source()
+            .and_then(|id| controller.get_node(id)),
+        _ => controller.find_node(key),
+    };
+
+    node.ok_or_else(|| PwError::DeviceNotFound(key.to_string()).into())
 }
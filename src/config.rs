@@ -0,0 +1,528 @@
+pub use crate::scene::Scene;
+use crate::{
+    launcher::LauncherType,
+    menu::MarkerPosition,
+    notification::NotificationUrgency,
+    pw::{
+        engine::DEFAULT_COMMAND_TIMEOUT,
+        restoration::{
+            RestorationSettings, DEFAULT_RESTORATION_ATTEMPTS, DEFAULT_RESTORATION_TIMEOUT,
+        },
+        VolumeCurve, VolumeScale,
+    },
+};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use toml::Table;
+use tracing::debug;
+
+const HOST_SECTION: &str = "host";
+const SESSION_SECTION: &str = "session";
+const SCENE_SECTION: &str = "scene";
+
+// Matches the upper bound of the volume menu
+const MAX_VOLUME_PERCENT: u8 = 200;
+
+// Steeper curves leave most of the range inaudible
+const MIN_CURVE_EXPONENT: f32 = 1.0;
+const MAX_CURVE_EXPONENT: f32 = 5.0;
+
+// Leaves room for a few characters on each side of the ellipsis
+const MIN_NAME_WIDTH: usize = 8;
+
+const ENTRY_FORMAT_PLACEHOLDERS: &[&str] = &[
+    "name", "volume", "format", "rate", "channels", "latency", "default",
+];
+
+/// A problem found by `pwmenu check-config`, located by 1-based line and column.
+#[derive(Debug, Clone)]
+pub struct ConfigIssue {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// Destroy every pwmenu-tagged object when pwmenu exits.
+    pub cleanup_on_exit: bool,
+    /// Volume adjustment step as percentage, overridden by `--volume-step`.
+    pub volume_step: Option<u8>,
+    /// Move playing or recording streams along when the default device changes.
+    pub move_streams: bool,
+    /// Offer turning cards off from the profile menu.
+    pub allow_card_off: bool,
+    /// List profiles that cannot be selected right now, such as HDMI ones without a display.
+    pub show_unavailable_profiles: bool,
+    /// Keep the defaults to restore after a profile switch in the state file, so a restart
+    /// in the middle of the switch does not lose them.
+    pub persist_restorations: bool,
+    /// Save device volumes in the state file and let the daemon put back those the session
+    /// manager did not restore.
+    pub save_volumes: bool,
+    /// Mute devices lowered to zero, and unmute them back to their previous volume when raised.
+    pub mute_at_zero: bool,
+    /// Keep the devices of the last run in the cache directory and list them while PipeWire
+    /// is still being read, so menus open at once.
+    pub cache_graph: bool,
+    /// Seconds a PipeWire command may take before it fails, as the loop is likely stuck.
+    pub command_timeout: u64,
+    /// Seconds the default devices are waited for after a profile switch.
+    pub restoration_timeout: u64,
+    /// Times the default devices are looked for after a profile switch, further and further apart.
+    pub restoration_attempts: u8,
+    /// Let the daemon notify about volume, mute and default device changes made by any application.
+    pub notify_changes: bool,
+    /// Notify about mutes and unmutes, from pwmenu or from other applications with `notify-changes`.
+    pub notify_mute: bool,
+    /// Urgency of mute notifications, `low`, `normal` or `critical`.
+    pub mute_urgency: Option<NotificationUrgency>,
+    /// Let the daemon notify when applications start or stop recording from a microphone.
+    pub notify_mic: bool,
+    /// Let the daemon notify when headphones or other jacks are plugged in or out.
+    pub notify_jacks: bool,
+    /// Let the daemon notify when a driver reports at least this many xruns within ten seconds.
+    pub xrun_threshold: Option<u32>,
+    /// Convention used to show and type volumes, `cubic` like pulse tools or `linear` like
+    /// the raw `channelVolumes` values.
+    pub volume_scale: VolumeScale,
+    /// Curve volumes are shown, set and stepped on, `cubic`, `linear` or a custom exponent such as `2.0`.
+    pub volume_curve: VolumeCurve,
+    /// Most percentage points a single change may raise a volume by; larger increases, such
+    /// as a script asking for 150%, are capped.
+    pub max_volume_jump: Option<u8>,
+    /// Highest microphone and recording volume in percent; past 100% it boosts quiet inputs.
+    pub max_input_volume: u8,
+    /// Template for device entries, e.g. `"{name} [{volume}] {rate} Hz"`.
+    pub entry_format: Option<String>,
+    /// Marker for the default device, current profile and sample rate; empty to hide it.
+    pub default_marker: Option<String>,
+    /// Side of the entry the marker goes on, `before` or `after`.
+    pub default_marker_position: MarkerPosition,
+    /// Longest device or stream name shown in menus, in characters, before eliding its middle.
+    pub name_width: Option<usize>,
+    /// Launchers tried in order when `--launcher` is omitted.
+    pub launcher_order: Vec<LauncherType>,
+    /// Overrides applied when the hostname matches, e.g. `[host."laptop"]`.
+    #[serde(rename = "host")]
+    pub hosts: HashMap<String, Table>,
+    /// Overrides applied when the desktop session matches, e.g. `[session."sway"]`.
+    #[serde(rename = "session")]
+    pub sessions: HashMap<String, Table>,
+    /// Shell commands run on audio changes, e.g. `[hooks] default-changed = "..."`.
+    pub hooks: Hooks,
+    /// Named audio setups applied from the menu or `pwmenu apply-scene`, e.g. `[scene."meeting"]`.
+    #[serde(rename = "scene")]
+    pub scenes: BTreeMap<String, Scene>,
+}
+
+/// Commands are run with `sh -c`; details about the device are passed as `PWMENU_*`
+/// environment variables.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Hooks {
+    pub default_changed: Option<String>,
+    pub device_added: Option<String>,
+    pub volume_changed: Option<String>,
+    pub mute_toggled: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            cleanup_on_exit: false,
+            volume_step: None,
+            move_streams: false,
+            allow_card_off: false,
+            show_unavailable_profiles: false,
+            persist_restorations: false,
+            save_volumes: false,
+            mute_at_zero: false,
+            cache_graph: true,
+            command_timeout: DEFAULT_COMMAND_TIMEOUT.as_secs(),
+            restoration_timeout: DEFAULT_RESTORATION_TIMEOUT.as_secs(),
+            restoration_attempts: DEFAULT_RESTORATION_ATTEMPTS,
+            notify_changes: true,
+            notify_mute: true,
+            mute_urgency: None,
+            notify_mic: false,
+            notify_jacks: false,
+            xrun_threshold: None,
+            volume_scale: VolumeScale::default(),
+            volume_curve: VolumeCurve::default(),
+            max_volume_jump: None,
+            max_input_volume: 100,
+            entry_format: None,
+            default_marker: None,
+            default_marker_position: MarkerPosition::default(),
+            name_width: None,
+            launcher_order: LauncherType::DETECTION_ORDER.to_vec(),
+            hooks: Hooks::default(),
+            hosts: HashMap::new(),
+            sessions: HashMap::new(),
+            scenes: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(config_dir.join("pwmenu").join("config.toml"))
+    }
+
+    pub fn load() -> Result<Self> {
+        match Self::path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => {
+                debug!("No config file found, using defaults");
+                Ok(Self::default())
+            }
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+
+        let config = Self::resolve(&content)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+        config
+            .validate()
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+
+        debug!("Loaded config from {}", path.display());
+        Ok(config)
+    }
+
+    pub fn restoration_settings(&self) -> RestorationSettings {
+        RestorationSettings {
+            timeout: Duration::from_secs(self.restoration_timeout),
+            max_attempts: self.restoration_attempts,
+            persist: self.persist_restorations,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match self.problems().into_iter().next() {
+            Some((key, message)) => Err(anyhow!("{key}: {message}")),
+            None => Ok(()),
+        }
+    }
+
+    /// Parses config content and applies the host and session overrides matching this machine.
+    /// Session overrides are applied first so host-specific values take precedence.
+    pub fn resolve(content: &str) -> Result<Self> {
+        let config: Self = toml::from_str(content)?;
+
+        let mut overrides = Vec::new();
+        for session in current_sessions() {
+            overrides.extend(config.sessions.get(&session).cloned());
+        }
+        if let Some(host) = current_hostname() {
+            overrides.extend(config.hosts.get(&host).cloned());
+        }
+
+        if overrides.is_empty() {
+            return Ok(config);
+        }
+
+        let mut table: Table = toml::from_str(content)?;
+        for override_table in overrides {
+            merge_tables(&mut table, override_table);
+        }
+
+        Ok(Self::from_table(table)?)
+    }
+
+    /// Parses and validates config content, reporting every problem with its position.
+    /// Every host and session section is checked, not only the ones matching this machine.
+    pub fn check(content: &str) -> Vec<ConfigIssue> {
+        let config: Self = match toml::from_str(content) {
+            Ok(config) => config,
+            Err(e) => {
+                let offset = e.span().map(|span| span.start).unwrap_or(0);
+                let (line, column) = line_column(content, offset);
+                return vec![ConfigIssue {
+                    line,
+                    column,
+                    message: e.message().to_string(),
+                }];
+            }
+        };
+
+        let mut issues: Vec<ConfigIssue> = config
+            .problems()
+            .into_iter()
+            .map(|(key, message)| {
+                let (line, column) = match key.split_once('.') {
+                    Some((section, name)) => {
+                        section_position(content, section, name.trim_matches('"'))
+                    }
+                    None => key_position(content, &key),
+                };
+                ConfigIssue {
+                    line,
+                    column,
+                    message: format!("{key}: {message}"),
+                }
+            })
+            .collect();
+
+        let Ok(base_table) = toml::from_str::<Table>(content) else {
+            return issues;
+        };
+
+        let sections = [
+            (HOST_SECTION, &config.hosts),
+            (SESSION_SECTION, &config.sessions),
+        ];
+        for (section, overrides) in sections {
+            for (name, override_table) in overrides {
+                let mut table = base_table.clone();
+                merge_tables(&mut table, override_table.clone());
+
+                let problems = match Self::from_table(table) {
+                    Ok(merged) => merged
+                        .problems()
+                        .into_iter()
+                        .map(|(key, message)| format!("{key}: {message}"))
+                        .collect(),
+                    Err(e) => vec![e.message().to_string()],
+                };
+
+                let (line, column) = section_position(content, section, name);
+                issues.extend(problems.into_iter().map(|message| ConfigIssue {
+                    line,
+                    column,
+                    message: format!("{section}.\"{name}\": {message}"),
+                }));
+            }
+        }
+
+        issues
+    }
+
+    fn from_table(mut table: Table) -> Result<Self, toml::de::Error> {
+        // Overrides cannot nest further overrides
+        table.remove(HOST_SECTION);
+        table.remove(SESSION_SECTION);
+        toml::Value::Table(table).try_into()
+    }
+
+    fn problems(&self) -> Vec<(String, String)> {
+        let mut problems = Vec::new();
+
+        if let Some(step) = self.volume_step {
+            if !(1..=25).contains(&step) {
+                problems.push((
+                    "volume-step".to_string(),
+                    format!("must be between 1 and 25, got {step}"),
+                ));
+            }
+        }
+
+        if let Some(width) = self.name_width {
+            if width < MIN_NAME_WIDTH {
+                problems.push((
+                    "name-width".to_string(),
+                    format!("must be at least {MIN_NAME_WIDTH}, got {width}"),
+                ));
+            }
+        }
+
+        if self.command_timeout == 0 {
+            problems.push((
+                "command-timeout".to_string(),
+                "must be at least 1".to_string(),
+            ));
+        }
+
+        if self.restoration_timeout == 0 {
+            problems.push((
+                "restoration-timeout".to_string(),
+                "must be at least 1".to_string(),
+            ));
+        }
+
+        if self.restoration_attempts == 0 {
+            problems.push((
+                "restoration-attempts".to_string(),
+                "must be at least 1".to_string(),
+            ));
+        }
+
+        if let VolumeCurve::Exponent(exponent) = self.volume_curve {
+            if !(MIN_CURVE_EXPONENT..=MAX_CURVE_EXPONENT).contains(&exponent) {
+                problems.push((
+                    "volume-curve".to_string(),
+                    format!(
+                        "must be between {MIN_CURVE_EXPONENT} and {MAX_CURVE_EXPONENT}, got {exponent}"
+                    ),
+                ));
+            }
+        }
+
+        if !(100..=MAX_VOLUME_PERCENT).contains(&self.max_input_volume) {
+            problems.push((
+                "max-input-volume".to_string(),
+                format!(
+                    "must be between 100 and {MAX_VOLUME_PERCENT}, got {}",
+                    self.max_input_volume
+                ),
+            ));
+        }
+
+        if let Some(jump) = self.max_volume_jump {
+            if !(1..=MAX_VOLUME_PERCENT).contains(&jump) {
+                problems.push((
+                    "max-volume-jump".to_string(),
+                    format!("must be between 1 and {MAX_VOLUME_PERCENT}, got {jump}"),
+                ));
+            }
+        }
+
+        if self.xrun_threshold == Some(0) {
+            problems.push((
+                "xrun-threshold".to_string(),
+                "must be at least 1".to_string(),
+            ));
+        }
+
+        if self
+            .launcher_order
+            .iter()
+            .any(|launcher| launcher.program().is_none())
+        {
+            problems.push((
+                "launcher-order".to_string(),
+                "custom and stdio cannot be detected, select them with --launcher".to_string(),
+            ));
+        }
+
+        if let Some(template) = &self.entry_format {
+            for placeholder in template
+                .split('{')
+                .skip(1)
+                .filter_map(|part| part.split_once('}').map(|(name, _)| name))
+            {
+                if !ENTRY_FORMAT_PLACEHOLDERS.contains(&placeholder) {
+                    problems.push((
+                        "entry-format".to_string(),
+                        format!(
+                            "unknown placeholder {{{placeholder}}}, expected one of {}",
+                            ENTRY_FORMAT_PLACEHOLDERS.join(", ")
+                        ),
+                    ));
+                }
+            }
+        }
+
+        for (name, scene) in &self.scenes {
+            let key = format!("{SCENE_SECTION}.\"{name}\"");
+            if scene.is_empty() {
+                problems.push((key.clone(), "does not change anything".to_string()));
+            }
+            for (device, volume) in &scene.volumes {
+                if *volume > MAX_VOLUME_PERCENT {
+                    problems.push((
+                        key.clone(),
+                        format!(
+                            "volume for \"{device}\" must be at most {MAX_VOLUME_PERCENT}, got {volume}"
+                        ),
+                    ));
+                }
+            }
+        }
+
+        problems
+    }
+}
+
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+    (line, column)
+}
+
+fn key_position(content: &str, key: &str) -> (usize, usize) {
+    content
+        .lines()
+        .enumerate()
+        .find_map(|(index, line)| {
+            let trimmed = line.trim_start();
+            let rest = trimmed.strip_prefix(key)?;
+            rest.trim_start().starts_with('=').then(|| {
+                let column = line.len() - trimmed.len() + 1;
+                (index + 1, column)
+            })
+        })
+        .unwrap_or((1, 1))
+}
+
+fn current_hostname() -> Option<String> {
+    nix::unistd::gethostname()
+        .ok()
+        .and_then(|name| name.into_string().ok())
+}
+
+fn current_sessions() -> Vec<String> {
+    let mut sessions: Vec<String> = Vec::new();
+
+    for var in ["XDG_SESSION_DESKTOP", "XDG_CURRENT_DESKTOP"] {
+        if let Ok(value) = env::var(var) {
+            for session in value.split(':').filter(|s| !s.is_empty()) {
+                let session = session.to_lowercase();
+                if !sessions.contains(&session) {
+                    sessions.push(session);
+                }
+            }
+        }
+    }
+
+    sessions
+}
+
+fn merge_tables(base: &mut Table, overrides: Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(override_table)) => {
+                merge_tables(base_table, override_table);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn section_position(content: &str, section: &str, name: &str) -> (usize, usize) {
+    let headers = [
+        format!("[{section}.\"{name}\"]"),
+        format!("[{section}.{name}]"),
+    ];
+
+    content
+        .lines()
+        .enumerate()
+        .find_map(|(index, line)| {
+            let trimmed = line.trim_start();
+            headers
+                .iter()
+                .any(|header| trimmed.starts_with(header.as_str()))
+                .then(|| (index + 1, line.len() - trimmed.len() + 1))
+        })
+        .unwrap_or((1, 1))
+}
@@ -0,0 +1,666 @@
+use crate::{
+    app::App,
+    config::Config,
+    hooks::{HookEvent, HookRunner, HookSnapshot},
+    icons::Icons,
+    ipc::{self, MenuOptions, Request, Response, ShowTarget},
+    menu::Menu,
+    notification::{NotificationManager, OsdBackend},
+    pw::{
+        controller::{Controller, Jack},
+        nodes::NodeType,
+        profiler::ProfilerStats,
+        volume::RouteDirection,
+    },
+    status::{self, MicUser},
+};
+use anyhow::{anyhow, Result};
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use rust_i18n::t;
+use std::{
+    collections::{BTreeMap, HashSet},
+    ffi::OsString,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+use tokio::{
+    net::{UnixListener, UnixStream},
+    signal::unix::{signal, SignalKind},
+    sync::mpsc,
+    time::{interval, sleep, Duration},
+};
+use tracing::{debug, error, info, warn};
+
+// Editors often write a file in several steps, so wait for the burst to settle
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(200);
+
+// Window over which xruns are counted against `xrun-threshold`
+const XRUN_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+// Saved often enough that a crash or power loss keeps recent volume changes
+const VOLUME_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Profiler bound while `xrun-threshold` is set, with the counts seen at the last check.
+struct XrunMonitor {
+    stats: ProfilerStats,
+    counts: BTreeMap<u32, u32>,
+}
+
+/// Serves one remote request on its own task, with the daemon's settings as they were when
+/// the request came in.
+struct ClientHandler {
+    controller: Arc<Controller>,
+    icons: Arc<Icons>,
+    config: Config,
+    menu_options: Option<MenuOptions>,
+    osd_backend: OsdBackend,
+}
+
+pub struct Daemon {
+    controller: Arc<Controller>,
+    icons: Arc<Icons>,
+    notification_manager: Arc<NotificationManager>,
+    config: Config,
+    config_path: Option<PathBuf>,
+    menu_options: Option<MenuOptions>,
+    osd_backend: OsdBackend,
+}
+
+impl Daemon {
+    pub async fn new(config: Config, icons: Arc<Icons>, remote: Option<String>) -> Result<Self> {
+        let command_timeout = Duration::from_secs(config.command_timeout);
+        let controller = Controller::connect(remote, command_timeout)
+            .await?
+            .with_volume_curve(config.volume_curve);
+        let controller = Arc::new(controller);
+        Ok(Self::with_controller(controller, config, icons))
+    }
+
+    /// Runs the daemon on an existing controller, such as one replaying a recording.
+    pub fn with_controller(controller: Arc<Controller>, config: Config, icons: Arc<Icons>) -> Self {
+        let notification_manager = Arc::new(
+            NotificationManager::new(icons.clone()).with_mute_urgency(config.mute_urgency),
+        );
+
+        Self {
+            controller,
+            icons,
+            notification_manager,
+            config,
+            config_path: Config::path(),
+            menu_options: None,
+            osd_backend: OsdBackend::default(),
+        }
+    }
+
+    /// Launcher settings used by remote requests that do not bring their own.
+    pub fn with_menu_options(mut self, menu_options: Option<MenuOptions>) -> Self {
+        self.menu_options = menu_options;
+        self
+    }
+
+    /// Shows volume changes, including those of remote menus, through another OSD.
+    pub fn with_osd_backend(mut self, osd_backend: OsdBackend) -> Self {
+        self.notification_manager = Arc::new(
+            NotificationManager::clone(&self.notification_manager).with_osd_backend(osd_backend),
+        );
+        self.osd_backend = osd_backend;
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        self.controller.wait_for_initialization().await?;
+        info!("{}", t!("notifications.pw.initialized"));
+        self.apply_restoration_settings();
+        if self.config.save_volumes {
+            match self.controller.restore_volumes().await {
+                Ok(0) => {}
+                Ok(count) => info!("Restored the volume of {count} devices"),
+                Err(e) => warn!("Failed to restore saved volumes: {e}"),
+            }
+        }
+
+        let (reload_tx, mut reload_rx) = mpsc::unbounded_channel();
+        if let Some(path) = &self.config_path {
+            if let Err(e) = spawn_config_watcher(path.clone(), reload_tx) {
+                warn!("Config hot-reload disabled: {e}");
+            }
+        }
+
+        let listener = match ipc::bind() {
+            Ok(listener) => Some(listener),
+            Err(e) => {
+                warn!("Remote menus disabled: {e:#}");
+                None
+            }
+        };
+
+        let mut sigterm = signal(SignalKind::terminate())?;
+        let mut sigint = signal(SignalKind::interrupt())?;
+
+        let mut graph_rx = self.controller.subscribe();
+        let mut snapshot = HookSnapshot::capture(&self.controller);
+        let mut mic_users = status::mic_users(&self.controller);
+        let mut jacks = self.controller.get_jacks();
+        let mut streams = stream_ids(&self.controller);
+        self.apply_stream_locks(&streams.iter().copied().collect::<Vec<_>>())
+            .await;
+        let mut xrun_check = interval(XRUN_CHECK_INTERVAL);
+        let mut xrun_monitor = None;
+        let mut volume_save = interval(VOLUME_SAVE_INTERVAL);
+        let mut connected = true;
+        let open_menus = Arc::new(AtomicUsize::new(0));
+
+        loop {
+            tokio::select! {
+                Some(()) = reload_rx.recv() => {
+                    sleep(RELOAD_DEBOUNCE).await;
+                    while reload_rx.try_recv().is_ok() {}
+                    self.reload_config();
+                }
+                Ok(()) = graph_rx.changed() => {
+                    let now_connected = self.controller.get_graph().is_connected();
+                    if now_connected != connected {
+                        self.notify_connection(now_connected);
+                        connected = now_connected;
+                    }
+                    // Keep the last snapshot so a reconnection only reports real changes
+                    if !connected {
+                        continue;
+                    }
+
+                    let current = HookSnapshot::capture(&self.controller);
+                    self.run_hooks(&snapshot, &current);
+                    // Menus notify about their own changes
+                    if self.config.notify_changes && open_menus.load(Ordering::Relaxed) == 0 {
+                        self.notify_changes(&snapshot, &current);
+                    }
+                    snapshot = current;
+
+                    let current_mic_users = status::mic_users(&self.controller);
+                    if self.config.notify_mic {
+                        self.notify_mic_usage(&mic_users, &current_mic_users);
+                    }
+                    mic_users = current_mic_users;
+
+                    let current_jacks = self.controller.get_jacks();
+                    if self.config.notify_jacks {
+                        self.notify_jacks(&jacks, &current_jacks);
+                    }
+                    jacks = current_jacks;
+
+                    // Applications get new streams when they reconnect
+                    let current_streams = stream_ids(&self.controller);
+                    let new_streams: Vec<u32> =
+                        current_streams.difference(&streams).copied().collect();
+                    if !new_streams.is_empty() {
+                        self.apply_stream_locks(&new_streams).await;
+                    }
+                    streams = current_streams;
+                }
+                Ok((stream, _)) = accept(&listener) => {
+                    // Menus stay open for as long as the user wants, so serve them aside
+                    let handler = self.client_handler();
+                    let open_menus = open_menus.clone();
+                    open_menus.fetch_add(1, Ordering::Relaxed);
+                    tokio::spawn(async move {
+                        handler.handle(stream).await;
+                        open_menus.fetch_sub(1, Ordering::Relaxed);
+                    });
+                }
+                _ = xrun_check.tick() => self.check_xruns(&mut xrun_monitor).await,
+                _ = volume_save.tick() => self.save_volumes(),
+                _ = sigterm.recv() => break,
+                _ = sigint.recv() => break,
+            }
+        }
+
+        if listener.is_some() {
+            ipc::unbind();
+        }
+
+        self.shutdown().await
+    }
+
+    fn client_handler(&self) -> ClientHandler {
+        ClientHandler {
+            controller: self.controller.clone(),
+            icons: self.icons.clone(),
+            config: self.config.clone(),
+            menu_options: self.menu_options.clone(),
+            osd_backend: self.osd_backend,
+        }
+    }
+
+    fn run_hooks(&self, previous: &HookSnapshot, current: &HookSnapshot) {
+        let runner = HookRunner::new(self.config.hooks.clone());
+        if runner.is_empty() {
+            return;
+        }
+
+        for (event, node) in previous.changes(current, &self.controller) {
+            let display_name = self.controller.get_node_base_name(&node);
+            runner.run(event, &node, &display_name);
+        }
+    }
+
+    fn notify_changes(&self, previous: &HookSnapshot, current: &HookSnapshot) {
+        for (event, node) in previous.changes(current, &self.controller) {
+            let display_name = self.controller.get_node_base_name(&node);
+            let result = match event {
+                HookEvent::DefaultChanged => {
+                    let device_type = if node.node_type == NodeType::AudioSink {
+                        "output"
+                    } else {
+                        "input"
+                    };
+                    self.notification_manager
+                        .send_default_changed_notification(device_type, &display_name)
+                }
+                HookEvent::VolumeChanged => self.notification_manager.send_volume_notification(
+                    &display_name,
+                    node.volume.percent_in(self.config.volume_scale),
+                    node.volume.muted,
+                    &node.node_type,
+                ),
+                HookEvent::MuteToggled if self.config.notify_mute => {
+                    self.notification_manager.send_mute_notification(
+                        &display_name,
+                        node.volume.percent_in(self.config.volume_scale),
+                        node.volume.muted,
+                        &node.node_type,
+                    )
+                }
+                HookEvent::MuteToggled => continue,
+                HookEvent::DeviceAdded => continue,
+            };
+
+            if let Err(e) = result {
+                debug!("Failed to notify about {}: {e}", event.as_str());
+            }
+        }
+    }
+
+    fn notify_connection(&self, connected: bool) {
+        let (msg, icon) = if connected {
+            (t!("notifications.pw.connection_restored"), "refresh")
+        } else {
+            (t!("notifications.pw.connection_lost"), "connection_lost")
+        };
+
+        info!("{msg}");
+        try_send_notification!(
+            self.notification_manager,
+            None,
+            Some(msg.to_string()),
+            Some(icon),
+            None
+        );
+    }
+
+    fn notify_mic_usage(&self, previous: &[MicUser], current: &[MicUser]) {
+        // Applications often open several streams, so report each one once
+        let mut started: Vec<&MicUser> = Vec::new();
+        for user in current {
+            let is_new = !previous.iter().any(|p| p.application == user.application)
+                && !started.iter().any(|s| s.application == user.application);
+            if is_new {
+                started.push(user);
+            }
+        }
+
+        let msgs: Vec<String> = if !started.is_empty() {
+            started
+                .into_iter()
+                .map(|user| {
+                    t!(
+                        "notifications.pw.mic_in_use",
+                        application = user.application,
+                        device_name = user.source
+                    )
+                    .to_string()
+                })
+                .collect()
+        } else if !previous.is_empty() && current.is_empty() {
+            vec![t!("notifications.pw.mic_released").to_string()]
+        } else {
+            return;
+        };
+
+        for msg in msgs {
+            info!("{msg}");
+            try_send_notification!(
+                self.notification_manager,
+                None,
+                Some(msg),
+                Some("microphone"),
+                None
+            );
+        }
+    }
+
+    /// Reports jacks whose plugged state changed. Jacks of cards that just appeared, or of a
+    /// profile that was just selected, are not reported.
+    fn notify_jacks(&self, previous: &[Jack], current: &[Jack]) {
+        for jack in current {
+            let Some(before) = previous.iter().find(|before| {
+                before.device_id == jack.device_id
+                    && before.port.direction == jack.port.direction
+                    && before.port.index == jack.port.index
+            }) else {
+                continue;
+            };
+            let Some(plugged) = jack.port.is_plugged() else {
+                continue;
+            };
+            if before.port.is_plugged() == Some(plugged) {
+                continue;
+            }
+
+            let key = if plugged {
+                "notifications.pw.jack_plugged"
+            } else {
+                "notifications.pw.jack_unplugged"
+            };
+            let msg = t!(
+                key,
+                port = jack.port.description,
+                device_name = jack.device_name
+            )
+            .to_string();
+            let icon = match jack.port.direction {
+                RouteDirection::Output => "headphone",
+                RouteDirection::Input => "microphone",
+            };
+
+            info!("{msg}");
+            try_send_notification!(self.notification_manager, None, Some(msg), Some(icon), None);
+        }
+    }
+
+    /// Binds or releases the profiler as `xrun-threshold` is set or removed, then alerts about
+    /// drivers that reached the threshold since the last check.
+    async fn check_xruns(&self, monitor: &mut Option<XrunMonitor>) {
+        let Some(threshold) = self.config.xrun_threshold else {
+            if monitor.take().is_some() {
+                if let Err(e) = self.controller.stop_profiler().await {
+                    debug!("Failed to stop profiler: {e}");
+                }
+            }
+            return;
+        };
+
+        let Some(monitor) = monitor else {
+            match self.controller.start_profiler().await {
+                Ok(stats) => {
+                    *monitor = Some(XrunMonitor {
+                        counts: stats.xrun_counts(),
+                        stats,
+                    })
+                }
+                Err(e) => debug!("Xrun alerts unavailable: {e}"),
+            }
+            return;
+        };
+
+        let counts = monitor.stats.xrun_counts();
+        for (&driver_id, &count) in &counts {
+            // Drivers seen for the first time only count from now on
+            let previous = monitor.counts.get(&driver_id).copied().unwrap_or(count);
+            let xruns = count.saturating_sub(previous);
+            if xruns < threshold {
+                continue;
+            }
+
+            let device_name = self
+                .controller
+                .get_node(driver_id)
+                .map(|node| self.controller.get_node_base_name(&node))
+                .unwrap_or_else(|| driver_id.to_string());
+            let msg = t!(
+                "notifications.pw.xruns",
+                count = xruns,
+                device_name = device_name
+            )
+            .to_string();
+            warn!("{msg}");
+            try_send_notification!(
+                self.notification_manager,
+                None,
+                Some(msg),
+                Some("xrun"),
+                None
+            );
+        }
+        monitor.counts = counts;
+    }
+
+    async fn apply_stream_locks(&self, stream_ids: &[u32]) {
+        match self.controller.apply_stream_locks(stream_ids).await {
+            Ok(0) => {}
+            Ok(count) => info!("Moved {count} locked streams back to their device"),
+            Err(e) => warn!("Failed to apply stream locks: {e}"),
+        }
+    }
+
+    fn apply_restoration_settings(&self) {
+        let controller = self.controller.clone();
+        let settings = self.config.restoration_settings();
+        tokio::spawn(async move {
+            if let Err(e) = controller.configure_restoration(settings).await {
+                warn!("Failed to configure default restoration: {e}");
+            }
+            if let Err(e) = controller.sync_preferred_nodes().await {
+                debug!("Failed to set preferred nodes: {e}");
+            }
+        });
+    }
+
+    fn reload_config(&mut self) {
+        match Config::load() {
+            Ok(config) => {
+                self.config = config;
+                self.controller.set_volume_curve(self.config.volume_curve);
+                self.notification_manager = Arc::new(
+                    NotificationManager::clone(&self.notification_manager)
+                        .with_mute_urgency(self.config.mute_urgency),
+                );
+                self.apply_restoration_settings();
+
+                let msg = t!("notifications.config.reloaded");
+                info!("{msg}");
+                try_send_notification!(
+                    self.notification_manager,
+                    None,
+                    Some(msg.to_string()),
+                    Some("settings"),
+                    None
+                );
+            }
+            Err(e) => {
+                // Keep running with the last valid config
+                let msg = t!(
+                    "notifications.config.reload_failed",
+                    error = format!("{e:#}")
+                );
+                error!("{msg}");
+                try_send_notification!(
+                    self.notification_manager,
+                    None,
+                    Some(msg.to_string()),
+                    Some("settings"),
+                    None
+                );
+            }
+        }
+    }
+
+    fn save_volumes(&self) {
+        // Volumes read while reconnecting are not the user's
+        if !self.config.save_volumes || !self.controller.get_graph().is_connected() {
+            return;
+        }
+        if let Err(e) = self.controller.save_volumes() {
+            warn!("Failed to save volumes: {e}");
+        }
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        debug!("Shutting down daemon");
+        self.save_volumes();
+
+        if self.config.cleanup_on_exit {
+            let count = self.controller.remove_all_managed_objects().await?;
+            if count > 0 {
+                info!(
+                    "{}",
+                    t!("notifications.pw.managed_objects_cleaned", count = count)
+                );
+            }
+        }
+
+        self.controller.shutdown().await
+    }
+}
+
+impl ClientHandler {
+    async fn handle(&self, mut stream: UnixStream) {
+        let response = match ipc::read_request(&mut stream).await {
+            Ok(Request::Show { target, options }) => match self.show(&target, options).await {
+                Ok(()) => Response::default(),
+                Err(e) => Response {
+                    error: Some(format!("{e:#}")),
+                },
+            },
+            Err(e) => Response {
+                error: Some(format!("{e:#}")),
+            },
+        };
+
+        if let Some(error) = &response.error {
+            warn!("Remote request failed: {error}");
+        }
+        if let Err(e) = ipc::write_response(&mut stream, &response).await {
+            debug!("Failed to reply to client: {e}");
+        }
+    }
+
+    async fn show(&self, target: &ShowTarget, options: Option<MenuOptions>) -> Result<()> {
+        let options = options
+            .or_else(|| self.menu_options.clone())
+            .ok_or_else(|| anyhow!("No launcher given and the daemon was started without one"))?;
+        let menu = Menu::new(options.launcher.clone(), self.icons.clone())
+            .with_volume_scale(self.config.volume_scale)
+            .with_launcher_args(options.launcher_args.clone())
+            .with_show_ids(options.show_ids)
+            .with_markup(options.markup)
+            .with_numbered(options.numbered)
+            .with_volume_icons(options.volume_icons)
+            .with_entry_format(self.config.entry_format.clone())
+            .with_default_marker(
+                self.config.default_marker.clone(),
+                self.config.default_marker_position,
+            )
+            .with_name_width(self.config.name_width)
+            .with_icon_layout(options.icon_layout.clone());
+
+        // Hooks are run from graph changes here, so the app must not run them again
+        let mut app = App::with_controller(
+            self.controller.clone(),
+            self.icons.clone(),
+            options.volume_step as f32 / 100.0,
+            options.interactive,
+        )
+        .with_move_streams(self.config.move_streams)
+        .with_allow_card_off(self.config.allow_card_off)
+        .with_show_unavailable_profiles(self.config.show_unavailable_profiles)
+        .with_osd_backend(self.osd_backend)
+        .with_volume_scale(self.config.volume_scale)
+        .with_max_input_volume(self.config.max_input_volume)
+        .with_mute_notifications(self.config.notify_mute, self.config.mute_urgency)
+        .with_mute_at_zero(self.config.mute_at_zero)
+        .with_repeat(options.repeat);
+
+        app.run_show(
+            &menu,
+            &options.launcher_command,
+            &options.icon_type(),
+            options.spaces,
+            target,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+fn stream_ids(controller: &Controller) -> HashSet<u32> {
+    controller
+        .get_output_streams()
+        .into_iter()
+        .chain(controller.get_input_streams())
+        .map(|stream| stream.id)
+        .collect()
+}
+
+async fn accept(
+    listener: &Option<UnixListener>,
+) -> std::io::Result<(UnixStream, tokio::net::unix::SocketAddr)> {
+    match listener {
+        Some(listener) => listener.accept().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn spawn_config_watcher(path: PathBuf, reload_tx: mpsc::UnboundedSender<()>) -> Result<()> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| anyhow!("Config path {} has no parent", path.display()))?
+        .to_path_buf();
+    let file_name: OsString = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Config path {} has no file name", path.display()))?
+        .to_os_string();
+
+    let inotify = Inotify::init(InitFlags::IN_CLOEXEC)?;
+    // Watch the directory so atomic saves (write to temp, rename over) are seen too
+    inotify
+        .add_watch(
+            &dir,
+            AddWatchFlags::IN_CLOSE_WRITE
+                | AddWatchFlags::IN_MOVED_TO
+                | AddWatchFlags::IN_CREATE
+                | AddWatchFlags::IN_DELETE,
+        )
+        .map_err(|e| anyhow!("Failed to watch {}: {e}", dir.display()))?;
+
+    debug!("Watching {} for config changes", path.display());
+
+    thread::spawn(move || loop {
+        let events = match inotify.read_events() {
+            Ok(events) => events,
+            Err(e) => {
+                error!("Config watcher stopped: {e}");
+                break;
+            }
+        };
+
+        if events
+            .iter()
+            .any(|event| event.name.as_ref() == Some(&file_name))
+            && reload_tx.send(()).is_err()
+        {
+            break;
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,366 @@
+use crate::pw::{
+    devices::{Device, RouteInfo},
+    AudioGraph, Link, Node, NodeType, Port, PortDirection, Profile,
+};
+use serde_json::{json, Map, Value};
+use std::{collections::BTreeMap, fmt::Write};
+
+/// Formats accepted by `pwmenu dump`.
+pub const DUMP_FORMATS: &[&str] = &["json", "dot", "pw-dump"];
+
+/// Serializes nodes, ports and links as a Graphviz document. Nodes are drawn as records with
+/// their input ports on the left and output ports on the right, so links join port to port.
+pub fn to_dot(graph: &AudioGraph) -> String {
+    let mut output = String::from(
+        "digraph pwmenu {\n  rankdir=LR;\n  node [shape=record, style=filled, fontname=\"sans\"];\n",
+    );
+
+    let mut nodes: Vec<&Node> = graph.nodes.values().collect();
+    nodes.sort_by_key(|node| node.id);
+
+    for node in nodes {
+        let mut ports: Vec<&Port> = graph
+            .ports
+            .values()
+            .filter(|port| port.node_id == node.id)
+            .collect();
+        ports.sort_by_key(|port| port.id);
+
+        let fields = |direction: PortDirection| {
+            ports
+                .iter()
+                .filter(|port| port.direction == direction)
+                .map(|port| format!("<p{}> {}", port.id, escape_record(&port_label(port))))
+                .collect::<Vec<_>>()
+                .join("|")
+        };
+
+        let title = node
+            .description
+            .as_deref()
+            .or(node.nick.as_deref())
+            .unwrap_or(&node.name);
+        let mut label = escape_record(title);
+        if let Some(media_class) = &node.media_class {
+            label.push_str(&format!("\\n{}", escape_record(media_class)));
+        }
+
+        let _ = writeln!(
+            output,
+            "  n{} [label=\"{{{{{}}}|{}|{{{}}}}}\", fillcolor=\"{}\"{}];",
+            node.id,
+            fields(PortDirection::Input),
+            label,
+            fields(PortDirection::Output),
+            fill_color(node.node_type),
+            if node.is_default { ", penwidth=2" } else { "" }
+        );
+    }
+
+    let mut links: Vec<_> = graph.links.values().collect();
+    links.sort_by_key(|link| link.id);
+
+    for link in links {
+        let _ = writeln!(
+            output,
+            "  n{}:p{} -> n{}:p{}{};",
+            link.output_node,
+            link.output_port,
+            link.input_node,
+            link.input_port,
+            if link.is_managed {
+                " [style=dashed]"
+            } else {
+                ""
+            }
+        );
+    }
+
+    output.push_str("}\n");
+    output
+}
+
+fn port_label(port: &Port) -> String {
+    if port.channel.is_empty() {
+        port.name.clone()
+    } else {
+        port.channel.clone()
+    }
+}
+
+fn fill_color(node_type: NodeType) -> &'static str {
+    match node_type {
+        NodeType::AudioSink => "lightblue",
+        NodeType::AudioSource => "lightpink",
+        NodeType::AudioDuplex => "plum",
+        NodeType::StreamOutputAudio | NodeType::StreamInputAudio => "lightyellow",
+        NodeType::AudioVirtual => "lightgrey",
+        NodeType::Unknown => "white",
+    }
+}
+
+// Characters with a meaning in record labels, plus quotes closing the attribute
+fn escape_record(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '{' | '}' | '|' | '<' | '>' | '"' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Serializes the graph as a `pw-dump` style array of objects. Only what pwmenu tracks is
+/// included: the props and params it reads, without states, permissions or change masks.
+pub fn to_pw_dump(graph: &AudioGraph) -> Value {
+    let mut objects = Vec::new();
+
+    let mut devices: Vec<&Device> = graph.devices.values().collect();
+    devices.sort_by_key(|device| device.id);
+    objects.extend(devices.into_iter().map(device_object));
+
+    let mut nodes: Vec<&Node> = graph.nodes.values().collect();
+    nodes.sort_by_key(|node| node.id);
+    objects.extend(nodes.into_iter().map(|node| node_object(graph, node)));
+
+    let mut ports: Vec<&Port> = graph.ports.values().collect();
+    ports.sort_by_key(|port| port.id);
+    objects.extend(ports.into_iter().map(port_object));
+
+    let mut links: Vec<&Link> = graph.links.values().collect();
+    links.sort_by_key(|link| link.id);
+    objects.extend(links.into_iter().map(link_object));
+
+    if let Some(id) = graph.default_metadata_id {
+        objects.push(default_metadata_object(graph, id));
+    }
+
+    Value::Array(objects)
+}
+
+fn device_object(device: &Device) -> Value {
+    let mut props = Map::new();
+    props.insert("object.id".to_string(), json!(device.id));
+    props.insert("device.name".to_string(), json!(device.name));
+    insert_opt(&mut props, "device.nick", &device.nick);
+    insert_opt(&mut props, "device.description", &device.description);
+    insert_opt(&mut props, "device.product.name", &device.product_name);
+    insert_opt(&mut props, "device.vendor.name", &device.vendor_name);
+    insert_opt(&mut props, "device.bus", &device.bus);
+    insert_opt(&mut props, "device.form-factor", &device.form_factor);
+    insert_dict(&mut props, &device.properties);
+
+    let profile = |profile: &Profile| {
+        json!({
+            "index": profile.index,
+            "name": profile.name,
+            "description": profile.description,
+            "priority": profile.priority,
+            "available": profile.available,
+        })
+    };
+
+    let mut params = Map::new();
+    params.insert(
+        "EnumProfile".to_string(),
+        device.profiles.iter().map(profile).collect(),
+    );
+    params.insert(
+        "Profile".to_string(),
+        device
+            .current_profile_index
+            .and_then(|index| device.profiles.iter().find(|p| p.index == index))
+            .map(profile)
+            .into_iter()
+            .collect(),
+    );
+
+    // Cards driving several devices report one route per device
+    let routes: Vec<Value> = [
+        ("Output", &device.output_route, &device.output_routes),
+        ("Input", &device.input_route, &device.input_routes),
+    ]
+    .into_iter()
+    .flat_map(|(direction, last_route, routes)| {
+        let routes: Vec<&RouteInfo> = if routes.is_empty() {
+            vec![last_route]
+        } else {
+            routes.values().collect()
+        };
+        routes.into_iter().map(move |route| (direction, route))
+    })
+    .filter(|(_, route)| route.is_available())
+    .map(|(direction, route)| {
+        json!({
+            "index": route.index,
+            "direction": direction,
+            "device": route.device,
+            "available": route.available.as_deref().unwrap_or("unknown"),
+            "props": {
+                "mute": route.muted.unwrap_or(false),
+                "channelVolumes": route.channel_volumes,
+            },
+        })
+    })
+    .collect();
+    if !routes.is_empty() {
+        params.insert("Route".to_string(), Value::Array(routes));
+    }
+
+    json!({
+        "id": device.id,
+        "type": "PipeWire:Interface:Device",
+        "info": {
+            "props": props,
+            "params": params,
+        },
+    })
+}
+
+fn node_object(graph: &AudioGraph, node: &Node) -> Value {
+    let mut props = Map::new();
+    props.insert("object.id".to_string(), json!(node.id));
+    props.insert("node.name".to_string(), json!(node.name));
+    insert_opt(&mut props, "node.nick", &node.nick);
+    insert_opt(&mut props, "node.description", &node.description);
+    insert_opt(&mut props, "media.class", &node.media_class);
+    insert_opt(&mut props, "media.name", &node.media_name);
+    insert_opt(&mut props, "application.name", &node.application_name);
+    if let Some(device_id) = node.device_id {
+        props.insert("device.id".to_string(), json!(device_id));
+    }
+    insert_opt(&mut props, "node.latency", &node.latency);
+    insert_dict(&mut props, &node.properties);
+
+    let count_ports = |direction: PortDirection| {
+        graph
+            .ports
+            .values()
+            .filter(|port| port.node_id == node.id && port.direction == direction)
+            .count()
+    };
+
+    let mut params = Map::new();
+    params.insert(
+        "Props".to_string(),
+        json!([{
+            "volume": node.volume.raw(),
+            "mute": node.volume.muted,
+            "channelVolumes": node.channel_volumes,
+        }]),
+    );
+    if let Some(spec) = &node.sample_spec {
+        params.insert(
+            "Format".to_string(),
+            json!([{
+                "mediaType": "audio",
+                "mediaSubtype": "raw",
+                "format": spec.format,
+                "rate": spec.rate,
+                "channels": spec.channels,
+            }]),
+        );
+    }
+
+    json!({
+        "id": node.id,
+        "type": "PipeWire:Interface:Node",
+        "info": {
+            "n-input-ports": count_ports(PortDirection::Input),
+            "n-output-ports": count_ports(PortDirection::Output),
+            "props": props,
+            "params": params,
+        },
+    })
+}
+
+fn port_object(port: &Port) -> Value {
+    let (direction, short_direction) = match port.direction {
+        PortDirection::Input => ("input", "in"),
+        PortDirection::Output => ("output", "out"),
+    };
+
+    json!({
+        "id": port.id,
+        "type": "PipeWire:Interface:Port",
+        "info": {
+            "direction": direction,
+            "props": {
+                "object.id": port.id,
+                "port.name": port.name,
+                "port.direction": short_direction,
+                "audio.channel": port.channel,
+                "node.id": port.node_id,
+            },
+            "params": {},
+        },
+    })
+}
+
+fn link_object(link: &Link) -> Value {
+    json!({
+        "id": link.id,
+        "type": "PipeWire:Interface:Link",
+        "info": {
+            "output-node-id": link.output_node,
+            "output-port-id": link.output_port,
+            "input-node-id": link.input_node,
+            "input-port-id": link.input_port,
+            "props": {
+                "object.id": link.id,
+                "link.output.node": link.output_node,
+                "link.output.port": link.output_port,
+                "link.input.node": link.input_node,
+                "link.input.port": link.input_port,
+            },
+        },
+    })
+}
+
+fn default_metadata_object(graph: &AudioGraph, id: u32) -> Value {
+    let entries: Vec<Value> = [
+        ("default.audio.sink", graph.default_sink),
+        ("default.audio.source", graph.default_source),
+    ]
+    .into_iter()
+    .filter_map(|(key, node_id)| {
+        let node = graph.nodes.get(&node_id?)?;
+        Some(json!({
+            "subject": 0,
+            "key": key,
+            "type": "Spa:String:JSON",
+            "value": { "name": node.name },
+        }))
+    })
+    .collect();
+
+    json!({
+        "id": id,
+        "type": "PipeWire:Interface:Metadata",
+        "props": { "metadata.name": "default" },
+        "metadata": entries,
+    })
+}
+
+fn insert_opt(props: &mut Map<String, Value>, key: &str, value: &Option<String>) {
+    if let Some(value) = value {
+        props.insert(key.to_string(), json!(value));
+    }
+}
+
+// pw-dump writes dictionary values that parse as JSON numbers or booleans unquoted
+fn insert_dict(props: &mut Map<String, Value>, dict: &BTreeMap<String, String>) {
+    for (key, value) in dict {
+        let value = match value.as_str() {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => value
+                .parse::<i64>()
+                .map(Value::from)
+                .or_else(|_| value.parse::<f64>().map(Value::from))
+                .unwrap_or_else(|_| Value::String(value.clone())),
+        };
+        props.entry(key.clone()).or_insert(value);
+    }
+}
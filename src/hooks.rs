@@ -0,0 +1,156 @@
+use crate::{
+    config::Hooks,
+    pw::{
+        controller::Controller,
+        graph::AudioGraph,
+        nodes::{Node, NodeType},
+    },
+};
+use std::{
+    process::{Command, Stdio},
+    sync::Arc,
+    thread,
+};
+use tracing::{debug, error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    DefaultChanged,
+    DeviceAdded,
+    VolumeChanged,
+    MuteToggled,
+}
+
+impl HookEvent {
+    pub const ALL: [HookEvent; 4] = [
+        HookEvent::DefaultChanged,
+        HookEvent::DeviceAdded,
+        HookEvent::VolumeChanged,
+        HookEvent::MuteToggled,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::DefaultChanged => "default-changed",
+            HookEvent::DeviceAdded => "device-added",
+            HookEvent::VolumeChanged => "volume-changed",
+            HookEvent::MuteToggled => "mute-toggled",
+        }
+    }
+
+    fn command(self, hooks: &Hooks) -> Option<&str> {
+        match self {
+            HookEvent::DefaultChanged => hooks.default_changed.as_deref(),
+            HookEvent::DeviceAdded => hooks.device_added.as_deref(),
+            HookEvent::VolumeChanged => hooks.volume_changed.as_deref(),
+            HookEvent::MuteToggled => hooks.mute_toggled.as_deref(),
+        }
+    }
+}
+
+/// Runs the configured shell command for an event without waiting for it to finish.
+#[derive(Debug, Clone, Default)]
+pub struct HookRunner {
+    hooks: Hooks,
+}
+
+impl HookRunner {
+    pub fn new(hooks: Hooks) -> Self {
+        Self { hooks }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        HookEvent::ALL
+            .iter()
+            .all(|event| event.command(&self.hooks).is_none())
+    }
+
+    pub fn run(&self, event: HookEvent, node: &Node, display_name: &str) {
+        let Some(command) = event.command(&self.hooks) else {
+            return;
+        };
+
+        let direction = match node.node_type {
+            NodeType::AudioSink | NodeType::StreamOutputAudio => "output",
+            _ => "input",
+        };
+
+        debug!("Running {} hook for {}", event.as_str(), node.name);
+
+        let spawned = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("PWMENU_EVENT", event.as_str())
+            .env("PWMENU_NODE_ID", node.id.to_string())
+            .env("PWMENU_NODE_NAME", &node.name)
+            .env("PWMENU_DEVICE_NAME", display_name)
+            .env("PWMENU_DIRECTION", direction)
+            .env("PWMENU_VOLUME", node.volume.percent().to_string())
+            .env("PWMENU_MUTED", node.volume.muted.to_string())
+            .stdin(Stdio::null())
+            .spawn();
+
+        match spawned {
+            Ok(mut child) => {
+                let event_name = event.as_str();
+                // Reap the child so finished hooks do not linger as zombies
+                thread::spawn(move || match child.wait() {
+                    Ok(status) if !status.success() => {
+                        error!("{event_name} hook exited with {status}");
+                    }
+                    Err(e) => error!("Failed to wait for {event_name} hook: {e}"),
+                    _ => {}
+                });
+            }
+            Err(e) => error!("Failed to run {} hook: {e}", event.as_str()),
+        }
+    }
+}
+
+/// The graph at one point in time, compared to find which output and input devices changed
+/// between graph updates.
+#[derive(Debug, Clone, Default)]
+pub struct HookSnapshot {
+    graph: Arc<AudioGraph>,
+}
+
+impl HookSnapshot {
+    pub fn capture(controller: &Controller) -> Self {
+        Self {
+            graph: controller.get_graph(),
+        }
+    }
+
+    pub fn changes(&self, newer: &HookSnapshot, controller: &Controller) -> Vec<(HookEvent, Node)> {
+        let mut diff = controller.diff(&self.graph, &newer.graph);
+        diff.retain_nodes(|node| {
+            matches!(node.node_type, NodeType::AudioSink | NodeType::AudioSource)
+        });
+
+        let mut changes: Vec<(HookEvent, Node)> = diff
+            .added
+            .into_iter()
+            .map(|node| (HookEvent::DeviceAdded, node))
+            .collect();
+
+        for change in diff.volume_changes {
+            if change.volume_changed() {
+                changes.push((HookEvent::VolumeChanged, change.node.clone()));
+            }
+            if change.mute_toggled() {
+                changes.push((HookEvent::MuteToggled, change.node));
+            }
+        }
+
+        for change in [diff.default_sink, diff.default_source]
+            .into_iter()
+            .flatten()
+        {
+            if let Some(node) = change.node {
+                changes.push((HookEvent::DefaultChanged, node));
+            }
+        }
+
+        changes
+    }
+}
@@ -1,7 +1,63 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use crate::pw::{controller::DeviceInfo, NodeType};
 
+/// Side of the label font icons are drawn on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IconPosition {
+    #[default]
+    Before,
+    After,
+}
+
+/// How font icons are joined to menu labels. Without a separator, the number of spaces
+/// given by `--spaces` is used.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IconLayout {
+    #[serde(default)]
+    pub position: IconPosition,
+    #[serde(default)]
+    pub separator: Option<String>,
+}
+
+impl IconLayout {
+    pub fn join(&self, icon: &str, text: &str, spaces: usize) -> String {
+        let separator = self.separator.clone().unwrap_or_else(|| " ".repeat(spaces));
+        match self.position {
+            IconPosition::Before => format!("{icon}{separator}{text}"),
+            IconPosition::After => format!("{text}{separator}{icon}"),
+        }
+    }
+
+    /// Removes the icon and separator added by [`IconLayout::join`] from a launcher selection.
+    pub fn strip(&self, output: &str) -> String {
+        match self.position {
+            IconPosition::Before => output
+                .chars()
+                .skip_while(|c| !c.is_ascii_alphanumeric())
+                .collect::<String>()
+                .trim()
+                .to_string(),
+            IconPosition::After => {
+                // Font icons are drawn from private use code points, never ASCII or letters
+                let label = output
+                    .trim_end_matches(|c: char| !c.is_ascii() && !c.is_alphanumeric())
+                    .trim_end();
+                // Space separators are already trimmed along with the launcher output
+                let separator = self.separator.as_deref().unwrap_or_default().trim();
+                label
+                    .strip_suffix(separator)
+                    .unwrap_or(label)
+                    .trim()
+                    .to_string()
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct IconDefinition {
     single: String,
@@ -105,6 +161,12 @@ impl Icons {
             ),
         );
 
+        font_icons.insert("turn_off", '\u{f0425}');
+        xdg_icons.insert(
+            "turn_off",
+            IconDefinition::simple("system-shutdown-symbolic"),
+        );
+
         font_icons.insert("switch_profile", '\u{f0ea2}');
         xdg_icons.insert(
             "switch_profile",
@@ -117,12 +179,129 @@ impl Icons {
             IconDefinition::simple("audio-x-generic-symbolic"),
         );
 
+        font_icons.insert("scenes", '\u{f0d3c}');
+        xdg_icons.insert(
+            "scenes",
+            IconDefinition::with_fallbacks(None, "view-grid-symbolic,preferences-system-symbolic"),
+        );
+
+        font_icons.insert("scene", '\u{f0d3c}');
+        xdg_icons.insert(
+            "scene",
+            IconDefinition::with_fallbacks(
+                None,
+                "media-playlist-consecutive-symbolic,audio-x-generic-symbolic",
+            ),
+        );
+
         font_icons.insert("set_sample_rate", '\u{f147d}');
         xdg_icons.insert(
             "set_sample_rate",
             IconDefinition::with_fallbacks(None, "filename-sample-rate-symbolic,view-media-visualization-symbolic,audio-x-generic-symbolic"),
         );
 
+        font_icons.insert("managed_objects", '\u{f0a72}');
+        xdg_icons.insert(
+            "managed_objects",
+            IconDefinition::with_fallbacks(None, "view-list-symbolic,preferences-system-symbolic"),
+        );
+
+        font_icons.insert("create_virtual_sink", '\u{f0415}');
+        xdg_icons.insert(
+            "create_virtual_sink",
+            IconDefinition::with_fallbacks(None, "list-add-symbolic,audio-speakers-symbolic"),
+        );
+
+        font_icons.insert("rename", '\u{f03eb}');
+        xdg_icons.insert("rename", IconDefinition::simple("document-edit-symbolic"));
+
+        font_icons.insert("remove", '\u{f01b4}');
+        xdg_icons.insert(
+            "remove",
+            IconDefinition::with_fallbacks(None, "list-remove-symbolic,edit-delete-symbolic"),
+        );
+
+        font_icons.insert("connections", '\u{f0337}');
+        xdg_icons.insert(
+            "connections",
+            IconDefinition::with_fallbacks(None, "network-wired-symbolic,insert-link-symbolic"),
+        );
+
+        font_icons.insert("test_speakers", '\u{f04c3}');
+        xdg_icons.insert(
+            "test_speakers",
+            IconDefinition::with_fallbacks(
+                None,
+                "audio-speakers-symbolic,audio-volume-high-symbolic",
+            ),
+        );
+
+        font_icons.insert("test_microphone", '\u{f036c}');
+        xdg_icons.insert(
+            "test_microphone",
+            IconDefinition::with_fallbacks(
+                None,
+                "audio-input-microphone-symbolic,microphone-sensitivity-high-symbolic",
+            ),
+        );
+
+        font_icons.insert("properties", '\u{f02fd}');
+        xdg_icons.insert(
+            "properties",
+            IconDefinition::with_fallbacks(
+                None,
+                "document-properties-symbolic,dialog-information-symbolic",
+            ),
+        );
+
+        font_icons.insert("property", '\u{f0315}');
+        xdg_icons.insert(
+            "property",
+            IconDefinition::with_fallbacks(
+                None,
+                "text-x-generic-symbolic,dialog-information-symbolic",
+            ),
+        );
+
+        font_icons.insert("lock", '\u{f033e}');
+        xdg_icons.insert(
+            "lock",
+            IconDefinition::with_fallbacks(
+                None,
+                "changes-prevent-symbolic,system-lock-screen-symbolic",
+            ),
+        );
+
+        font_icons.insert("xrun", '\u{f0026}');
+        xdg_icons.insert(
+            "xrun",
+            IconDefinition::with_fallbacks(None, "dialog-warning-symbolic,audio-card-symbolic"),
+        );
+
+        font_icons.insert("connection_lost", '\u{f0318}');
+        xdg_icons.insert(
+            "connection_lost",
+            IconDefinition::with_fallbacks(
+                None,
+                "network-offline-symbolic,dialog-warning-symbolic",
+            ),
+        );
+
+        font_icons.insert("link", '\u{f0339}');
+        xdg_icons.insert(
+            "link",
+            IconDefinition::with_fallbacks(None, "insert-link-symbolic,list-add-symbolic"),
+        );
+
+        font_icons.insert("port_mapping", '\u{f0623}');
+        xdg_icons.insert(
+            "port_mapping",
+            IconDefinition::with_fallbacks(
+                None,
+                "media-playlist-shuffle-symbolic,audio-x-generic-symbolic",
+            ),
+        );
+
         font_icons.insert("sample_rate", '\u{f0384}');
         xdg_icons.insert(
             "sample_rate",
@@ -244,10 +423,13 @@ impl Icons {
             IconDefinition::simple("audio-volume-overamplified-symbolic"),
         );
 
-        font_icons.insert("input_volume_overamplified", '\u{f1120}');
+        font_icons.insert("input_volume_overamplified", '\u{f08b4}');
         xdg_icons.insert(
             "input_volume_overamplified",
-            IconDefinition::simple("microphone-sensitivity-high-symbolic"),
+            IconDefinition::with_fallbacks(
+                None,
+                "dialog-warning-symbolic,microphone-sensitivity-high-symbolic",
+            ),
         );
 
         font_icons.insert("analog", '\u{f1543}');
@@ -438,6 +620,51 @@ impl Icons {
         }
     }
 
+    /// Icon key matching a volume level and mute state, shared by notifications and menus.
+    pub fn volume_icon_key(
+        node_type: &NodeType,
+        volume_percent: u16,
+        is_muted: bool,
+    ) -> &'static str {
+        if is_muted {
+            match node_type {
+                NodeType::AudioSink => "output_mute",
+                NodeType::AudioSource => "input_mute",
+                _ => "output_mute",
+            }
+        } else {
+            let volume_level = if volume_percent > 100 {
+                "overamplified"
+            } else if volume_percent > 67 {
+                "high"
+            } else if volume_percent > 33 {
+                "medium"
+            } else {
+                "low"
+            };
+
+            match (node_type, volume_level) {
+                (NodeType::AudioSink | NodeType::StreamOutputAudio, "overamplified") => {
+                    "output_volume_overamplified"
+                }
+                (NodeType::AudioSource | NodeType::StreamInputAudio, "overamplified") => {
+                    "input_volume_overamplified"
+                }
+                (NodeType::AudioSink | NodeType::StreamOutputAudio, "high") => "output_volume_high",
+                (NodeType::AudioSink | NodeType::StreamOutputAudio, "medium") => {
+                    "output_volume_medium"
+                }
+                (NodeType::AudioSink | NodeType::StreamOutputAudio, "low") => "output_volume_low",
+                (NodeType::AudioSource | NodeType::StreamInputAudio, "high") => "input_volume_high",
+                (NodeType::AudioSource | NodeType::StreamInputAudio, "medium") => {
+                    "input_volume_medium"
+                }
+                (NodeType::AudioSource | NodeType::StreamInputAudio, "low") => "input_volume_low",
+                _ => "output_volume_high",
+            }
+        }
+    }
+
     pub fn get_device_icon(&self, device_info: &DeviceInfo, icon_type: &str) -> String {
         if let Some(media_class) = &device_info.media_class {
             if media_class.contains("Monitor") {
@@ -0,0 +1,144 @@
+use crate::{icons::IconLayout, launcher::LauncherType};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{env, fs, io::ErrorKind, os::unix::net::UnixStream as StdUnixStream, path::PathBuf};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+use tracing::debug;
+
+const SOCKET_NAME: &str = "pwmenu.sock";
+
+pub const DEFAULT_SINK_TARGET: &str = "@DEFAULT_SINK@";
+pub const DEFAULT_SOURCE_TARGET: &str = "@DEFAULT_SOURCE@";
+
+/// Submenu to open directly, identified by a device name or a default-device placeholder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShowTarget {
+    Device(String),
+    Volume(String),
+}
+
+/// Launcher settings of a menu opened remotely. Requests without them use the ones the
+/// daemon was started with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MenuOptions {
+    pub launcher: LauncherType,
+    pub launcher_command: Option<String>,
+    /// Left unset to use the launcher's default icon type.
+    #[serde(default)]
+    pub icon: Option<String>,
+    pub spaces: usize,
+    pub volume_step: u8,
+    pub interactive: bool,
+    #[serde(default)]
+    pub repeat: Option<u8>,
+    #[serde(default)]
+    pub launcher_args: Vec<String>,
+    #[serde(default)]
+    pub show_ids: bool,
+    #[serde(default)]
+    pub markup: bool,
+    #[serde(default)]
+    pub numbered: bool,
+    #[serde(default)]
+    pub icon_layout: IconLayout,
+    #[serde(default)]
+    pub volume_icons: bool,
+}
+
+impl MenuOptions {
+    pub fn icon_type(&self) -> String {
+        self.icon
+            .clone()
+            .unwrap_or_else(|| self.launcher.default_icon_type().to_string())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Request {
+    Show {
+        target: ShowTarget,
+        options: Option<MenuOptions>,
+    },
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Response {
+    pub error: Option<String>,
+}
+
+pub fn socket_path() -> Option<PathBuf> {
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .map(|dir| dir.join(SOCKET_NAME))
+}
+
+pub fn daemon_running() -> bool {
+    socket_path().is_some_and(|path| StdUnixStream::connect(path).is_ok())
+}
+
+/// Binds the daemon socket, replacing a stale one left behind by a crash.
+pub fn bind() -> Result<UnixListener> {
+    let path = socket_path().ok_or_else(|| anyhow!("XDG_RUNTIME_DIR is not set"))?;
+
+    if daemon_running() {
+        return Err(anyhow!("Another daemon is listening on {}", path.display()));
+    }
+
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            return Err(e).with_context(|| format!("Failed to remove {}", path.display()));
+        }
+        _ => {}
+    }
+
+    let listener =
+        UnixListener::bind(&path).with_context(|| format!("Failed to bind {}", path.display()))?;
+    debug!("Listening on {}", path.display());
+
+    Ok(listener)
+}
+
+pub fn unbind() {
+    if let Some(path) = socket_path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Sends a request to the daemon and waits until it has been handled.
+pub async fn send(request: &Request) -> Result<Response> {
+    let path = socket_path().ok_or_else(|| anyhow!("XDG_RUNTIME_DIR is not set"))?;
+    let mut stream = UnixStream::connect(&path)
+        .await
+        .with_context(|| format!("Failed to connect to {}", path.display()))?;
+
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).await?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response).await?;
+    if response.is_empty() {
+        return Err(anyhow!("Daemon closed the connection without replying"));
+    }
+
+    Ok(serde_json::from_str(&response)?)
+}
+
+pub async fn read_request(stream: &mut UnixStream) -> Result<Request> {
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).await?;
+    serde_json::from_str(&line).context("Invalid request")
+}
+
+pub async fn write_response(stream: &mut UnixStream, response: &Response) -> Result<()> {
+    let mut line = serde_json::to_string(response)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).await?;
+    Ok(())
+}
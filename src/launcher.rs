@@ -6,9 +6,13 @@ use nix::{
     unistd::Pid,
 };
 use process_wrap::std::{CommandWrap, ProcessGroup};
+use serde::{Deserialize, Serialize};
 use signal_hook::iterator::Signals;
 use std::{
-    io::Write,
+    env, fs,
+    io::{self, BufRead, Write},
+    os::unix::fs::PermissionsExt,
+    path::Path,
     process::{exit, Command, Stdio},
     sync::{
         atomic::{AtomicI32, Ordering},
@@ -17,15 +21,70 @@ use std::{
     thread,
 };
 
-#[derive(Debug, Clone, ValueEnum)]
+#[derive(Debug, Clone, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LauncherType {
     Fuzzel,
     Rofi,
     Dmenu,
     Bemenu,
+    Wofi,
+    Tofi,
+    Fzf,
+    Stdio,
     Custom,
 }
 
+impl LauncherType {
+    /// Launchers probed when `--launcher` is omitted and the config sets no order of its own.
+    pub const DETECTION_ORDER: [LauncherType; 5] = [
+        LauncherType::Fuzzel,
+        LauncherType::Rofi,
+        LauncherType::Wofi,
+        LauncherType::Bemenu,
+        LauncherType::Dmenu,
+    ];
+
+    pub fn program(&self) -> Option<&'static str> {
+        match self {
+            LauncherType::Fuzzel => Some("fuzzel"),
+            LauncherType::Rofi => Some("rofi"),
+            LauncherType::Dmenu => Some("dmenu"),
+            LauncherType::Bemenu => Some("bemenu"),
+            LauncherType::Wofi => Some("wofi"),
+            LauncherType::Tofi => Some("tofi"),
+            LauncherType::Fzf => Some("fzf"),
+            LauncherType::Stdio | LauncherType::Custom => None,
+        }
+    }
+
+    /// Icon type used when `--icon` is omitted: image icons where the launcher can draw them.
+    pub fn default_icon_type(&self) -> &'static str {
+        match self {
+            LauncherType::Fuzzel | LauncherType::Rofi => "xdg",
+            _ => "font",
+        }
+    }
+
+    /// First launcher of `order` whose program is found in `PATH`.
+    pub fn detect(order: &[LauncherType]) -> Option<LauncherType> {
+        let path = env::var_os("PATH")?;
+
+        order
+            .iter()
+            .find(|launcher| {
+                launcher.program().is_some_and(|program| {
+                    env::split_paths(&path).any(|dir| is_executable(&dir.join(program)))
+                })
+            })
+            .cloned()
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
 #[derive(Debug, Clone)]
 pub enum LauncherCommand {
     Fuzzel {
@@ -35,6 +94,7 @@ pub enum LauncherCommand {
     Rofi {
         icon_type: String,
         placeholder: Option<String>,
+        markup: bool,
     },
     Dmenu {
         prompt: Option<String>,
@@ -42,27 +102,74 @@ pub enum LauncherCommand {
     Bemenu {
         prompt: Option<String>,
     },
+    Wofi {
+        prompt: Option<String>,
+    },
+    Tofi {
+        prompt: Option<String>,
+    },
+    Fzf {
+        prompt: Option<String>,
+    },
+    Stdio {
+        prompt: Option<String>,
+    },
     Custom {
         program: String,
         args: Vec<String>,
     },
 }
 
+/// Rofi exits with 10 for `kb-custom-1` up to 28 for `kb-custom-19`.
+const ROFI_CUSTOM_KEY_EXIT_CODES: std::ops::RangeInclusive<i32> = 10..=28;
+
+/// Entry picked in the launcher, along with the custom keybinding used to accept it, if any.
+pub struct LauncherSelection {
+    pub text: String,
+    pub custom_key: Option<u8>,
+}
+
 static CURRENT_LAUNCHER_PID: AtomicI32 = AtomicI32::new(-1);
 static SIGNAL_HANDLER_INIT: Once = Once::new();
 
 pub struct Launcher;
 
 impl Launcher {
-    pub fn run(cmd: LauncherCommand, input: Option<&str>) -> Result<Option<String>> {
-        let command = match cmd {
+    /// Runs the launcher, appending `extra_args` after the built-in arguments.
+    pub fn run(
+        cmd: LauncherCommand,
+        input: Option<&str>,
+        extra_args: &[String],
+    ) -> Result<Option<String>> {
+        Ok(Self::run_with_custom_keys(cmd, input, extra_args)?.map(|selection| selection.text))
+    }
+
+    /// Like [`Launcher::run`], also reporting which rofi `kb-custom-N` binding accepted the entry.
+    pub fn run_with_custom_keys(
+        cmd: LauncherCommand,
+        input: Option<&str>,
+        extra_args: &[String],
+    ) -> Result<Option<LauncherSelection>> {
+        if let LauncherCommand::Stdio { prompt } = &cmd {
+            return Ok(
+                Self::run_stdio(prompt.as_deref(), input)?.map(|text| LauncherSelection {
+                    text,
+                    custom_key: None,
+                }),
+            );
+        }
+
+        // fzf draws on the controlling terminal, which only the foreground process group may use
+        let process_group = !matches!(cmd, LauncherCommand::Fzf { .. });
+
+        let mut command = match cmd {
             LauncherCommand::Fuzzel {
                 icon_type,
                 placeholder,
             } => {
                 let mut cmd = Command::new("fuzzel");
                 cmd.arg("-d").arg("--minimal-lines");
-                if icon_type == "font" {
+                if icon_type != "xdg" {
                     cmd.arg("-I");
                 }
                 if let Some(hint_text) = placeholder {
@@ -73,12 +180,16 @@ impl Launcher {
             LauncherCommand::Rofi {
                 icon_type,
                 placeholder,
+                markup,
             } => {
                 let mut cmd = Command::new("rofi");
                 cmd.arg("-m").arg("-1").arg("-dmenu").arg("-i");
                 if icon_type == "xdg" {
                     cmd.arg("-show-icons");
                 }
+                if markup {
+                    cmd.arg("-markup-rows");
+                }
                 if let Some(hint_text) = placeholder {
                     cmd.arg("-theme-str")
                         .arg(format!("entry {{ placeholder: \"{hint_text}\"; }}"));
@@ -99,14 +210,66 @@ impl Launcher {
                 }
                 cmd
             }
+            LauncherCommand::Wofi { prompt } => {
+                let mut cmd = Command::new("wofi");
+                cmd.arg("--dmenu").arg("-i");
+                if let Some(hint_text) = prompt {
+                    cmd.arg("--prompt").arg(hint_text);
+                }
+                cmd
+            }
+            LauncherCommand::Tofi { prompt } => {
+                let mut cmd = Command::new("tofi");
+                if let Some(hint_text) = prompt {
+                    cmd.arg("--prompt-text").arg(format!("{hint_text}: "));
+                }
+                cmd
+            }
+            LauncherCommand::Fzf { prompt } => {
+                let mut cmd = Command::new("fzf");
+                cmd.arg("--reverse").arg("--no-sort");
+                if let Some(hint_text) = prompt {
+                    cmd.arg("--prompt").arg(format!("{hint_text}: "));
+                }
+                cmd
+            }
             LauncherCommand::Custom { program, args } => {
                 let mut cmd = Command::new(&program);
                 cmd.args(&args);
                 cmd
             }
+            LauncherCommand::Stdio { .. } => unreachable!("stdio menus run in process"),
         };
 
-        Self::run_command(command, input)
+        command.args(extra_args);
+
+        Self::run_command(command, input, process_group)
+    }
+
+    /// Prints the entries to stdout followed by an empty line, then reads the selection as one
+    /// line from stdin. The hint goes to stderr. An empty line or end of input cancels the menu
+    /// like escape does in a launcher.
+    fn run_stdio(prompt: Option<&str>, input: Option<&str>) -> Result<Option<String>> {
+        if let Some(hint_text) = prompt {
+            eprintln!("{hint_text}");
+        }
+
+        let mut stdout = io::stdout().lock();
+        if let Some(entries) = input.filter(|entries| !entries.is_empty()) {
+            writeln!(stdout, "{entries}")?;
+        }
+        writeln!(stdout)?;
+        stdout.flush()?;
+
+        let mut selection = String::new();
+        io::stdin().lock().read_line(&mut selection)?;
+        let selection = selection.trim();
+
+        if selection.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(selection.to_string()))
+        }
     }
 
     fn substitute_placeholders(template: &str, hint: Option<&str>) -> Result<String> {
@@ -143,11 +306,17 @@ impl Launcher {
         Ok((program, args))
     }
 
-    fn run_command(mut command: Command, input: Option<&str>) -> Result<Option<String>> {
+    fn run_command(
+        mut command: Command,
+        input: Option<&str>,
+        process_group: bool,
+    ) -> Result<Option<LauncherSelection>> {
         command.stdin(Stdio::piped()).stdout(Stdio::piped());
 
         let mut command_wrap = CommandWrap::from(command);
-        command_wrap.wrap(ProcessGroup::leader());
+        if process_group {
+            command_wrap.wrap(ProcessGroup::leader());
+        }
 
         let mut child = command_wrap
             .spawn()
@@ -161,7 +330,10 @@ impl Launcher {
                 if let Some(_signal) = signals.forever().next() {
                     let current_pid = CURRENT_LAUNCHER_PID.load(Ordering::Relaxed);
                     if current_pid > 0 && kill(Pid::from_raw(current_pid), None).is_ok() {
-                        let _ = killpg(Pid::from_raw(current_pid), Signal::SIGTERM);
+                        let pid = Pid::from_raw(current_pid);
+                        if killpg(pid, Signal::SIGTERM).is_err() {
+                            let _ = kill(pid, Signal::SIGTERM);
+                        }
                     }
                     exit(0);
                 }
@@ -181,10 +353,19 @@ impl Launcher {
 
         CURRENT_LAUNCHER_PID.store(-1, Ordering::Relaxed);
 
+        let custom_key = output
+            .status
+            .code()
+            .filter(|code| ROFI_CUSTOM_KEY_EXIT_CODES.contains(code))
+            .map(|code| (code - ROFI_CUSTOM_KEY_EXIT_CODES.start() + 1) as u8);
+
         if trimmed_output.is_empty() {
             Ok(None)
         } else {
-            Ok(Some(trimmed_output))
+            Ok(Some(LauncherSelection {
+                text: trimmed_output,
+                custom_key,
+            }))
         }
     }
 
@@ -192,6 +373,7 @@ impl Launcher {
         launcher_type: &LauncherType,
         command_str: &Option<String>,
         icon_type: &str,
+        markup: bool,
         hint: Option<&str>,
     ) -> Result<LauncherCommand> {
         let hint_text = hint.filter(|h| !h.is_empty()).map(|h| h.to_string());
@@ -204,9 +386,14 @@ impl Launcher {
             LauncherType::Rofi => Ok(LauncherCommand::Rofi {
                 icon_type: icon_type.to_string(),
                 placeholder: hint_text,
+                markup,
             }),
             LauncherType::Dmenu => Ok(LauncherCommand::Dmenu { prompt: hint_text }),
             LauncherType::Bemenu => Ok(LauncherCommand::Bemenu { prompt: hint_text }),
+            LauncherType::Wofi => Ok(LauncherCommand::Wofi { prompt: hint_text }),
+            LauncherType::Tofi => Ok(LauncherCommand::Tofi { prompt: hint_text }),
+            LauncherType::Fzf => Ok(LauncherCommand::Fzf { prompt: hint_text }),
+            LauncherType::Stdio => Ok(LauncherCommand::Stdio { prompt: hint_text }),
             LauncherType::Custom => {
                 if let Some(cmd) = command_str {
                     let processed_cmd = Self::substitute_placeholders(cmd, hint)?;
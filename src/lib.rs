@@ -1,32 +1,67 @@
+#[cfg(feature = "cli")]
 #[macro_use]
 extern crate rust_i18n;
+#[cfg(feature = "cli")]
 #[macro_use]
 mod macros;
+#[cfg(feature = "cli")]
 i18n!("locales", fallback = "en");
 
+#[cfg(feature = "cli")]
 pub mod app;
+#[cfg(feature = "cli")]
+pub mod config;
+#[cfg(feature = "cli")]
+pub mod daemon;
+pub mod dump;
+#[cfg(feature = "cli")]
+pub mod hooks;
+#[cfg(feature = "cli")]
 pub mod icons;
+#[cfg(feature = "cli")]
+pub mod ipc;
+#[cfg(feature = "cli")]
 pub mod launcher;
+#[cfg(feature = "cli")]
 pub mod menu;
+#[cfg(feature = "cli")]
 pub mod notification;
+pub mod scene;
+pub mod state;
+pub mod status;
+pub mod top;
 
 pub mod pw {
+    pub mod cache;
     pub mod commands;
     pub mod controller;
     pub mod devices;
     pub mod engine;
+    pub mod error;
+    pub mod events;
     pub mod graph;
     pub mod links;
+    pub mod managed;
     pub mod metadata;
+    pub mod meter;
+    pub mod mock;
     pub mod nodes;
+    pub mod profiler;
+    pub mod recording;
     pub mod restoration;
+    pub mod source;
+    pub mod tone;
     pub mod volume;
 
-    pub use self::devices::{DeviceType, Profile};
+    pub use self::commands::{BatchStep, CommandError};
+    pub use self::devices::{Device, DeviceType, Profile, RouteInfo, RoutePort};
     pub use self::engine::PwEngine;
+    pub use self::error::{ObjectKind, PwError};
+    pub use self::events::{DefaultChange, GraphDiff, GraphEvent, GraphEvents, VolumeChange};
     pub use self::graph::{AudioGraph, ConnectionStatus};
-    pub use self::links::{Link, Port, PortDirection};
+    pub use self::links::{map_ports, Link, Port, PortDirection, PortMapping};
     pub use self::nodes::{Node, NodeType, Volume};
     pub use self::restoration::RestorationManager;
-    pub use self::volume::{RouteDirection, VolumeResolver};
+    pub use self::source::GraphSource;
+    pub use self::volume::{RouteDirection, VolumeCurve, VolumeResolver, VolumeScale};
 }
@@ -3,7 +3,7 @@ macro_rules! try_send_notification {
     ($manager:expr, $summary:expr, $body:expr, $icon:expr, $timeout:expr) => {{
         let _ = $manager
             .send_notification($summary, $body, $icon, $timeout)
-            .map_err(|e| eprintln!("Notification failed: {e}"));
+            .map_err(|e| ::tracing::warn!("Notification failed: {e}"));
     }};
 }
 
@@ -13,7 +13,7 @@ macro_rules! try_send_notification_with_id {
         match $manager.send_notification($summary, $body, $icon, $timeout) {
             Ok(id) => Some(id),
             Err(e) => {
-                eprintln!("Notification failed: {e}");
+                ::tracing::warn!("Notification failed: {e}");
                 None
             }
         }
@@ -1,12 +1,41 @@
-use anyhow::{anyhow, Result};
-use clap::{value_parser, Arg, Command};
-use pwmenu::{app::App, icons::Icons, launcher::LauncherType, menu::Menu};
-use rust_i18n::{i18n, set_locale};
-use std::{env, sync::Arc};
+use anyhow::{anyhow, Context, Result};
+use clap::{error::ErrorKind, parser::ValueSource, value_parser, Arg, ArgMatches, Command};
+use pwmenu::{
+    app::{resolve_target_node, App},
+    config::Config,
+    daemon::Daemon,
+    dump::{self, DUMP_FORMATS},
+    icons::{IconLayout, IconPosition, Icons},
+    ipc::{self, MenuOptions, Request, ShowTarget},
+    launcher::LauncherType,
+    menu::Menu,
+    notification::{NotificationManager, OsdBackend},
+    pw::{
+        cache, controller::Controller, engine::DEFAULT_COMMAND_TIMEOUT, meter::InputLevel,
+        nodes::Node, recording::GraphRecorder, BatchStep, PwError, VolumeCurve, VolumeResolver,
+        VolumeScale,
+    },
+    status::{self, ListEntry, Status},
+    top,
+};
+use rust_i18n::{i18n, set_locale, t};
+use std::{
+    collections::HashMap,
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{Arc, Mutex},
+};
 use sys_locale::get_locale;
+use tokio::time::{interval, Duration};
+use tracing_subscriber::{fmt::writer::BoxMakeWriter, EnvFilter};
+#[cfg(feature = "journald")]
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
 i18n!("locales", fallback = "en");
 
+const METER_REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
 fn validate_launcher_command(command: &str) -> Result<String, String> {
     if command.contains("{placeholder}") {
         eprintln!("WARNING: {{placeholder}} is deprecated. Use {{hint}} instead.");
@@ -18,22 +47,303 @@ fn validate_launcher_command(command: &str) -> Result<String, String> {
     Ok(command.to_string())
 }
 
+fn parse_launcher_args(args: &str) -> Result<Vec<String>, String> {
+    shlex::split(args).ok_or_else(|| "Invalid shell syntax in launcher arguments".to_string())
+}
+
+/// Volume argument of `set-volume`, in percent of the configured scale.
+#[derive(Debug, Clone, Copy)]
+enum VolumeChange {
+    Set(u16),
+    Raise(u16),
+    Lower(u16),
+}
+
+fn parse_volume_change(value: &str) -> Result<VolumeChange, String> {
+    let value = value.trim().trim_end_matches('%');
+    let (build, digits): (fn(u16) -> VolumeChange, &str) =
+        if let Some(digits) = value.strip_prefix('+') {
+            (VolumeChange::Raise, digits)
+        } else if let Some(digits) = value.strip_prefix('-') {
+            (VolumeChange::Lower, digits)
+        } else {
+            (VolumeChange::Set, value)
+        };
+
+    digits
+        .parse::<u16>()
+        .ok()
+        .filter(|percent| *percent <= 200)
+        .map(build)
+        .ok_or_else(|| "Expected a percentage from 0 to 200, e.g. 40, +5 or -5".to_string())
+}
+
+// Exit statuses for scripts, besides 0 on success
+const EXIT_CANCELLED: u8 = 1;
+const EXIT_NOT_FOUND: u8 = 2;
+const EXIT_UNAVAILABLE: u8 = 3;
+const EXIT_FAILURE: u8 = 4;
+
+/// Every menu was dismissed without picking an entry.
+#[derive(Debug)]
+struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Menu closed without a selection")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
 #[tokio::main]
-async fn main() -> Result<()> {
-    env_logger::init();
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) if err.is::<Cancelled>() => ExitCode::from(EXIT_CANCELLED),
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitCode::from(exit_code(&err))
+        }
+    }
+}
 
+fn exit_code(err: &anyhow::Error) -> u8 {
+    match PwError::find(err) {
+        Some(PwError::NotFound { .. } | PwError::DeviceNotFound(_)) => EXIT_NOT_FOUND,
+        Some(PwError::Unavailable | PwError::Command(_)) => EXIT_UNAVAILABLE,
+        _ => EXIT_FAILURE,
+    }
+}
+
+async fn run() -> Result<()> {
     let locale = get_locale().unwrap_or_else(|| String::from("en"));
     set_locale(&locale);
 
-    let matches = Command::new(env!("CARGO_PKG_NAME"))
+    let command = Command::new(env!("CARGO_PKG_NAME"))
         .version(env!("CARGO_PKG_VERSION"))
         .author(env!("CARGO_PKG_AUTHORS"))
         .about(env!("CARGO_PKG_DESCRIPTION"))
+        .subcommand_negates_reqs(true)
+        .subcommand(
+            Command::new("daemon")
+                .about("Stay connected to PipeWire and reload the config file when it changes"),
+        )
+        .subcommand(
+            Command::new("show")
+                .about("Open a submenu directly, through the daemon when it is running")
+                .arg(
+                    Arg::new("menu")
+                        .required(true)
+                        .value_parser(["device", "volume"])
+                        .help("Submenu to open"),
+                )
+                .arg(Arg::new("target").required(true).help(
+                    "Device name, or @DEFAULT_SINK@ / @DEFAULT_SOURCE@ for the default devices",
+                )),
+        )
+        .subcommand(
+            Command::new("get")
+                .about("Print audio state and exit")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("volume")
+                        .about("Print the volume of a device as shown and as stored by PipeWire")
+                        .arg(
+                            Arg::new("target")
+                                .default_value(ipc::DEFAULT_SINK_TARGET)
+                                .help("Device name, or @DEFAULT_SINK@ / @DEFAULT_SOURCE@"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("get-default")
+                .about("Print the default output or input device and its volume")
+                .arg(
+                    Arg::new("kind")
+                        .required(true)
+                        .value_parser(["sink", "source"])
+                        .help("Default device to print"),
+                ),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("Print devices or streams with their volume, in menu order")
+                .arg(
+                    Arg::new("kind")
+                        .required(true)
+                        .value_parser(["outputs", "inputs", "streams"])
+                        .help("What to list"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with("tsv")
+                        .help("Print a JSON array"),
+                )
+                .arg(
+                    Arg::new("tsv")
+                        .long("tsv")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Print tab separated id, name, description, volume, muted and default"),
+                ),
+        )
+        .subcommand(
+            Command::new("set-volume")
+                .about("Set the volume of a device or stream and exit")
+                .arg(Arg::new("target").required(true).help(
+                    "Node name, description or glob such as \"Focusrite*\", or @DEFAULT_SINK@ / @DEFAULT_SOURCE@",
+                ))
+                .arg(
+                    Arg::new("volume")
+                        .required(true)
+                        .allow_hyphen_values(true)
+                        .value_parser(parse_volume_change)
+                        .help("Percentage to set, or to add or remove with a + or - prefix"),
+                ),
+        )
+        .subcommand(
+            Command::new("status")
+                .about("Print the default devices and playback streams as JSON")
+                .arg(
+                    Arg::new("watch")
+                        .long("watch")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Keep running and print a new line whenever the status changes"),
+                )
+                .arg(
+                    Arg::new("meters")
+                        .long("meters")
+                        .requires("watch")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Include the peak level of each playback stream"),
+                )
+                .arg(
+                    Arg::new("mic")
+                        .long("mic")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Include the applications currently recording from a microphone"),
+                ),
+        )
+        .subcommand(
+            Command::new("top")
+                .about("Show driver and node timings from the PipeWire profiler, like pw-top")
+                .arg(
+                    Arg::new("interval")
+                        .long("interval")
+                        .value_name("MS")
+                        .default_value("1000")
+                        .value_parser(value_parser!(u64).range(100..))
+                        .help("Refresh interval in milliseconds"),
+                ),
+        )
+        .subcommand(
+            Command::new("check-config")
+                .about("Validate the config file and report errors with their location")
+                .arg(
+                    Arg::new("path")
+                        .value_parser(value_parser!(PathBuf))
+                        .help("Config file to check instead of the default location"),
+                ),
+        )
+        .subcommand(
+            Command::new("cycle-sink")
+                .about("Switch the default output to the next device in priority order"),
+        )
+        .subcommand(
+            Command::new("cycle-source")
+                .about("Switch the default input to the next device in priority order"),
+        )
+        .subcommand(
+            Command::new("set-default-sink")
+                .about("Make the matching output the default one")
+                .arg(
+                    Arg::new("target")
+                        .required(true)
+                        .help("Node name, description or glob such as \"*HDMI*\""),
+                ),
+        )
+        .subcommand(
+            Command::new("set-default-source")
+                .about("Make the matching input the default one")
+                .arg(
+                    Arg::new("target")
+                        .required(true)
+                        .help("Node name, description or glob such as \"*USB*\""),
+                ),
+        )
+        .subcommand(
+            Command::new("apply-scene")
+                .about("Apply a scene from the config file and exit")
+                .arg(
+                    Arg::new("name")
+                        .required(true)
+                        .help("Name of the [scene.\"name\"] section to apply"),
+                ),
+        )
+        .subcommand(
+            Command::new("diagnostics")
+                .about("Print how volume can be controlled on each device, for bug reports"),
+        )
+        .subcommand(
+            Command::new("dump")
+                .about("Print the audio graph as JSON, including raw and displayed volumes")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .default_value("json")
+                        .value_parser(DUMP_FORMATS.to_vec())
+                        .help("Output format, dot renders with Graphviz and pw-dump mirrors its JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("record-graph")
+                .about("Record graph updates to a file until interrupted, for bug reports")
+                .arg(
+                    Arg::new("file")
+                        .required(true)
+                        .value_parser(value_parser!(PathBuf))
+                        .help("File to write, replayed with --replay"),
+                ),
+        )
+        .subcommand(
+            Command::new("cleanup").about("Remove all virtual objects created by pwmenu and exit"),
+        )
+        .arg(
+            Arg::new("log_level")
+                .long("log-level")
+                .global(true)
+                .value_parser(["off", "error", "warn", "info", "debug", "trace"])
+                .help("Log verbosity, overriding RUST_LOG [default: error]"),
+        )
+        .arg(
+            Arg::new("log_file")
+                .long("log-file")
+                .global(true)
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .help("Append logs to this file instead of stderr"),
+        )
+        .arg(
+            Arg::new("remote")
+                .long("remote")
+                .global(true)
+                .help("Connect to this PipeWire remote instead of the default one"),
+        )
+        .arg(
+            Arg::new("replay")
+                .long("replay")
+                .global(true)
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .conflicts_with("remote")
+                .help("Replay a recording made with record-graph instead of connecting to PipeWire"),
+        )
         .arg(
             Arg::new("launcher")
                 .short('l')
                 .long("launcher")
-                .required(true)
                 .value_parser(clap::value_parser!(LauncherType))
                 .help("Launcher to use"),
         )
@@ -44,13 +354,18 @@ async fn main() -> Result<()> {
                 .value_parser(validate_launcher_command)
                 .help("Launcher command to use when --launcher is set to custom"),
         )
+        .arg(
+            Arg::new("launcher_args")
+                .long("launcher-args")
+                .value_parser(parse_launcher_args)
+                .help("Extra arguments appended to the built-in launcher command"),
+        )
         .arg(
             Arg::new("icon")
                 .short('i')
                 .long("icon")
-                .value_parser(["font", "xdg"])
-                .default_value("font")
-                .help("Choose the type of icons to use"),
+                .value_parser(["font", "xdg", "none"])
+                .help("Choose the type of icons to use [default: xdg for rofi and fuzzel, font otherwise]"),
         )
         .arg(
             Arg::new("spaces")
@@ -59,6 +374,19 @@ async fn main() -> Result<()> {
                 .default_value("1")
                 .help("Number of spaces between icon and text when using font icons"),
         )
+        .arg(
+            Arg::new("icon_position")
+                .long("icon-position")
+                .value_parser(value_parser!(IconPosition))
+                .default_value("before")
+                .help("Draw font icons before or after the label"),
+        )
+        .arg(
+            Arg::new("icon_separator")
+                .long("icon-separator")
+                .value_name("TEXT")
+                .help("Text between font icons and labels, such as \" │ \" or a tab, instead of --spaces"),
+        )
         .arg(
             Arg::new("menu")
                 .short('m')
@@ -68,6 +396,9 @@ async fn main() -> Result<()> {
                     "input-devices",
                     "output-streams",
                     "input-streams",
+                    "playback",
+                    "recording",
+                    "all",
                 ])
                 .help("Start in the specified root menu"),
         )
@@ -78,34 +409,256 @@ async fn main() -> Result<()> {
                 .default_value("5")
                 .help("Volume adjustment step as percentage (1-25)"),
         )
+        .arg(
+            Arg::new("repeat")
+                .long("repeat")
+                .value_parser(value_parser!(u8).range(2..=20))
+                .help("Offer an entry repeating the last volume step this many times"),
+        )
+        .arg(
+            Arg::new("osd_backend")
+                .long("osd-backend")
+                .value_parser(value_parser!(OsdBackend))
+                .default_value("notify")
+                .help("Show volume changes as notifications, on a wob pipe or with swayosd-client"),
+        )
+        .arg(
+            Arg::new("group_by_device")
+                .long("group-by-device")
+                .action(clap::ArgAction::SetTrue)
+                .help("Group output and input devices under their parent card"),
+        )
+        .arg(
+            Arg::new("show_ids")
+                .long("show-ids")
+                .action(clap::ArgAction::SetTrue)
+                .help("Append PipeWire object ids to menu entries"),
+        )
+        .arg(
+            Arg::new("markup")
+                .long("markup")
+                .action(clap::ArgAction::SetTrue)
+                .help("Style entries with pango markup (rofi only)"),
+        )
+        .arg(
+            Arg::new("volume_icons")
+                .long("volume-icons")
+                .action(clap::ArgAction::SetTrue)
+                .help("Show each device's volume level and mute state as its icon"),
+        )
+        .arg(
+            Arg::new("numbered")
+                .long("numbered")
+                .action(clap::ArgAction::SetTrue)
+                .help("Number menu entries and accept a typed number as selection"),
+        )
         .arg(
             Arg::new("interactive")
                 .long("interactive")
                 .action(clap::ArgAction::SetTrue)
                 .help("Stay in menus after actions and return to previous menu on escape"),
-        )
-        .get_matches();
+        );
+
+    #[cfg(feature = "journald")]
+    let command = command.arg(
+        Arg::new("journald")
+            .long("journald")
+            .global(true)
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with("log_file")
+            .help("Send logs to the systemd journal instead of stderr"),
+    );
 
-    let launcher_type: LauncherType = matches.get_one::<LauncherType>("launcher").unwrap().clone();
+    let matches = match command.try_get_matches() {
+        Ok(matches) => matches,
+        Err(e) if matches!(e.kind(), ErrorKind::DisplayHelp | ErrorKind::DisplayVersion) => {
+            e.exit()
+        }
+        // clap exits with 2 on usage errors, which scripts would take for a missing device
+        Err(e) => {
+            let _ = e.print();
+            std::process::exit(EXIT_FAILURE.into());
+        }
+    };
+
+    init_logging(&matches)?;
+
+    // Only required past the subcommands below, which also run with a broken config
+    let config = Config::load();
+
+    let move_streams = config.as_ref().map_or(false, |config| config.move_streams);
+    let volume_curve = config
+        .as_ref()
+        .map_or(VolumeCurve::default(), |config| config.volume_curve);
+    let backend = match matches.get_one::<PathBuf>("replay") {
+        Some(path) => Backend::Replay {
+            path: path.clone(),
+            volume_curve,
+        },
+        None => Backend::PipeWire {
+            remote: matches.get_one::<String>("remote").cloned(),
+            command_timeout: config.as_ref().map_or(DEFAULT_COMMAND_TIMEOUT, |config| {
+                Duration::from_secs(config.command_timeout)
+            }),
+            volume_curve,
+        },
+    };
+
+    match matches.subcommand() {
+        Some(("cleanup", _)) => return run_cleanup(&backend).await,
+        Some(("diagnostics", _)) => return run_diagnostics(&backend).await,
+        Some(("dump", sub_matches)) => {
+            return run_dump(sub_matches.get_one::<String>("format").unwrap(), &backend).await;
+        }
+        Some(("record-graph", sub_matches)) => {
+            let path = sub_matches.get_one::<PathBuf>("file").unwrap();
+            return run_record_graph(path, &backend).await;
+        }
+        Some(("cycle-sink", _)) => return run_cycle_default(true, move_streams, &backend).await,
+        Some(("cycle-source", _)) => return run_cycle_default(false, move_streams, &backend).await,
+        Some(("set-default-sink", sub_matches)) => {
+            let target = sub_matches.get_one::<String>("target").unwrap();
+            return run_set_default(target, true, move_streams, &backend).await;
+        }
+        Some(("set-default-source", sub_matches)) => {
+            let target = sub_matches.get_one::<String>("target").unwrap();
+            return run_set_default(target, false, move_streams, &backend).await;
+        }
+        Some(("apply-scene", sub_matches)) => {
+            let name = sub_matches.get_one::<String>("name").unwrap();
+            return run_apply_scene(name, &backend).await;
+        }
+        Some(("check-config", sub_matches)) => {
+            return run_check_config(sub_matches.get_one::<PathBuf>("path").cloned());
+        }
+        _ => {}
+    }
+
+    let config = config?;
+
+    match matches.subcommand() {
+        Some(("daemon", _)) => {
+            let menu_options = menu_options(&matches, &config, true)?;
+            let osd_backend = *matches.get_one::<OsdBackend>("osd_backend").unwrap();
+            let controller = Arc::new(
+                backend
+                    .controller()
+                    .await?
+                    .with_max_volume_jump(config.max_volume_jump),
+            );
+            let mut daemon = Daemon::with_controller(controller, config, Arc::new(Icons::new()))
+                .with_menu_options(menu_options)
+                .with_osd_backend(osd_backend);
+            return daemon.run().await;
+        }
+        Some(("status", sub_matches)) => {
+            return run_status(
+                sub_matches.get_flag("watch"),
+                sub_matches.get_flag("meters"),
+                sub_matches.get_flag("mic"),
+                config.volume_scale,
+                &backend,
+            )
+            .await;
+        }
+        Some(("top", sub_matches)) => {
+            let refresh = Duration::from_millis(*sub_matches.get_one::<u64>("interval").unwrap());
+            return run_top(refresh, &backend).await;
+        }
+        Some(("get", sub_matches)) => {
+            if let Some(("volume", volume_matches)) = sub_matches.subcommand() {
+                let target = volume_matches.get_one::<String>("target").unwrap();
+                return run_get_volume(target, config.volume_scale, &backend).await;
+            }
+        }
+        Some(("get-default", sub_matches)) => {
+            let is_output = sub_matches.get_one::<String>("kind").unwrap() == "sink";
+            return run_get_default(is_output, config.volume_scale, &backend).await;
+        }
+        Some(("list", sub_matches)) => {
+            let format = if sub_matches.get_flag("json") {
+                ListFormat::Json
+            } else if sub_matches.get_flag("tsv") {
+                ListFormat::Tsv
+            } else {
+                ListFormat::Text
+            };
+            let kind = sub_matches.get_one::<String>("kind").unwrap();
+            return run_list(kind, format, config.volume_scale, &backend).await;
+        }
+        Some(("set-volume", sub_matches)) => {
+            let target = sub_matches.get_one::<String>("target").unwrap();
+            let change = *sub_matches.get_one::<VolumeChange>("volume").unwrap();
+            return run_set_volume(target, change, &config, &backend).await;
+        }
+        Some(("show", sub_matches)) => {
+            let target = sub_matches.get_one::<String>("target").unwrap().clone();
+            let target = match sub_matches.get_one::<String>("menu").unwrap().as_str() {
+                "device" => ShowTarget::Device(target),
+                _ => ShowTarget::Volume(target),
+            };
+            let osd_backend = *matches.get_one::<OsdBackend>("osd_backend").unwrap();
+            return run_show(
+                target,
+                // The daemon's own launcher settings apply unless given explicitly
+                menu_options(&matches, &config, !ipc::daemon_running())?,
+                osd_backend,
+                &config,
+                &backend,
+            )
+            .await;
+        }
+        _ => {}
+    }
+
+    let launcher_type = resolve_launcher(&matches, &config, true).ok_or_else(|| {
+        anyhow!("No supported launcher found in PATH, select one with --launcher")
+    })?;
 
     let command_str = matches.get_one::<String>("launcher_command").cloned();
 
-    let icon_type = matches.get_one::<String>("icon").unwrap().clone();
+    let icon_type = matches
+        .get_one::<String>("icon")
+        .cloned()
+        .unwrap_or_else(|| launcher_type.default_icon_type().to_string());
 
     let root_menu = matches.get_one::<String>("menu").cloned();
 
     let icons = Arc::new(Icons::new());
-    let menu = Menu::new(launcher_type, icons.clone());
+    let group_by_device = matches.get_flag("group_by_device");
+    let launcher_args = matches
+        .get_one::<Vec<String>>("launcher_args")
+        .cloned()
+        .unwrap_or_default();
+    let menu = Menu::new(launcher_type, icons.clone())
+        .with_group_by_device(group_by_device)
+        .with_show_ids(matches.get_flag("show_ids"))
+        .with_markup(matches.get_flag("markup"))
+        .with_numbered(matches.get_flag("numbered"))
+        .with_volume_icons(matches.get_flag("volume_icons"))
+        .with_launcher_args(launcher_args)
+        .with_volume_scale(config.volume_scale)
+        .with_entry_format(config.entry_format.clone())
+        .with_default_marker(
+            config.default_marker.clone(),
+            config.default_marker_position,
+        )
+        .with_name_width(config.name_width)
+        .with_icon_layout(icon_layout(&matches));
 
     let spaces = matches
         .get_one::<String>("spaces")
         .and_then(|s| s.parse::<usize>().ok())
         .ok_or_else(|| anyhow!("Invalid value for --spaces. Must be a positive integer."))?;
 
-    let volume_step = matches.get_one::<u8>("volume_step").copied().unwrap() as f32 / 100.0;
+    let volume_step = resolve_volume_step(&matches, &config) as f32 / 100.0;
 
     let interactive = matches.get_flag("interactive");
 
+    let repeat = matches.get_one::<u8>("repeat").copied();
+
+    let osd_backend = *matches.get_one::<OsdBackend>("osd_backend").unwrap();
+
     run_app_loop(
         &menu,
         &command_str,
@@ -115,6 +668,10 @@ async fn main() -> Result<()> {
         root_menu,
         volume_step,
         interactive,
+        repeat,
+        osd_backend,
+        &config,
+        &backend,
     )
     .await?;
 
@@ -131,11 +688,59 @@ async fn run_app_loop(
     root_menu: Option<String>,
     volume_step: f32,
     interactive: bool,
+    repeat: Option<u8>,
+    osd_backend: OsdBackend,
+    config: &Config,
+    backend: &Backend,
 ) -> Result<()> {
-    let mut app = App::new(menu.clone(), icons.clone(), volume_step, interactive).await?;
+    // A replayed recording is someone else's setup, so it neither reads nor writes the cache
+    let use_cache = config.cache_graph && matches!(backend, Backend::PipeWire { .. });
+    let mut controller = backend
+        .controller()
+        .await?
+        .with_max_volume_jump(config.max_volume_jump);
+    if let Some(graph) = cache::load().filter(|_| use_cache) {
+        controller = controller.with_cached_graph(graph);
+    }
+    let controller = Arc::new(controller);
+    if let Err(e) = controller
+        .configure_restoration(config.restoration_settings())
+        .await
+    {
+        eprintln!("Failed to configure default restoration: {e}");
+    }
+    if let Err(e) = controller.sync_preferred_nodes().await {
+        eprintln!("Failed to set preferred nodes: {e}");
+    }
+    let mut app = App::with_controller(controller.clone(), icons.clone(), volume_step, interactive)
+        .with_move_streams(config.move_streams)
+        .with_allow_card_off(config.allow_card_off)
+        .with_show_unavailable_profiles(config.show_unavailable_profiles)
+        .with_repeat(repeat)
+        .with_osd_backend(osd_backend)
+        .with_volume_scale(config.volume_scale)
+        .with_max_input_volume(config.max_input_volume)
+        .with_mute_notifications(config.notify_mute, config.mute_urgency)
+        .with_mute_at_zero(config.mute_at_zero)
+        .with_scenes(config.scenes.clone());
 
-    let result = if let Some(ref menu_name) = root_menu {
+    // A running daemon already reports every change, including those made from this menu
+    if ipc::daemon_running() {
+        app = app.with_change_notifications(!config.notify_changes);
+    } else {
+        app = app.with_hooks(config.hooks.clone());
+    }
+
+    // The cached devices stand in until PipeWire answers; other menus would be empty
+    let lists_devices = matches!(
+        root_menu.as_deref(),
+        None | Some("output-devices" | "input-devices" | "all")
+    );
+    if !lists_devices || !controller.is_showing_cache() {
         app.wait_for_initialization().await?;
+    }
+
+    let result = if let Some(ref menu_name) = root_menu {
         match menu_name.as_str() {
             "output-devices" => {
                 app.run_output_device_menu(menu, command_str, icon_type, spaces)
@@ -145,23 +750,772 @@ async fn run_app_loop(
                 app.run_input_device_menu(menu, command_str, icon_type, spaces)
                     .await
             }
-            "output-streams" => {
+            "output-streams" | "playback" => {
                 app.run_output_streams_menu(menu, command_str, icon_type, spaces)
                     .await
             }
-            "input-streams" => {
+            "input-streams" | "recording" => {
                 app.run_input_streams_menu(menu, command_str, icon_type, spaces)
                     .await
             }
+            "all" => {
+                app.run_all_devices_menu(menu, command_str, icon_type, spaces)
+                    .await
+            }
             _ => Err(anyhow!("Invalid menu value: {menu_name}")),
         }
     } else {
         app.run(menu, command_str, icon_type, spaces).await
     };
 
+    // A replayed recording holds someone else's volumes
+    if config.save_volumes && matches!(backend, Backend::PipeWire { .. }) {
+        if let Err(err) = app.save_volumes() {
+            eprintln!("Failed to save volumes: {err}");
+        }
+    }
+
+    if use_cache && !controller.is_showing_cache() {
+        if let Err(err) = cache::save(&controller.get_graph()) {
+            eprintln!("Failed to save graph cache: {err}");
+        }
+    }
+
+    if config.cleanup_on_exit {
+        if let Err(err) = app.cleanup_managed_objects().await {
+            eprintln!("Failed to clean up managed objects: {err}");
+        }
+    }
+
+    if let Err(err) = app.shutdown().await {
+        eprintln!("{err:#}");
+    }
+
     if let Err(err) = result {
-        return Err(anyhow!("Fatal error in application: {err}"));
+        return Err(err.context("Fatal error in application"));
+    }
+
+    if !menu.selection_made() {
+        return Err(Cancelled.into());
     }
 
     Ok(())
 }
+
+fn resolve_volume_step(matches: &ArgMatches, config: &Config) -> u8 {
+    let cli_volume_step = matches.get_one::<u8>("volume_step").copied().unwrap();
+    match (matches.value_source("volume_step"), config.volume_step) {
+        (Some(ValueSource::CommandLine), _) | (_, None) => cli_volume_step,
+        (_, Some(config_step)) => config_step,
+    }
+}
+
+/// The launcher given with `--launcher`, or with `detect` the first one of the configured
+/// order found in `PATH`.
+fn resolve_launcher(matches: &ArgMatches, config: &Config, detect: bool) -> Option<LauncherType> {
+    matches
+        .get_one::<LauncherType>("launcher")
+        .cloned()
+        .or_else(|| {
+            if detect {
+                LauncherType::detect(&config.launcher_order)
+            } else {
+                None
+            }
+        })
+}
+
+fn menu_options(
+    matches: &ArgMatches,
+    config: &Config,
+    detect: bool,
+) -> Result<Option<MenuOptions>> {
+    let Some(launcher) = resolve_launcher(matches, config, detect) else {
+        return Ok(None);
+    };
+
+    let spaces = matches
+        .get_one::<String>("spaces")
+        .and_then(|s| s.parse::<usize>().ok())
+        .ok_or_else(|| anyhow!("Invalid value for --spaces. Must be a positive integer."))?;
+
+    Ok(Some(MenuOptions {
+        launcher,
+        launcher_command: matches.get_one::<String>("launcher_command").cloned(),
+        icon: matches.get_one::<String>("icon").cloned(),
+        spaces,
+        volume_step: resolve_volume_step(matches, config),
+        interactive: matches.get_flag("interactive"),
+        repeat: matches.get_one::<u8>("repeat").copied(),
+        launcher_args: matches
+            .get_one::<Vec<String>>("launcher_args")
+            .cloned()
+            .unwrap_or_default(),
+        show_ids: matches.get_flag("show_ids"),
+        markup: matches.get_flag("markup"),
+        numbered: matches.get_flag("numbered"),
+        volume_icons: matches.get_flag("volume_icons"),
+        icon_layout: icon_layout(matches),
+    }))
+}
+
+fn icon_layout(matches: &ArgMatches) -> IconLayout {
+    IconLayout {
+        position: *matches.get_one::<IconPosition>("icon_position").unwrap(),
+        // Shells make typing a tab awkward, so `\t` is accepted as one
+        separator: matches
+            .get_one::<String>("icon_separator")
+            .map(|separator| separator.replace("\\t", "\t")),
+    }
+}
+
+async fn run_show(
+    target: ShowTarget,
+    options: Option<MenuOptions>,
+    osd_backend: OsdBackend,
+    config: &Config,
+    backend: &Backend,
+) -> Result<()> {
+    if ipc::daemon_running() {
+        let response = ipc::send(&Request::Show { target, options }).await?;
+        return match response.error {
+            Some(error) => Err(anyhow!(error)),
+            None => Ok(()),
+        };
+    }
+
+    let options = options.ok_or_else(|| {
+        anyhow!("No supported launcher found in PATH, select one with --launcher")
+    })?;
+    let icons = Arc::new(Icons::new());
+    let menu = Menu::new(options.launcher.clone(), icons.clone())
+        .with_volume_scale(config.volume_scale)
+        .with_launcher_args(options.launcher_args.clone())
+        .with_show_ids(options.show_ids)
+        .with_markup(options.markup)
+        .with_numbered(options.numbered)
+        .with_volume_icons(options.volume_icons)
+        .with_entry_format(config.entry_format.clone())
+        .with_default_marker(
+            config.default_marker.clone(),
+            config.default_marker_position,
+        )
+        .with_name_width(config.name_width)
+        .with_icon_layout(options.icon_layout.clone());
+
+    let controller = Arc::new(
+        backend
+            .controller()
+            .await?
+            .with_max_volume_jump(config.max_volume_jump),
+    );
+    let mut app = App::with_controller(
+        controller,
+        icons,
+        options.volume_step as f32 / 100.0,
+        options.interactive,
+    )
+    .with_move_streams(config.move_streams)
+    .with_allow_card_off(config.allow_card_off)
+    .with_show_unavailable_profiles(config.show_unavailable_profiles)
+    .with_repeat(options.repeat)
+    .with_osd_backend(osd_backend)
+    .with_volume_scale(config.volume_scale)
+    .with_max_input_volume(config.max_input_volume)
+    .with_mute_notifications(config.notify_mute, config.mute_urgency)
+    .with_mute_at_zero(config.mute_at_zero)
+    .with_hooks(config.hooks.clone());
+
+    app.wait_for_initialization().await?;
+    app.run_show(
+        &menu,
+        &options.launcher_command,
+        &options.icon_type(),
+        options.spaces,
+        &target,
+    )
+    .await?;
+
+    if let Err(err) = app.shutdown().await {
+        eprintln!("{err:#}");
+    }
+
+    if !menu.selection_made() {
+        return Err(Cancelled.into());
+    }
+
+    Ok(())
+}
+
+async fn run_diagnostics(backend: &Backend) -> Result<()> {
+    let controller = backend.controller().await?;
+    controller.wait_for_initialization().await?;
+
+    let graph = controller.get_graph();
+    if !graph.session_manager {
+        println!("No session manager is running: default devices cannot be changed and only nodes are listed\n");
+    }
+
+    let mut devices: Vec<_> = graph.devices.values().collect();
+    devices.sort_by_key(|device| device.id);
+
+    let rows: Vec<[String; 7]> = devices
+        .into_iter()
+        .map(|device| {
+            [
+                device.id.to_string(),
+                device
+                    .description
+                    .clone()
+                    .unwrap_or_else(|| device.name.clone()),
+                if device.has_route_volume {
+                    "route"
+                } else {
+                    "node"
+                }
+                .to_string(),
+                device.route_count.to_string(),
+                device.profiles.len().to_string(),
+                device.nodes.len().to_string(),
+                format!(
+                    "{}/{}",
+                    device.output_channel_count, device.input_channel_count
+                ),
+            ]
+        })
+        .collect();
+
+    let header = [
+        "ID", "DEVICE", "VOLUME", "ROUTES", "PROFILES", "NODES", "CHANNELS",
+    ]
+    .map(String::from);
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| {
+            rows.iter()
+                .chain([&header])
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    for row in [&header].into_iter().chain(&rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{cell:<width$}"))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+
+    shutdown(&controller).await;
+
+    Ok(())
+}
+
+async fn run_cleanup(backend: &Backend) -> Result<()> {
+    let controller = backend.controller().await?;
+    controller.wait_for_initialization().await?;
+
+    let count = controller.remove_all_managed_objects().await?;
+    println!(
+        "{}",
+        t!("notifications.pw.managed_objects_cleaned", count = count)
+    );
+
+    shutdown(&controller).await;
+
+    Ok(())
+}
+
+async fn run_get_volume(target: &str, scale: VolumeScale, backend: &Backend) -> Result<()> {
+    let controller = backend.controller().await?;
+    controller.wait_for_initialization().await?;
+
+    let node = resolve_target_node(&controller, target)?;
+    let raw = node.volume.raw();
+    let db = if raw > 0.0 {
+        format!("{:.2}", 20.0 * raw.log10())
+    } else {
+        "-inf".to_string()
+    };
+
+    println!("node={}", node.name);
+    println!("volume={}", node.volume.percent_in(scale));
+    println!("scale={}", scale.as_str());
+    println!(
+        "cubic={}",
+        (VolumeResolver::apply_cubic_scaling(raw) * 100.0).round()
+    );
+    println!("linear={}", node.volume.percent_in(VolumeScale::Linear));
+    println!("raw={raw:.6}");
+    println!("db={db}");
+    println!("muted={}", node.volume.muted);
+
+    shutdown(&controller).await;
+
+    Ok(())
+}
+
+async fn run_get_default(is_output: bool, scale: VolumeScale, backend: &Backend) -> Result<()> {
+    let controller = backend.controller().await?;
+    controller.wait_for_initialization().await?;
+
+    let target = if is_output {
+        ipc::DEFAULT_SINK_TARGET
+    } else {
+        ipc::DEFAULT_SOURCE_TARGET
+    };
+    let node = resolve_target_node(&controller, target)?;
+
+    println!("id={}", node.id);
+    println!("node={}", node.name);
+    println!("description={}", controller.get_node_base_name(&node));
+    println!("volume={}", node.volume.percent_in(scale));
+    println!("muted={}", node.volume.muted);
+
+    shutdown(&controller).await;
+
+    Ok(())
+}
+
+enum ListFormat {
+    Text,
+    Json,
+    Tsv,
+}
+
+async fn run_list(
+    kind: &str,
+    format: ListFormat,
+    scale: VolumeScale,
+    backend: &Backend,
+) -> Result<()> {
+    let controller = backend.controller().await?;
+    controller.wait_for_initialization().await?;
+
+    let nodes = match kind {
+        "outputs" => controller.get_output_nodes(),
+        "inputs" => controller.get_input_nodes(),
+        _ => {
+            let mut streams = controller.get_output_streams();
+            streams.extend(controller.get_input_streams());
+            streams.sort_by_key(|node| node.id);
+            streams
+        }
+    };
+    let entries: Vec<ListEntry> = nodes
+        .iter()
+        .map(|node| ListEntry::new(&controller, node, scale))
+        .collect();
+
+    match format {
+        ListFormat::Json => println!("{}", serde_json::to_string(&entries)?),
+        ListFormat::Tsv => entries
+            .iter()
+            .for_each(|entry| println!("{}", entry.to_tsv())),
+        ListFormat::Text => {
+            for entry in &entries {
+                println!(
+                    "{} {:>4} {:>4}%{} {}",
+                    if entry.default { '*' } else { ' ' },
+                    entry.id,
+                    entry.volume,
+                    if entry.muted { " (muted)" } else { "" },
+                    entry.description
+                );
+            }
+        }
+    }
+
+    shutdown(&controller).await;
+
+    Ok(())
+}
+
+async fn run_set_volume(
+    target: &str,
+    change: VolumeChange,
+    config: &Config,
+    backend: &Backend,
+) -> Result<()> {
+    let controller = backend
+        .controller()
+        .await?
+        .with_max_volume_jump(config.max_volume_jump);
+    controller.wait_for_initialization().await?;
+
+    let node = resolve_target_node(&controller, target)?;
+    let max_input_volume = f32::from(config.max_input_volume) / 100.0;
+    // Typed percentages follow the volume scale, steps go along the volume curve like the menu's
+    let volume = match change {
+        VolumeChange::Set(percent) => config
+            .volume_scale
+            .from_display(controller.volume_curve(), f32::from(percent) / 100.0)
+            .min(node.max_volume(max_input_volume)),
+        VolumeChange::Raise(step) => {
+            (node.volume.linear + f32::from(step) / 100.0).min(node.volume_limit(max_input_volume))
+        }
+        VolumeChange::Lower(step) => (node.volume.linear - f32::from(step) / 100.0)
+            .max(0.0)
+            .min(node.volume_limit(max_input_volume)),
+    };
+    let result = if config.mute_at_zero {
+        controller
+            .set_volume_muting_at_zero(
+                &node,
+                volume,
+                matches!(change, VolumeChange::Raise(_)),
+                node.max_volume(max_input_volume),
+            )
+            .await
+            .map(|_| ())
+    } else {
+        controller.set_volume(node.id, volume).await
+    };
+
+    shutdown(&controller).await;
+
+    result
+}
+
+async fn run_status(
+    watch: bool,
+    meters: bool,
+    mic: bool,
+    scale: VolumeScale,
+    backend: &Backend,
+) -> Result<()> {
+    let controller = backend.controller().await?;
+    controller.wait_for_initialization().await?;
+
+    let capture = |levels: &HashMap<u32, InputLevel>| {
+        let status = Status::capture(&controller, scale, levels);
+        if mic {
+            status.with_mic(&controller)
+        } else {
+            status
+        }
+    };
+
+    if !watch {
+        println!("{}", serde_json::to_string(&capture(&HashMap::new()))?);
+        shutdown(&controller).await;
+        return Ok(());
+    }
+
+    let mut graph_rx = controller.subscribe();
+    let mut refresh = interval(METER_REFRESH_INTERVAL);
+    let mut levels = HashMap::new();
+    let mut last_line = String::new();
+
+    loop {
+        if meters {
+            levels = controller
+                .sync_stream_meters(status::playback_stream_ids(&controller))
+                .await?;
+        }
+
+        let line = serde_json::to_string(&capture(&levels))?;
+        if line != last_line {
+            println!("{line}");
+            last_line = line;
+        }
+
+        // Levels change without graph updates, so poll them while meters run
+        if meters {
+            tokio::select! {
+                result = graph_rx.changed() => result?,
+                _ = refresh.tick() => {}
+            }
+        } else {
+            graph_rx.changed().await?;
+        }
+    }
+}
+
+async fn run_dump(format: &str, backend: &Backend) -> Result<()> {
+    let controller = backend.controller().await?;
+    controller.wait_for_initialization().await?;
+
+    let graph = controller.get_graph();
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&*graph)?),
+        "dot" => print!("{}", dump::to_dot(&graph)),
+        "pw-dump" => println!(
+            "{}",
+            serde_json::to_string_pretty(&dump::to_pw_dump(&graph))?
+        ),
+        _ => return Err(anyhow!("Unsupported dump format: {format}")),
+    }
+
+    shutdown(&controller).await;
+
+    Ok(())
+}
+
+async fn run_record_graph(path: &Path, backend: &Backend) -> Result<()> {
+    let file =
+        fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut recorder = GraphRecorder::new(io::BufWriter::new(file));
+
+    let controller = backend.controller().await?;
+    controller.wait_for_initialization().await?;
+
+    let mut graph_rx = controller.subscribe();
+    eprintln!(
+        "Recording graph updates to {}, press Ctrl+C to stop",
+        path.display()
+    );
+
+    loop {
+        let graph = graph_rx.borrow_and_update().clone();
+        recorder.record(&graph)?;
+
+        tokio::select! {
+            result = graph_rx.changed() => result?,
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    eprintln!("Recorded {} frames", recorder.frames());
+    shutdown(&controller).await;
+
+    Ok(())
+}
+
+async fn run_top(refresh: Duration, backend: &Backend) -> Result<()> {
+    let controller = backend.controller().await?;
+    controller.wait_for_initialization().await?;
+
+    let stats = controller.start_profiler().await?;
+    let mut ticks = interval(refresh);
+
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {
+                // Clear the terminal and redraw from the top left corner
+                print!("\x1b[H\x1b[2J{}", top::render(&stats.snapshot(), &controller));
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    controller.stop_profiler().await?;
+    shutdown(&controller).await;
+
+    Ok(())
+}
+
+async fn run_cycle_default(is_output: bool, move_streams: bool, backend: &Backend) -> Result<()> {
+    let controller = backend.controller().await?;
+    controller.wait_for_initialization().await?;
+
+    let device_type = if is_output { "output" } else { "input" };
+    let node = controller
+        .get_next_default_node(is_output)
+        .ok_or_else(|| anyhow!("No {device_type} device available"))?;
+
+    let display_name = apply_default(&controller, &node, is_output, move_streams).await?;
+
+    NotificationManager::new(Arc::new(Icons::new()))
+        .send_default_changed_notification(device_type, &display_name)?;
+
+    shutdown(&controller).await;
+
+    Ok(())
+}
+
+async fn run_set_default(
+    target: &str,
+    is_output: bool,
+    move_streams: bool,
+    backend: &Backend,
+) -> Result<()> {
+    let controller = backend.controller().await?;
+    controller.wait_for_initialization().await?;
+
+    let node = controller
+        .find_device(target, is_output)
+        .ok_or_else(|| PwError::DeviceNotFound(target.to_string()))?;
+    let result = apply_default(&controller, &node, is_output, move_streams).await;
+
+    shutdown(&controller).await;
+
+    result.map(|_| ())
+}
+
+/// Makes `node` the default device, moving streams along when configured, and prints the
+/// name it was set by.
+async fn apply_default(
+    controller: &Controller,
+    node: &Node,
+    is_output: bool,
+    move_streams: bool,
+) -> Result<String> {
+    let mut steps = vec![if is_output {
+        BatchStep::SetDefaultSink { node_id: node.id }
+    } else {
+        BatchStep::SetDefaultSource { node_id: node.id }
+    }];
+    if move_streams {
+        steps.push(BatchStep::MoveStreams { node_id: node.id });
+    }
+    controller.batch(steps).await?;
+
+    let display_name = controller.get_node_base_name(node);
+    println!(
+        "{}",
+        t!(
+            "notifications.pw.default_set",
+            device_type = if is_output { "output" } else { "input" },
+            device_name = display_name
+        )
+    );
+
+    Ok(display_name)
+}
+
+async fn run_apply_scene(name: &str, backend: &Backend) -> Result<()> {
+    let config = Config::load()?;
+    let scene = config.scenes.get(name).ok_or_else(|| {
+        let available: Vec<&str> = config.scenes.keys().map(String::as_str).collect();
+        anyhow!(
+            "Scene \"{name}\" not found (available: {})",
+            if available.is_empty() {
+                "none".to_string()
+            } else {
+                available.join(", ")
+            }
+        )
+    })?;
+
+    let controller = backend
+        .controller()
+        .await?
+        .with_max_volume_jump(config.max_volume_jump);
+    controller.wait_for_initialization().await?;
+    controller.apply_scene(scene).await?;
+
+    let msg = t!("notifications.pw.scene_applied", name = name);
+    println!("{msg}");
+    NotificationManager::new(Arc::new(Icons::new())).send_notification(
+        None,
+        Some(msg.to_string()),
+        Some("scene"),
+        None,
+    )?;
+
+    shutdown(&controller).await;
+
+    Ok(())
+}
+
+fn init_logging(matches: &ArgMatches) -> Result<()> {
+    // Without RUST_LOG or --log-level, only errors are logged
+    let filter = match matches.get_one::<String>("log_level") {
+        Some(level) => EnvFilter::new(level),
+        None => EnvFilter::from_default_env(),
+    };
+
+    #[cfg(feature = "journald")]
+    if matches.get_flag("journald") {
+        return tracing_subscriber::registry()
+            .with(
+                tracing_journald::layer()
+                    .context("Failed to connect to the journal")?
+                    .with_syslog_identifier(env!("CARGO_PKG_NAME").to_string())
+                    .with_filter(filter),
+            )
+            .try_init()
+            .map_err(|e| anyhow!(e));
+    }
+
+    let log_file = matches.get_one::<PathBuf>("log_file");
+    let writer = match log_file {
+        Some(path) => {
+            let file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open log file {}", path.display()))?;
+            BoxMakeWriter::new(Mutex::new(file))
+        }
+        None => BoxMakeWriter::new(std::io::stderr),
+    };
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(log_file.is_none())
+        .try_init()
+        .map_err(|e| anyhow!(e))
+}
+
+/// Where commands get the audio graph from.
+enum Backend {
+    PipeWire {
+        remote: Option<String>,
+        command_timeout: Duration,
+        volume_curve: VolumeCurve,
+    },
+    Replay {
+        path: PathBuf,
+        volume_curve: VolumeCurve,
+    },
+}
+
+impl Backend {
+    async fn controller(&self) -> Result<Controller> {
+        let (controller, volume_curve) = match self {
+            Backend::PipeWire {
+                remote,
+                command_timeout,
+                volume_curve,
+            } => (
+                Controller::connect(remote.clone(), *command_timeout).await?,
+                volume_curve,
+            ),
+            Backend::Replay { path, volume_curve } => (Controller::replay(path)?, volume_curve),
+        };
+        Ok(controller.with_volume_curve(*volume_curve))
+    }
+}
+
+// Lets the PipeWire thread disconnect before the process exits
+async fn shutdown(controller: &Controller) {
+    if let Err(err) = controller.shutdown().await {
+        eprintln!("{err:#}");
+    }
+}
+
+fn run_check_config(path: Option<PathBuf>) -> Result<()> {
+    let path = path
+        .or_else(Config::path)
+        .ok_or_else(|| anyhow!("Unable to determine config file location"))?;
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| anyhow!("Failed to read config file {}: {e}", path.display()))?;
+
+    let issues = Config::check(&content);
+    if issues.is_empty() {
+        println!("{}: OK", path.display());
+        return Ok(());
+    }
+
+    for issue in &issues {
+        eprintln!(
+            "{}:{}:{}: {}",
+            path.display(),
+            issue.line,
+            issue.column,
+            issue.message
+        );
+    }
+
+    Err(anyhow!(
+        "Found {} problem(s) in {}",
+        issues.len(),
+        path.display()
+    ))
+}
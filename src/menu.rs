@@ -1,19 +1,32 @@
 use crate::{
-    icons::Icons,
-    launcher::{Launcher, LauncherType},
-    pw::{controller::Controller, nodes::Node, Profile},
+    icons::{IconLayout, Icons},
+    launcher::{Launcher, LauncherSelection, LauncherType},
+    pw::{
+        controller::Controller,
+        nodes::{Node, NodeType},
+        ConnectionStatus, PortMapping, Profile, VolumeScale,
+    },
 };
 use anyhow::Result;
 use rust_i18n::t;
+use serde::Deserialize;
 use std::borrow::Cow;
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 
 #[derive(Debug, Clone)]
 pub enum MainMenuOptions {
     ShowOutputDeviceMenu,
     ShowInputDeviceMenu,
-    ShowOutputStreamsMenu,
-    ShowInputStreamsMenu,
+    ShowPlaybackMenu,
+    ShowRecordingMenu,
+    RaiseDefaultOutputVolume,
+    LowerDefaultOutputVolume,
+    MuteDefaultOutput,
+    UnmuteDefaultOutput,
+    ShowScenesMenu,
     ShowSettingsMenu,
 }
 
@@ -26,12 +39,25 @@ impl MainMenuOptions {
             s if s == t!("menus.main.options.input_devices.name") => {
                 Some(MainMenuOptions::ShowInputDeviceMenu)
             }
-            s if s == t!("menus.main.options.output_streams.name") => {
-                Some(MainMenuOptions::ShowOutputStreamsMenu)
+            s if s == t!("menus.main.options.playback.name") => {
+                Some(MainMenuOptions::ShowPlaybackMenu)
+            }
+            s if s == t!("menus.main.options.recording.name") => {
+                Some(MainMenuOptions::ShowRecordingMenu)
+            }
+            s if s == t!("menus.main.options.output_volume_up.name") => {
+                Some(MainMenuOptions::RaiseDefaultOutputVolume)
+            }
+            s if s == t!("menus.main.options.output_volume_down.name") => {
+                Some(MainMenuOptions::LowerDefaultOutputVolume)
             }
-            s if s == t!("menus.main.options.input_streams.name") => {
-                Some(MainMenuOptions::ShowInputStreamsMenu)
+            s if s == t!("menus.main.options.output_mute.name") => {
+                Some(MainMenuOptions::MuteDefaultOutput)
             }
+            s if s == t!("menus.main.options.output_unmute.name") => {
+                Some(MainMenuOptions::UnmuteDefaultOutput)
+            }
+            s if s == t!("menus.main.options.scenes.name") => Some(MainMenuOptions::ShowScenesMenu),
             s if s == t!("menus.main.options.settings.name") => {
                 Some(MainMenuOptions::ShowSettingsMenu)
             }
@@ -43,8 +69,17 @@ impl MainMenuOptions {
         match self {
             MainMenuOptions::ShowOutputDeviceMenu => t!("menus.main.options.output_devices.name"),
             MainMenuOptions::ShowInputDeviceMenu => t!("menus.main.options.input_devices.name"),
-            MainMenuOptions::ShowOutputStreamsMenu => t!("menus.main.options.output_streams.name"),
-            MainMenuOptions::ShowInputStreamsMenu => t!("menus.main.options.input_streams.name"),
+            MainMenuOptions::ShowPlaybackMenu => t!("menus.main.options.playback.name"),
+            MainMenuOptions::ShowRecordingMenu => t!("menus.main.options.recording.name"),
+            MainMenuOptions::RaiseDefaultOutputVolume => {
+                t!("menus.main.options.output_volume_up.name")
+            }
+            MainMenuOptions::LowerDefaultOutputVolume => {
+                t!("menus.main.options.output_volume_down.name")
+            }
+            MainMenuOptions::MuteDefaultOutput => t!("menus.main.options.output_mute.name"),
+            MainMenuOptions::UnmuteDefaultOutput => t!("menus.main.options.output_unmute.name"),
+            MainMenuOptions::ShowScenesMenu => t!("menus.main.options.scenes.name"),
             MainMenuOptions::ShowSettingsMenu => t!("menus.main.options.settings.name"),
         }
     }
@@ -53,6 +88,7 @@ impl MainMenuOptions {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SettingsMenuOptions {
     SetSampleRate,
+    ManageObjects,
     Back,
 }
 
@@ -62,6 +98,9 @@ impl SettingsMenuOptions {
             s if s == t!("menus.settings.options.set_sample_rate.name") => {
                 Some(SettingsMenuOptions::SetSampleRate)
             }
+            s if s == t!("menus.settings.options.manage_objects.name") => {
+                Some(SettingsMenuOptions::ManageObjects)
+            }
             s if s == t!("menus.common.back") => Some(SettingsMenuOptions::Back),
             _ => None,
         }
@@ -72,11 +111,100 @@ impl SettingsMenuOptions {
             SettingsMenuOptions::SetSampleRate => {
                 t!("menus.settings.options.set_sample_rate.name")
             }
+            SettingsMenuOptions::ManageObjects => {
+                t!("menus.settings.options.manage_objects.name")
+            }
             SettingsMenuOptions::Back => t!("menus.common.back"),
         }
     }
 }
 
+#[derive(Debug, Clone)]
+pub enum ManagedObjectsMenuOptions {
+    CreateVirtualSink,
+    Object(String),
+}
+
+impl ManagedObjectsMenuOptions {
+    pub fn from_string(option: &str) -> Option<Self> {
+        match option {
+            s if s == t!("menus.managed_objects.options.create_virtual_sink.name") => {
+                Some(ManagedObjectsMenuOptions::CreateVirtualSink)
+            }
+            other => Some(ManagedObjectsMenuOptions::Object(other.to_string())),
+        }
+    }
+
+    pub fn to_str(&self) -> Cow<'static, str> {
+        match self {
+            ManagedObjectsMenuOptions::CreateVirtualSink => {
+                t!("menus.managed_objects.options.create_virtual_sink.name")
+            }
+            ManagedObjectsMenuOptions::Object(_) => {
+                t!("menus.managed_objects.options.object.name")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ManagedObjectMenuOptions {
+    Rename,
+    Remove,
+    Back,
+}
+
+impl ManagedObjectMenuOptions {
+    pub fn from_string(option: &str) -> Option<Self> {
+        match option {
+            s if s == t!("menus.managed_object.options.rename.name") => {
+                Some(ManagedObjectMenuOptions::Rename)
+            }
+            s if s == t!("menus.managed_object.options.remove.name") => {
+                Some(ManagedObjectMenuOptions::Remove)
+            }
+            s if s == t!("menus.common.back") => Some(ManagedObjectMenuOptions::Back),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(&self) -> Cow<'static, str> {
+        match self {
+            ManagedObjectMenuOptions::Rename => t!("menus.managed_object.options.rename.name"),
+            ManagedObjectMenuOptions::Remove => t!("menus.managed_object.options.remove.name"),
+            ManagedObjectMenuOptions::Back => t!("menus.common.back"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScenesMenuOptions {
+    Scene(String),
+    Device(u32),
+    Back,
+}
+
+impl ScenesMenuOptions {
+    pub fn from_string_with_entries(
+        option: &str,
+        scenes: &[String],
+        devices: &[(u32, String)],
+    ) -> Option<Self> {
+        if option == t!("menus.common.back") {
+            return Some(ScenesMenuOptions::Back);
+        }
+
+        if let Some(scene) = scenes.iter().find(|scene| scene.as_str() == option) {
+            return Some(ScenesMenuOptions::Scene(scene.clone()));
+        }
+
+        devices
+            .iter()
+            .find(|(_, name)| name == option)
+            .map(|(id, _)| ScenesMenuOptions::Device(*id))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum StreamMenuOptions {
     RefreshList,
@@ -125,6 +253,58 @@ impl OutputDeviceMenuOptions {
     }
 }
 
+#[derive(Debug, Clone)]
+/// Device list actions bound to rofi's `kb-custom-1` and `kb-custom-2` keys, applied to the
+/// highlighted entry without opening its submenu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceQuickAction {
+    ToggleMute,
+    SetDefault,
+}
+
+impl DeviceQuickAction {
+    pub fn from_custom_key(key: u8) -> Option<Self> {
+        match key {
+            1 => Some(DeviceQuickAction::ToggleMute),
+            2 => Some(DeviceQuickAction::SetDefault),
+            _ => None,
+        }
+    }
+}
+
+pub enum AllDevicesMenuOptions {
+    RefreshList,
+    OutputHeader,
+    InputHeader,
+    Device(String),
+}
+
+impl AllDevicesMenuOptions {
+    pub fn from_string(option: &str) -> Option<Self> {
+        match option {
+            s if s == t!("menus.all_devices.options.refresh.name") => {
+                Some(AllDevicesMenuOptions::RefreshList)
+            }
+            s if s == t!("menus.all_devices.headers.output") => {
+                Some(AllDevicesMenuOptions::OutputHeader)
+            }
+            s if s == t!("menus.all_devices.headers.input") => {
+                Some(AllDevicesMenuOptions::InputHeader)
+            }
+            other => Some(AllDevicesMenuOptions::Device(other.to_string())),
+        }
+    }
+
+    pub fn to_str(&self) -> Cow<'static, str> {
+        match self {
+            AllDevicesMenuOptions::RefreshList => t!("menus.all_devices.options.refresh.name"),
+            AllDevicesMenuOptions::OutputHeader => t!("menus.all_devices.headers.output"),
+            AllDevicesMenuOptions::InputHeader => t!("menus.all_devices.headers.input"),
+            AllDevicesMenuOptions::Device(_) => t!("menus.all_devices.options.device.name"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum InputDeviceMenuOptions {
     RefreshList,
@@ -152,6 +332,8 @@ impl InputDeviceMenuOptions {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ProfileMenuOptions {
     SelectProfile(u32),
+    Unavailable(u32),
+    TurnOff,
     Back,
 }
 
@@ -160,6 +342,20 @@ impl ProfileMenuOptions {
         if option == t!("menus.common.back") {
             return Some(ProfileMenuOptions::Back);
         }
+        if option == t!("menus.profile.options.turn_off.name") {
+            return Some(ProfileMenuOptions::TurnOff);
+        }
+
+        if let Some(profile) = profiles.iter().find(|profile| {
+            !profile.is_available()
+                && option
+                    == t!(
+                        "menus.profile.unavailable",
+                        profile_name = &profile.description
+                    )
+        }) {
+            return Some(ProfileMenuOptions::Unavailable(profile.index));
+        }
 
         profiles
             .iter()
@@ -173,6 +369,10 @@ pub enum DeviceMenuOptions {
     SetDefault,
     SwitchProfile,
     AdjustVolume,
+    Connections,
+    TestSpeakers,
+    TestMicrophone,
+    Properties,
     Back,
 }
 
@@ -188,6 +388,18 @@ impl DeviceMenuOptions {
             s if s == t!("menus.device.options.adjust_volume.name") => {
                 Some(DeviceMenuOptions::AdjustVolume)
             }
+            s if s == t!("menus.device.options.connections.name") => {
+                Some(DeviceMenuOptions::Connections)
+            }
+            s if s == t!("menus.device.options.test_speakers.name") => {
+                Some(DeviceMenuOptions::TestSpeakers)
+            }
+            s if s == t!("menus.device.options.test_microphone.name") => {
+                Some(DeviceMenuOptions::TestMicrophone)
+            }
+            s if s == t!("menus.device.options.properties.name") => {
+                Some(DeviceMenuOptions::Properties)
+            }
             s if s == t!("menus.common.back") => Some(DeviceMenuOptions::Back),
             _ => None,
         }
@@ -198,30 +410,128 @@ impl DeviceMenuOptions {
             DeviceMenuOptions::SetDefault => t!("menus.device.options.set_default.name"),
             DeviceMenuOptions::SwitchProfile => t!("menus.device.options.switch_profile.name"),
             DeviceMenuOptions::AdjustVolume => t!("menus.device.options.adjust_volume.name"),
+            DeviceMenuOptions::Connections => t!("menus.device.options.connections.name"),
+            DeviceMenuOptions::TestSpeakers => t!("menus.device.options.test_speakers.name"),
+            DeviceMenuOptions::TestMicrophone => t!("menus.device.options.test_microphone.name"),
+            DeviceMenuOptions::Properties => t!("menus.device.options.properties.name"),
             DeviceMenuOptions::Back => t!("menus.common.back"),
         }
     }
 }
 
+#[derive(Debug, Clone)]
+pub enum ConnectionsMenuOptions {
+    LinkTo,
+    Linked(String),
+}
+
+impl ConnectionsMenuOptions {
+    pub fn from_string(option: &str) -> Option<Self> {
+        match option {
+            s if s == t!("menus.connections.options.link_to.name") => {
+                Some(ConnectionsMenuOptions::LinkTo)
+            }
+            other => Some(ConnectionsMenuOptions::Linked(other.to_string())),
+        }
+    }
+
+    pub fn to_str(&self) -> Cow<'static, str> {
+        match self {
+            ConnectionsMenuOptions::LinkTo => t!("menus.connections.options.link_to.name"),
+            ConnectionsMenuOptions::Linked(_) => t!("menus.connections.options.linked.name"),
+        }
+    }
+}
+
+pub fn port_mapping_to_str(mapping: PortMapping) -> Cow<'static, str> {
+    match mapping {
+        PortMapping::Auto => t!("menus.port_mapping.options.auto.name"),
+        PortMapping::Strict => t!("menus.port_mapping.options.strict.name"),
+        PortMapping::Positional => t!("menus.port_mapping.options.positional.name"),
+        PortMapping::Downmix => t!("menus.port_mapping.options.downmix.name"),
+        PortMapping::DuplicateMono => t!("menus.port_mapping.options.duplicate_mono.name"),
+    }
+}
+
+// Largest change accepted from typed input, matching the full volume range
+const MAX_VOLUME_ADJUST: i16 = 200;
+
+/// Parses a step count such as "x4", "×4" or "*4", with optional surrounding spaces.
+fn parse_step_count(input: &str) -> Option<i16> {
+    let count = input
+        .trim()
+        .strip_prefix(['x', 'X', '×', '*'])?
+        .trim()
+        .parse::<i16>()
+        .ok()?;
+    (1..=MAX_VOLUME_ADJUST).contains(&count).then_some(count)
+}
+
+/// Parses a signed percentage such as "+20" or "-15%"; the sign is required.
+fn parse_volume_percent(input: &str) -> Option<i16> {
+    let input = input.trim();
+    let sign = match input.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let percent = input[1..]
+        .trim()
+        .trim_end_matches('%')
+        .trim_end()
+        .parse::<i16>()
+        .ok()?;
+    (1..=MAX_VOLUME_ADJUST)
+        .contains(&percent)
+        .then_some(sign * percent)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum VolumeMenuOptions {
     Increase,
     Decrease,
+    Repeat,
+    Adjust(i16),
     Mute,
     Unmute,
+    StreamProperties,
     Back,
 }
 
 impl VolumeMenuOptions {
-    pub fn from_string(option: &str, step_percent: u8) -> Option<Self> {
+    /// Besides the entries themselves, accepts a step count typed after an entry or on its own
+    /// ("x4" repeats the last increase or decrease) and a signed percentage ("+20", "-15%").
+    pub fn from_string(option: &str, step_percent: u8, last_action: Option<Self>) -> Option<Self> {
         let increase_text = t!("menus.volume.options.increase.name", step = step_percent);
         let decrease_text = t!("menus.volume.options.decrease.name", step = step_percent);
+        let step = step_percent as i16;
+
+        if let Some(percent) = parse_volume_percent(option) {
+            return Some(VolumeMenuOptions::Adjust(percent));
+        }
+        if let Some(count) = parse_step_count(option) {
+            return Some(match last_action {
+                Some(VolumeMenuOptions::Decrease) => VolumeMenuOptions::Adjust(-step * count),
+                _ => VolumeMenuOptions::Adjust(step * count),
+            });
+        }
+        for (text, sign) in [(&increase_text, 1), (&decrease_text, -1)] {
+            if let Some(count) = option
+                .strip_prefix(text.as_ref())
+                .and_then(parse_step_count)
+            {
+                return Some(VolumeMenuOptions::Adjust(sign * step * count));
+            }
+        }
 
         match option {
             s if s == increase_text => Some(VolumeMenuOptions::Increase),
             s if s == decrease_text => Some(VolumeMenuOptions::Decrease),
             s if s == t!("menus.volume.options.mute.name") => Some(VolumeMenuOptions::Mute),
             s if s == t!("menus.volume.options.unmute.name") => Some(VolumeMenuOptions::Unmute),
+            s if s == t!("menus.volume.options.stream_properties.name") => {
+                Some(VolumeMenuOptions::StreamProperties)
+            }
             s if s == t!("menus.common.back") => Some(VolumeMenuOptions::Back),
             _ => None,
         }
@@ -237,11 +547,79 @@ impl VolumeMenuOptions {
                 let step = step_percent.unwrap_or(5);
                 t!("menus.volume.options.decrease.name", step = step)
             }
+            VolumeMenuOptions::Repeat => t!("menus.volume.options.repeat.name"),
+            VolumeMenuOptions::Adjust(percent) => Cow::Owned(format!("{percent:+}%")),
             VolumeMenuOptions::Mute => t!("menus.volume.options.mute.name"),
             VolumeMenuOptions::Unmute => t!("menus.volume.options.unmute.name"),
+            VolumeMenuOptions::StreamProperties => {
+                t!("menus.volume.options.stream_properties.name")
+            }
             VolumeMenuOptions::Back => t!("menus.common.back"),
         }
     }
+
+    /// Label of the entry repeating the last increase or decrease `count` times.
+    pub fn repeat_label(
+        last_action: Self,
+        step_percent: u8,
+        count: u8,
+    ) -> Option<Cow<'static, str>> {
+        let total = step_percent as u32 * count as u32;
+        match last_action {
+            VolumeMenuOptions::Increase => Some(t!(
+                "menus.volume.options.repeat_increase.name",
+                count = count,
+                total = total
+            )),
+            VolumeMenuOptions::Decrease => Some(t!(
+                "menus.volume.options.repeat_decrease.name",
+                count = count,
+                total = total
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamPropertiesMenuOptions {
+    Target,
+    FollowDefault,
+    PinToCurrent,
+    Latency,
+    Lock,
+    Unlock,
+    Back,
+}
+
+/// Quanta offered by the latency menu, in samples.
+pub const LATENCY_QUANTA: [u32; 6] = [64, 128, 256, 512, 1024, 2048];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LatencyMenuOptions {
+    SelectQuantum(u32),
+    Back,
+}
+
+impl LatencyMenuOptions {
+    pub fn from_string_with_quanta(option: &str, quanta: &[u32], rate: u32) -> Option<Self> {
+        if option == t!("menus.common.back") {
+            return Some(LatencyMenuOptions::Back);
+        }
+
+        quanta
+            .iter()
+            .find(|&&quantum| option == format_latency(quantum, rate))
+            .map(|&quantum| LatencyMenuOptions::SelectQuantum(quantum))
+    }
+}
+
+/// Formats a quantum the way `node.latency` is written, with its duration.
+pub fn format_latency(quantum: u32, rate: u32) -> String {
+    format!(
+        "{quantum}/{rate} ({:.1} ms)",
+        quantum as f32 * 1000.0 / rate.max(1) as f32
+    )
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -266,10 +644,125 @@ impl SampleRateMenuOptions {
     }
 }
 
+/// Side of an entry the default and current markers go on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkerPosition {
+    Before,
+    #[default]
+    After,
+}
+
+/// Marks a device list hint while the list comes from the cached graph.
+fn cached_hint(hint: Cow<'static, str>, controller: &Controller) -> Cow<'static, str> {
+    if controller.is_showing_cache() {
+        t!("menus.common.cached", hint = hint)
+    } else {
+        hint
+    }
+}
+
+// Launcher selections are trimmed, so indentation does not affect matching
+const GROUP_INDENT: &str = "    ";
+
+/// Prefixes each launcher line with its 1-based index.
+fn number_entries(input: &str) -> String {
+    input
+        .lines()
+        .enumerate()
+        .map(|(index, line)| format!("{}. {line}", index + 1))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Maps a numbered entry, or a bare index typed into the launcher, back to the original line.
+/// Anything else is returned unchanged.
+fn resolve_numbered_selection(selection: String, input: &str) -> String {
+    let trimmed = selection.trim();
+    let index = trimmed
+        .split_once(". ")
+        .map_or(trimmed.trim_end_matches('.'), |(index, _)| index);
+
+    if index.is_empty() || !index.chars().all(|c| c.is_ascii_digit()) {
+        return selection;
+    }
+
+    index
+        .parse::<usize>()
+        .ok()
+        .and_then(|index| index.checked_sub(1))
+        .and_then(|index| input.lines().nth(index))
+        .map(str::to_string)
+        .unwrap_or(selection)
+}
+
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Drops pango tags and decodes entities, leaving the text rofi displayed.
+fn strip_markup(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut in_tag = false;
+
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => plain.push(c),
+            _ => {}
+        }
+    }
+
+    plain
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Object id from the `[#57]` suffix entries carry with `--show-ids`.
+/// Shortens `name` to `width` characters by eliding its middle, so names that only differ
+/// at the end, like numbered HDMI outputs, stay distinct.
+fn ellipsize(name: &str, width: usize) -> String {
+    let length = name.chars().count();
+    if length <= width {
+        return name.to_string();
+    }
+
+    let kept = width.saturating_sub(1);
+    let head = kept.div_ceil(2);
+    let start: String = name.chars().take(head).collect();
+    let end: String = name.chars().skip(length - (kept - head)).collect();
+    format!("{}…{}", start.trim_end(), end.trim_start())
+}
+
+pub fn parse_object_id(selection: &str) -> Option<u32> {
+    let (_, id) = selection.trim_end().strip_suffix(']')?.rsplit_once("[#")?;
+    id.parse().ok()
+}
+
 #[derive(Clone)]
 pub struct Menu {
     pub launcher_type: LauncherType,
     pub icons: Arc<Icons>,
+    pub group_by_device: bool,
+    pub volume_scale: VolumeScale,
+    pub launcher_args: Vec<String>,
+    pub show_ids: bool,
+    pub markup: bool,
+    pub numbered: bool,
+    pub entry_format: Option<String>,
+    pub icon_layout: IconLayout,
+    pub volume_icons: bool,
+    pub default_marker: Option<String>,
+    pub marker_position: MarkerPosition,
+    pub name_width: Option<usize>,
+    // Shared by clones, so it covers every menu of a session
+    selection_made: Arc<AtomicBool>,
 }
 
 impl Menu {
@@ -277,6 +770,134 @@ impl Menu {
         Self {
             launcher_type,
             icons,
+            group_by_device: false,
+            volume_scale: VolumeScale::default(),
+            launcher_args: Vec::new(),
+            show_ids: false,
+            markup: false,
+            numbered: false,
+            entry_format: None,
+            icon_layout: IconLayout::default(),
+            volume_icons: false,
+            default_marker: None,
+            marker_position: MarkerPosition::default(),
+            name_width: None,
+            selection_made: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Whether any launcher returned an entry, as opposed to every menu being dismissed.
+    pub fn selection_made(&self) -> bool {
+        self.selection_made.load(Ordering::Relaxed)
+    }
+
+    fn record_selection<T>(&self, selection: Option<T>) -> Option<T> {
+        if selection.is_some() {
+            self.selection_made.store(true, Ordering::Relaxed);
+        }
+        selection
+    }
+
+    pub fn with_group_by_device(mut self, group_by_device: bool) -> Self {
+        self.group_by_device = group_by_device;
+        self
+    }
+
+    pub fn with_volume_scale(mut self, volume_scale: VolumeScale) -> Self {
+        self.volume_scale = volume_scale;
+        self
+    }
+
+    pub fn with_launcher_args(mut self, launcher_args: Vec<String>) -> Self {
+        self.launcher_args = launcher_args;
+        self
+    }
+
+    pub fn with_show_ids(mut self, show_ids: bool) -> Self {
+        self.show_ids = show_ids;
+        self
+    }
+
+    pub fn with_markup(mut self, markup: bool) -> Self {
+        self.markup = markup;
+        self
+    }
+
+    pub fn with_numbered(mut self, numbered: bool) -> Self {
+        self.numbered = numbered;
+        self
+    }
+
+    pub fn with_entry_format(mut self, entry_format: Option<String>) -> Self {
+        self.entry_format = entry_format;
+        self
+    }
+
+    pub fn with_icon_layout(mut self, icon_layout: IconLayout) -> Self {
+        self.icon_layout = icon_layout;
+        self
+    }
+
+    pub fn with_volume_icons(mut self, volume_icons: bool) -> Self {
+        self.volume_icons = volume_icons;
+        self
+    }
+
+    pub fn with_name_width(mut self, name_width: Option<usize>) -> Self {
+        self.name_width = name_width;
+        self
+    }
+
+    /// Replaces the built-in default and current marker; an empty marker hides it.
+    pub fn with_default_marker(
+        mut self,
+        default_marker: Option<String>,
+        marker_position: MarkerPosition,
+    ) -> Self {
+        self.default_marker = default_marker;
+        self.marker_position = marker_position;
+        self
+    }
+
+    /// Markup is only emitted for rofi, other launchers get plain text.
+    fn uses_markup(&self) -> bool {
+        self.markup && self.launcher_type == LauncherType::Rofi
+    }
+
+    /// Escapes `text` when entries are written as pango markup.
+    fn entry_text(&self, text: &str) -> String {
+        if self.uses_markup() {
+            escape_markup(text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Volume suffix of node and stream entries, dimmed when writing markup.
+    fn volume_label(&self, node: &Node) -> String {
+        let volume = &node.volume;
+        let label = if volume.muted {
+            format!("[{}]", t!("menus.volume.muted"))
+        } else {
+            format!("[{}%]", volume.percent_in(self.volume_scale))
+        };
+
+        let mut label = if self.uses_markup() {
+            format!(" <span alpha=\"50%\">{}</span>", escape_markup(&label))
+        } else {
+            format!(" {label}")
+        };
+        // Boosted inputs clip easily, so they stand out
+        if node.is_boosted() && !volume.muted {
+            label.push(' ');
+            label.push_str(&self.entry_text(&t!("menus.volume.boosted_marker")));
+        }
+        label
+    }
+
+    fn push_object_id(&self, display_name: &mut String, id: u32) {
+        if self.show_ids {
+            display_name.push_str(&format!(" [#{id}]"));
         }
     }
 
@@ -287,21 +908,74 @@ impl Menu {
         icon_type: &str,
         hint: Option<&str>,
     ) -> Result<Option<String>> {
-        let cmd = Launcher::create_command(&self.launcher_type, launcher_command, icon_type, hint)?;
+        let cmd = Launcher::create_command(
+            &self.launcher_type,
+            launcher_command,
+            icon_type,
+            self.uses_markup(),
+            hint,
+        )?;
+
+        let Some(input) = input.filter(|_| self.numbered) else {
+            return Ok(self.record_selection(Launcher::run(cmd, input, &self.launcher_args)?));
+        };
+
+        Ok(self.record_selection(
+            Launcher::run(cmd, Some(&number_entries(input)), &self.launcher_args)?
+                .map(|selection| resolve_numbered_selection(selection, input)),
+        ))
+    }
+
+    /// Runs a device list, returning the cleaned selection and the quick action its key maps to.
+    fn run_device_list_launcher(
+        &self,
+        launcher_command: &Option<String>,
+        input: &str,
+        icon_type: &str,
+        hint: &str,
+    ) -> Result<Option<(String, Option<DeviceQuickAction>)>> {
+        let cmd = Launcher::create_command(
+            &self.launcher_type,
+            launcher_command,
+            icon_type,
+            self.uses_markup(),
+            Some(hint),
+        )?;
+
+        let launcher_input = if self.numbered {
+            Cow::Owned(number_entries(input))
+        } else {
+            Cow::Borrowed(input)
+        };
 
-        Launcher::run(cmd, input)
+        Ok(self.record_selection(
+            Launcher::run_with_custom_keys(cmd, Some(&launcher_input), &self.launcher_args)?.map(
+                |LauncherSelection { text, custom_key }| {
+                    let text = if self.numbered {
+                        resolve_numbered_selection(text, input)
+                    } else {
+                        text
+                    };
+                    (
+                        self.clean_menu_output(&text, icon_type),
+                        custom_key.and_then(DeviceQuickAction::from_custom_key),
+                    )
+                },
+            ),
+        ))
     }
 
     pub fn clean_menu_output(&self, output: &str, icon_type: &str) -> String {
-        let output_trimmed = output.trim();
+        let plain_output;
+        let output_trimmed = if self.uses_markup() {
+            plain_output = strip_markup(output);
+            plain_output.trim()
+        } else {
+            output.trim()
+        };
 
         if icon_type == "font" {
-            output_trimmed
-                .chars()
-                .skip_while(|c| !c.is_ascii_alphanumeric())
-                .collect::<String>()
-                .trim()
-                .to_string()
+            self.icon_layout.strip(output_trimmed)
         } else if icon_type == "xdg" {
             output_trimmed
                 .split('\0')
@@ -322,9 +996,9 @@ impl Menu {
             .into_iter()
             .map(|(icon_key, text)| {
                 let icon = self.icons.get_icon(icon_key, icon_type);
-                let text = text.as_ref();
+                let text = self.entry_text(text.as_ref());
                 match icon_type {
-                    "font" => format!("{}{}{}", icon, " ".repeat(spaces), text),
+                    "font" => self.icon_layout.join(&icon, &text, spaces),
                     "xdg" => format!("{text}\0icon\x1f{icon}"),
                     _ => text.to_string(),
                 }
@@ -340,7 +1014,7 @@ impl Menu {
         icon_type: &str,
         spaces: usize,
     ) -> String {
-        let mut display_name = controller.get_node_base_name(node);
+        let mut display_name = self.short_name(&controller.get_node_base_name(node));
 
         if let Some(app_name) = &node.application_name {
             display_name = format!("{display_name} ({app_name})");
@@ -350,70 +1024,239 @@ impl Menu {
             display_name.push_str(&format!(" - {port_number}"));
         }
 
-        let volume_str = if node.volume.muted {
-            format!(" [{}]", t!("menus.volume.muted"))
+        if let Some(port) = controller
+            .get_active_route_port(node)
+            .filter(|port| port.is_plugged() == Some(false))
+        {
+            display_name = t!(
+                "menus.common.unplugged",
+                name = display_name,
+                port = port.description
+            )
+            .to_string();
+        }
+
+        let marker = if node.is_default {
+            self.marker("menus.common.default_marker", icon_type)
         } else {
-            format!(" [{}%]", node.volume.percent())
+            String::new()
+        };
+
+        // Markers are inserted after escaping so they can carry markup of their own
+        let mut display_name = match &self.entry_format {
+            Some(template) => self
+                .entry_text(&self.render_entry_format(template, &display_name, node))
+                .replace("{default}", &marker),
+            None => self.entry_text(&display_name),
         };
-        display_name.push_str(&volume_str);
+        if node.is_default && self.uses_markup() {
+            display_name = format!("<b>{display_name}</b>");
+        }
 
-        if node.is_default {
-            display_name.push_str(&format!(" {}", self.icons.get_icon("default", "generic")));
+        if self.entry_format.is_none() {
+            display_name.push_str(&self.volume_label(node));
         }
 
-        let device_info = controller.get_device_info(node);
-        let icon = self.icons.get_device_icon(&device_info, icon_type);
+        let marker_in_template = self
+            .entry_format
+            .as_ref()
+            .is_some_and(|template| template.contains("{default}"));
+        if !marker_in_template {
+            display_name = self.with_marker(display_name, &marker);
+        }
+
+        self.push_object_id(&mut display_name, node.id);
+
+        let icon = if self.volume_icons {
+            let icon_key = Icons::volume_icon_key(
+                &node.node_type,
+                node.volume.percent_in(self.volume_scale),
+                node.volume.muted,
+            );
+            self.icons.get_icon(icon_key, icon_type)
+        } else {
+            let device_info = controller.get_device_info(node);
+            self.icons.get_device_icon(&device_info, icon_type)
+        };
 
         self.format_display_with_icon(&display_name, &icon, icon_type, spaces)
     }
 
-    pub fn format_display_with_icon(
+    /// Formats device nodes as launcher lines, each prefixed with a newline.
+    /// When grouping is enabled, nodes sharing a card are listed under its name.
+    pub fn format_node_list(
         &self,
-        text: &str,
-        icon: &str,
+        nodes: &[Node],
+        controller: &Controller,
         icon_type: &str,
         spaces: usize,
     ) -> String {
-        match icon_type {
-            "xdg" => format!("{text}\0icon\x1f{icon}"),
-            "font" | "generic" => format!("{}{}{}", icon, " ".repeat(spaces), text),
-            _ => text.to_string(),
-        }
-    }
+        let mut output = String::new();
 
-    pub fn format_stream_display_name(&self, node: &Node, controller: &Controller) -> String {
-        let app_name = controller.get_application_name(node);
-
-        if let Some(media_name) = controller.get_media_name(node) {
-            format!("{app_name} - {media_name}")
-        } else {
-            app_name
+        if !self.group_by_device {
+            for node in nodes {
+                let node_display = self.format_node_display(node, controller, icon_type, spaces);
+                output.push_str(&format!("\n{node_display}"));
+            }
+            return output;
         }
-    }
+
+        for (device_id, group) in controller.group_nodes_by_device(nodes) {
+            let Some(device_id) = device_id.filter(|_| group.len() > 1) else {
+                for node in &group {
+                    let node_display =
+                        self.format_node_display(node, controller, icon_type, spaces);
+                    output.push_str(&format!("\n{node_display}"));
+                }
+                continue;
+            };
+
+            let mut device_name =
+                self.entry_text(&self.short_name(&controller.get_device_name(device_id)));
+            self.push_object_id(&mut device_name, device_id);
+            let device_info = controller.get_device_info(&group[0]);
+            let icon = self.icons.get_device_icon(&device_info, icon_type);
+            let header = self.format_display_with_icon(&device_name, &icon, icon_type, spaces);
+            output.push_str(&format!("\n{header}"));
+
+            for node in &group {
+                let node_display = self.format_node_display(node, controller, icon_type, spaces);
+                output.push_str(&format!("\n{GROUP_INDENT}{node_display}"));
+            }
+        }
+
+        output
+    }
+
+    /// Fills the `entry-format` placeholders, leaving the ones without a known value empty.
+    fn render_entry_format(&self, template: &str, name: &str, node: &Node) -> String {
+        let volume = if node.volume.muted {
+            t!("menus.volume.muted").to_string()
+        } else {
+            format!("{}%", node.volume.percent_in(self.volume_scale))
+        };
+        let spec = node.sample_spec.as_ref();
+        let fields = [
+            ("name", name.to_string()),
+            ("volume", volume),
+            ("format", spec.map(|s| s.format.clone()).unwrap_or_default()),
+            ("rate", spec.map(|s| s.rate.to_string()).unwrap_or_default()),
+            (
+                "channels",
+                spec.map(|s| s.channels.to_string()).unwrap_or_default(),
+            ),
+            ("latency", node.latency.clone().unwrap_or_default()),
+        ];
+
+        fields
+            .iter()
+            .fold(template.to_string(), |text, (key, value)| {
+                text.replace(&format!("{{{key}}}"), value)
+            })
+    }
+
+    /// Marks the default or current entry, in words when icons are disabled.
+    fn marker(&self, text_key: &str, icon_type: &str) -> String {
+        if let Some(marker) = &self.default_marker {
+            return marker.clone();
+        }
+        match icon_type {
+            "none" => t!(text_key).to_string(),
+            _ => self.icons.get_icon("default", "generic"),
+        }
+    }
+
+    fn with_marker(&self, label: String, marker: &str) -> String {
+        match (marker.is_empty(), self.marker_position) {
+            (true, _) => label,
+            (false, MarkerPosition::Before) => format!("{marker} {label}"),
+            (false, MarkerPosition::After) => format!("{label} {marker}"),
+        }
+    }
+
+    pub fn format_display_with_icon(
+        &self,
+        text: &str,
+        icon: &str,
+        icon_type: &str,
+        spaces: usize,
+    ) -> String {
+        match icon_type {
+            "xdg" => format!("{text}\0icon\x1f{icon}"),
+            "font" | "generic" => self.icon_layout.join(icon, text, spaces),
+            _ => text.to_string(),
+        }
+    }
+
+    pub fn format_stream_display_name(&self, node: &Node, controller: &Controller) -> String {
+        let app_name = controller.get_application_name(node);
+
+        let name = if let Some(media_name) = controller.get_media_name(node) {
+            format!("{app_name} - {media_name}")
+        } else {
+            app_name
+        };
+        self.short_name(&name)
+    }
+
+    /// Applies `name-width`. Selections are matched against names shortened the same way,
+    /// while subcommand targets still match the full names.
+    fn short_name(&self, name: &str) -> String {
+        match self.name_width {
+            Some(width) => ellipsize(name, width),
+            None => name.to_string(),
+        }
+    }
 
     pub async fn show_main_menu(
         &self,
         launcher_command: &Option<String>,
         icon_type: &str,
         spaces: usize,
+        connection: ConnectionStatus,
+        default_output_muted: Option<bool>,
     ) -> Result<Option<MainMenuOptions>> {
-        let options = vec![
+        let mut options = vec![
             ("output", MainMenuOptions::ShowOutputDeviceMenu.to_str()),
             ("input", MainMenuOptions::ShowInputDeviceMenu.to_str()),
-            (
-                "output_streams",
-                MainMenuOptions::ShowOutputStreamsMenu.to_str(),
-            ),
-            (
-                "input_streams",
-                MainMenuOptions::ShowInputStreamsMenu.to_str(),
-            ),
-            ("settings", MainMenuOptions::ShowSettingsMenu.to_str()),
+            ("output_streams", MainMenuOptions::ShowPlaybackMenu.to_str()),
+            ("input_streams", MainMenuOptions::ShowRecordingMenu.to_str()),
         ];
 
+        // Volume shortcuts for the default output, hidden when there is none
+        if let Some(muted) = default_output_muted {
+            options.push((
+                "output_volume_up",
+                MainMenuOptions::RaiseDefaultOutputVolume.to_str(),
+            ));
+            options.push((
+                "output_volume_down",
+                MainMenuOptions::LowerDefaultOutputVolume.to_str(),
+            ));
+            if muted {
+                options.push((
+                    "output_unmute",
+                    MainMenuOptions::UnmuteDefaultOutput.to_str(),
+                ));
+            } else {
+                options.push(("output_mute", MainMenuOptions::MuteDefaultOutput.to_str()));
+            }
+        }
+
+        options.push(("scenes", MainMenuOptions::ShowScenesMenu.to_str()));
+        options.push(("settings", MainMenuOptions::ShowSettingsMenu.to_str()));
+
         let input = self.get_icon_text(options, icon_type, spaces);
+        // Device lists come up empty without a connection, so say why up front
+        let hint = match connection {
+            ConnectionStatus::Connected => None,
+            ConnectionStatus::Disconnected => Some(t!("menus.main.connection.disconnected")),
+            ConnectionStatus::Error => Some(t!("menus.main.connection.error")),
+            ConnectionStatus::Reconnecting => Some(t!("menus.main.connection.reconnecting")),
+        };
 
-        let menu_output = self.run_launcher(launcher_command, Some(&input), icon_type, None)?;
+        let menu_output =
+            self.run_launcher(launcher_command, Some(&input), icon_type, hint.as_deref())?;
 
         if let Some(output) = menu_output {
             let cleaned_output = self.clean_menu_output(&output, icon_type);
@@ -423,6 +1266,47 @@ impl Menu {
         Ok(None)
     }
 
+    pub async fn show_scenes_menu(
+        &self,
+        launcher_command: &Option<String>,
+        icon_type: &str,
+        spaces: usize,
+        scenes: &[String],
+        devices: &[(u32, String)],
+        interactive: bool,
+    ) -> Result<Option<ScenesMenuOptions>> {
+        let mut options: Vec<(&str, Cow<'static, str>)> = Vec::new();
+
+        for scene in scenes {
+            options.push(("scene", Cow::Owned(scene.clone())));
+        }
+
+        for (_, name) in devices {
+            options.push(("switch_profile", Cow::Owned(name.clone())));
+        }
+
+        if !interactive {
+            options.push(("back", t!("menus.common.back")));
+        }
+
+        let input = self.get_icon_text(options, icon_type, spaces);
+        let hint = t!("menus.scenes.hint");
+
+        let menu_output =
+            self.run_launcher(launcher_command, Some(&input), icon_type, Some(&hint))?;
+
+        if let Some(output) = menu_output {
+            let cleaned_output = self.clean_menu_output(&output, icon_type);
+            return Ok(ScenesMenuOptions::from_string_with_entries(
+                &cleaned_output,
+                scenes,
+                devices,
+            ));
+        }
+
+        Ok(None)
+    }
+
     pub async fn show_settings_menu(
         &self,
         launcher_command: &Option<String>,
@@ -430,10 +1314,16 @@ impl Menu {
         spaces: usize,
         interactive: bool,
     ) -> Result<Option<SettingsMenuOptions>> {
-        let mut options: Vec<(&str, Cow<'static, str>)> = vec![(
-            "set_sample_rate",
-            SettingsMenuOptions::SetSampleRate.to_str(),
-        )];
+        let mut options: Vec<(&str, Cow<'static, str>)> = vec![
+            (
+                "set_sample_rate",
+                SettingsMenuOptions::SetSampleRate.to_str(),
+            ),
+            (
+                "managed_objects",
+                SettingsMenuOptions::ManageObjects.to_str(),
+            ),
+        ];
 
         if !interactive {
             options.push(("back", t!("menus.common.back")));
@@ -468,7 +1358,8 @@ impl Menu {
             let mut display_name = format!("{:.1} kHz", rate as f32 / 1000.0);
 
             if rate == current_rate {
-                display_name.push_str(&format!(" {}", self.icons.get_icon("default", "generic")));
+                let marker = self.marker("menus.common.current_marker", icon_type);
+                display_name = self.with_marker(display_name, &marker);
             }
 
             options.push(("profile", Cow::Owned(display_name)));
@@ -517,13 +1408,12 @@ impl Menu {
         for stream in streams {
             let display_name = self.format_stream_display_name(stream, controller);
 
-            let volume_str = if stream.volume.muted {
-                format!(" [{}]", t!("menus.volume.muted"))
-            } else {
-                format!(" [{}%]", stream.volume.percent())
-            };
-
-            let full_display = format!("{display_name}{volume_str}");
+            let mut full_display = format!(
+                "{}{}",
+                self.entry_text(&display_name),
+                self.volume_label(stream)
+            );
+            self.push_object_id(&mut full_display, stream.id);
             let formatted = self.format_display_with_icon(
                 &full_display,
                 &self.icons.get_icon("stream", icon_type),
@@ -564,16 +1454,13 @@ impl Menu {
         icon_type: &str,
         spaces: usize,
         interactive: bool,
-    ) -> Result<Option<String>> {
+    ) -> Result<Option<(String, Option<DeviceQuickAction>)>> {
         let refresh_text = OutputDeviceMenuOptions::RefreshList.to_str();
         let options_start = vec![("refresh", refresh_text.as_ref())];
 
         let mut input = self.get_icon_text(options_start, icon_type, spaces);
 
-        for node in nodes {
-            let node_display = self.format_node_display(node, controller, icon_type, spaces);
-            input.push_str(&format!("\n{node_display}"));
-        }
+        input.push_str(&self.format_node_list(nodes, controller, icon_type, spaces));
 
         if !interactive {
             let back_text = t!("menus.common.back");
@@ -581,16 +1468,58 @@ impl Menu {
             input.push_str(&format!("\n{back_formatted}"));
         }
 
-        let hint = t!("menus.output_devices.hint");
-        let menu_output =
-            self.run_launcher(launcher_command, Some(&input), icon_type, Some(&hint))?;
+        let hint = cached_hint(t!("menus.output_devices.hint"), controller);
+        self.run_device_list_launcher(launcher_command, &input, icon_type, &hint)
+    }
 
-        if let Some(output) = menu_output {
-            let cleaned_output = self.clean_menu_output(&output, icon_type);
-            return Ok(Some(cleaned_output));
+    #[allow(clippy::too_many_arguments)]
+    pub async fn show_all_devices_menu(
+        &self,
+        launcher_command: &Option<String>,
+        output_nodes: &[Node],
+        input_nodes: &[Node],
+        controller: &Controller,
+        icon_type: &str,
+        spaces: usize,
+        interactive: bool,
+    ) -> Result<Option<(String, Option<DeviceQuickAction>)>> {
+        let refresh_text = AllDevicesMenuOptions::RefreshList.to_str();
+        let options_start = vec![("refresh", refresh_text.as_ref())];
+
+        let mut input = self.get_icon_text(options_start, icon_type, spaces);
+
+        let sections = [
+            (
+                "output",
+                AllDevicesMenuOptions::OutputHeader.to_str(),
+                output_nodes,
+            ),
+            (
+                "input",
+                AllDevicesMenuOptions::InputHeader.to_str(),
+                input_nodes,
+            ),
+        ];
+
+        for (icon_key, header_text, nodes) in sections {
+            if nodes.is_empty() {
+                continue;
+            }
+
+            let header = self.get_icon_text(vec![(icon_key, header_text)], icon_type, spaces);
+            input.push_str(&format!("\n{header}"));
+
+            input.push_str(&self.format_node_list(nodes, controller, icon_type, spaces));
         }
 
-        Ok(None)
+        if !interactive {
+            let back_text = t!("menus.common.back");
+            let back_formatted = self.get_icon_text(vec![("back", back_text)], icon_type, spaces);
+            input.push_str(&format!("\n{back_formatted}"));
+        }
+
+        let hint = cached_hint(t!("menus.all_devices.hint"), controller);
+        self.run_device_list_launcher(launcher_command, &input, icon_type, &hint)
     }
 
     pub async fn show_input_device_menu(
@@ -601,16 +1530,13 @@ impl Menu {
         icon_type: &str,
         spaces: usize,
         interactive: bool,
-    ) -> Result<Option<String>> {
+    ) -> Result<Option<(String, Option<DeviceQuickAction>)>> {
         let refresh_text = InputDeviceMenuOptions::RefreshList.to_str();
         let options_start = vec![("refresh", refresh_text.as_ref())];
 
         let mut input = self.get_icon_text(options_start, icon_type, spaces);
 
-        for node in nodes {
-            let node_display = self.format_node_display(node, controller, icon_type, spaces);
-            input.push_str(&format!("\n{node_display}"));
-        }
+        input.push_str(&self.format_node_list(nodes, controller, icon_type, spaces));
 
         if !interactive {
             let back_text = t!("menus.common.back");
@@ -618,16 +1544,8 @@ impl Menu {
             input.push_str(&format!("\n{back_formatted}"));
         }
 
-        let hint = t!("menus.input_devices.hint");
-        let menu_output =
-            self.run_launcher(launcher_command, Some(&input), icon_type, Some(&hint))?;
-
-        if let Some(output) = menu_output {
-            let cleaned_output = self.clean_menu_output(&output, icon_type);
-            return Ok(Some(cleaned_output));
-        }
-
-        Ok(None)
+        let hint = cached_hint(t!("menus.input_devices.hint"), controller);
+        self.run_device_list_launcher(launcher_command, &input, icon_type, &hint)
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -638,13 +1556,14 @@ impl Menu {
         spaces: usize,
         device_name: &str,
         is_default: bool,
+        can_set_default: bool,
         is_output_menu: bool,
         has_profiles: bool,
         interactive: bool,
     ) -> Result<Option<DeviceMenuOptions>> {
         let mut options = Vec::new();
 
-        if !is_default {
+        if !is_default && can_set_default {
             options.push(("set_default", DeviceMenuOptions::SetDefault.to_str()));
         }
 
@@ -659,6 +1578,18 @@ impl Menu {
         };
 
         options.push((volume_icon_key, DeviceMenuOptions::AdjustVolume.to_str()));
+        options.push(("connections", DeviceMenuOptions::Connections.to_str()));
+
+        if is_output_menu {
+            options.push(("test_speakers", DeviceMenuOptions::TestSpeakers.to_str()));
+        } else {
+            options.push((
+                "test_microphone",
+                DeviceMenuOptions::TestMicrophone.to_str(),
+            ));
+        }
+
+        options.push(("properties", DeviceMenuOptions::Properties.to_str()));
 
         if !interactive {
             let back_text = t!("menus.common.back");
@@ -688,6 +1619,7 @@ impl Menu {
         device_name: &str,
         profiles: &[Profile],
         current_profile_index: Option<u32>,
+        can_turn_off: bool,
         interactive: bool,
     ) -> Result<Option<ProfileMenuOptions>> {
         if profiles.is_empty() {
@@ -696,21 +1628,53 @@ impl Menu {
 
         let mut options: Vec<(&str, Cow<'static, str>)> = Vec::new();
 
-        for profile in profiles {
+        for profile in profiles.iter().filter(|profile| profile.is_available()) {
             let mut display_name = profile.description.clone();
 
             if Some(profile.index) == current_profile_index {
-                display_name.push_str(&format!(" {}", self.icons.get_icon("default", "generic")));
+                let marker = self.marker("menus.common.current_marker", icon_type);
+                display_name = self.with_marker(display_name, &marker);
             }
 
             options.push(("profile", Cow::Owned(display_name)));
         }
 
+        let mut input = self.get_icon_text(options, icon_type, spaces);
+
+        // Listed after the selectable profiles, dimmed when markup is enabled
+        for profile in profiles.iter().filter(|profile| !profile.is_available()) {
+            let label = t!(
+                "menus.profile.unavailable",
+                profile_name = &profile.description
+            );
+            let label = if self.uses_markup() {
+                format!("<span alpha=\"50%\">{}</span>", escape_markup(&label))
+            } else {
+                label.to_string()
+            };
+            let formatted = self.format_display_with_icon(
+                &label,
+                &self.icons.get_icon("profile", icon_type),
+                icon_type,
+                spaces,
+            );
+            input.push_str(&format!("\n{formatted}"));
+        }
+
+        let mut options: Vec<(&str, Cow<'static, str>)> = Vec::new();
+        if can_turn_off {
+            options.push(("turn_off", t!("menus.profile.options.turn_off.name")));
+        }
         if !interactive {
             options.push(("back", t!("menus.common.back")));
         }
+        if !options.is_empty() {
+            input.push_str(&format!(
+                "\n{}",
+                self.get_icon_text(options, icon_type, spaces)
+            ));
+        }
 
-        let input = self.get_icon_text(options, icon_type, spaces);
         let hint = t!("menus.profile.hint", device_name = device_name);
 
         let menu_output =
@@ -739,6 +1703,7 @@ impl Menu {
         device_name: &str,
         volume_display: &str,
         step_percent: u8,
+        repeat_count: Option<u8>,
         interactive: bool,
     ) -> Result<Option<VolumeMenuOptions>> {
         let mut options = Vec::new();
@@ -754,6 +1719,17 @@ impl Menu {
             "input_volume_down"
         };
 
+        let repeat_label = last_action.zip(repeat_count).and_then(|(action, count)| {
+            VolumeMenuOptions::repeat_label(action, step_percent, count)
+        });
+        if let Some(label) = &repeat_label {
+            let repeat_key = match last_action {
+                Some(VolumeMenuOptions::Decrease) => decrease_key,
+                _ => increase_key,
+            };
+            options.push((repeat_key, label.clone()));
+        }
+
         match last_action {
             Some(VolumeMenuOptions::Decrease) => {
                 options.push((
@@ -803,6 +1779,16 @@ impl Menu {
             options.push((mute_key, VolumeMenuOptions::Mute.to_str(None)));
         }
 
+        if matches!(
+            node.node_type,
+            NodeType::StreamOutputAudio | NodeType::StreamInputAudio
+        ) {
+            options.push((
+                "properties",
+                VolumeMenuOptions::StreamProperties.to_str(None),
+            ));
+        }
+
         if !interactive {
             let back_text = t!("menus.common.back");
             options.push(("back", back_text));
@@ -820,12 +1806,374 @@ impl Menu {
 
         if let Some(output) = menu_output {
             let cleaned_output = self.clean_menu_output(&output, icon_type);
+            if repeat_label.is_some_and(|label| label == cleaned_output) {
+                return Ok(Some(VolumeMenuOptions::Repeat));
+            }
             return Ok(VolumeMenuOptions::from_string(
                 &cleaned_output,
                 step_percent,
+                last_action,
             ));
         }
 
         Ok(None)
     }
+
+    pub async fn show_managed_objects_menu(
+        &self,
+        launcher_command: &Option<String>,
+        nodes: &[Node],
+        controller: &Controller,
+        icon_type: &str,
+        spaces: usize,
+        interactive: bool,
+    ) -> Result<Option<String>> {
+        let create_text = ManagedObjectsMenuOptions::CreateVirtualSink.to_str();
+        let options_start = vec![("create_virtual_sink", create_text.as_ref())];
+
+        let mut input = self.get_icon_text(options_start, icon_type, spaces);
+
+        for node in nodes {
+            let node_display = self.format_node_display(node, controller, icon_type, spaces);
+            input.push_str(&format!("\n{node_display}"));
+        }
+
+        if !interactive {
+            let back_text = t!("menus.common.back");
+            let back_formatted = self.get_icon_text(vec![("back", back_text)], icon_type, spaces);
+            input.push_str(&format!("\n{back_formatted}"));
+        }
+
+        let hint = t!("menus.managed_objects.hint");
+        let menu_output =
+            self.run_launcher(launcher_command, Some(&input), icon_type, Some(&hint))?;
+
+        if let Some(output) = menu_output {
+            let cleaned_output = self.clean_menu_output(&output, icon_type);
+            return Ok(Some(cleaned_output));
+        }
+
+        Ok(None)
+    }
+
+    pub async fn show_managed_object_menu(
+        &self,
+        launcher_command: &Option<String>,
+        icon_type: &str,
+        spaces: usize,
+        object_name: &str,
+        interactive: bool,
+    ) -> Result<Option<ManagedObjectMenuOptions>> {
+        let mut options = vec![
+            ("rename", ManagedObjectMenuOptions::Rename.to_str()),
+            ("remove", ManagedObjectMenuOptions::Remove.to_str()),
+        ];
+
+        if !interactive {
+            options.push(("back", ManagedObjectMenuOptions::Back.to_str()));
+        }
+
+        let input = self.get_icon_text(options, icon_type, spaces);
+        let hint = t!("menus.managed_object.hint", object_name = object_name);
+
+        let menu_output =
+            self.run_launcher(launcher_command, Some(&input), icon_type, Some(&hint))?;
+
+        if let Some(output) = menu_output {
+            let cleaned_output = self.clean_menu_output(&output, icon_type);
+            return Ok(ManagedObjectMenuOptions::from_string(&cleaned_output));
+        }
+
+        Ok(None)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn show_connections_menu(
+        &self,
+        launcher_command: &Option<String>,
+        linked_nodes: &[Node],
+        controller: &Controller,
+        icon_type: &str,
+        spaces: usize,
+        device_name: &str,
+        interactive: bool,
+    ) -> Result<Option<String>> {
+        let link_text = ConnectionsMenuOptions::LinkTo.to_str();
+        let options_start = vec![("link", link_text.as_ref())];
+
+        let mut input = self.get_icon_text(options_start, icon_type, spaces);
+
+        for node in linked_nodes {
+            let node_display = self.format_node_display(node, controller, icon_type, spaces);
+            input.push_str(&format!("\n{node_display}"));
+        }
+
+        if !interactive {
+            let back_text = t!("menus.common.back");
+            let back_formatted = self.get_icon_text(vec![("back", back_text)], icon_type, spaces);
+            input.push_str(&format!("\n{back_formatted}"));
+        }
+
+        let hint = t!("menus.connections.hint", device_name = device_name);
+        let menu_output =
+            self.run_launcher(launcher_command, Some(&input), icon_type, Some(&hint))?;
+
+        if let Some(output) = menu_output {
+            let cleaned_output = self.clean_menu_output(&output, icon_type);
+            return Ok(Some(cleaned_output));
+        }
+
+        Ok(None)
+    }
+
+    /// Read-only list of `key: value` rows, for debugging without `pw-dump`.
+    pub async fn show_properties_menu(
+        &self,
+        launcher_command: &Option<String>,
+        icon_type: &str,
+        spaces: usize,
+        device_name: &str,
+        properties: &[(String, String)],
+        interactive: bool,
+    ) -> Result<Option<String>> {
+        let mut options: Vec<(&str, Cow<'static, str>)> = properties
+            .iter()
+            .map(|(key, value)| ("property", Cow::Owned(format!("{key}: {value}"))))
+            .collect();
+
+        if !interactive {
+            options.push(("back", t!("menus.common.back")));
+        }
+
+        let input = self.get_icon_text(options, icon_type, spaces);
+        let hint = t!("menus.properties.hint", device_name = device_name);
+
+        let menu_output =
+            self.run_launcher(launcher_command, Some(&input), icon_type, Some(&hint))?;
+
+        Ok(menu_output.map(|output| self.clean_menu_output(&output, icon_type)))
+    }
+
+    /// Rows show the stream's target and latency; picking one changes it.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn show_stream_properties_menu(
+        &self,
+        launcher_command: &Option<String>,
+        icon_type: &str,
+        spaces: usize,
+        stream_name: &str,
+        target_name: Option<&str>,
+        follows_default: bool,
+        latency: Option<&str>,
+        can_lock: bool,
+        lock_target: Option<&str>,
+        interactive: bool,
+    ) -> Result<Option<StreamPropertiesMenuOptions>> {
+        let default_text = t!("menus.stream_properties.default");
+        let target_text = t!(
+            "menus.stream_properties.options.target.name",
+            target = target_name.unwrap_or(&default_text)
+        );
+        let latency_text = t!(
+            "menus.stream_properties.options.latency.name",
+            latency = latency.unwrap_or(&default_text)
+        );
+        let (follow_option, follow_text) = if follows_default {
+            (
+                StreamPropertiesMenuOptions::PinToCurrent,
+                t!("menus.stream_properties.options.pin.name"),
+            )
+        } else {
+            (
+                StreamPropertiesMenuOptions::FollowDefault,
+                t!("menus.stream_properties.options.follow_default.name"),
+            )
+        };
+
+        let (lock_option, lock_text) = match lock_target {
+            Some(target) => (
+                StreamPropertiesMenuOptions::Unlock,
+                t!(
+                    "menus.stream_properties.options.unlock.name",
+                    target = target
+                ),
+            ),
+            None => (
+                StreamPropertiesMenuOptions::Lock,
+                t!("menus.stream_properties.options.lock.name"),
+            ),
+        };
+
+        let mut options = vec![
+            ("property", target_text.clone()),
+            ("property", latency_text.clone()),
+            ("stream", follow_text.clone()),
+        ];
+        // Locks are remembered by application name
+        if can_lock {
+            options.push(("lock", lock_text.clone()));
+        }
+        if !interactive {
+            options.push(("back", t!("menus.common.back")));
+        }
+
+        let input = self.get_icon_text(options, icon_type, spaces);
+        let hint = t!("menus.properties.hint", device_name = stream_name);
+
+        let menu_output =
+            self.run_launcher(launcher_command, Some(&input), icon_type, Some(&hint))?;
+
+        Ok(
+            menu_output.and_then(|output| match self.clean_menu_output(&output, icon_type) {
+                s if s == target_text => Some(StreamPropertiesMenuOptions::Target),
+                s if s == latency_text => Some(StreamPropertiesMenuOptions::Latency),
+                s if s == follow_text => Some(follow_option),
+                s if can_lock && s == lock_text => Some(lock_option),
+                s if s == t!("menus.common.back") => Some(StreamPropertiesMenuOptions::Back),
+                _ => None,
+            }),
+        )
+    }
+
+    pub async fn show_stream_target_menu(
+        &self,
+        launcher_command: &Option<String>,
+        targets: &[Node],
+        controller: &Controller,
+        icon_type: &str,
+        spaces: usize,
+        stream_name: &str,
+    ) -> Result<Option<String>> {
+        let input = targets
+            .iter()
+            .map(|node| self.format_node_display(node, controller, icon_type, spaces))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let hint = t!(
+            "menus.stream_properties.target_hint",
+            stream_name = stream_name
+        );
+        let menu_output =
+            self.run_launcher(launcher_command, Some(&input), icon_type, Some(&hint))?;
+
+        Ok(menu_output.map(|output| self.clean_menu_output(&output, icon_type)))
+    }
+
+    pub async fn show_latency_menu(
+        &self,
+        launcher_command: &Option<String>,
+        icon_type: &str,
+        spaces: usize,
+        current_latency: Option<&str>,
+        rate: u32,
+        interactive: bool,
+    ) -> Result<Option<LatencyMenuOptions>> {
+        let mut options: Vec<(&str, Cow<'static, str>)> = Vec::new();
+
+        for &quantum in &LATENCY_QUANTA {
+            let mut display_name = format_latency(quantum, rate);
+
+            if current_latency == Some(format!("{quantum}/{rate}").as_str()) {
+                let marker = self.marker("menus.common.current_marker", icon_type);
+                display_name = self.with_marker(display_name, &marker);
+            }
+
+            options.push(("property", Cow::Owned(display_name)));
+        }
+
+        if !interactive {
+            options.push(("back", t!("menus.common.back")));
+        }
+
+        let input = self.get_icon_text(options, icon_type, spaces);
+        let default_text = t!("menus.stream_properties.default");
+        let hint = t!(
+            "menus.stream_properties.latency_hint",
+            latency = current_latency.unwrap_or(&default_text)
+        );
+
+        let menu_output =
+            self.run_launcher(launcher_command, Some(&input), icon_type, Some(&hint))?;
+
+        Ok(menu_output.and_then(|output| {
+            LatencyMenuOptions::from_string_with_quanta(
+                &self.clean_menu_output(&output, icon_type),
+                &LATENCY_QUANTA,
+                rate,
+            )
+        }))
+    }
+
+    pub async fn show_link_target_menu(
+        &self,
+        launcher_command: &Option<String>,
+        targets: &[Node],
+        controller: &Controller,
+        icon_type: &str,
+        spaces: usize,
+    ) -> Result<Option<String>> {
+        let input = targets
+            .iter()
+            .map(|node| self.format_node_display(node, controller, icon_type, spaces))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let hint = t!("menus.connections.target_hint");
+        let menu_output =
+            self.run_launcher(launcher_command, Some(&input), icon_type, Some(&hint))?;
+
+        if let Some(output) = menu_output {
+            let cleaned_output = self.clean_menu_output(&output, icon_type);
+            return Ok(Some(cleaned_output));
+        }
+
+        Ok(None)
+    }
+
+    pub async fn show_port_mapping_menu(
+        &self,
+        launcher_command: &Option<String>,
+        icon_type: &str,
+        spaces: usize,
+        output_channels: usize,
+        input_channels: usize,
+    ) -> Result<Option<PortMapping>> {
+        let options: Vec<(&str, Cow<'static, str>)> = PortMapping::ALL
+            .iter()
+            .map(|&mapping| ("port_mapping", port_mapping_to_str(mapping)))
+            .collect();
+
+        let input = self.get_icon_text(options, icon_type, spaces);
+        let hint = t!(
+            "menus.port_mapping.hint",
+            output_channels = output_channels,
+            input_channels = input_channels
+        );
+
+        let menu_output =
+            self.run_launcher(launcher_command, Some(&input), icon_type, Some(&hint))?;
+
+        if let Some(output) = menu_output {
+            let cleaned_output = self.clean_menu_output(&output, icon_type);
+            return Ok(PortMapping::ALL
+                .into_iter()
+                .find(|&mapping| port_mapping_to_str(mapping) == cleaned_output));
+        }
+
+        Ok(None)
+    }
+
+    pub async fn prompt_for_name(
+        &self,
+        launcher_command: &Option<String>,
+        icon_type: &str,
+        hint: &str,
+    ) -> Result<Option<String>> {
+        let menu_output = self.run_launcher(launcher_command, Some(""), icon_type, Some(hint))?;
+
+        Ok(menu_output
+            .map(|output| output.trim().to_string())
+            .filter(|name| !name.is_empty()))
+    }
 }
@@ -1,16 +1,87 @@
-use anyhow::{anyhow, Result};
-use notify_rust::{Hint, Notification, NotificationHandle, Timeout};
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use notify_rust::{
+    get_server_information, Hint, Notification, NotificationHandle, Timeout, Urgency,
+};
+use serde::Deserialize;
 use std::{
     collections::HashMap,
-    sync::{Arc, Mutex},
+    env,
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::{Arc, Mutex, OnceLock},
+    thread,
+    time::{Duration, Instant},
 };
+use tracing::{debug, error, info, warn};
 
 use crate::{icons::Icons, pw::NodeType};
 
+const WOB_PIPE_NAME: &str = "wob.sock";
+const LEVEL_BAR_WIDTH: usize = 20;
+// Volume notifications closer together than this, as when a key is held, are merged
+const VOLUME_NOTIFICATION_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Where volume feedback is shown. Other notifications always go to the notification daemon.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OsdBackend {
+    #[default]
+    Notify,
+    Wob,
+    #[value(name = "swayosd")]
+    SwayOsd,
+}
+
+/// Urgency of mute notifications, for notification daemons that style or keep them apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationUrgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+impl From<NotificationUrgency> for Urgency {
+    fn from(urgency: NotificationUrgency) -> Self {
+        match urgency {
+            NotificationUrgency::Low => Urgency::Low,
+            NotificationUrgency::Normal => Urgency::Normal,
+            NotificationUrgency::Critical => Urgency::Critical,
+        }
+    }
+}
+
+/// A volume change as shown by the OSD backend in use.
+enum VolumeDisplay {
+    Notify(Notification),
+    Wob(u16),
+    SwayOsd {
+        icon_name: String,
+        volume_percent: u16,
+        is_muted: bool,
+    },
+}
+
+/// Last time a volume change was shown, and the one waiting for the interval to end.
+#[derive(Default)]
+struct VolumeThrottle {
+    last_shown: Option<Instant>,
+    pending: Option<VolumeDisplay>,
+}
+
+#[derive(Clone)]
 pub struct NotificationManager {
     icons: Arc<Icons>,
     handles: Arc<Mutex<HashMap<u32, NotificationHandle>>>,
     volume_notification_id: Arc<Mutex<Option<u32>>>,
+    level_notification_id: Arc<Mutex<Option<u32>>>,
+    progress_notification_id: Arc<Mutex<Option<u32>>>,
+    volume_throttle: Arc<Mutex<VolumeThrottle>>,
+    osd_backend: OsdBackend,
+    mute_urgency: Option<NotificationUrgency>,
+    available: Arc<OnceLock<bool>>,
 }
 
 impl NotificationManager {
@@ -19,13 +90,46 @@ impl NotificationManager {
             icons,
             handles: Arc::new(Mutex::new(HashMap::new())),
             volume_notification_id: Arc::new(Mutex::new(None)),
+            level_notification_id: Arc::new(Mutex::new(None)),
+            progress_notification_id: Arc::new(Mutex::new(None)),
+            volume_throttle: Arc::new(Mutex::new(VolumeThrottle::default())),
+            osd_backend: OsdBackend::default(),
+            mute_urgency: None,
+            available: Arc::new(OnceLock::new()),
         }
     }
 
+    pub fn with_osd_backend(mut self, osd_backend: OsdBackend) -> Self {
+        self.osd_backend = osd_backend;
+        self
+    }
+
+    pub fn with_mute_urgency(mut self, mute_urgency: Option<NotificationUrgency>) -> Self {
+        self.mute_urgency = mute_urgency;
+        self
+    }
+
     pub fn with_icons_default() -> Self {
         Self::new(Arc::new(Icons::default()))
     }
 
+    /// Checks once whether a notification daemon is reachable. Without one, notifications are
+    /// logged instead so headless sessions are not flooded with the same error on every action.
+    fn is_available(&self) -> bool {
+        *self
+            .available
+            .get_or_init(|| match get_server_information() {
+                Ok(server) => {
+                    debug!("Sending notifications to {}", server.name);
+                    true
+                }
+                Err(e) => {
+                    warn!("No notification daemon available, logging notifications instead: {e}");
+                    false
+                }
+            })
+    }
+
     pub fn send_notification(
         &self,
         summary: Option<String>,
@@ -33,12 +137,20 @@ impl NotificationManager {
         icon: Option<&str>,
         timeout: Option<Timeout>,
     ) -> Result<u32> {
+        let summary = summary.as_deref().unwrap_or("PipeWire Menu");
+        let body = body.as_deref().unwrap_or("");
+
+        if !self.is_available() {
+            info!("{summary}: {body}");
+            return Ok(0);
+        }
+
         let icon_name = self.icons.get_xdg_icon(icon.unwrap_or("output"));
 
         let mut notification = Notification::new();
         notification
-            .summary(summary.as_deref().unwrap_or("PipeWire Menu"))
-            .body(body.as_deref().unwrap_or(""))
+            .summary(summary)
+            .body(body)
             .icon(&icon_name)
             .timeout(timeout.unwrap_or(Timeout::Milliseconds(3000)));
 
@@ -68,61 +180,33 @@ impl NotificationManager {
         }
     }
 
-    fn get_volume_notification_icon_key(
-        &self,
-        node_type: &NodeType,
-        volume_percent: u8,
-        is_muted: bool,
-    ) -> &str {
-        if is_muted {
-            match node_type {
-                NodeType::AudioSink => "output_mute",
-                NodeType::AudioSource => "input_mute",
-                _ => "output_mute",
-            }
-        } else {
-            let volume_level = if volume_percent > 100 {
-                "overamplified"
-            } else if volume_percent > 67 {
-                "high"
-            } else if volume_percent > 33 {
-                "medium"
-            } else {
-                "low"
-            };
-
-            match (node_type, volume_level) {
-                (NodeType::AudioSink | NodeType::StreamOutputAudio, "overamplified") => {
-                    "output_volume_overamplified"
-                }
-                (NodeType::AudioSource | NodeType::StreamInputAudio, "overamplified") => {
-                    "input_volume_overamplified"
-                }
-                (NodeType::AudioSink | NodeType::StreamOutputAudio, "high") => "output_volume_high",
-                (NodeType::AudioSink | NodeType::StreamOutputAudio, "medium") => {
-                    "output_volume_medium"
-                }
-                (NodeType::AudioSink | NodeType::StreamOutputAudio, "low") => "output_volume_low",
-                (NodeType::AudioSource | NodeType::StreamInputAudio, "high") => "input_volume_high",
-                (NodeType::AudioSource | NodeType::StreamInputAudio, "medium") => {
-                    "input_volume_medium"
-                }
-                (NodeType::AudioSource | NodeType::StreamInputAudio, "low") => "input_volume_low",
-                _ => "output_volume_high",
-            }
-        }
-    }
-
+    /// Shows volume feedback through the configured OSD backend. Only a shown notification has
+    /// an ID to return; otherwise 0 is returned.
     pub fn send_volume_notification(
         &self,
         device_name: &str,
-        volume_percent: u8,
+        volume_percent: u16,
         is_muted: bool,
         node_type: &NodeType,
     ) -> Result<u32> {
-        let icon_key = self.get_volume_notification_icon_key(node_type, volume_percent, is_muted);
+        let icon_key = Icons::volume_icon_key(node_type, volume_percent, is_muted);
         let icon_name = self.icons.get_xdg_icon(icon_key);
 
+        match self.osd_backend {
+            OsdBackend::Notify => {}
+            OsdBackend::Wob => {
+                let value = if is_muted { 0 } else { volume_percent };
+                return self.show_volume_throttled(VolumeDisplay::Wob(value));
+            }
+            OsdBackend::SwayOsd => {
+                return self.show_volume_throttled(VolumeDisplay::SwayOsd {
+                    icon_name,
+                    volume_percent,
+                    is_muted,
+                });
+            }
+        }
+
         let summary = if is_muted {
             t!("notifications.pw.device_muted", device_name = device_name)
         } else if volume_percent > 100 {
@@ -136,6 +220,11 @@ impl NotificationManager {
 
         let body = device_name.to_string();
 
+        if !self.is_available() {
+            info!("{summary}: {body}");
+            return Ok(0);
+        }
+
         let progress_value = if is_muted {
             0
         } else if volume_percent <= 100 {
@@ -145,56 +234,233 @@ impl NotificationManager {
         }
         .clamp(0, 100);
 
-        let volume_id = {
-            let mut volume_id_lock = self
-                .volume_notification_id
-                .lock()
-                .map_err(|e| anyhow!("Failed to acquire volume notification ID lock: {e}"))?;
-
-            if let Some(existing_id) = *volume_id_lock {
-                existing_id
-            } else {
-                let initial_notification = Notification::new()
-                    .summary(&summary)
-                    .body(&body)
-                    .icon(&icon_name)
-                    .timeout(Timeout::Milliseconds(3000))
-                    .hint(Hint::Transient(true))
-                    .hint(Hint::Category("progress".to_string()))
-                    .hint(Hint::CustomInt("value".to_string(), progress_value))
-                    .show()?;
-
-                let new_id = initial_notification.id();
-                *volume_id_lock = Some(new_id);
-
-                let mut handles = self
-                    .handles
-                    .lock()
-                    .map_err(|e| anyhow!("Failed to acquire handles lock: {e}"))?;
-                handles.insert(new_id, initial_notification);
-
-                return Ok(new_id);
+        let mut notification = Notification::new();
+        notification
+            .summary(&summary)
+            .body(&body)
+            .icon(&icon_name)
+            .timeout(Timeout::Milliseconds(3000))
+            .hint(Hint::Transient(true))
+            .hint(Hint::Category("progress".to_string()))
+            .hint(Hint::CustomInt("value".to_string(), progress_value));
+
+        self.show_volume_throttled(VolumeDisplay::Notify(notification))
+    }
+
+    /// Shows a volume change at most once per [`VOLUME_NOTIFICATION_INTERVAL`], whatever the
+    /// OSD backend. Later ones within the interval replace each other and the last is shown
+    /// when it ends, or by [`NotificationManager::flush_volume_notification`].
+    fn show_volume_throttled(&self, display: VolumeDisplay) -> Result<u32> {
+        let mut throttle = self
+            .volume_throttle
+            .lock()
+            .map_err(|e| anyhow!("Failed to acquire volume throttle lock: {e}"))?;
+
+        let elapsed = throttle.last_shown.map(|shown| shown.elapsed());
+        if let Some(elapsed) = elapsed.filter(|&elapsed| elapsed < VOLUME_NOTIFICATION_INTERVAL) {
+            // Only the first merged notification schedules the flush
+            if throttle.pending.replace(display).is_none() {
+                let manager = self.clone();
+                thread::spawn(move || {
+                    thread::sleep(VOLUME_NOTIFICATION_INTERVAL - elapsed);
+                    manager.flush_volume_notification();
+                });
+            }
+            return Ok(0);
+        }
+
+        throttle.last_shown = Some(Instant::now());
+        drop(throttle);
+        self.show_volume(display)
+    }
+
+    fn show_volume(&self, display: VolumeDisplay) -> Result<u32> {
+        match display {
+            VolumeDisplay::Notify(mut notification) => {
+                self.show_replacing(&self.volume_notification_id, &mut notification)
+            }
+            VolumeDisplay::Wob(value) => write_wob_value(value).map(|()| 0),
+            VolumeDisplay::SwayOsd {
+                icon_name,
+                volume_percent,
+                is_muted,
+            } => run_swayosd_client(&icon_name, volume_percent, is_muted).map(|()| 0),
+        }
+    }
+
+    /// Shows the volume notification held back by the throttle, if any. Called before exiting
+    /// so the final volume is not lost.
+    pub fn flush_volume_notification(&self) {
+        let pending = self.volume_throttle.lock().ok().and_then(|mut throttle| {
+            let pending = throttle.pending.take();
+            if pending.is_some() {
+                throttle.last_shown = Some(Instant::now());
+            }
+            pending
+        });
+
+        if let Some(display) = pending {
+            if let Err(e) = self.show_volume(display) {
+                debug!("Failed to show volume notification: {e}");
             }
+        }
+    }
+
+    /// Shows a mute or unmute as a plain notification with the mute icon, in place of the last
+    /// volume notification. OSD backends show it like a volume change.
+    pub fn send_mute_notification(
+        &self,
+        device_name: &str,
+        volume_percent: u16,
+        is_muted: bool,
+        node_type: &NodeType,
+    ) -> Result<u32> {
+        if self.osd_backend != OsdBackend::Notify {
+            return self.send_volume_notification(device_name, volume_percent, is_muted, node_type);
+        }
+
+        let is_input = matches!(
+            node_type,
+            NodeType::AudioSource | NodeType::StreamInputAudio
+        );
+        let icon_key = match (is_input, is_muted) {
+            (true, true) => "input_mute",
+            (true, false) => "input_unmute",
+            (false, true) => "output_mute",
+            (false, false) => "output_unmute",
         };
+        let icon_name = self.icons.get_xdg_icon(icon_key);
 
-        let notification = Notification::new()
-            .id(volume_id)
+        let summary = if is_muted {
+            t!("notifications.pw.device_muted")
+        } else {
+            t!("notifications.pw.device_unmuted")
+        };
+        let body = device_name.to_string();
+
+        if !self.is_available() {
+            info!("{summary}: {body}");
+            return Ok(0);
+        }
+
+        let mut notification = Notification::new();
+        notification
             .summary(&summary)
             .body(&body)
             .icon(&icon_name)
             .timeout(Timeout::Milliseconds(3000))
+            .hint(Hint::Transient(true));
+        if let Some(urgency) = self.mute_urgency {
+            notification.urgency(urgency.into());
+        }
+
+        // A held back volume would otherwise replace the mute once the interval ends
+        if let Ok(mut throttle) = self.volume_throttle.lock() {
+            throttle.pending = None;
+        }
+        self.show_replacing(&self.volume_notification_id, &mut notification)
+    }
+
+    /// Shows an input level as a progress notification refreshed in place, or on the OSD
+    /// backend when one is set.
+    pub fn send_level_notification(
+        &self,
+        device_name: &str,
+        level_percent: u8,
+        level_db: f32,
+    ) -> Result<u32> {
+        let icon_name = self.icons.get_xdg_icon("input");
+
+        match self.osd_backend {
+            OsdBackend::Notify => {}
+            OsdBackend::Wob => {
+                write_wob_value(level_percent as u16)?;
+                return Ok(0);
+            }
+            OsdBackend::SwayOsd => {
+                run_swayosd_client(&icon_name, level_percent as u16, false)?;
+                return Ok(0);
+            }
+        }
+
+        let summary = t!("notifications.pw.input_level", device_name = device_name);
+        let body = format!("{} {level_db:.0} dB", level_bar(level_percent));
+
+        // Levels refresh several times a second, so skip the logging fallback
+        if !self.is_available() {
+            return Ok(0);
+        }
+
+        let mut notification = Notification::new();
+        notification
+            .summary(&summary)
+            .body(&body)
+            .icon(&icon_name)
+            .timeout(Timeout::Milliseconds(1000))
             .hint(Hint::Transient(true))
             .hint(Hint::Category("progress".to_string()))
-            .hint(Hint::CustomInt("value".to_string(), progress_value))
-            .show()?;
+            .hint(Hint::CustomInt("value".to_string(), level_percent as i32));
+
+        self.show_replacing(&self.level_notification_id, &mut notification)
+    }
+
+    /// Shows an ongoing task until [`NotificationManager::close_progress_notification`],
+    /// replacing the progress text shown before.
+    pub fn send_progress_notification(&self, body: &str) -> Result<u32> {
+        if !self.is_available() {
+            info!("{body}");
+            return Ok(0);
+        }
+
+        let icon_name = self.icons.get_xdg_icon("refresh");
+        let mut notification = Notification::new();
+        notification
+            .summary("PipeWire Menu")
+            .body(body)
+            .icon(&icon_name)
+            .timeout(Timeout::Never);
+
+        self.show_replacing(&self.progress_notification_id, &mut notification)
+    }
+
+    pub fn close_progress_notification(&self) -> Result<()> {
+        let id = self
+            .progress_notification_id
+            .lock()
+            .map_err(|e| anyhow!("Failed to acquire notification ID lock: {e}"))?
+            .take();
+
+        match id {
+            Some(id) => self.close_notification(id),
+            None => Ok(()),
+        }
+    }
+
+    /// Shows `notification` in place of the one last shown through `slot`, if any.
+    fn show_replacing(
+        &self,
+        slot: &Mutex<Option<u32>>,
+        notification: &mut Notification,
+    ) -> Result<u32> {
+        let mut id_lock = slot
+            .lock()
+            .map_err(|e| anyhow!("Failed to acquire notification ID lock: {e}"))?;
+
+        if let Some(existing_id) = *id_lock {
+            notification.id(existing_id);
+        }
+
+        let handle = notification.show()?;
+        let id = handle.id();
+        *id_lock = Some(id);
 
         let mut handles = self
             .handles
             .lock()
             .map_err(|e| anyhow!("Failed to acquire handles lock: {e}"))?;
-        handles.insert(volume_id, notification);
+        handles.insert(id, handle);
 
-        Ok(volume_id)
+        Ok(id)
     }
 
     pub fn send_default_changed_notification(
@@ -213,3 +479,54 @@ impl NotificationManager {
         self.send_notification(Some(summary), Some(body), Some(icon), None)
     }
 }
+
+fn level_bar(level_percent: u8) -> String {
+    let filled = (level_percent as usize * LEVEL_BAR_WIDTH).div_ceil(100);
+    format!(
+        "{}{}",
+        "█".repeat(filled),
+        "░".repeat(LEVEL_BAR_WIDTH.saturating_sub(filled))
+    )
+}
+
+/// Writes a value to the wob pipe, which is expected at `$XDG_RUNTIME_DIR/wob.sock` as in the
+/// wob documentation. Values above 100 are clamped since wob rejects them by default.
+fn write_wob_value(volume_percent: u16) -> Result<()> {
+    let path = env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow!("XDG_RUNTIME_DIR is not set"))?
+        .join(WOB_PIPE_NAME);
+
+    let mut pipe = OpenOptions::new()
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open wob pipe {}", path.display()))?;
+    writeln!(pipe, "{}", volume_percent.min(100))
+        .with_context(|| format!("Failed to write to wob pipe {}", path.display()))
+}
+
+fn run_swayosd_client(icon_name: &str, volume_percent: u16, is_muted: bool) -> Result<()> {
+    let progress = if is_muted {
+        0.0
+    } else {
+        (volume_percent as f32 / 100.0).min(1.0)
+    };
+
+    let mut child = Command::new("swayosd-client")
+        .arg("--custom-icon")
+        .arg(icon_name)
+        .arg("--custom-progress")
+        .arg(progress.to_string())
+        .stdin(Stdio::null())
+        .spawn()
+        .context("Failed to run swayosd-client")?;
+
+    // Reap the client in the background so quick volume steps are not held up
+    thread::spawn(move || match child.wait() {
+        Ok(status) if !status.success() => error!("swayosd-client exited with {status}"),
+        Err(e) => error!("Failed to wait for swayosd-client: {e}"),
+        _ => {}
+    });
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use anyhow::{Context, Result};
+use std::{env, fs, path::PathBuf};
+use tracing::warn;
+
+use crate::pw::{graph::AudioGraph, nodes::NodeType};
+
+/// Location of the last known graph, under the XDG cache directory.
+pub fn path() -> Option<PathBuf> {
+    let cache_dir = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+
+    Some(cache_dir.join("pwmenu").join("graph.json"))
+}
+
+/// Devices of `graph` without streams, ports and links, which rarely outlive a session.
+pub fn trim(graph: &AudioGraph) -> AudioGraph {
+    let mut trimmed = graph.clone();
+    trimmed
+        .nodes
+        .retain(|_, node| matches!(node.node_type, NodeType::AudioSink | NodeType::AudioSource));
+    trimmed.ports.clear();
+    trimmed.links.clear();
+    trimmed
+}
+
+/// Reads the graph saved by [`save`], `None` when there is none or it cannot be read.
+pub fn load() -> Option<AudioGraph> {
+    let path = path().filter(|p| p.exists())?;
+
+    let result = fs::read_to_string(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| serde_json::from_str(&content).map_err(Into::into));
+
+    match result {
+        Ok(graph) => Some(graph),
+        Err(e) => {
+            warn!("Ignoring unreadable graph cache {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// Saves the devices of `graph` for the next start, see [`trim`].
+pub fn save(graph: &AudioGraph) -> Result<()> {
+    let Some(path) = path() else {
+        return Ok(());
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create cache directory {}", dir.display()))?;
+    }
+
+    let content = serde_json::to_string(&trim(graph))?;
+    fs::write(&path, content)
+        .with_context(|| format!("Failed to write graph cache {}", path.display()))
+}
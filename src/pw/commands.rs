@@ -1,7 +1,16 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use std::{collections::HashMap, fmt, time::Duration};
 use tokio::sync::oneshot;
+use tracing::debug;
 
-use crate::pw::volume::RouteDirection;
+use crate::pw::{
+    error::PwError,
+    links::PortMapping,
+    meter::InputLevel,
+    profiler::ProfilerStats,
+    restoration::RestorationSettings,
+    volume::{RouteDirection, VolumeCurve},
+};
 
 #[derive(Debug)]
 pub enum PwCommand {
@@ -9,6 +18,8 @@ pub enum PwCommand {
         node_id: u32,
         volume: f32,
         result_sender: oneshot::Sender<Result<()>>,
+        /// Senders of the commands merged into this one, see [`coalesce_volume_commands`].
+        merged: Vec<oneshot::Sender<Result<()>>>,
     },
     SetNodeMute {
         node_id: u32,
@@ -18,6 +29,7 @@ pub enum PwCommand {
     CreateLink {
         output_node: u32,
         input_node: u32,
+        mapping: PortMapping,
         result_sender: oneshot::Sender<Result<()>>,
     },
     RemoveLink {
@@ -33,6 +45,20 @@ pub enum PwCommand {
         node_id: u32,
         result_sender: oneshot::Sender<Result<()>>,
     },
+    MoveStreams {
+        node_id: u32,
+        result_sender: oneshot::Sender<Result<usize>>,
+    },
+    SetStreamTarget {
+        stream_id: u32,
+        target_id: Option<u32>,
+        result_sender: oneshot::Sender<Result<()>>,
+    },
+    SetNodeLatency {
+        node_id: u32,
+        latency: String,
+        result_sender: oneshot::Sender<Result<()>>,
+    },
     SwitchDeviceProfile {
         device_id: u32,
         profile_index: u32,
@@ -43,21 +69,324 @@ pub enum PwCommand {
         profile_index: u32,
         result_sender: oneshot::Sender<Result<()>>,
     },
+    CancelProfileRestoration {
+        device_id: u32,
+        result_sender: oneshot::Sender<Result<()>>,
+    },
+    ConfigureRestoration {
+        settings: RestorationSettings,
+        result_sender: oneshot::Sender<Result<()>>,
+    },
+    SetPreferredNodes {
+        names: Vec<String>,
+        result_sender: oneshot::Sender<Result<()>>,
+    },
+    /// Kept across reconnections, and applied even while disconnected.
+    SetVolumeCurve {
+        curve: VolumeCurve,
+    },
     SetDeviceVolume {
         device_id: u32,
         volume: f32,
         direction: Option<RouteDirection>,
+        card_device: Option<i32>,
         result_sender: oneshot::Sender<Result<()>>,
+        merged: Vec<oneshot::Sender<Result<()>>>,
     },
     SetDeviceMute {
         device_id: u32,
         mute: bool,
         direction: Option<RouteDirection>,
+        card_device: Option<i32>,
         result_sender: oneshot::Sender<Result<()>>,
     },
     SetSampleRate {
         sample_rate: u32,
         result_sender: oneshot::Sender<Result<()>>,
     },
+    CreateVirtualSink {
+        description: String,
+        result_sender: oneshot::Sender<Result<()>>,
+    },
+    DestroyManagedNode {
+        node_id: u32,
+        result_sender: oneshot::Sender<Result<()>>,
+    },
+    RenameManagedNode {
+        node_id: u32,
+        description: String,
+        result_sender: oneshot::Sender<Result<()>>,
+    },
+    DestroyAllManaged {
+        result_sender: oneshot::Sender<Result<usize>>,
+    },
+    PlayTestTone {
+        node_id: u32,
+        result_sender: oneshot::Sender<Result<()>>,
+    },
+    StartLevelMeter {
+        node_id: u32,
+        result_sender: oneshot::Sender<Result<InputLevel>>,
+    },
+    StopLevelMeter {
+        result_sender: oneshot::Sender<Result<()>>,
+    },
+    SyncStreamMeters {
+        node_ids: Vec<u32>,
+        result_sender: oneshot::Sender<Result<HashMap<u32, InputLevel>>>,
+    },
+    StartProfiler {
+        result_sender: oneshot::Sender<Result<ProfilerStats>>,
+    },
+    StopProfiler {
+        result_sender: oneshot::Sender<Result<()>>,
+    },
+    /// Runs every step in one pass through the loop, with a single graph update.
+    Batch {
+        steps: Vec<BatchStep>,
+        result_sender: oneshot::Sender<Result<()>>,
+    },
     Exit,
 }
+
+/// One operation of a [`PwCommand::Batch`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchStep {
+    SetNodeVolume { node_id: u32, volume: f32 },
+    SetNodeMute { node_id: u32, mute: bool },
+    SetDefaultSink { node_id: u32 },
+    SetDefaultSource { node_id: u32 },
+    MoveStreams { node_id: u32 },
+}
+
+/// What a volume command sets, see [`coalesce_volume_commands`].
+#[derive(Debug, PartialEq)]
+enum VolumeTarget {
+    Node(u32),
+    Device(u32, Option<RouteDirection>, Option<i32>),
+}
+
+/// Merges runs of volume commands for the same node or device route into their last one, so
+/// a held key costs one update instead of a round trip per step. The merged commands get the
+/// result of the one they were merged into, see [`send_volume_result`].
+pub fn coalesce_volume_commands(commands: Vec<PwCommand>) -> Vec<PwCommand> {
+    let mut coalesced: Vec<PwCommand> = Vec::with_capacity(commands.len());
+    for mut cmd in commands {
+        let target = cmd.volume_target();
+        if target.is_some() && coalesced.last().and_then(PwCommand::volume_target) == target {
+            if let Some(superseded) = coalesced.pop() {
+                cmd.absorb(superseded);
+            }
+        }
+        coalesced.push(cmd);
+    }
+    coalesced
+}
+
+/// Answers a volume command and the commands merged into it with the same result.
+pub fn send_volume_result(
+    result_sender: oneshot::Sender<Result<()>>,
+    merged: Vec<oneshot::Sender<Result<()>>>,
+    result: Result<()>,
+) -> Result<(), Result<()>> {
+    for sender in merged {
+        // anyhow errors cannot be cloned, the copies keep the message and the PwError
+        let copy = match &result {
+            Ok(()) => Ok(()),
+            Err(e) => Err(match PwError::find(e) {
+                Some(error) => anyhow::Error::from(error.clone()).context(format!("{e:#}")),
+                None => anyhow!("{e:#}"),
+            }),
+        };
+        let _ = sender.send(copy);
+    }
+    result_sender.send(result)
+}
+
+impl PwCommand {
+    fn volume_target(&self) -> Option<VolumeTarget> {
+        match self {
+            Self::SetNodeVolume { node_id, .. } => Some(VolumeTarget::Node(*node_id)),
+            Self::SetDeviceVolume {
+                device_id,
+                direction,
+                card_device,
+                ..
+            } => Some(VolumeTarget::Device(*device_id, *direction, *card_device)),
+            _ => None,
+        }
+    }
+
+    /// Takes over the callers of `superseded`, whose volume this command replaces.
+    fn absorb(&mut self, superseded: PwCommand) {
+        debug!("{} superseded by a later volume", superseded.name());
+        let (Self::SetNodeVolume { merged, .. } | Self::SetDeviceVolume { merged, .. }) = self
+        else {
+            return;
+        };
+        if let PwCommand::SetNodeVolume {
+            result_sender,
+            merged: earlier,
+            ..
+        }
+        | PwCommand::SetDeviceVolume {
+            result_sender,
+            merged: earlier,
+            ..
+        } = superseded
+        {
+            merged.extend(earlier);
+            merged.push(result_sender);
+        }
+    }
+
+    /// Whether the caller stopped waiting for the result, in which case the command is skipped.
+    pub fn is_cancelled(&self) -> bool {
+        match self {
+            Self::SetNodeVolume { result_sender, .. } => result_sender.is_closed(),
+            Self::SetNodeMute { result_sender, .. } => result_sender.is_closed(),
+            Self::CreateLink { result_sender, .. } => result_sender.is_closed(),
+            Self::RemoveLink { result_sender, .. } => result_sender.is_closed(),
+            Self::SetDefaultSink { result_sender, .. } => result_sender.is_closed(),
+            Self::SetDefaultSource { result_sender, .. } => result_sender.is_closed(),
+            Self::MoveStreams { result_sender, .. } => result_sender.is_closed(),
+            Self::SetStreamTarget { result_sender, .. } => result_sender.is_closed(),
+            Self::SetNodeLatency { result_sender, .. } => result_sender.is_closed(),
+            Self::SwitchDeviceProfile { result_sender, .. } => result_sender.is_closed(),
+            Self::SwitchDeviceProfileWithRestoration { result_sender, .. } => {
+                result_sender.is_closed()
+            }
+            Self::CancelProfileRestoration { result_sender, .. } => result_sender.is_closed(),
+            Self::ConfigureRestoration { result_sender, .. } => result_sender.is_closed(),
+            Self::SetPreferredNodes { result_sender, .. } => result_sender.is_closed(),
+            Self::SetVolumeCurve { .. } => false,
+            Self::SetDeviceVolume { result_sender, .. } => result_sender.is_closed(),
+            Self::SetDeviceMute { result_sender, .. } => result_sender.is_closed(),
+            Self::SetSampleRate { result_sender, .. } => result_sender.is_closed(),
+            Self::CreateVirtualSink { result_sender, .. } => result_sender.is_closed(),
+            Self::DestroyManagedNode { result_sender, .. } => result_sender.is_closed(),
+            Self::RenameManagedNode { result_sender, .. } => result_sender.is_closed(),
+            Self::DestroyAllManaged { result_sender, .. } => result_sender.is_closed(),
+            Self::PlayTestTone { result_sender, .. } => result_sender.is_closed(),
+            Self::StartLevelMeter { result_sender, .. } => result_sender.is_closed(),
+            Self::StopLevelMeter { result_sender, .. } => result_sender.is_closed(),
+            Self::SyncStreamMeters { result_sender, .. } => result_sender.is_closed(),
+            Self::StartProfiler { result_sender, .. } => result_sender.is_closed(),
+            Self::StopProfiler { result_sender, .. } => result_sender.is_closed(),
+            Self::Batch { result_sender, .. } => result_sender.is_closed(),
+            Self::Exit => false,
+        }
+    }
+
+    /// Variant name, for logs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SetNodeVolume { .. } => "SetNodeVolume",
+            Self::SetNodeMute { .. } => "SetNodeMute",
+            Self::CreateLink { .. } => "CreateLink",
+            Self::RemoveLink { .. } => "RemoveLink",
+            Self::SetDefaultSink { .. } => "SetDefaultSink",
+            Self::SetDefaultSource { .. } => "SetDefaultSource",
+            Self::MoveStreams { .. } => "MoveStreams",
+            Self::SetStreamTarget { .. } => "SetStreamTarget",
+            Self::SetNodeLatency { .. } => "SetNodeLatency",
+            Self::SwitchDeviceProfile { .. } => "SwitchDeviceProfile",
+            Self::SwitchDeviceProfileWithRestoration { .. } => "SwitchDeviceProfileWithRestoration",
+            Self::CancelProfileRestoration { .. } => "CancelProfileRestoration",
+            Self::ConfigureRestoration { .. } => "ConfigureRestoration",
+            Self::SetPreferredNodes { .. } => "SetPreferredNodes",
+            Self::SetVolumeCurve { .. } => "SetVolumeCurve",
+            Self::SetDeviceVolume { .. } => "SetDeviceVolume",
+            Self::SetDeviceMute { .. } => "SetDeviceMute",
+            Self::SetSampleRate { .. } => "SetSampleRate",
+            Self::CreateVirtualSink { .. } => "CreateVirtualSink",
+            Self::DestroyManagedNode { .. } => "DestroyManagedNode",
+            Self::RenameManagedNode { .. } => "RenameManagedNode",
+            Self::DestroyAllManaged { .. } => "DestroyAllManaged",
+            Self::PlayTestTone { .. } => "PlayTestTone",
+            Self::StartLevelMeter { .. } => "StartLevelMeter",
+            Self::StopLevelMeter { .. } => "StopLevelMeter",
+            Self::SyncStreamMeters { .. } => "SyncStreamMeters",
+            Self::StartProfiler { .. } => "StartProfiler",
+            Self::StopProfiler { .. } => "StopProfiler",
+            Self::Batch { .. } => "Batch",
+            Self::Exit => "Exit",
+        }
+    }
+}
+
+/// Failure to get a command result back from the PipeWire thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandError {
+    /// The PipeWire thread is not running.
+    Closed,
+    /// No result arrived within the command timeout. The command is dropped if the loop has
+    /// not started it yet.
+    TimedOut(Duration),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Closed => write!(f, "PipeWire thread is not running"),
+            Self::TimedOut(timeout) => write!(f, "PipeWire command timed out after {timeout:?}"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consecutive_volume_commands_are_merged() {
+        let mut receivers = Vec::new();
+        let mut set_volume = |node_id: u32, volume: f32| {
+            let (result_sender, receiver) = oneshot::channel();
+            receivers.push(receiver);
+            PwCommand::SetNodeVolume {
+                node_id,
+                volume,
+                result_sender,
+                merged: Vec::new(),
+            }
+        };
+        let commands = vec![
+            set_volume(52, 0.5),
+            set_volume(52, 0.55),
+            set_volume(52, 0.6),
+            set_volume(53, 0.3),
+            set_volume(52, 0.65),
+        ];
+
+        let mut coalesced = coalesce_volume_commands(commands);
+        let kept: Vec<(u32, f32)> = coalesced
+            .iter()
+            .map(|cmd| match cmd {
+                PwCommand::SetNodeVolume {
+                    node_id, volume, ..
+                } => (*node_id, *volume),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(kept, [(52, 0.6), (53, 0.3), (52, 0.65)]);
+
+        // Merged commands wait for the result of the one they were merged into
+        assert!(receivers[0].try_recv().is_err());
+        let PwCommand::SetNodeVolume {
+            result_sender,
+            merged,
+            ..
+        } = coalesced.remove(0)
+        else {
+            unreachable!();
+        };
+        let _ = send_volume_result(result_sender, merged, Err(anyhow!("Node 52 is gone")));
+        for receiver in &mut receivers[..3] {
+            assert!(receiver.try_recv().unwrap().is_err());
+        }
+        assert!(receivers[3].try_recv().is_err());
+    }
+}
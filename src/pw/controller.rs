@@ -1,17 +1,48 @@
 use anyhow::{anyhow, Result};
-use log::debug;
-use std::sync::Arc;
-
-use crate::pw::{
-    devices::{DeviceType, Profile},
-    engine::PwEngine,
-    nodes::{Node, NodeType, Volume},
-    volume::RouteDirection,
-    AudioGraph,
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex, Once},
+};
+use tracing::{debug, warn};
+
+use crate::{
+    pw::{
+        commands::BatchStep,
+        devices::{DeviceType, Profile, RoutePort},
+        engine::{PwEngine, DEFAULT_COMMAND_TIMEOUT},
+        error::{ObjectKind, PwError},
+        events::{GraphDiff, GraphEvents},
+        links::{PortDirection, PortMapping},
+        meter::InputLevel,
+        mock::MockEngine,
+        nodes::{Node, NodeType, Volume},
+        profiler::ProfilerStats,
+        recording::read_recording,
+        restoration::RestorationSettings,
+        source::GraphSource,
+        volume::{RouteDirection, VolumeCurve, VolumeResolver},
+        AudioGraph, ConnectionStatus,
+    },
+    scene::Scene,
+    state::{State, StreamLock},
+};
+use tokio::{
+    sync::watch,
+    time::{sleep, Duration, Instant},
 };
 
+// Profile switches recreate nodes, so give them time to show up before applying the rest
+const SCENE_NODE_TIMEOUT: Duration = Duration::from_secs(3);
+const SCENE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Bluetooth codecs can take a few seconds to renegotiate
+const PROFILE_SWITCH_TIMEOUT: Duration = Duration::from_secs(5);
+// Route volumes come back through the volume curve, so allow for rounding
+const SAVED_VOLUME_TOLERANCE: f32 = 0.005;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-enum FormFactorPriority {
+pub(crate) enum FormFactorPriority {
     Headphones = 0,
     Headset = 1,
     HandsFree = 2,
@@ -29,13 +60,45 @@ enum FormFactorPriority {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-enum BusPriority {
+pub(crate) enum BusPriority {
     Usb = 0,
     Bluetooth = 1,
     Pci = 2,
     Unknown = 3,
 }
 
+impl FormFactorPriority {
+    pub(crate) fn from_form_factor(form_factor: Option<&str>) -> Self {
+        match form_factor {
+            Some("headphone") => FormFactorPriority::Headphones,
+            Some("headset") => FormFactorPriority::Headset,
+            Some("hands-free") => FormFactorPriority::HandsFree,
+            Some("handset") => FormFactorPriority::Handset,
+            Some("speaker") => FormFactorPriority::Speaker,
+            Some("microphone") => FormFactorPriority::Microphone,
+            Some("webcam") => FormFactorPriority::Webcam,
+            Some("portable") => FormFactorPriority::Portable,
+            Some("car") => FormFactorPriority::Car,
+            Some("hifi") => FormFactorPriority::Hifi,
+            Some("tv") => FormFactorPriority::Tv,
+            Some("computer") => FormFactorPriority::Computer,
+            Some("internal") => FormFactorPriority::Internal,
+            _ => FormFactorPriority::Unknown,
+        }
+    }
+}
+
+impl BusPriority {
+    pub(crate) fn from_bus(bus: Option<&str>) -> Self {
+        match bus {
+            Some("bluetooth") => BusPriority::Bluetooth,
+            Some("pci") => BusPriority::Pci,
+            Some("usb") => BusPriority::Usb,
+            _ => BusPriority::Unknown,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DeviceInfo {
     pub nick: Option<String>,
@@ -46,23 +109,378 @@ pub struct DeviceInfo {
     pub node_type: NodeType,
 }
 
+/// A connector with jack detection, on the card named `device_name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Jack {
+    pub device_id: u32,
+    pub device_name: String,
+    pub port: RoutePort,
+}
+
 pub struct Controller {
-    engine: Arc<PwEngine>,
+    engine: Arc<dyn GraphSource>,
+    cached_graph: Option<Arc<AudioGraph>>,
+    state_path: Option<PathBuf>,
+    state: Mutex<State>,
+    session_manager_warning: Once,
+    max_volume_jump: Option<f32>,
+    volume_curve: Mutex<VolumeCurve>,
 }
 
 impl Controller {
     pub async fn new() -> Result<Self> {
-        let engine = Arc::new(PwEngine::new().await?);
+        Self::connect(None, DEFAULT_COMMAND_TIMEOUT).await
+    }
+
+    /// See [`PwEngine::connect`].
+    pub async fn connect(remote: Option<String>, command_timeout: Duration) -> Result<Self> {
+        let engine = PwEngine::connect(remote, command_timeout).await?;
+        Ok(Self::with_source(Arc::new(engine)))
+    }
+
+    /// Controller over a recording made with `pwmenu record-graph`, replayed in real time.
+    pub fn replay(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let frames = read_recording(path)?;
+        let engine = Arc::new(MockEngine::from_recording(&frames)?);
+
+        let replay = engine.clone();
+        tokio::spawn(async move { replay.replay(frames).await });
+
+        Ok(Self::with_source(engine))
+    }
+
+    /// Controller over any graph source, such as a
+    /// [`MockEngine`](crate::pw::mock::MockEngine) in tests.
+    pub fn with_source(engine: Arc<dyn GraphSource>) -> Self {
+        Self::with_state_file(engine, State::path())
+    }
+
+    /// Controller over `engine` keeping its state at `state_path` instead of the user's state
+    /// file, or nowhere without a path, as in tests.
+    pub fn with_state_file(engine: Arc<dyn GraphSource>, state_path: Option<PathBuf>) -> Self {
+        Self {
+            engine,
+            cached_graph: None,
+            state: Mutex::new(State::load_from(state_path.as_deref())),
+            state_path,
+            session_manager_warning: Once::new(),
+            max_volume_jump: None,
+            volume_curve: Mutex::new(VolumeCurve::default()),
+        }
+    }
+
+    /// Serves `graph`, such as one from [`crate::pw::cache::load`], until the engine has
+    /// listed the registry, so menus can open before PipeWire answers.
+    pub fn with_cached_graph(mut self, mut graph: AudioGraph) -> Self {
+        graph.apply_volume_curve(self.volume_curve());
+        self.cached_graph = Some(Arc::new(graph));
+        self
+    }
+
+    /// Holds volume increases to `percent` points per change, see
+    /// [`Controller::limit_volume_jump`].
+    pub fn with_max_volume_jump(mut self, percent: Option<u8>) -> Self {
+        self.max_volume_jump = percent.map(|percent| f32::from(percent) / 100.0);
+        self
+    }
+
+    /// Reads and writes volumes on `curve`, see [`Controller::set_volume_curve`].
+    pub fn with_volume_curve(mut self, curve: VolumeCurve) -> Self {
+        self.set_volume_curve(curve);
+        if let Some(graph) = &mut self.cached_graph {
+            Arc::make_mut(graph).apply_volume_curve(curve);
+        }
+        self
+    }
+
+    /// Switches the curve volumes are shown, set and stepped on, such as when the config is
+    /// reloaded. Saved volumes are kept as raw amplitudes, so they come back on the new curve.
+    pub fn set_volume_curve(&self, curve: VolumeCurve) {
+        if let Ok(mut volume_curve) = self.volume_curve.lock() {
+            *volume_curve = curve;
+        }
+        if let Err(e) = self.engine.set_volume_curve(curve) {
+            warn!("Failed to switch the volume curve: {e}");
+        }
+    }
+
+    pub fn volume_curve(&self) -> VolumeCurve {
+        self.volume_curve
+            .lock()
+            .map_or(VolumeCurve::default(), |curve| *curve)
+    }
+
+    /// Whether reads still come from the cached graph rather than from PipeWire.
+    pub fn is_showing_cache(&self) -> bool {
+        self.cached_graph.is_some() && !self.engine.graph().is_connected()
+    }
+
+    fn graph(&self) -> Arc<AudioGraph> {
+        let live = self.engine.graph();
+        match &self.cached_graph {
+            Some(cached) if !live.is_connected() => cached.clone(),
+            _ => live,
+        }
+    }
+
+    pub async fn record_node_selection(&self, node: &Node) {
+        self.update_state(|state| state.record_node(&node.name));
+        if let Err(e) = self.sync_preferred_nodes().await {
+            debug!("Failed to update preferred nodes: {e}");
+        }
+    }
+
+    /// Hands the recently selected nodes to the engine, which promotes them first when the
+    /// default node goes away.
+    pub async fn sync_preferred_nodes(&self) -> Result<()> {
+        let names = self
+            .state
+            .lock()
+            .map(|state| state.recent_nodes.clone())
+            .unwrap_or_default();
+        self.engine.set_preferred_nodes(names).await
+    }
+
+    /// Applies `f` to the state file, keeping the loaded copy in sync.
+    fn update_state(&self, f: impl FnOnce(&mut State)) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+
+        match State::update_at(self.state_path.as_deref(), f) {
+            Ok(updated) => *state = updated,
+            Err(e) => warn!("Failed to save state: {e}"),
+        }
+    }
+
+    fn load_state(&self) -> State {
+        State::load_from(self.state_path.as_deref())
+    }
+
+    /// Records the volume and mute state of output and input devices in the state file,
+    /// keeping what was saved for devices that are not plugged in. Returns whether anything
+    /// changed.
+    pub fn save_volumes(&self) -> Result<bool> {
+        let mut state = self.load_state();
+        let curve = self.volume_curve();
+        let mut changed = false;
+
+        for node in self
+            .get_output_nodes()
+            .into_iter()
+            .chain(self.get_input_nodes())
+        {
+            if state
+                .volumes
+                .get(&node.name)
+                .is_some_and(|saved| same_volume(&saved.with_curve(curve), &node.volume))
+            {
+                continue;
+            }
+            state.volumes.insert(node.name, node.volume);
+            changed = true;
+        }
 
-        Ok(Self { engine })
+        if changed {
+            state.save_to(self.state_path.as_deref())?;
+        }
+        Ok(changed)
+    }
+
+    /// Puts back the saved volume and mute state of devices that differ from it, such as
+    /// those the session manager did not restore. Returns how many devices were changed.
+    pub async fn restore_volumes(&self) -> Result<usize> {
+        let state = self.load_state();
+        let curve = self.volume_curve();
+        let mut restored = 0;
+
+        for node in self
+            .get_output_nodes()
+            .into_iter()
+            .chain(self.get_input_nodes())
+        {
+            let Some(saved) = state
+                .volumes
+                .get(&node.name)
+                .map(|saved| saved.with_curve(curve))
+            else {
+                continue;
+            };
+            if same_volume(&saved, &node.volume) {
+                continue;
+            }
+
+            if (saved.linear - node.volume.linear).abs() >= SAVED_VOLUME_TOLERANCE {
+                self.set_volume(node.id, saved.linear).await?;
+            }
+            if saved.muted != node.volume.muted {
+                self.set_mute(node.id, saved.muted).await?;
+            }
+            debug!(
+                "Restored volume of {} to {}%{}",
+                node.name,
+                saved.percent(),
+                if saved.muted { " (muted)" } else { "" }
+            );
+            restored += 1;
+        }
+
+        Ok(restored)
+    }
+
+    /// Pins the streams of `stream`'s application to `target`, now and whenever it reconnects
+    /// while the daemon runs. Returns how many streams were moved.
+    pub async fn lock_stream_to(&self, stream: &Node, target: &Node) -> Result<usize> {
+        let application = stream
+            .application_name
+            .clone()
+            .ok_or_else(|| anyhow!("Stream {} has no application name", stream.id))?;
+
+        State::update_at(self.state_path.as_deref(), |state| {
+            state.lock_stream(StreamLock {
+                application: application.clone(),
+                stream_type: stream.node_type,
+                target: target.name.clone(),
+            })
+        })?;
+
+        let mut locked = 0;
+        for node in self.get_application_streams(&application, stream.node_type) {
+            self.set_stream_target(node.id, Some(target.id)).await?;
+            locked += 1;
+        }
+        Ok(locked)
+    }
+
+    /// Drops the lock of `stream`'s application, its streams follow the default again.
+    pub async fn unlock_stream(&self, stream: &Node) -> Result<()> {
+        let Some(application) = &stream.application_name else {
+            return Ok(());
+        };
+
+        State::update_at(self.state_path.as_deref(), |state| {
+            state.unlock_stream(application, stream.node_type)
+        })?;
+
+        for node in self.get_application_streams(application, stream.node_type) {
+            self.set_stream_target(node.id, None).await?;
+        }
+        Ok(())
+    }
+
+    pub fn get_stream_lock(&self, stream: &Node) -> Option<StreamLock> {
+        let application = stream.application_name.as_ref()?;
+        self.load_state()
+            .stream_lock(application, stream.node_type)
+            .cloned()
+    }
+
+    /// Re-applies the locks matching the given streams, for applications that reconnected.
+    /// Locks whose device is missing are left for later.
+    pub async fn apply_stream_locks(&self, stream_ids: &[u32]) -> Result<usize> {
+        let state = self.load_state();
+        if state.stream_locks.is_empty() {
+            return Ok(0);
+        }
+
+        let graph = self.graph();
+        let mut applied = 0;
+        for stream in stream_ids.iter().filter_map(|id| graph.nodes.get(id)) {
+            let Some(lock) = stream
+                .application_name
+                .as_ref()
+                .and_then(|application| state.stream_lock(application, stream.node_type))
+            else {
+                continue;
+            };
+            if stream.target_object.as_ref() == Some(&lock.target) {
+                continue;
+            }
+            let Some(target) = graph.nodes.values().find(|node| node.name == lock.target) else {
+                continue;
+            };
+
+            self.set_stream_target(stream.id, Some(target.id)).await?;
+            debug!("Locked stream {} to {}", stream.id, target.name);
+            applied += 1;
+        }
+
+        Ok(applied)
+    }
+
+    fn get_application_streams(&self, application: &str, stream_type: NodeType) -> Vec<Node> {
+        self.engine
+            .graph()
+            .nodes
+            .values()
+            .filter(|node| {
+                node.node_type == stream_type
+                    && node.application_name.as_deref() == Some(application)
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn get_recent_rank(&self, node: &Node) -> usize {
+        self.state
+            .lock()
+            .ok()
+            .and_then(|state| state.recent_rank(&node.name))
+            .unwrap_or(usize::MAX)
     }
 
     pub async fn wait_for_initialization(&self) -> Result<()> {
-        self.engine.wait_for_initialization().await
+        self.engine.wait_for_initialization().await?;
+
+        if !self.has_session_manager() {
+            self.session_manager_warning.call_once(|| {
+                warn!("No session manager is running, default devices cannot be changed");
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Stops the PipeWire connection, waiting for it to close. See [`PwEngine::shutdown`].
+    pub async fn shutdown(&self) -> Result<()> {
+        self.engine.shutdown().await
+    }
+
+    /// Snapshot of every node, device, port and link as last reported by PipeWire, or the
+    /// cached graph while [`Controller::is_showing_cache`].
+    pub fn get_graph(&self) -> Arc<AudioGraph> {
+        self.graph()
+    }
+
+    /// Receiver notified whenever the audio graph changes.
+    pub fn subscribe(&self) -> watch::Receiver<Arc<AudioGraph>> {
+        self.engine.subscribe()
+    }
+
+    /// Stream of node, default device and volume changes computed from graph updates.
+    pub fn events(&self) -> GraphEvents {
+        self.engine.events()
+    }
+
+    /// What changed from `old` to `new`, such as two graphs from [`Controller::subscribe`].
+    /// Sink and source volumes are the device volumes shown in menus.
+    pub fn diff(&self, old: &AudioGraph, new: &AudioGraph) -> GraphDiff {
+        GraphDiff::between(
+            &self.with_device_volumes(old),
+            &self.with_device_volumes(new),
+        )
+    }
+
+    fn with_device_volumes(&self, graph: &AudioGraph) -> AudioGraph {
+        let mut enhanced = graph.clone();
+        for node in enhanced.nodes.values_mut() {
+            *node = self.enhance_node_volume(node, graph);
+        }
+        enhanced
     }
 
     pub fn get_output_nodes(&self) -> Vec<Node> {
-        let graph = self.engine.graph();
+        let graph = self.graph();
 
         let nodes: Vec<Node> = graph
             .nodes
@@ -75,7 +493,7 @@ impl Controller {
     }
 
     pub fn get_input_nodes(&self) -> Vec<Node> {
-        let graph = self.engine.graph();
+        let graph = self.graph();
 
         let nodes: Vec<Node> = graph
             .nodes
@@ -88,7 +506,7 @@ impl Controller {
     }
 
     pub fn get_output_streams(&self) -> Vec<Node> {
-        let graph = self.engine.graph();
+        let graph = self.graph();
 
         graph
             .nodes
@@ -99,7 +517,7 @@ impl Controller {
     }
 
     pub fn get_input_streams(&self) -> Vec<Node> {
-        let graph = self.engine.graph();
+        let graph = self.graph();
 
         graph
             .nodes
@@ -109,8 +527,94 @@ impl Controller {
             .collect()
     }
 
+    pub fn get_managed_nodes(&self) -> Vec<Node> {
+        let graph = self.graph();
+
+        let mut nodes: Vec<Node> = graph
+            .nodes
+            .values()
+            .filter(|n| n.is_managed)
+            .map(|n| self.enhance_node_volume(n, &graph))
+            .collect();
+
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        nodes
+    }
+
+    /// Recording streams linked to a source, each with the first source feeding it. pwmenu's own
+    /// streams are left out.
+    pub fn get_active_capture_streams(&self) -> Vec<(Node, Node)> {
+        let graph = self.graph();
+
+        let mut streams: Vec<(Node, Node)> = Vec::new();
+        for link in graph.links.values() {
+            let (Some(stream), Some(source)) = (
+                graph.nodes.get(&link.input_node),
+                graph.nodes.get(&link.output_node),
+            ) else {
+                continue;
+            };
+
+            if stream.node_type != NodeType::StreamInputAudio
+                || stream.application_name.as_deref() == Some("pwmenu")
+                || !matches!(
+                    source.node_type,
+                    NodeType::AudioSource | NodeType::AudioDuplex
+                )
+                || streams.iter().any(|(s, _)| s.id == stream.id)
+            {
+                continue;
+            }
+
+            streams.push((
+                self.enhance_node_volume(stream, &graph),
+                self.enhance_node_volume(source, &graph),
+            ));
+        }
+
+        streams.sort_by_key(|(stream, _)| stream.id);
+        streams
+    }
+
+    /// Sinks that `node_id` can be linked into, excluding itself.
+    pub fn get_link_targets(&self, node_id: u32) -> Vec<Node> {
+        self.get_output_nodes()
+            .into_iter()
+            .filter(|n| n.id != node_id)
+            .collect()
+    }
+
+    /// Nodes fed by links pwmenu created from `node_id`.
+    pub fn get_linked_nodes(&self, node_id: u32) -> Vec<Node> {
+        let graph = self.graph();
+
+        let mut target_ids: Vec<u32> = graph
+            .links
+            .values()
+            .filter(|l| l.is_managed && l.output_node == node_id)
+            .map(|l| l.input_node)
+            .collect();
+        target_ids.sort_unstable();
+        target_ids.dedup();
+
+        target_ids
+            .into_iter()
+            .filter_map(|id| graph.nodes.get(&id))
+            .map(|n| self.enhance_node_volume(n, &graph))
+            .collect()
+    }
+
+    pub fn get_port_count(&self, node_id: u32, direction: PortDirection) -> usize {
+        let graph = self.graph();
+        graph
+            .ports
+            .values()
+            .filter(|p| p.node_id == node_id && p.direction == direction)
+            .count()
+    }
+
     pub fn get_node(&self, node_id: u32) -> Option<Node> {
-        let graph = self.engine.graph();
+        let graph = self.graph();
         let node = graph.nodes.get(&node_id)?;
         Some(self.enhance_node_volume(node, &graph))
     }
@@ -118,21 +622,23 @@ impl Controller {
     fn enhance_node_volume(&self, node: &Node, graph: &AudioGraph) -> Node {
         if let Some(device_id) = node.device_id {
             if let Some(device) = graph.devices.get(&device_id) {
-                if device.has_route_volume {
-                    let route_direction = match node.node_type {
-                        NodeType::AudioSink => Some(RouteDirection::Output),
-                        NodeType::AudioSource => Some(RouteDirection::Input),
-                        _ => None,
-                    };
-
-                    if let Some(direction) = route_direction {
-                        if let Some((route_volume, route_muted)) =
-                            self.get_cached_route_volume(device, direction)
-                        {
-                            let mut enhanced_node = node.clone();
-                            enhanced_node.volume = Volume::new(route_volume, route_muted);
-                            return enhanced_node;
-                        }
+                let route_direction = match node.node_type {
+                    NodeType::AudioSink => Some(RouteDirection::Output),
+                    NodeType::AudioSource => Some(RouteDirection::Input),
+                    _ => None,
+                };
+
+                if let Some(direction) = route_direction
+                    .filter(|&direction| device.uses_route_volume(direction, node.card_device()))
+                {
+                    if let Some((route_volume, route_muted)) = device
+                        .route(direction, node.card_device())
+                        .get_volume_state()
+                    {
+                        let mut enhanced_node = node.clone();
+                        enhanced_node.volume =
+                            Volume::on_curve(self.volume_curve(), route_volume, route_muted);
+                        return enhanced_node;
                     }
                 }
             }
@@ -140,89 +646,104 @@ impl Controller {
         node.clone()
     }
 
-    fn get_cached_route_volume(
-        &self,
-        device: &crate::pw::devices::Device,
-        direction: RouteDirection,
-    ) -> Option<(f32, bool)> {
-        match direction {
-            RouteDirection::Output => device.output_route.get_volume_state(),
-            RouteDirection::Input => device.input_route.get_volume_state(),
+    /// Groups nodes under their parent device, keeping the order in which devices first appear.
+    pub fn group_nodes_by_device(&self, nodes: &[Node]) -> Vec<(Option<u32>, Vec<Node>)> {
+        let graph = self.graph();
+        let mut groups: Vec<(Option<u32>, Vec<Node>)> = Vec::new();
+
+        for node in nodes {
+            let device_id = node.device_id.or_else(|| {
+                graph
+                    .devices
+                    .values()
+                    .find(|d| d.nodes.contains(&node.id))
+                    .map(|d| d.id)
+            });
+
+            match groups
+                .iter_mut()
+                .find(|(id, _)| device_id.is_some() && *id == device_id)
+            {
+                Some((_, group)) => group.push(node.clone()),
+                None => groups.push((device_id, vec![node.clone()])),
+            }
         }
+
+        groups
     }
 
     fn sort_nodes_by_priority(&self, mut nodes: Vec<Node>) -> Vec<Node> {
-        let graph = self.engine.graph();
+        let graph = self.graph();
 
         nodes.sort_by(|a, b| {
             b.is_default
                 .cmp(&a.is_default)
-                .then_with(|| {
-                    let a_form_factor = self.get_form_factor_priority(a, &graph);
-                    let b_form_factor = self.get_form_factor_priority(b, &graph);
-                    a_form_factor.cmp(&b_form_factor)
-                })
-                .then_with(|| {
-                    let a_bus = self.get_bus_priority(a, &graph);
-                    let b_bus = self.get_bus_priority(b, &graph);
-                    a_bus.cmp(&b_bus)
-                })
-                .then_with(|| {
-                    a.description
-                        .as_ref()
-                        .unwrap_or(&a.name)
-                        .cmp(b.description.as_ref().unwrap_or(&b.name))
-                })
+                .then_with(|| self.compare_node_priority(a, b, &graph))
         });
         nodes
     }
 
-    fn get_form_factor_priority(&self, node: &Node, graph: &AudioGraph) -> FormFactorPriority {
-        if let Some(device_id) = node.device_id {
-            if let Some(device) = graph.devices.get(&device_id) {
-                match device.form_factor.as_deref() {
-                    Some("headphone") => FormFactorPriority::Headphones,
-                    Some("headset") => FormFactorPriority::Headset,
-                    Some("hands-free") => FormFactorPriority::HandsFree,
-                    Some("handset") => FormFactorPriority::Handset,
-                    Some("speaker") => FormFactorPriority::Speaker,
-                    Some("microphone") => FormFactorPriority::Microphone,
-                    Some("webcam") => FormFactorPriority::Webcam,
-                    Some("portable") => FormFactorPriority::Portable,
-                    Some("car") => FormFactorPriority::Car,
-                    Some("hifi") => FormFactorPriority::Hifi,
-                    Some("tv") => FormFactorPriority::Tv,
-                    Some("computer") => FormFactorPriority::Computer,
-                    Some("internal") => FormFactorPriority::Internal,
-                    _ => FormFactorPriority::Unknown,
-                }
-            } else {
-                FormFactorPriority::Unknown
-            }
+    fn compare_node_priority(&self, a: &Node, b: &Node, graph: &AudioGraph) -> Ordering {
+        self.get_recent_rank(a)
+            .cmp(&self.get_recent_rank(b))
+            .then_with(|| {
+                let a_form_factor = self.get_form_factor_priority(a, graph);
+                let b_form_factor = self.get_form_factor_priority(b, graph);
+                a_form_factor.cmp(&b_form_factor)
+            })
+            .then_with(|| {
+                let a_bus = self.get_bus_priority(a, graph);
+                let b_bus = self.get_bus_priority(b, graph);
+                a_bus.cmp(&b_bus)
+            })
+            .then_with(|| {
+                a.description
+                    .as_ref()
+                    .unwrap_or(&a.name)
+                    .cmp(b.description.as_ref().unwrap_or(&b.name))
+            })
+    }
+
+    /// Returns the node following the current default in priority order, wrapping around.
+    pub fn get_next_default_node(&self, is_output: bool) -> Option<Node> {
+        let graph = self.graph();
+        let mut nodes = if is_output {
+            self.get_output_nodes()
         } else {
-            FormFactorPriority::Unknown
-        }
+            self.get_input_nodes()
+        };
+
+        // Ignore the default-first ordering so the cycle stays stable
+        nodes.sort_by(|a, b| self.compare_node_priority(a, b, &graph));
+
+        let next_index = nodes
+            .iter()
+            .position(|n| n.is_default)
+            .map_or(0, |index| (index + 1) % nodes.len());
+
+        nodes.into_iter().nth(next_index)
+    }
+
+    fn get_form_factor_priority(&self, node: &Node, graph: &AudioGraph) -> FormFactorPriority {
+        let form_factor = node
+            .device_id
+            .and_then(|device_id| graph.devices.get(&device_id))
+            .and_then(|device| device.form_factor.as_deref());
+
+        FormFactorPriority::from_form_factor(form_factor)
     }
 
     fn get_bus_priority(&self, node: &Node, graph: &AudioGraph) -> BusPriority {
-        if let Some(device_id) = node.device_id {
-            if let Some(device) = graph.devices.get(&device_id) {
-                match device.bus.as_deref() {
-                    Some("bluetooth") => BusPriority::Bluetooth,
-                    Some("pci") => BusPriority::Pci,
-                    Some("usb") => BusPriority::Usb,
-                    _ => BusPriority::Unknown,
-                }
-            } else {
-                BusPriority::Unknown
-            }
-        } else {
-            BusPriority::Unknown
-        }
+        let bus = node
+            .device_id
+            .and_then(|device_id| graph.devices.get(&device_id))
+            .and_then(|device| device.bus.as_deref());
+
+        BusPriority::from_bus(bus)
     }
 
     pub fn get_output_devices(&self) -> Vec<(u32, String)> {
-        let graph = self.engine.graph();
+        let graph = self.graph();
 
         graph
             .devices
@@ -233,7 +754,7 @@ impl Controller {
     }
 
     pub fn get_input_devices(&self) -> Vec<(u32, String)> {
-        let graph = self.engine.graph();
+        let graph = self.graph();
 
         graph
             .devices
@@ -243,12 +764,28 @@ impl Controller {
             .collect()
     }
 
+    /// Devices offering a profile choice sorted by display name, including turned off cards
+    /// so they can be turned back on.
+    pub fn get_devices_with_profiles(&self) -> Vec<(u32, String)> {
+        let device_ids: Vec<u32> = self.graph().devices.keys().copied().collect();
+
+        let mut devices: Vec<(u32, String)> = device_ids
+            .into_iter()
+            .filter(|&id| self.get_device_profiles(id).len() > 1 || self.is_device_off(id))
+            .map(|id| (id, self.get_device_name(id)))
+            .collect();
+
+        devices.sort_by(|a, b| a.1.cmp(&b.1));
+        devices
+    }
+
     pub async fn set_volume(&self, node_id: u32, volume: f32) -> Result<()> {
-        let graph = self.engine.graph();
-        let node = graph
-            .nodes
-            .get(&node_id)
-            .ok_or_else(|| anyhow!("Node {node_id} not found"))?;
+        let graph = self.graph();
+        let node = graph.nodes.get(&node_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Node,
+            id: node_id,
+        })?;
+        let volume = self.limit_volume_jump(&self.enhance_node_volume(node, &graph), volume);
 
         let result = match node.node_type {
             NodeType::StreamOutputAudio | NodeType::StreamInputAudio => {
@@ -264,12 +801,20 @@ impl Controller {
                                 }
                                 NodeType::AudioSource => Some(RouteDirection::Input),
                                 _ => None,
-                            };
+                            }
+                            .filter(|&direction| {
+                                device.uses_route_volume(direction, node.card_device())
+                            });
 
                             if let Some(direction) = target_direction {
                                 match self
                                     .engine
-                                    .set_device_volume(device_id, volume, Some(direction))
+                                    .set_device_volume(
+                                        device_id,
+                                        volume,
+                                        Some(direction),
+                                        node.card_device(),
+                                    )
                                     .await
                                 {
                                     Ok(()) => Ok(()),
@@ -294,39 +839,93 @@ impl Controller {
         result
     }
 
+    /// Mutes `node` at zero volume for `mute-at-zero`, keeping the volume it had in the state
+    /// so [`Controller::take_muted_volume`] can bring it back.
+    pub async fn mute_at_zero(&self, node: &Node) -> Result<()> {
+        if node.volume.linear > 0.0 {
+            let (name, raw) = (node.name.clone(), node.volume.raw());
+            self.update_state(|state| {
+                state.muted_volumes.insert(name, raw);
+            });
+        }
+
+        self.set_volume(node.id, 0.0).await?;
+        self.set_mute(node.id, true).await
+    }
+
+    /// The volume `node` had before [`Controller::mute_at_zero`], forgotten once returned.
+    /// Only a node still muted at zero gets it back.
+    pub fn take_muted_volume(&self, node: &Node) -> Option<f32> {
+        if !node.volume.muted || node.volume.percent() > 0 {
+            return None;
+        }
+
+        // Read from disk, the level may have been kept by another pwmenu process
+        let raw = self.load_state().muted_volumes.get(&node.name).copied()?;
+        self.update_state(|state| {
+            state.muted_volumes.remove(&node.name);
+        });
+        Some(VolumeResolver::apply_curve(self.volume_curve(), raw))
+    }
+
+    /// Sets the volume of `node` for `mute-at-zero`: zero mutes it, any other volume unmutes
+    /// it, and a raise of a node muted at zero brings back the volume it had, up to
+    /// `max_volume`. Returns the volume set, held to the maximum jump like any other change.
+    pub async fn set_volume_muting_at_zero(
+        &self,
+        node: &Node,
+        volume: f32,
+        raise: bool,
+        max_volume: f32,
+    ) -> Result<f32> {
+        if volume <= 0.0 {
+            self.mute_at_zero(node).await?;
+            return Ok(0.0);
+        }
+
+        let volume = match raise.then(|| self.take_muted_volume(node)).flatten() {
+            Some(restored) => restored.min(max_volume),
+            None => volume,
+        };
+        let volume = self
+            .get_node(node.id)
+            .map_or(volume, |current| self.limit_volume_jump(&current, volume));
+        if node.volume.muted {
+            self.set_mute(node.id, false).await?;
+        }
+        self.set_volume(node.id, volume).await?;
+        Ok(volume)
+    }
+
     pub async fn set_mute(&self, node_id: u32, mute: bool) -> Result<()> {
-        let graph = self.engine.graph();
-        let node = graph
-            .nodes
-            .get(&node_id)
-            .ok_or_else(|| anyhow!("Node {node_id} not found"))?;
+        let graph = self.graph();
+        let node = graph.nodes.get(&node_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Node,
+            id: node_id,
+        })?;
+
+        // A volume kept by mute-at-zero no longer applies once the node is unmuted
+        if !mute && self.load_state().muted_volumes.contains_key(&node.name) {
+            self.update_state(|state| {
+                state.muted_volumes.remove(&node.name);
+            });
+        }
 
         // Try device-level control first, fall back to node-level
         let result = if let Some(device_id) = node.device_id {
             if let Some(device) = graph.devices.get(&device_id) {
                 if device.has_route_volume {
                     let target_direction = match node.node_type {
-                        NodeType::AudioSink => {
-                            if device.output_route.is_available() {
-                                Some(RouteDirection::Output)
-                            } else {
-                                None
-                            }
-                        }
-                        NodeType::AudioSource => {
-                            if device.input_route.is_available() {
-                                Some(RouteDirection::Input)
-                            } else {
-                                None
-                            }
-                        }
+                        NodeType::AudioSink => Some(RouteDirection::Output),
+                        NodeType::AudioSource => Some(RouteDirection::Input),
                         _ => None,
-                    };
+                    }
+                    .filter(|&direction| device.uses_route_volume(direction, node.card_device()));
 
                     if let Some(direction) = target_direction {
                         match self
                             .engine
-                            .set_device_mute(device_id, mute, Some(direction))
+                            .set_device_mute(device_id, mute, Some(direction), node.card_device())
                             .await
                         {
                             Ok(()) => Ok(()),
@@ -380,11 +979,19 @@ impl Controller {
         node.media_name.clone()
     }
 
-    pub async fn create_link(&self, output_node: u32, input_node: u32) -> Result<()> {
-        let result = self.engine.create_link(output_node, input_node).await;
+    pub async fn create_link(
+        &self,
+        output_node: u32,
+        input_node: u32,
+        mapping: PortMapping,
+    ) -> Result<()> {
+        let result = self
+            .engine
+            .create_link(output_node, input_node, mapping)
+            .await;
 
         if result.is_ok() {
-            let graph = self.engine.graph();
+            let graph = self.graph();
             let output_name = graph.nodes.get(&output_node).map_or("unknown", |n| &n.name);
             let input_name = graph.nodes.get(&input_node).map_or("unknown", |n| &n.name);
 
@@ -398,7 +1005,7 @@ impl Controller {
         let result = self.engine.remove_link(output_node, input_node).await;
 
         if result.is_ok() {
-            let graph = self.engine.graph();
+            let graph = self.graph();
             let output_name = graph.nodes.get(&output_node).map_or("unknown", |n| &n.name);
             let input_name = graph.nodes.get(&input_node).map_or("unknown", |n| &n.name);
 
@@ -408,7 +1015,21 @@ impl Controller {
         result
     }
 
+    /// Without a session manager there is no default metadata to write to.
+    pub fn has_session_manager(&self) -> bool {
+        self.graph().session_manager
+    }
+
+    fn ensure_session_manager(&self) -> Result<()> {
+        if self.has_session_manager() {
+            Ok(())
+        } else {
+            Err(PwError::NoSessionManager.into())
+        }
+    }
+
     pub async fn set_default_sink(&self, node_id: u32) -> Result<()> {
+        self.ensure_session_manager()?;
         let result = self.engine.set_default_sink(node_id).await;
 
         if result.is_ok() {
@@ -420,7 +1041,115 @@ impl Controller {
         result
     }
 
+    pub async fn move_streams_to(&self, node_id: u32) -> Result<usize> {
+        let result = self.engine.move_streams_to(node_id).await;
+
+        if let Ok(count) = result {
+            debug!("Moved {count} streams to node {node_id}");
+        }
+
+        result
+    }
+
+    /// Pins a stream to a sink or source, or lets it follow the default again with `None`.
+    pub async fn set_stream_target(&self, stream_id: u32, target_id: Option<u32>) -> Result<()> {
+        let result = self.engine.set_stream_target(stream_id, target_id).await;
+
+        if result.is_ok() {
+            debug!("Set target of stream {stream_id} to {target_id:?}");
+        }
+
+        result
+    }
+
+    /// Requests a latency of `quantum` samples at the node's current rate.
+    pub async fn set_node_latency(&self, node_id: u32, quantum: u32) -> Result<()> {
+        let node = self.get_node(node_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Node,
+            id: node_id,
+        })?;
+        let rate = node
+            .sample_spec
+            .map_or(self.graph().default_clock_rate, |spec| spec.rate);
+
+        self.engine
+            .set_node_latency(node_id, format!("{quantum}/{rate}"))
+            .await
+    }
+
+    /// Node a stream is pinned to, from the metadata or its own `target.object`, which may
+    /// name it by `node.name`, `object.serial` or id.
+    pub fn get_stream_target(&self, stream: &Node) -> Option<Node> {
+        let target = stream
+            .target_object
+            .as_ref()
+            .or_else(|| stream.properties.get("target.object"))?;
+
+        let graph = self.graph();
+        graph
+            .nodes
+            .values()
+            .find(|node| {
+                node.name == *target
+                    || node.properties.get("object.serial") == Some(target)
+                    || node.id.to_string() == *target
+            })
+            .map(|node| self.enhance_node_volume(node, &graph))
+    }
+
+    /// Caps an increase past `max-volume-jump` points above the current volume, so a script
+    /// asking for 150% by mistake cannot hurt someone wearing headphones. Decreases always go
+    /// through.
+    fn limit_volume_jump(&self, node: &Node, volume: f32) -> f32 {
+        let Some(max_jump) = self.max_volume_jump else {
+            return volume;
+        };
+
+        let limit = node.volume.linear + max_jump;
+        if volume <= limit {
+            return volume;
+        }
+
+        warn!(
+            "Limiting {} to {}% instead of {}%, at most {}% above its current volume",
+            node.name,
+            Volume::new(limit, false).percent(),
+            Volume::new(volume, false).percent(),
+            (max_jump * 100.0).round()
+        );
+        limit
+    }
+
+    /// Runs several operations in one trip through the PipeWire loop. Every step is attempted
+    /// and failures are reported together.
+    pub async fn batch(&self, steps: Vec<BatchStep>) -> Result<()> {
+        let changes_default = steps.iter().any(|step| {
+            matches!(
+                step,
+                BatchStep::SetDefaultSink { .. } | BatchStep::SetDefaultSource { .. }
+            )
+        });
+        if changes_default {
+            self.ensure_session_manager()?;
+        }
+
+        let steps = steps
+            .into_iter()
+            .map(|step| match step {
+                BatchStep::SetNodeVolume { node_id, volume } => BatchStep::SetNodeVolume {
+                    node_id,
+                    volume: self
+                        .get_node(node_id)
+                        .map_or(volume, |node| self.limit_volume_jump(&node, volume)),
+                },
+                step => step,
+            })
+            .collect();
+        self.engine.batch(steps).await
+    }
+
     pub async fn set_default_source(&self, node_id: u32) -> Result<()> {
+        self.ensure_session_manager()?;
         let result = self.engine.set_default_source(node_id).await;
 
         if result.is_ok() {
@@ -432,12 +1161,17 @@ impl Controller {
         result
     }
 
+    /// State of the PipeWire connection, for telling a broken connection from an empty graph.
+    pub fn connection_status(&self) -> ConnectionStatus {
+        self.engine.graph().connection_status
+    }
+
     pub fn get_default_sink(&self) -> Option<u32> {
-        self.engine.graph().default_sink
+        self.graph().default_sink
     }
 
     pub fn get_default_source(&self) -> Option<u32> {
-        self.engine.graph().default_source
+        self.graph().default_source
     }
 
     pub fn get_device_info(&self, node: &Node) -> DeviceInfo {
@@ -451,9 +1185,9 @@ impl Controller {
         };
 
         if let Some(device_id) = node.device_id {
-            let graph = self.engine.graph();
+            let graph = self.graph();
             if let Some(device) = graph.devices.get(&device_id) {
-                device_info.nick = device.nick.clone();
+                device_info.nick = device.hardware_name().or_else(|| device.nick.clone());
                 device_info.form_factor = device.form_factor.clone();
                 device_info.bus = device.bus.clone();
             }
@@ -462,8 +1196,112 @@ impl Controller {
         device_info
     }
 
+    /// The connector `node` plays to or records from, such as speakers or headphones. Among
+    /// those wired to the node's card device, the active one is preferred, so nodes of other
+    /// HDMI outputs get their own connector.
+    pub fn get_active_route_port(&self, node: &Node) -> Option<RoutePort> {
+        let graph = self.graph();
+        let device = graph.devices.get(&node.device_id?)?;
+        let direction = match node.node_type {
+            NodeType::AudioSink => RouteDirection::Output,
+            NodeType::AudioSource => RouteDirection::Input,
+            _ => return None,
+        };
+        let card_device = node.card_device();
+        let route = device.route(direction, card_device);
+
+        let candidates: Vec<&RoutePort> = device
+            .route_ports
+            .iter()
+            .filter(|port| port.direction == direction)
+            .filter(|port| {
+                card_device.is_none_or(|card_device| port.devices.contains(&card_device))
+            })
+            .collect();
+
+        candidates
+            .iter()
+            .find(|port| Some(port.index) == route.index)
+            .or_else(|| candidates.first().filter(|_| card_device.is_some()))
+            .map(|port| (*port).clone())
+    }
+
+    /// Connectors whose cards tell whether something is plugged in, in card order.
+    pub fn get_jacks(&self) -> Vec<Jack> {
+        let graph = self.graph();
+        let mut device_ids: Vec<u32> = graph.devices.keys().copied().collect();
+        device_ids.sort_unstable();
+
+        device_ids
+            .into_iter()
+            .flat_map(|device_id| {
+                let device_name = self.get_device_name(device_id);
+                graph.devices[&device_id]
+                    .route_ports
+                    .iter()
+                    .filter(|port| port.is_plugged().is_some())
+                    .map(move |port| Jack {
+                        device_id,
+                        device_name: device_name.clone(),
+                        port: port.clone(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Properties of `node`, followed by those of its card and the active profile, as shown by
+    /// the properties inspector.
+    pub fn get_node_properties(&self, node: &Node) -> Vec<(String, String)> {
+        let mut properties = vec![("node.name".to_string(), node.name.clone())];
+        if let Some(media_class) = &node.media_class {
+            properties.push(("media.class".to_string(), media_class.clone()));
+        }
+        properties.extend(node.properties.clone());
+
+        if !node.properties.contains_key("audio.channels") && !node.channel_volumes.is_empty() {
+            properties.push((
+                "audio.channels".to_string(),
+                node.channel_volumes.len().to_string(),
+            ));
+        }
+
+        if let Some(spec) = &node.sample_spec {
+            properties.push(("format".to_string(), spec.to_string()));
+        }
+        if let Some(latency) = &node.latency {
+            properties.push(("node.latency".to_string(), latency.clone()));
+        }
+
+        let graph = self.graph();
+        properties.push((
+            "clock.rate".to_string(),
+            graph.default_clock_rate.to_string(),
+        ));
+
+        let Some(device) = node.device_id.and_then(|id| graph.devices.get(&id)) else {
+            return properties;
+        };
+
+        properties.push(("device.name".to_string(), device.name.clone()));
+        for (key, value) in &device.properties {
+            if !properties.iter().any(|(existing, _)| existing == key) {
+                properties.push((key.clone(), value.clone()));
+            }
+        }
+
+        if let Some(profile) = device
+            .current_profile_index
+            .and_then(|index| device.profiles.iter().find(|p| p.index == index))
+        {
+            properties.push(("device.profile".to_string(), profile.description.clone()));
+        }
+
+        properties
+    }
+
     pub fn get_device_profiles(&self, device_id: u32) -> Vec<Profile> {
-        let graph = self.engine.graph();
+        let graph = self.graph();
         graph
             .devices
             .get(&device_id)
@@ -474,8 +1312,36 @@ impl Controller {
             .collect()
     }
 
+    /// Profiles the device reports as unavailable, usually because nothing is plugged into
+    /// the port they use.
+    pub fn get_device_unavailable_profiles(&self, device_id: u32) -> Vec<Profile> {
+        let graph = self.graph();
+        graph
+            .devices
+            .get(&device_id)
+            .map(|device| device.profiles.clone())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|p| !p.is_available() && !p.is_off())
+            .collect()
+    }
+
+    /// The profile disabling the card, left out of [`Controller::get_device_profiles`].
+    pub fn get_device_off_profile(&self, device_id: u32) -> Option<Profile> {
+        let graph = self.graph();
+        graph
+            .devices
+            .get(&device_id)
+            .and_then(|device| device.profiles.iter().find(|p| p.is_off()).cloned())
+    }
+
+    pub fn is_device_off(&self, device_id: u32) -> bool {
+        self.get_device_current_profile(device_id)
+            .is_some_and(|profile| profile.is_off())
+    }
+
     pub fn get_device_current_profile(&self, device_id: u32) -> Option<Profile> {
-        let graph = self.engine.graph();
+        let graph = self.graph();
         graph.devices.get(&device_id).and_then(|device| {
             device
                 .current_profile_index
@@ -489,11 +1355,13 @@ impl Controller {
             .devices
             .get(&device_id)
             .map(|d| {
-                d.nick
-                    .as_ref()
-                    .or(d.description.as_ref())
-                    .unwrap_or(&d.name)
-                    .clone()
+                d.hardware_name().unwrap_or_else(|| {
+                    d.nick
+                        .as_ref()
+                        .or(d.description.as_ref())
+                        .unwrap_or(&d.name)
+                        .clone()
+                })
             })
             .unwrap_or_else(|| "Unknown Device".to_string())
     }
@@ -505,7 +1373,7 @@ impl Controller {
             .await;
 
         if result.is_ok() {
-            if let Some(device) = self.engine.graph().devices.get(&device_id) {
+            if let Some(device) = self.graph().devices.get(&device_id) {
                 if let Some(profile) = device.profiles.iter().find(|p| p.index == profile_index) {
                     debug!(
                         "Switched device {} to profile: {}",
@@ -518,7 +1386,53 @@ impl Controller {
         result
     }
 
+    /// See [`PwEngine::configure_restoration`].
+    pub async fn configure_restoration(&self, settings: RestorationSettings) -> Result<()> {
+        self.engine.configure_restoration(settings).await
+    }
+
+    /// Waits until the device reports `profile_index` as active. When it does not in time,
+    /// the defaults captured for the switch are dropped so they are not restored later on.
+    pub async fn wait_for_profile(&self, device_id: u32, profile_index: u32) -> Result<()> {
+        let mut graph = self.engine.subscribe();
+        let switched = tokio::time::timeout(
+            PROFILE_SWITCH_TIMEOUT,
+            graph.wait_for(|graph| {
+                graph
+                    .devices
+                    .get(&device_id)
+                    .is_some_and(|device| device.current_profile_index == Some(profile_index))
+            }),
+        )
+        .await;
+
+        match switched {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(_)) => Err(PwError::Unavailable.into()),
+            Err(_) => {
+                warn!("Device {device_id} did not switch to profile {profile_index} within {PROFILE_SWITCH_TIMEOUT:?}");
+                self.engine.cancel_profile_restoration(device_id).await?;
+                Err(PwError::ProfileNotApplied {
+                    device_id,
+                    profile_index,
+                }
+                .into())
+            }
+        }
+    }
+
+    /// Name shown for a device node: the display plugged into HDMI outputs when known, then
+    /// the card's name, the node description or its name.
     pub fn get_node_base_name(&self, node: &Node) -> String {
+        if node.node_type == NodeType::AudioSink {
+            if let Some(label) = self
+                .get_active_route_port(node)
+                .and_then(|port| port.monitor_label())
+            {
+                return label;
+            }
+        }
+
         self.get_device_info(node)
             .nick
             .as_ref()
@@ -541,7 +1455,7 @@ impl Controller {
             return None;
         }
 
-        let graph = self.engine.graph();
+        let graph = self.graph();
         let node_ports = graph
             .ports
             .values()
@@ -566,11 +1480,297 @@ impl Controller {
         None
     }
 
+    pub async fn create_virtual_sink(&self, description: &str) -> Result<()> {
+        let result = self.engine.create_virtual_sink(description).await;
+
+        if result.is_ok() {
+            debug!("Created virtual sink {description}");
+        }
+
+        result
+    }
+
+    pub async fn remove_managed_node(&self, node_id: u32) -> Result<()> {
+        let result = self.engine.destroy_managed_node(node_id).await;
+
+        if result.is_ok() {
+            debug!("Removed managed node {node_id}");
+        }
+
+        result
+    }
+
+    pub async fn play_test_tone(&self, node_id: u32) -> Result<()> {
+        let result = self.engine.play_test_tone(node_id).await;
+
+        if result.is_ok() {
+            debug!("Started test tone on node {node_id}");
+        }
+
+        result
+    }
+
+    pub async fn start_level_meter(&self, node_id: u32) -> Result<InputLevel> {
+        self.engine.start_level_meter(node_id).await
+    }
+
+    pub async fn stop_level_meter(&self) -> Result<()> {
+        self.engine.stop_level_meter().await
+    }
+
+    /// Keeps a level meter on each of `node_ids`, returning the levels of all metered streams.
+    pub async fn sync_stream_meters(&self, node_ids: Vec<u32>) -> Result<HashMap<u32, InputLevel>> {
+        self.engine.sync_stream_meters(node_ids).await
+    }
+
+    /// Binds the PipeWire profiler, returning its timings until `stop_profiler` is called.
+    pub async fn start_profiler(&self) -> Result<ProfilerStats> {
+        self.engine.start_profiler().await
+    }
+
+    pub async fn stop_profiler(&self) -> Result<()> {
+        self.engine.stop_profiler().await
+    }
+
+    pub async fn rename_managed_node(&self, node_id: u32, description: &str) -> Result<()> {
+        let result = self.engine.rename_managed_node(node_id, description).await;
+
+        if result.is_ok() {
+            debug!("Renamed managed node {node_id} to {description}");
+        }
+
+        result
+    }
+
+    pub async fn remove_all_managed_objects(&self) -> Result<usize> {
+        let result = self.engine.destroy_all_managed().await;
+
+        if let Ok(count) = result {
+            debug!("Removed {count} managed objects");
+        }
+
+        result
+    }
+
     pub async fn set_sample_rate(&self, sample_rate: u32) -> Result<()> {
         self.engine.set_sample_rate(sample_rate).await
     }
 
     pub fn get_system_default_sample_rate(&self) -> u32 {
-        self.engine.graph().default_clock_rate
+        self.graph().default_clock_rate
+    }
+
+    /// Applies profiles first, then defaults and volumes once the affected nodes exist.
+    /// Everything that can be applied is, and the failures are reported together.
+    pub async fn apply_scene(&self, scene: &Scene) -> Result<()> {
+        let mut failures = Vec::new();
+        let mut switched_profiles = false;
+
+        for (device_key, profile_key) in &scene.profiles {
+            match self.find_scene_profile(device_key, profile_key) {
+                Ok(Some((device_id, profile_index))) => {
+                    match self.switch_device_profile(device_id, profile_index).await {
+                        Ok(()) => switched_profiles = true,
+                        Err(e) => failures.push(format!("{device_key}: {e}")),
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => failures.push(e.to_string()),
+            }
+        }
+
+        let node_keys: Vec<(&str, bool)> = scene
+            .default_sink
+            .iter()
+            .map(|key| (key.as_str(), true))
+            .chain(scene.default_source.iter().map(|key| (key.as_str(), false)))
+            .collect();
+        if switched_profiles {
+            self.wait_for_scene_nodes(&node_keys, scene).await;
+        }
+
+        for (key, is_output) in node_keys {
+            let Some(node) = self.find_device_node(key) else {
+                failures.push(format!("Device \"{key}\" not found"));
+                continue;
+            };
+            let result = if is_output {
+                self.set_default_sink(node.id).await
+            } else {
+                self.set_default_source(node.id).await
+            };
+            if let Err(e) = result {
+                failures.push(format!("{key}: {e}"));
+            }
+        }
+
+        for (key, volume) in &scene.volumes {
+            let Some(node) = self.find_device_node(key) else {
+                failures.push(format!("Device \"{key}\" not found"));
+                continue;
+            };
+            if let Err(e) = self.set_volume(node.id, *volume as f32 / 100.0).await {
+                failures.push(format!("{key}: {e}"));
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(failures.join("; ")))
+        }
     }
+
+    async fn wait_for_scene_nodes(&self, node_keys: &[(&str, bool)], scene: &Scene) {
+        let deadline = Instant::now() + SCENE_NODE_TIMEOUT;
+
+        loop {
+            let all_present = node_keys
+                .iter()
+                .map(|(key, _)| *key)
+                .chain(scene.volumes.keys().map(String::as_str))
+                .all(|key| self.find_device_node(key).is_some());
+            if all_present || Instant::now() >= deadline {
+                return;
+            }
+            sleep(SCENE_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Finds an output or input device by node name, description, or the name shown in menus.
+    pub fn find_device_node(&self, key: &str) -> Option<Node> {
+        self.get_output_nodes()
+            .into_iter()
+            .chain(self.get_input_nodes())
+            .find(|node| {
+                node.name == key
+                    || node.description.as_deref() == Some(key)
+                    || self.get_node_base_name(node) == key
+            })
+    }
+
+    /// Finds a device or stream for a name given on the command line. Exact node names,
+    /// descriptions and menu names win; otherwise a pattern with `*` or `?` is matched as a
+    /// glob and any other text as a substring of those names, ignoring case. Devices come
+    /// before streams, in menu order.
+    pub fn find_node(&self, pattern: &str) -> Option<Node> {
+        let candidates: Vec<Node> = self
+            .get_output_nodes()
+            .into_iter()
+            .chain(self.get_input_nodes())
+            .chain(self.get_output_streams())
+            .chain(self.get_input_streams())
+            .collect();
+        self.match_node(candidates, pattern)
+    }
+
+    /// Like [`find_node`](Self::find_node), among output or input devices only.
+    pub fn find_device(&self, pattern: &str, is_output: bool) -> Option<Node> {
+        let candidates = if is_output {
+            self.get_output_nodes()
+        } else {
+            self.get_input_nodes()
+        };
+        self.match_node(candidates, pattern)
+    }
+
+    fn match_node(&self, candidates: Vec<Node>, pattern: &str) -> Option<Node> {
+        let names = |node: &Node| {
+            [
+                Some(node.name.clone()),
+                node.description.clone(),
+                node.application_name.clone(),
+                Some(self.get_node_base_name(node)),
+            ]
+            .into_iter()
+            .flatten()
+        };
+
+        if let Some(node) = candidates
+            .iter()
+            .find(|node| names(node).any(|name| name == pattern))
+        {
+            return Some(node.clone());
+        }
+
+        let pattern = pattern.to_lowercase();
+        let is_glob = pattern.contains(['*', '?']);
+        candidates.into_iter().find(|node| {
+            names(node).any(|name| {
+                let name = name.to_lowercase();
+                if is_glob {
+                    glob_match(&pattern, &name)
+                } else {
+                    name.contains(&pattern)
+                }
+            })
+        })
+    }
+
+    /// Returns `None` when the device already uses the requested profile.
+    fn find_scene_profile(
+        &self,
+        device_key: &str,
+        profile_key: &str,
+    ) -> Result<Option<(u32, u32)>> {
+        let graph = self.graph();
+        let device = graph
+            .devices
+            .values()
+            .find(|d| {
+                d.name == device_key
+                    || d.description.as_deref() == Some(device_key)
+                    || d.nick.as_deref() == Some(device_key)
+                    || d.hardware_name().as_deref() == Some(device_key)
+            })
+            .ok_or_else(|| PwError::DeviceNotFound(device_key.to_string()))?;
+        let profile = device
+            .profiles
+            .iter()
+            .find(|p| p.name == profile_key || p.description == profile_key)
+            .ok_or_else(|| anyhow!("Profile \"{profile_key}\" not found on \"{device_key}\""))?;
+
+        if device.current_profile_index == Some(profile.index) {
+            Ok(None)
+        } else {
+            Ok(Some((device.id, profile.index)))
+        }
+    }
+}
+
+fn same_volume(a: &Volume, b: &Volume) -> bool {
+    a.muted == b.muted && (a.linear - b.linear).abs() < SAVED_VOLUME_TOLERANCE
+}
+
+/// Matches `text` against a pattern where `*` stands for any run of characters and `?` for
+/// a single one.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and of the text it was tried against, to backtrack to
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(&'*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
 }
@@ -1,5 +1,7 @@
 use crate::pw::{
-    graph::{AudioGraph, Store},
+    error::{ObjectKind, PwError},
+    graph::{OwnedPod, QueuedEvent, Store},
+    nodes::collect_props,
     volume::{RouteDirection, VolumeResolver},
     NodeType,
 };
@@ -12,7 +14,6 @@ use libspa::{
         SPA_PARAM_PROFILE_save, SPA_TYPE_OBJECT_ParamProfile,
     },
 };
-use log::{debug, error, warn};
 use pipewire::spa::{
     param::ParamType,
     pod::{deserialize::PodDeserializer, Pod, Value},
@@ -20,8 +21,11 @@ use pipewire::spa::{
 use pipewire::{keys::*, registry::GlobalObject, spa::utils::dict::DictRef};
 use serde::{Deserialize, Serialize};
 use std::rc::Rc;
-use std::{cell::RefCell, mem::MaybeUninit};
-use tokio::sync::watch;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    mem::MaybeUninit,
+};
+use tracing::{debug, warn};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DeviceType {
@@ -49,12 +53,18 @@ impl Profile {
     }
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct RouteInfo {
     pub index: Option<i32>,
     pub device: Option<i32>,
     pub volume: Option<f32>,
     pub muted: Option<bool>,
+    /// Raw per-channel amplitudes from `channelVolumes`, before the volume curve.
+    pub channel_volumes: Vec<f32>,
+    /// `yes` or `no` when the card can tell whether the route's connector is plugged in,
+    /// `unknown` otherwise, and `None` until the route is reported.
+    #[serde(default)]
+    pub available: Option<String>,
 }
 
 impl RouteInfo {
@@ -62,6 +72,11 @@ impl RouteInfo {
         self.index.is_some() && self.device.is_some()
     }
 
+    /// Whether the route reported its own volume and mute, which inputs of some cards don't.
+    pub fn has_volume(&self) -> bool {
+        self.volume.is_some() && self.muted.is_some()
+    }
+
     pub fn get_route_params(&self) -> Option<(i32, i32)> {
         self.index.zip(self.device)
     }
@@ -71,6 +86,63 @@ impl RouteInfo {
     }
 }
 
+/// A connector of a card, such as speakers or a headphone jack, as listed by `EnumRoute`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutePort {
+    pub index: i32,
+    pub direction: RouteDirection,
+    pub name: String,
+    pub description: String,
+    /// `yes` or `no` for jacks the card can tell are plugged in, `unknown` otherwise.
+    pub available: String,
+    /// Card devices the connector is wired to, matching the `card.profile.device` of nodes.
+    #[serde(default)]
+    pub devices: Vec<i32>,
+    /// Connector kind such as `hdmi`, `headphones` or `speaker`.
+    #[serde(default)]
+    pub port_type: Option<String>,
+    /// Name of the display plugged into an HDMI or DisplayPort connector, read from its EDID.
+    #[serde(default)]
+    pub monitor_name: Option<String>,
+}
+
+impl RoutePort {
+    /// Whether something is plugged into the jack, `None` without jack detection.
+    pub fn is_plugged(&self) -> Option<bool> {
+        match self.available.as_str() {
+            "yes" => Some(true),
+            "no" => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn is_hdmi(&self) -> bool {
+        self.port_type.as_deref() == Some("hdmi") || self.name.contains("hdmi")
+    }
+
+    /// Display name of an HDMI connector with a known display, e.g. "HDMI – DELL U2720Q".
+    pub fn monitor_label(&self) -> Option<String> {
+        if !self.is_hdmi() {
+            return None;
+        }
+        let monitor = self.monitor_name.as_deref()?.trim();
+        (!monitor.is_empty()).then(|| format!("HDMI – {monitor}"))
+    }
+}
+
+/// Device properties shown in the properties inspector.
+const INSPECTED_DEVICE_PROPS: &[&str] = &[
+    "device.api",
+    "device.bus",
+    "device.bus-path",
+    "device.profile-set",
+    "api.alsa.path",
+    "api.alsa.card",
+    "api.alsa.card.name",
+    "api.bluez5.address",
+    "api.bluez5.profile",
+];
+
 fn get_device_bus(props: &DictRef) -> Option<&str> {
     props.get("device.bus")
 }
@@ -79,12 +151,40 @@ fn get_device_form_factor(props: &DictRef) -> Option<&str> {
     props.get("device.form-factor")
 }
 
+/// Properties read from a device's info. Keys left out of an update keep their last value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceInfoProps {
+    pub properties: BTreeMap<String, String>,
+    pub bus: Option<String>,
+    pub form_factor: Option<String>,
+    pub description: Option<String>,
+    pub nick: Option<String>,
+    pub product_name: Option<String>,
+    pub vendor_name: Option<String>,
+}
+
+impl DeviceInfoProps {
+    pub fn from_props(props: &DictRef) -> Self {
+        Self {
+            properties: collect_props(props, INSPECTED_DEVICE_PROPS),
+            bus: get_device_bus(props).map(str::to_string),
+            form_factor: get_device_form_factor(props).map(str::to_string),
+            description: props.get("device.description").map(str::to_string),
+            nick: props.get("device.nick").map(str::to_string),
+            product_name: props.get(*DEVICE_PRODUCT_NAME).map(str::to_string),
+            vendor_name: props.get(*DEVICE_VENDOR_NAME).map(str::to_string),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Device {
     pub id: u32,
     pub name: String,
     pub nick: Option<String>,
     pub description: Option<String>,
+    pub product_name: Option<String>,
+    pub vendor_name: Option<String>,
     pub device_type: DeviceType,
     pub bus: Option<String>,
     pub form_factor: Option<String>,
@@ -94,6 +194,80 @@ pub struct Device {
     pub has_route_volume: bool,
     pub output_route: RouteInfo,
     pub input_route: RouteInfo,
+    /// Active routes keyed by the card device they drive, so cards with several outputs
+    /// such as HDMI and analog keep one volume each.
+    #[serde(default)]
+    pub output_routes: BTreeMap<i32, RouteInfo>,
+    #[serde(default)]
+    pub input_routes: BTreeMap<i32, RouteInfo>,
+    /// Number of distinct routes reported, zero for cards that only expose Props.
+    pub route_count: usize,
+    /// Every connector of the card, with its jack state.
+    #[serde(default)]
+    pub route_ports: Vec<RoutePort>,
+    pub output_channel_count: usize,
+    pub input_channel_count: usize,
+    /// Selected PipeWire properties such as the API or bus path, for inspection.
+    pub properties: BTreeMap<String, String>,
+}
+
+impl Device {
+    /// Whether volume and mute for `direction` go through the active route. Cards without one,
+    /// such as some HDMI-only ones, are controlled through their nodes instead, and so is a
+    /// direction whose route has no volume even when the other one does.
+    pub fn uses_route_volume(&self, direction: RouteDirection, card_device: Option<i32>) -> bool {
+        let route = self.route(direction, card_device);
+        self.has_route_volume && route.is_available() && route.has_volume()
+    }
+
+    /// The route driving `card_device`, or the last one reported for `direction` when the
+    /// node does not tell its card device.
+    pub fn route(&self, direction: RouteDirection, card_device: Option<i32>) -> &RouteInfo {
+        let (routes, last_route) = match direction {
+            RouteDirection::Output => (&self.output_routes, &self.output_route),
+            RouteDirection::Input => (&self.input_routes, &self.input_route),
+        };
+        card_device
+            .and_then(|card_device| routes.get(&card_device))
+            .unwrap_or(last_route)
+    }
+
+    /// Human-friendly name built from the USB product and vendor strings, e.g. "Dell WD19 Dock".
+    /// Other buses keep their card names, which are usually more descriptive there.
+    pub fn hardware_name(&self) -> Option<String> {
+        if self.bus.as_deref() != Some("usb") {
+            return None;
+        }
+
+        let product = self.product_name.as_deref()?.trim();
+        if product.is_empty() {
+            return None;
+        }
+
+        // Vendor strings carry suffixes like "Inc." or "Semiconductor Corp.", keep the brand
+        let brand = self
+            .vendor_name
+            .as_deref()
+            .and_then(|vendor| vendor.split_whitespace().next())
+            .map(|word| word.trim_end_matches(|c: char| !c.is_alphanumeric()))
+            .filter(|brand| !brand.is_empty());
+
+        match brand {
+            Some(brand) if !product.to_lowercase().contains(&brand.to_lowercase()) => {
+                Some(format!("{brand} {product}"))
+            }
+            _ => Some(product.to_string()),
+        }
+    }
+}
+
+/// Name of an `SPA_PARAM_AVAILABILITY` value, as used by PipeWire tools.
+fn availability_name(id: u32) -> &'static str {
+    match id {
+        libspa::sys::SPA_PARAM_AVAILABILITY_yes => "yes",
+        libspa::sys::SPA_PARAM_AVAILABILITY_no => "no",
+        _ => "unknown",
+    }
 }
 
 pub struct DeviceInternal {
@@ -101,6 +275,8 @@ pub struct DeviceInternal {
     pub name: String,
     pub nick: Option<String>,
     pub description: Option<String>,
+    pub product_name: Option<String>,
+    pub vendor_name: Option<String>,
     pub device_type: DeviceType,
     pub bus: Option<String>,
     pub form_factor: Option<String>,
@@ -109,20 +285,60 @@ pub struct DeviceInternal {
     pub current_profile_index: Option<u32>,
     pub proxy: pipewire::device::Device,
     pub listener: Option<pipewire::device::DeviceListener>,
+    /// Tells this device apart from a later one reusing its id, see [`Store::next_generation`].
+    pub generation: u64,
     pub output_route: RouteInfo,
     pub input_route: RouteInfo,
+    pub output_routes: BTreeMap<i32, RouteInfo>,
+    pub input_routes: BTreeMap<i32, RouteInfo>,
     pub has_route_volume: bool,
     pub output_channel_count: usize,
     pub input_channel_count: usize,
+    pub known_routes: BTreeSet<(u32, i32)>,
+    pub route_ports: Vec<RoutePort>,
+    pub properties: BTreeMap<String, String>,
 }
 
 impl DeviceInternal {
+    /// Unregisters the listener while the proxy it listens on is still alive.
+    pub fn teardown(&mut self) {
+        self.listener = None;
+    }
+
+    /// Applies the properties of the device's info, returning whether any of them changed.
+    pub fn apply_info_props(&mut self, info: DeviceInfoProps) -> bool {
+        let mut updated = false;
+
+        if self.properties != info.properties {
+            self.properties = info.properties;
+            updated = true;
+        }
+
+        for (field, value) in [
+            (&mut self.bus, info.bus),
+            (&mut self.form_factor, info.form_factor),
+            (&mut self.description, info.description),
+            (&mut self.nick, info.nick),
+            (&mut self.product_name, info.product_name),
+            (&mut self.vendor_name, info.vendor_name),
+        ] {
+            if value.is_some() && *field != value {
+                *field = value;
+                updated = true;
+            }
+        }
+
+        updated
+    }
+
     pub fn to_device(&self) -> Device {
         Device {
             id: self.id,
             name: self.name.clone(),
             nick: self.nick.clone(),
             description: self.description.clone(),
+            product_name: self.product_name.clone(),
+            vendor_name: self.vendor_name.clone(),
             device_type: self.device_type,
             bus: self.bus.clone(),
             form_factor: self.form_factor.clone(),
@@ -132,6 +348,13 @@ impl DeviceInternal {
             has_route_volume: self.has_route_volume,
             output_route: self.output_route.clone(),
             input_route: self.input_route.clone(),
+            output_routes: self.output_routes.clone(),
+            input_routes: self.input_routes.clone(),
+            route_count: self.known_routes.len(),
+            route_ports: self.route_ports.clone(),
+            output_channel_count: self.output_channel_count,
+            input_channel_count: self.input_channel_count,
+            properties: self.properties.clone(),
         }
     }
 
@@ -154,6 +377,37 @@ impl DeviceInternal {
         }
     }
 
+    /// Same as [`Device::route`].
+    pub fn route(&self, direction: RouteDirection, card_device: Option<i32>) -> &RouteInfo {
+        let (routes, last_route) = match direction {
+            RouteDirection::Output => (&self.output_routes, &self.output_route),
+            RouteDirection::Input => (&self.input_routes, &self.input_route),
+        };
+        card_device
+            .and_then(|card_device| routes.get(&card_device))
+            .unwrap_or(last_route)
+    }
+
+    /// Applies `update` to the route driving `card_device`, and to the last reported route
+    /// of `direction` when that is the same one.
+    fn update_route(
+        &mut self,
+        direction: RouteDirection,
+        card_device: i32,
+        update: impl Fn(&mut RouteInfo),
+    ) {
+        let (routes, last_route) = match direction {
+            RouteDirection::Output => (&mut self.output_routes, &mut self.output_route),
+            RouteDirection::Input => (&mut self.input_routes, &mut self.input_route),
+        };
+        if let Some(route) = routes.get_mut(&card_device) {
+            update(route);
+        }
+        if last_route.device == Some(card_device) {
+            update(last_route);
+        }
+    }
+
     pub fn switch_profile(&self, profile_index: u32) -> Result<()> {
         let target_profile = self.profiles.iter().find(|p| p.index == profile_index);
         if let Some(profile) = target_profile {
@@ -179,8 +433,9 @@ impl DeviceInternal {
 
         let pod_data = self.build_profile_switch_pod(profile_index)?;
 
-        let pod_ref = Pod::from_bytes(&pod_data)
-            .ok_or_else(|| anyhow!("Failed to create Pod reference for profile switch"))?;
+        let pod_ref = Pod::from_bytes(&pod_data).ok_or_else(|| {
+            PwError::PodBuild("Failed to create Pod reference for profile switch".to_string())
+        })?;
 
         self.proxy.set_param(ParamType::Profile, 0, pod_ref);
 
@@ -235,8 +490,6 @@ impl Store {
         &mut self,
         registry: &Rc<pipewire::registry::RegistryRc>,
         global: &GlobalObject<&DictRef>,
-        store_rc: &Rc<RefCell<Store>>,
-        graph_tx: &watch::Sender<AudioGraph>,
     ) -> Result<()> {
         let props = global
             .props
@@ -247,6 +500,8 @@ impl Store {
 
         let nick = props.get(*DEVICE_NICK).map(str::to_string);
         let description = props.get(*DEVICE_DESCRIPTION).map(str::to_string);
+        let product_name = props.get(*DEVICE_PRODUCT_NAME).map(str::to_string);
+        let vendor_name = props.get(*DEVICE_VENDOR_NAME).map(str::to_string);
         let name = props
             .get(*DEVICE_NAME)
             .or(nick.as_deref())
@@ -264,6 +519,8 @@ impl Store {
             name,
             nick,
             description: None,
+            product_name,
+            vendor_name,
             device_type,
             bus: None,
             form_factor: None,
@@ -272,20 +529,27 @@ impl Store {
             current_profile_index: None,
             proxy,
             listener: None,
+            generation: self.next_generation(),
             output_route: RouteInfo::default(),
             input_route: RouteInfo::default(),
+            output_routes: BTreeMap::new(),
+            input_routes: BTreeMap::new(),
             has_route_volume: false,
             output_channel_count: 0,
             input_channel_count: 0,
+            known_routes: BTreeSet::new(),
+            route_ports: Vec::new(),
+            properties: collect_props(props, INSPECTED_DEVICE_PROPS),
         };
 
-        self.setup_device_monitoring(&mut device, store_rc, graph_tx);
+        self.setup_device_monitoring(&mut device);
 
         self.devices.insert(global.id, device);
+        self.track_param_sync(global.id);
         Ok(())
     }
 
-    fn handle_device_parameter(
+    pub fn handle_device_parameter(
         &mut self,
         device_id: u32,
         param_type: ParamType,
@@ -295,6 +559,9 @@ impl Store {
             ParamType::Route => self
                 .parse_route_volume_data(device_id, pod)
                 .unwrap_or(false),
+            ParamType::EnumRoute => self
+                .handle_device_route_list(device_id, pod)
+                .unwrap_or(false),
             ParamType::EnumProfile => self
                 .handle_device_profile_list(device_id, pod)
                 .unwrap_or(false),
@@ -305,102 +572,37 @@ impl Store {
         }
     }
 
-    pub fn setup_device_monitoring(
-        &self,
-        device: &mut DeviceInternal,
-        store_rc: &Rc<RefCell<Store>>,
-        graph_tx: &watch::Sender<AudioGraph>,
-    ) {
+    pub fn setup_device_monitoring(&self, device: &mut DeviceInternal) {
         let device_id = device.id;
-        let store_weak = Rc::downgrade(store_rc);
-        let graph_tx_clone = graph_tx.clone();
+        let generation = device.generation;
 
         let listener = device
             .proxy
             .add_listener_local()
             .param({
-                let store_weak = store_weak.clone();
-                let graph_tx = graph_tx_clone.clone();
+                let event_queue = self.event_queue.clone();
                 move |_seq, param_type, _index, _next, pod_opt| {
                     if let Some(pod) = pod_opt {
-                        if let Some(store_rc) = store_weak.upgrade() {
-                            let updated = match store_rc.try_borrow_mut() {
-                                Ok(mut store) => {
-                                    store.handle_device_parameter(device_id, param_type, pod)
-                                }
-                                Err(_) => false,
-                            };
-
-                            if updated {
-                                crate::pw::graph::update_graph(&store_rc, &graph_tx);
-                            }
-                        }
+                        event_queue
+                            .borrow_mut()
+                            .push_back(QueuedEvent::DeviceParam {
+                                device_id,
+                                generation,
+                                param_type,
+                                pod: OwnedPod::new(pod),
+                            });
                     }
                 }
             })
             .info({
-                let store_weak = store_weak.clone();
-                let graph_tx = graph_tx_clone.clone();
+                let event_queue = self.event_queue.clone();
                 move |info| {
-                    if let Some(store_rc) = store_weak.upgrade() {
-                        let updated = match store_rc.try_borrow_mut() {
-                            Ok(mut store) => {
-                                if let Some(props) = info.props() {
-                                    if let Some(device) = store.devices.get_mut(&device_id) {
-                                        let mut updated = false;
-
-                                        if let Some(bus) = get_device_bus(props).map(str::to_string)
-                                        {
-                                            if device.bus.as_ref() != Some(&bus) {
-                                                device.bus = Some(bus);
-                                                updated = true;
-                                            }
-                                        }
-
-                                        if let Some(form_factor) =
-                                            get_device_form_factor(props).map(str::to_string)
-                                        {
-                                            if device.form_factor.as_ref() != Some(&form_factor) {
-                                                device.form_factor = Some(form_factor);
-                                                updated = true;
-                                            }
-                                        }
-
-                                        if let Some(description) =
-                                            props.get("device.description").map(str::to_string)
-                                        {
-                                            if device.description.as_ref() != Some(&description) {
-                                                device.description = Some(description);
-                                                updated = true;
-                                            }
-                                        }
-
-                                        if let Some(nick) =
-                                            props.get("device.nick").map(str::to_string)
-                                        {
-                                            if device.nick.as_ref() != Some(&nick) {
-                                                device.nick = Some(nick);
-                                                updated = true;
-                                            }
-                                        }
-
-                                        updated
-                                    } else {
-                                        false
-                                    }
-                                } else {
-                                    false
-                                }
-                            }
-                            Err(e) => {
-                                error!("Failed to borrow store for device info {device_id}: {e}");
-                                false
-                            }
-                        };
-
-                        if updated {
-                            crate::pw::graph::update_graph(&store_rc, &graph_tx);
-                        }
+                    if let Some(props) = info.props() {
+                        event_queue.borrow_mut().push_back(QueuedEvent::DeviceInfo {
+                            device_id,
+                            generation,
+                            props: DeviceInfoProps::from_props(props),
+                        });
                     }
                 }
             })
@@ -410,6 +612,7 @@ impl Store {
 
         device.proxy.subscribe_params(&[
             ParamType::Route,
+            ParamType::EnumRoute,
             ParamType::EnumProfile,
             ParamType::Profile,
         ]);
@@ -420,10 +623,11 @@ impl Store {
     }
 
     pub fn parse_route_volume_data(&mut self, device_id: u32, pod: &Pod) -> Result<bool> {
-        let device = self
-            .devices
-            .get_mut(&device_id)
-            .ok_or_else(|| anyhow!("Device {device_id} not found"))?;
+        let curve = self.volume_curve;
+        let device = self.devices.get_mut(&device_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Device,
+            id: device_id,
+        })?;
 
         if let Ok((_, Value::Object(obj))) = PodDeserializer::deserialize_any_from(pod.as_bytes()) {
             let mut route_direction: Option<u32> = None;
@@ -432,7 +636,8 @@ impl Store {
             let mut has_volume_props = false;
             let mut route_volume: Option<f32> = None;
             let mut route_muted: Option<bool> = None;
-            let mut route_channel_count: Option<usize> = None;
+            let mut route_channel_volumes: Option<Vec<f32>> = None;
+            let mut route_available: Option<&str> = None;
 
             for prop in &obj.properties {
                 match prop.key {
@@ -451,6 +656,11 @@ impl Store {
                             route_device = Some(device_num);
                         }
                     }
+                    libspa::sys::SPA_PARAM_ROUTE_available => {
+                        if let Value::Id(spa_id) = &prop.value {
+                            route_available = Some(availability_name(spa_id.0));
+                        }
+                    }
                     libspa::sys::SPA_PARAM_ROUTE_props => {
                         if let Value::Object(props_obj) = &prop.value {
                             for volume_prop in &props_obj.properties {
@@ -461,22 +671,24 @@ impl Store {
                                             ref float_vec,
                                         )) = volume_prop.value
                                         {
-                                            route_channel_count = Some(float_vec.len());
+                                            route_channel_volumes = Some(float_vec.clone());
                                             if let Some(raw_volume) =
                                                 VolumeResolver::extract_channel_volume(
                                                     &volume_prop.value,
                                                 )
                                             {
-                                                route_volume = Some(
-                                                    VolumeResolver::apply_cubic_scaling(raw_volume),
-                                                );
+                                                route_volume = Some(VolumeResolver::apply_curve(
+                                                    curve, raw_volume,
+                                                ));
                                             }
                                         }
                                     }
                                     k if k == libspa::sys::SPA_PROP_volume => {
                                         has_volume_props = true;
-                                        if let Value::Float(vol) = volume_prop.value {
-                                            route_volume = Some(vol);
+                                        if let Value::Float(raw_volume) = volume_prop.value {
+                                            route_volume = Some(VolumeResolver::apply_curve(
+                                                curve, raw_volume,
+                                            ));
                                         }
                                     }
                                     k if k == libspa::sys::SPA_PROP_mute => {
@@ -497,52 +709,47 @@ impl Store {
             if let (Some(direction), Some(index), Some(device_num)) =
                 (route_direction, route_index, route_device)
             {
-                let mut cache_updated = false;
+                let mut cache_updated = device.known_routes.insert((direction, index));
 
-                if direction == 1 {
-                    device.output_route.index = Some(index);
-                    device.output_route.device = Some(device_num);
+                let (routes, last_route, channel_count) = match direction {
+                    1 => (
+                        &mut device.output_routes,
+                        &mut device.output_route,
+                        &mut device.output_channel_count,
+                    ),
+                    0 => (
+                        &mut device.input_routes,
+                        &mut device.input_route,
+                        &mut device.input_channel_count,
+                    ),
+                    _ => return Ok(cache_updated),
+                };
 
-                    if let Some(volume) = route_volume {
-                        if device.output_route.volume != Some(volume) {
-                            device.output_route.volume = Some(volume);
-                            cache_updated = true;
-                        }
-                    }
-                    if let Some(muted) = route_muted {
-                        if device.output_route.muted != Some(muted) {
-                            device.output_route.muted = Some(muted);
-                            cache_updated = true;
-                        }
-                    }
-                    if let Some(count) = route_channel_count {
-                        if device.output_channel_count != count {
-                            device.output_channel_count = count;
-                            cache_updated = true;
-                        }
-                    }
-                } else if direction == 0 {
-                    device.input_route.index = Some(index);
-                    device.input_route.device = Some(device_num);
-
-                    if let Some(volume) = route_volume {
-                        if device.input_route.volume != Some(volume) {
-                            device.input_route.volume = Some(volume);
-                            cache_updated = true;
-                        }
-                    }
-                    if let Some(muted) = route_muted {
-                        if device.input_route.muted != Some(muted) {
-                            device.input_route.muted = Some(muted);
-                            cache_updated = true;
-                        }
-                    }
-                    if let Some(count) = route_channel_count {
-                        if device.input_channel_count != count {
-                            device.input_channel_count = count;
-                            cache_updated = true;
-                        }
+                // Each card device keeps its own route, the per-direction one follows the
+                // last reported so nodes without a card device still get a volume
+                let route = routes.entry(device_num).or_default();
+                route.index = Some(index);
+                route.device = Some(device_num);
+                if let Some(available) = route_available {
+                    route.available = Some(available.to_string());
+                }
+                if let Some(volume) = route_volume {
+                    route.volume = Some(volume);
+                }
+                if let Some(muted) = route_muted {
+                    route.muted = Some(muted);
+                }
+                if let Some(channel_volumes) = route_channel_volumes.take() {
+                    if *channel_count != channel_volumes.len() {
+                        *channel_count = channel_volumes.len();
+                        cache_updated = true;
                     }
+                    route.channel_volumes = channel_volumes;
+                }
+
+                if *last_route != *route {
+                    *last_route = route.clone();
+                    cache_updated = true;
                 }
 
                 if has_volume_props {
@@ -576,10 +783,10 @@ impl Store {
         // Parse the profile first to avoid borrowing conflicts
         let profile = Self::parse_profile_from_pod(pod)?;
 
-        let device = self
-            .devices
-            .get_mut(&device_id)
-            .ok_or_else(|| anyhow!("Device {device_id} not found for profile list update"))?;
+        let device = self.devices.get_mut(&device_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Device,
+            id: device_id,
+        })?;
 
         debug!(
             "Updated profile {} for device {}: '{}' ({}) - available: {}",
@@ -603,17 +810,55 @@ impl Store {
         Ok(true)
     }
 
+    /// Records a connector of the card, returning whether it is new or its jack state changed.
+    pub fn handle_device_route_list(&mut self, device_id: u32, pod: &Pod) -> Result<bool> {
+        let port = Self::parse_route_port_from_pod(pod)?;
+
+        let device = self.devices.get_mut(&device_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Device,
+            id: device_id,
+        })?;
+
+        match device
+            .route_ports
+            .iter_mut()
+            .find(|p| p.direction == port.direction && p.index == port.index)
+        {
+            Some(existing) if *existing == port => Ok(false),
+            Some(existing) => {
+                debug!(
+                    "Port '{}' of device {device_id} changed - available: {}",
+                    port.description, port.available
+                );
+                *existing = port;
+                Ok(true)
+            }
+            None => {
+                debug!(
+                    "Added port {} to device {device_id}: '{}' ({}) - available: {}",
+                    port.index, port.name, port.description, port.available
+                );
+                device.route_ports.push(port);
+                device
+                    .route_ports
+                    .sort_by_key(|p| (p.direction == RouteDirection::Input, p.index));
+                Ok(true)
+            }
+        }
+    }
+
     pub fn handle_device_current_profile(&mut self, device_id: u32, pod: &Pod) -> Result<bool> {
-        let device = self
-            .devices
-            .get_mut(&device_id)
-            .ok_or_else(|| anyhow!("Device {device_id} not found for current profile update"))?;
+        let device = self.devices.get_mut(&device_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Device,
+            id: device_id,
+        })?;
 
-        let (_, value) = PodDeserializer::deserialize_any_from(pod.as_bytes())
-            .map_err(|e| anyhow!("Failed to deserialize current profile pod: {e:?}"))?;
+        let (_, value) = PodDeserializer::deserialize_any_from(pod.as_bytes()).map_err(|e| {
+            PwError::PodBuild(format!("Failed to deserialize current profile pod: {e:?}"))
+        })?;
 
         let Value::Object(obj) = value else {
-            return Err(anyhow!("Expected Object value, got {value:?}"));
+            return Err(PwError::PodBuild(format!("Expected Object value, got {value:?}")).into());
         };
 
         for prop in &obj.properties {
@@ -659,10 +904,10 @@ impl Store {
 
     fn parse_profile_from_pod(pod: &Pod) -> Result<Profile> {
         let (_, value) = PodDeserializer::deserialize_any_from(pod.as_bytes())
-            .map_err(|e| anyhow!("Failed to deserialize profile pod: {e:?}"))?;
+            .map_err(|e| PwError::PodBuild(format!("Failed to deserialize profile pod: {e:?}")))?;
 
         let Value::Object(obj) = value else {
-            return Err(anyhow!("Expected Object value, got {value:?}"));
+            return Err(PwError::PodBuild(format!("Expected Object value, got {value:?}")).into());
         };
 
         let mut profile = Profile {
@@ -714,6 +959,96 @@ impl Store {
         Ok(profile)
     }
 
+    fn parse_route_port_from_pod(pod: &Pod) -> Result<RoutePort> {
+        let (_, value) = PodDeserializer::deserialize_any_from(pod.as_bytes())
+            .map_err(|e| PwError::PodBuild(format!("Failed to deserialize route pod: {e:?}")))?;
+
+        let Value::Object(obj) = value else {
+            return Err(PwError::PodBuild(format!("Expected Object value, got {value:?}")).into());
+        };
+
+        let mut index = None;
+        let mut direction = None;
+        let mut port_name = String::new();
+        let mut description = String::new();
+        let mut available = "unknown".to_string();
+        let mut devices = Vec::new();
+        let mut port_type = None;
+        let mut monitor_name = None;
+
+        for prop in &obj.properties {
+            match prop.key {
+                libspa::sys::SPA_PARAM_ROUTE_index => {
+                    if let Value::Int(route_index) = prop.value {
+                        index = Some(route_index);
+                    }
+                }
+                libspa::sys::SPA_PARAM_ROUTE_direction => {
+                    if let Value::Id(spa_id) = &prop.value {
+                        direction = match spa_id.0 {
+                            1 => Some(RouteDirection::Output),
+                            0 => Some(RouteDirection::Input),
+                            _ => None,
+                        };
+                    }
+                }
+                libspa::sys::SPA_PARAM_ROUTE_name => {
+                    if let Value::String(name) = &prop.value {
+                        port_name = name.clone();
+                    }
+                }
+                libspa::sys::SPA_PARAM_ROUTE_description => {
+                    if let Value::String(desc) = &prop.value {
+                        description = desc.clone();
+                    }
+                }
+                libspa::sys::SPA_PARAM_ROUTE_available => {
+                    if let Value::Id(spa_id) = &prop.value {
+                        available = availability_name(spa_id.0).to_string();
+                    }
+                }
+                libspa::sys::SPA_PARAM_ROUTE_devices => {
+                    if let Value::ValueArray(libspa::pod::ValueArray::Int(card_devices)) =
+                        &prop.value
+                    {
+                        devices = card_devices.clone();
+                    }
+                }
+                libspa::sys::SPA_PARAM_ROUTE_info => {
+                    // An item count, then keys and values
+                    if let Value::Struct(items) = &prop.value {
+                        for item in items.get(1..).unwrap_or_default().chunks(2) {
+                            let [Value::String(key), Value::String(value)] = item else {
+                                continue;
+                            };
+                            match key.as_str() {
+                                "port.type" => port_type = Some(value.clone()),
+                                "device.product.name" => monitor_name = Some(value.clone()),
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let (Some(index), Some(direction)) = (index, direction) else {
+            return Err(PwError::PodBuild("Route without index or direction".to_string()).into());
+        };
+
+        Ok(RoutePort {
+            index,
+            direction,
+            name: port_name,
+            description,
+            available,
+            devices,
+            port_type,
+            monitor_name,
+        })
+    }
+
     pub fn get_device_profiles(&self, device_id: u32) -> Vec<Profile> {
         self.devices
             .get(&device_id)
@@ -735,10 +1070,10 @@ impl Store {
     }
 
     pub fn switch_device_profile(&mut self, device_id: u32, profile_index: u32) -> Result<()> {
-        let device = self
-            .devices
-            .get(&device_id)
-            .ok_or_else(|| anyhow!("Device {device_id} not found for profile switch"))?;
+        let device = self.devices.get(&device_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Device,
+            id: device_id,
+        })?;
 
         device.switch_profile(profile_index)
     }
@@ -796,48 +1131,65 @@ impl Store {
         Ok(buffer)
     }
 
+    /// Nodes of the device, only those playing or recording in `direction` when given, so
+    /// falling back to node volume on a duplex card leaves the other direction alone.
+    fn device_nodes(&self, device_id: u32, direction: Option<RouteDirection>) -> Result<Vec<u32>> {
+        let device = self.devices.get(&device_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Device,
+            id: device_id,
+        })?;
+        let node_type = direction.map(|direction| match direction {
+            RouteDirection::Output => NodeType::AudioSink,
+            RouteDirection::Input => NodeType::AudioSource,
+        });
+
+        Ok(device
+            .nodes
+            .iter()
+            .filter(|id| {
+                node_type.is_none_or(|node_type| {
+                    self.nodes
+                        .get(*id)
+                        .is_some_and(|node| node.node_type == node_type)
+                })
+            })
+            .copied()
+            .collect())
+    }
+
     pub fn set_device_volume(
         &mut self,
         device_id: u32,
         volume: f32,
         direction: Option<RouteDirection>,
+        card_device: Option<i32>,
     ) -> Result<()> {
         let target_direction = if let Some(dir) = direction {
-            let device = self
-                .devices
-                .get(&device_id)
-                .ok_or_else(|| anyhow!("Device {device_id} not found"))?;
-
-            match dir {
-                RouteDirection::Output => {
-                    if device.output_route.is_available() {
-                        Some(dir)
-                    } else {
-                        None
-                    }
-                }
-                RouteDirection::Input => {
-                    if device.input_route.is_available() {
-                        Some(dir)
-                    } else {
-                        None
-                    }
-                }
-            }
+            let device = self.devices.get(&device_id).ok_or(PwError::NotFound {
+                kind: ObjectKind::Device,
+                id: device_id,
+            })?;
+
+            Some(dir).filter(|&dir| {
+                let route = device.route(dir, card_device);
+                route.is_available() && route.has_volume()
+            })
         } else {
             None
         };
 
         if let Some(direction) = target_direction {
             let (route_index, route_device, channel_count) = {
-                let device = self
-                    .devices
-                    .get(&device_id)
-                    .ok_or_else(|| anyhow!("Device {device_id} not found"))?;
-
-                let count = match direction {
-                    RouteDirection::Output => device.output_channel_count,
-                    RouteDirection::Input => device.input_channel_count,
+                let device = self.devices.get(&device_id).ok_or(PwError::NotFound {
+                    kind: ObjectKind::Device,
+                    id: device_id,
+                })?;
+
+                let route = device.route(direction, card_device);
+                let count = match (route.channel_volumes.len(), direction) {
+                    (0, RouteDirection::Output) => device.output_channel_count,
+                    (0, RouteDirection::Input) => device.input_channel_count,
+                    (count, _) => count,
                 };
 
                 if count == 0 {
@@ -846,21 +1198,14 @@ impl Store {
                     ));
                 }
 
-                match direction {
-                    RouteDirection::Output => (
-                        device.output_route.get_route_params().unwrap().0,
-                        device.output_route.get_route_params().unwrap().1,
-                        count,
-                    ),
-                    RouteDirection::Input => (
-                        device.input_route.get_route_params().unwrap().0,
-                        device.input_route.get_route_params().unwrap().1,
-                        count,
-                    ),
-                }
+                let (index, route_device) = route
+                    .get_route_params()
+                    .ok_or(PwError::NoRouteInfo { device_id })?;
+                (index, route_device, count)
             };
 
-            let raw_volume = VolumeResolver::apply_inverse_cubic_scaling(volume.clamp(0.0, 2.0));
+            let raw_volume =
+                VolumeResolver::apply_inverse_curve(self.volume_curve, volume.clamp(0.0, 2.0));
             let volumes: Vec<f32> = vec![raw_volume; channel_count];
 
             let buffer = self.build_route_parameter_pod(route_index, route_device, |builder| {
@@ -880,32 +1225,20 @@ impl Store {
                 }
             })?;
 
-            let pod_ref = Pod::from_bytes(&buffer)
-                .ok_or_else(|| anyhow!("Failed to create Pod reference for device volume"))?;
+            let pod_ref = Pod::from_bytes(&buffer).ok_or_else(|| {
+                PwError::PodBuild("Failed to create Pod reference for device volume".to_string())
+            })?;
 
-            let device = self
-                .devices
-                .get_mut(&device_id)
-                .ok_or_else(|| anyhow!("Device {device_id} not found"))?;
+            let device = self.devices.get_mut(&device_id).ok_or(PwError::NotFound {
+                kind: ObjectKind::Device,
+                id: device_id,
+            })?;
 
             device.proxy.set_param(ParamType::Route, 0, pod_ref);
 
-            match direction {
-                RouteDirection::Output => {
-                    device.output_route.volume = Some(volume);
-                }
-                RouteDirection::Input => {
-                    device.input_route.volume = Some(volume);
-                }
-            }
+            device.update_route(direction, route_device, |route| route.volume = Some(volume));
         } else {
-            let node_ids: Vec<u32> = {
-                let device = self
-                    .devices
-                    .get(&device_id)
-                    .ok_or_else(|| anyhow!("Device {device_id} not found"))?;
-                device.nodes.clone()
-            };
+            let node_ids = self.device_nodes(device_id, direction)?;
 
             for node_id in node_ids {
                 if let Err(e) = self.set_node_volume(node_id, volume) {
@@ -922,44 +1255,33 @@ impl Store {
         device_id: u32,
         mute: bool,
         direction: Option<RouteDirection>,
+        card_device: Option<i32>,
     ) -> Result<()> {
         let target_direction = if let Some(dir) = direction {
-            let device = self
-                .devices
-                .get(&device_id)
-                .ok_or_else(|| anyhow!("Device {device_id} not found"))?;
-
-            match dir {
-                RouteDirection::Output => {
-                    if device.output_route.is_available() {
-                        Some(dir)
-                    } else {
-                        None
-                    }
-                }
-                RouteDirection::Input => {
-                    if device.input_route.is_available() {
-                        Some(dir)
-                    } else {
-                        None
-                    }
-                }
-            }
+            let device = self.devices.get(&device_id).ok_or(PwError::NotFound {
+                kind: ObjectKind::Device,
+                id: device_id,
+            })?;
+
+            Some(dir).filter(|&dir| {
+                let route = device.route(dir, card_device);
+                route.is_available() && route.has_volume()
+            })
         } else {
             None
         };
 
         if let Some(direction) = target_direction {
             let (route_index, route_device) = {
-                let device = self
-                    .devices
-                    .get(&device_id)
-                    .ok_or_else(|| anyhow!("Device {device_id} not found"))?;
-
-                match direction {
-                    RouteDirection::Output => device.output_route.get_route_params().unwrap(),
-                    RouteDirection::Input => device.input_route.get_route_params().unwrap(),
-                }
+                let device = self.devices.get(&device_id).ok_or(PwError::NotFound {
+                    kind: ObjectKind::Device,
+                    id: device_id,
+                })?;
+
+                device
+                    .route(direction, card_device)
+                    .get_route_params()
+                    .ok_or(PwError::NoRouteInfo { device_id })?
             };
 
             let buffer = self.build_route_parameter_pod(route_index, route_device, |builder| {
@@ -969,32 +1291,20 @@ impl Store {
                     .context("Failed to add mute property")
             })?;
 
-            let pod_ref = Pod::from_bytes(&buffer)
-                .ok_or_else(|| anyhow!("Failed to create Pod reference for device mute"))?;
+            let pod_ref = Pod::from_bytes(&buffer).ok_or_else(|| {
+                PwError::PodBuild("Failed to create Pod reference for device mute".to_string())
+            })?;
 
-            let device = self
-                .devices
-                .get_mut(&device_id)
-                .ok_or_else(|| anyhow!("Device {device_id} not found"))?;
+            let device = self.devices.get_mut(&device_id).ok_or(PwError::NotFound {
+                kind: ObjectKind::Device,
+                id: device_id,
+            })?;
 
             device.proxy.set_param(ParamType::Route, 0, pod_ref);
 
-            match direction {
-                RouteDirection::Output => {
-                    device.output_route.muted = Some(mute);
-                }
-                RouteDirection::Input => {
-                    device.input_route.muted = Some(mute);
-                }
-            }
+            device.update_route(direction, route_device, |route| route.muted = Some(mute));
         } else {
-            let node_ids: Vec<u32> = {
-                let device = self
-                    .devices
-                    .get(&device_id)
-                    .ok_or_else(|| anyhow!("Device {device_id} not found"))?;
-                device.nodes.clone()
-            };
+            let node_ids = self.device_nodes(device_id, direction)?;
 
             for node_id in node_ids {
                 if let Err(e) = self.set_node_mute(node_id, mute) {
@@ -1,46 +1,103 @@
 use anyhow::{anyhow, Context as AnyhowContext, Result};
 use libspa::param::ParamType;
-use log::{debug, error, warn};
 use pipewire::{
-    context::ContextRc, core::Info as CoreInfo, main_loop::MainLoopRc, registry::GlobalObject,
-    spa::utils::dict::DictRef, types::ObjectType,
+    context::ContextRc,
+    core::{Info as CoreInfo, PW_ID_CORE},
+    main_loop::MainLoopRc,
+    properties::PropertiesBox,
+    registry::GlobalObject,
+    spa::utils::dict::DictRef,
+    types::ObjectType,
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
-use std::{cell::RefCell, rc::Rc, time::Duration};
 use tokio::{
     sync::{mpsc, oneshot, watch},
     time::{timeout, Instant},
 };
+use tracing::{debug, debug_span, error, warn};
 
 use crate::pw::{
-    commands::PwCommand,
-    graph::{update_graph, AudioGraph, ConnectionStatus, Store},
-    volume::RouteDirection,
+    commands::{coalesce_volume_commands, send_volume_result, BatchStep, CommandError, PwCommand},
+    controller::{BusPriority, FormFactorPriority},
+    error::PwError,
+    events::GraphEvents,
+    graph::{
+        apply_queued_events, flush_graph, flush_pending_graph, pending_graph_delay,
+        pending_restoration_delay, run_due_restorations, update_graph, AudioGraph,
+        ConnectionStatus, QueuedEvent, Store,
+    },
+    links::PortMapping,
+    meter::InputLevel,
+    nodes::NodeType,
+    profiler::ProfilerStats,
+    restoration::RestorationSettings,
+    volume::{RouteDirection, VolumeCurve},
 };
 
+// Upper bound on how long housekeeping such as test tone expiry waits while the loop is idle
+const LOOP_TIMEOUT: Duration = Duration::from_millis(100);
+
+// Commands run without blocking the loop, so a slower result means the loop is stuck
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How long shutdown waits for the PipeWire thread to release its resources
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Reconnection attempts back off exponentially between these delays
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Why a connection to the PipeWire daemon ended.
+enum SessionEnd {
+    Exit,
+    ConnectionLost,
+}
+
 pub struct PwEngine {
     cmd_tx: mpsc::UnboundedSender<PwCommand>,
-    graph_rx: watch::Receiver<AudioGraph>,
-    _join_handle: Option<tokio::task::JoinHandle<()>>,
+    // Wakes the PipeWire loop so commands are handled as soon as they are queued
+    wake_tx: pipewire::channel::Sender<()>,
+    graph_rx: watch::Receiver<Arc<AudioGraph>>,
+    command_timeout: Duration,
+    join_handle: Mutex<Option<tokio::task::JoinHandle<Result<()>>>>,
 }
 
 impl PwEngine {
     pub async fn new() -> Result<Self> {
+        Self::connect(None, DEFAULT_COMMAND_TIMEOUT).await
+    }
+
+    /// Connects to the named PipeWire instance, such as `pipewire-0-manager` or one in a
+    /// container, rather than the default one (`PIPEWIRE_REMOTE` or `pipewire-0`). Commands
+    /// wait `command_timeout` for their result before failing with [`CommandError::TimedOut`].
+    pub async fn connect(remote: Option<String>, command_timeout: Duration) -> Result<Self> {
         let (cmd_tx, cmd_rx) = mpsc::unbounded_channel::<PwCommand>();
-        let (graph_tx, graph_rx) = watch::channel(AudioGraph::default());
+        let (wake_tx, wake_rx) = pipewire::channel::channel::<()>();
+        let (graph_tx, graph_rx) = watch::channel(Arc::new(AudioGraph::default()));
 
         let join_handle = tokio::task::spawn_blocking(move || {
             debug!("PipeWire blocking thread started.");
-            if let Err(e) = run_pipewire_loop(cmd_rx, graph_tx) {
+            let result = run_pipewire_loop(cmd_rx, wake_rx, graph_tx, remote);
+            if let Err(e) = &result {
                 error!("PipeWire loop exited with error: {e:?}");
             } else {
                 debug!("PipeWire loop exited cleanly.");
             }
+            result
         });
 
         Ok(Self {
             cmd_tx,
+            wake_tx,
             graph_rx,
-            _join_handle: Some(join_handle),
+            command_timeout,
+            join_handle: Mutex::new(Some(join_handle)),
         })
     }
 
@@ -49,13 +106,12 @@ impl PwEngine {
 
         // Phase 1: Wait for registry sync
         loop {
-            let graph = graph_rx.borrow().clone();
-            if graph.connection_status == ConnectionStatus::Connected && graph.initial_sync_complete
-            {
+            if graph_rx.borrow().is_connected() {
                 break;
             }
             if graph_rx.changed().await.is_err() {
-                return Err(anyhow!("Graph updates channel closed during registry sync"));
+                // The loop exits before the first sync only when connecting failed
+                return Err(PwError::Unavailable.into());
             }
         }
 
@@ -86,10 +142,51 @@ impl PwEngine {
         }
     }
 
-    pub fn graph(&self) -> AudioGraph {
+    /// Latest snapshot, shared with other readers rather than copied.
+    pub fn graph(&self) -> Arc<AudioGraph> {
         self.graph_rx.borrow().clone()
     }
 
+    pub fn subscribe(&self) -> watch::Receiver<Arc<AudioGraph>> {
+        self.graph_rx.clone()
+    }
+
+    /// Semantic changes from now on, such as added nodes or volume changes.
+    pub fn events(&self) -> GraphEvents {
+        GraphEvents::new(self.graph_rx.clone())
+    }
+
+    /// Stops the PipeWire loop and waits for its thread to finish. Fails if the loop ended
+    /// with an error or did not stop in time, in which case the thread is left running.
+    pub async fn shutdown(&self) -> Result<()> {
+        let Some(join_handle) = self.join_handle.lock().ok().and_then(|mut h| h.take()) else {
+            return Ok(());
+        };
+
+        debug!("Shutting down PipeWire engine");
+        // The loop may already be gone, which the join below reports
+        let _ = self.send_command(PwCommand::Exit);
+
+        match timeout(SHUTDOWN_TIMEOUT, join_handle).await {
+            Ok(Ok(result)) => result.context("PipeWire loop exited with an error"),
+            Ok(Err(e)) => Err(anyhow!("PipeWire thread panicked: {e}")),
+            Err(_) => Err(anyhow!(
+                "PipeWire loop did not exit within {SHUTDOWN_TIMEOUT:?}"
+            )),
+        }
+    }
+
+    fn send_command(&self, command: PwCommand) -> Result<()> {
+        self.cmd_tx
+            .send(command)
+            .map_err(|_| PwError::Command(CommandError::Closed))?;
+        // A failed wakeup only delays the command until the next loop timeout
+        if self.wake_tx.send(()).is_err() {
+            warn!("Failed to wake up the PipeWire loop");
+        }
+        Ok(())
+    }
+
     async fn send_command_and_wait<F, T>(&self, command_builder: F) -> Result<T>
     where
         F: FnOnce(oneshot::Sender<Result<T>>) -> PwCommand,
@@ -97,15 +194,23 @@ impl PwEngine {
     {
         let (result_tx, result_rx) = oneshot::channel::<Result<T>>();
         let command = command_builder(result_tx);
+        let span = debug_span!("pw_command", command = command.name());
+        let started = Instant::now();
 
-        self.cmd_tx
-            .send(command)
-            .map_err(|e| anyhow!("PipeWire thread command channel closed: {e}"))?;
+        self.send_command(command)?;
 
-        result_rx
-            .await
-            .map_err(|e| anyhow!("PipeWire thread result channel closed: {e}"))?
-            .context("PipeWire command execution failed")
+        // Dropping the receiver on timeout cancels the command if it is still queued
+        let result = match timeout(self.command_timeout, result_rx).await {
+            Ok(Ok(result)) => result.context("PipeWire command execution failed"),
+            Ok(Err(_)) => Err(PwError::Command(CommandError::Closed).into()),
+            Err(_) => Err(PwError::Command(CommandError::TimedOut(self.command_timeout)).into()),
+        };
+
+        span.in_scope(|| match &result {
+            Ok(_) => debug!("Completed in {:?}", started.elapsed()),
+            Err(e) => debug!("Failed after {:?}: {e:#}", started.elapsed()),
+        });
+        result
     }
 
     pub async fn set_node_volume(&self, node_id: u32, volume: f32) -> Result<()> {
@@ -113,6 +218,7 @@ impl PwEngine {
             node_id,
             volume,
             result_sender: rs,
+            merged: Vec::new(),
         })
         .await
     }
@@ -126,10 +232,16 @@ impl PwEngine {
         .await
     }
 
-    pub async fn create_link(&self, output_node: u32, input_node: u32) -> Result<()> {
+    pub async fn create_link(
+        &self,
+        output_node: u32,
+        input_node: u32,
+        mapping: PortMapping,
+    ) -> Result<()> {
         self.send_command_and_wait(|rs| PwCommand::CreateLink {
             output_node,
             input_node,
+            mapping,
             result_sender: rs,
         })
         .await
@@ -152,6 +264,32 @@ impl PwEngine {
         .await
     }
 
+    pub async fn move_streams_to(&self, node_id: u32) -> Result<usize> {
+        self.send_command_and_wait(|rs| PwCommand::MoveStreams {
+            node_id,
+            result_sender: rs,
+        })
+        .await
+    }
+
+    pub async fn set_stream_target(&self, stream_id: u32, target_id: Option<u32>) -> Result<()> {
+        self.send_command_and_wait(|rs| PwCommand::SetStreamTarget {
+            stream_id,
+            target_id,
+            result_sender: rs,
+        })
+        .await
+    }
+
+    pub async fn set_node_latency(&self, node_id: u32, latency: String) -> Result<()> {
+        self.send_command_and_wait(|rs| PwCommand::SetNodeLatency {
+            node_id,
+            latency,
+            result_sender: rs,
+        })
+        .await
+    }
+
     pub async fn set_default_source(&self, node_id: u32) -> Result<()> {
         self.send_command_and_wait(|rs| PwCommand::SetDefaultSource {
             node_id,
@@ -160,6 +298,15 @@ impl PwEngine {
         .await
     }
 
+    /// Runs several operations in one trip through the PipeWire loop.
+    pub async fn batch(&self, steps: Vec<BatchStep>) -> Result<()> {
+        self.send_command_and_wait(|rs| PwCommand::Batch {
+            steps,
+            result_sender: rs,
+        })
+        .await
+    }
+
     pub async fn switch_device_profile(&self, device_id: u32, profile_index: u32) -> Result<()> {
         self.send_command_and_wait(|rs| PwCommand::SwitchDeviceProfile {
             device_id,
@@ -182,17 +329,54 @@ impl PwEngine {
         .await
     }
 
+    /// Forgets the defaults captured by [`PwEngine::switch_device_profile_with_restoration`].
+    pub async fn cancel_profile_restoration(&self, device_id: u32) -> Result<()> {
+        self.send_command_and_wait(|rs| PwCommand::CancelProfileRestoration {
+            device_id,
+            result_sender: rs,
+        })
+        .await
+    }
+
+    /// Sets how long defaults are waited for after a profile switch, and whether pending ones
+    /// are kept in the state file, resuming those a previous run left.
+    pub async fn configure_restoration(&self, settings: RestorationSettings) -> Result<()> {
+        self.send_command_and_wait(|rs| PwCommand::ConfigureRestoration {
+            settings,
+            result_sender: rs,
+        })
+        .await
+    }
+
+    /// Sets the nodes promoted first, most preferred first, when a default node goes away.
+    pub async fn set_preferred_nodes(&self, names: Vec<String>) -> Result<()> {
+        self.send_command_and_wait(|rs| PwCommand::SetPreferredNodes {
+            names,
+            result_sender: rs,
+        })
+        .await
+    }
+
+    /// Reads and writes volumes on `curve` from now on, including after a reconnection.
+    /// Graph receivers get the volumes on the new curve with the next update.
+    pub fn set_volume_curve(&self, curve: VolumeCurve) -> Result<()> {
+        self.send_command(PwCommand::SetVolumeCurve { curve })
+    }
+
     pub async fn set_device_volume(
         &self,
         device_id: u32,
         volume: f32,
         direction: Option<RouteDirection>,
+        card_device: Option<i32>,
     ) -> Result<()> {
         self.send_command_and_wait(|rs| PwCommand::SetDeviceVolume {
             device_id,
             volume,
             direction,
+            card_device,
             result_sender: rs,
+            merged: Vec::new(),
         })
         .await
     }
@@ -202,11 +386,13 @@ impl PwEngine {
         device_id: u32,
         mute: bool,
         direction: Option<RouteDirection>,
+        card_device: Option<i32>,
     ) -> Result<()> {
         self.send_command_and_wait(|rs| PwCommand::SetDeviceMute {
             device_id,
             mute,
             direction,
+            card_device,
             result_sender: rs,
         })
         .await
@@ -219,152 +405,237 @@ impl PwEngine {
         })
         .await
     }
+
+    pub async fn create_virtual_sink(&self, description: &str) -> Result<()> {
+        self.send_command_and_wait(|rs| PwCommand::CreateVirtualSink {
+            description: description.to_string(),
+            result_sender: rs,
+        })
+        .await
+    }
+
+    pub async fn destroy_managed_node(&self, node_id: u32) -> Result<()> {
+        self.send_command_and_wait(|rs| PwCommand::DestroyManagedNode {
+            node_id,
+            result_sender: rs,
+        })
+        .await
+    }
+
+    pub async fn rename_managed_node(&self, node_id: u32, description: &str) -> Result<()> {
+        self.send_command_and_wait(|rs| PwCommand::RenameManagedNode {
+            node_id,
+            description: description.to_string(),
+            result_sender: rs,
+        })
+        .await
+    }
+
+    pub async fn play_test_tone(&self, node_id: u32) -> Result<()> {
+        self.send_command_and_wait(|rs| PwCommand::PlayTestTone {
+            node_id,
+            result_sender: rs,
+        })
+        .await
+    }
+
+    pub async fn start_level_meter(&self, node_id: u32) -> Result<InputLevel> {
+        self.send_command_and_wait(|rs| PwCommand::StartLevelMeter {
+            node_id,
+            result_sender: rs,
+        })
+        .await
+    }
+
+    pub async fn stop_level_meter(&self) -> Result<()> {
+        self.send_command_and_wait(|rs| PwCommand::StopLevelMeter { result_sender: rs })
+            .await
+    }
+
+    pub async fn sync_stream_meters(&self, node_ids: Vec<u32>) -> Result<HashMap<u32, InputLevel>> {
+        self.send_command_and_wait(|rs| PwCommand::SyncStreamMeters {
+            node_ids,
+            result_sender: rs,
+        })
+        .await
+    }
+
+    pub async fn start_profiler(&self) -> Result<ProfilerStats> {
+        self.send_command_and_wait(|rs| PwCommand::StartProfiler { result_sender: rs })
+            .await
+    }
+
+    pub async fn stop_profiler(&self) -> Result<()> {
+        self.send_command_and_wait(|rs| PwCommand::StopProfiler { result_sender: rs })
+            .await
+    }
+
+    pub async fn destroy_all_managed(&self) -> Result<usize> {
+        self.send_command_and_wait(|rs| PwCommand::DestroyAllManaged { result_sender: rs })
+            .await
+    }
 }
 
 impl Drop for PwEngine {
     fn drop(&mut self) {
         debug!("PwEngine dropping. Sending Exit command.");
-        let _ = self.cmd_tx.send(PwCommand::Exit);
+        let _ = self.send_command(PwCommand::Exit);
     }
 }
 
 fn run_pipewire_loop(
     mut cmd_rx: mpsc::UnboundedReceiver<PwCommand>,
-    graph_tx: watch::Sender<AudioGraph>,
+    wake_rx: pipewire::channel::Receiver<()>,
+    graph_tx: watch::Sender<Arc<AudioGraph>>,
+    remote: Option<String>,
 ) -> Result<()> {
     pipewire::init();
     debug!("PipeWire library initialized.");
 
     let mainloop = MainLoopRc::new(None).context("Failed to create PipeWire MainLoop")?;
-    let context = ContextRc::new(&mainloop, None).context("Failed to create PipeWire Context")?;
+
+    // Commands are drained after each iteration; the wakeup only interrupts the wait
+    let _wake_receiver = wake_rx.attach(mainloop.loop_(), |()| {});
+
+    let mut connected_once = false;
+    let mut delay = RECONNECT_INITIAL_DELAY;
+    let mut volume_curve = VolumeCurve::default();
+
+    loop {
+        match run_session(
+            &mainloop,
+            &mut cmd_rx,
+            &graph_tx,
+            remote.as_deref(),
+            &mut volume_curve,
+        ) {
+            Ok(SessionEnd::Exit) => break,
+            Ok(SessionEnd::ConnectionLost) => {
+                warn!("Lost connection to PipeWire, reconnecting");
+                connected_once = true;
+                delay = RECONNECT_INITIAL_DELAY;
+            }
+            // Without a daemon to begin with, there is nothing to reconnect to
+            Err(e) if !connected_once => return Err(e),
+            Err(e) => {
+                warn!("Reconnecting to PipeWire failed: {e:#}");
+                delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+            }
+        }
+
+        graph_tx.send_replace(Arc::new(AudioGraph {
+            connection_status: ConnectionStatus::Reconnecting,
+            ..AudioGraph::default()
+        }));
+
+        debug!("Next PipeWire connection attempt in {delay:?}");
+        if !wait_before_reconnect(&mainloop, &mut cmd_rx, delay, &mut volume_curve) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Waits out a reconnection delay, failing commands sent meanwhile other than a new volume
+/// curve, which the next session starts with. Returns false when the engine is shutting down.
+fn wait_before_reconnect(
+    mainloop: &MainLoopRc,
+    cmd_rx: &mut mpsc::UnboundedReceiver<PwCommand>,
+    delay: Duration,
+    volume_curve: &mut VolumeCurve,
+) -> bool {
+    let deadline = std::time::Instant::now() + delay;
+
+    loop {
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            return true;
+        }
+        mainloop.loop_().iterate(remaining);
+
+        loop {
+            match cmd_rx.try_recv() {
+                Ok(PwCommand::Exit) | Err(mpsc::error::TryRecvError::Disconnected) => {
+                    return false;
+                }
+                Ok(PwCommand::SetVolumeCurve { curve }) => *volume_curve = curve,
+                // Dropping the command closes its result channel, so the caller gets an error
+                Ok(cmd) => debug!("Not connected to PipeWire, dropping command: {cmd:?}"),
+                Err(mpsc::error::TryRecvError::Empty) => break,
+            }
+        }
+    }
+}
+
+/// Connects to the daemon and serves commands until the engine exits or the connection drops.
+fn run_session(
+    mainloop: &MainLoopRc,
+    cmd_rx: &mut mpsc::UnboundedReceiver<PwCommand>,
+    graph_tx: &watch::Sender<Arc<AudioGraph>>,
+    remote: Option<&str>,
+    volume_curve: &mut VolumeCurve,
+) -> Result<SessionEnd> {
+    let context = ContextRc::new(mainloop, None).context("Failed to create PipeWire Context")?;
+    let mut props = pipewire::properties::properties! {
+        *pipewire::keys::APP_NAME => "pwmenu",
+        *pipewire::keys::APP_ID => "io.github.e-tho.pwmenu"
+    };
+    if let Some(remote) = remote {
+        props.insert(*pipewire::keys::REMOTE_NAME, remote);
+    }
     let core = Rc::new(
         context
-            .connect_rc(Some(pipewire::properties::properties! {
-                *pipewire::keys::APP_NAME => "pwmenu",
-                *pipewire::keys::APP_ID => "io.github.e-tho.pwmenu"
-            }))
-            .context("Failed to connect PipeWire Core")?,
+            .connect_rc(Some(props))
+            .with_context(|| match remote {
+                Some(remote) => format!("Failed to connect to PipeWire remote {remote}"),
+                None => "Failed to connect PipeWire Core".to_string(),
+            })?,
     );
     let registry = Rc::new(
         core.get_registry_rc()
             .context("Failed to get PipeWire Registry")?,
     );
-    let store = Rc::new(RefCell::new(Store::new(core.clone())));
+    let store = Rc::new(RefCell::new(Store::new(core.clone(), registry.clone())));
+    // Unlike the rest of the store, the volume curve carries over from the last session
+    store.borrow_mut().volume_curve = *volume_curve;
 
     // Setup metadata manager with graph update callback
     store.borrow_mut().setup_metadata_manager(&store, &graph_tx);
 
-    // Update the metadata binding section
+    // Globals are applied from the queue, as the store may be borrowed when they arrive
     let _registry_listener = {
-        let store_clone = store.clone();
-        let graph_tx_clone = graph_tx.clone();
-        let registry_clone = registry.clone();
+        let event_queue = store.borrow().event_queue.clone();
 
         registry
             .add_listener_local()
             .global({
-                let store_rc = store_clone.clone();
-                let registry = registry_clone.clone();
-                let graph_tx = graph_tx_clone.clone();
-
+                let event_queue = event_queue.clone();
                 move |global| {
                     debug!(
                         "Registry: Global event: id {}, type {:?}",
                         global.id, global.type_
                     );
-
-                    if global.type_ == ObjectType::Metadata {
-                        if let Some(props) = &global.props {
-                            let metadata_name = props.get("metadata.name");
-
-                            match metadata_name {
-                                Some("default") => {
-                                    match registry
-                                        .bind::<pipewire::metadata::Metadata, &DictRef>(global)
-                                    {
-                                        Ok(metadata) => {
-                                            debug!("Found and bound to default metadata object");
-                                            if let Ok(mut store) = store_rc.try_borrow_mut() {
-                                                if let Some(mm) = &mut store.metadata_manager {
-                                                    mm.register_default_metadata(metadata);
-                                                }
-                                            }
-                                        }
-                                        Err(e) => error!("Failed to bind to default metadata: {e}"),
-                                    }
-                                }
-                                Some("settings") => {
-                                    match registry
-                                        .bind::<pipewire::metadata::Metadata, &DictRef>(global)
-                                    {
-                                        Ok(metadata) => {
-                                            debug!("Found and bound to settings metadata object");
-                                            if let Ok(mut store) = store_rc.try_borrow_mut() {
-                                                if let Some(mm) = &mut store.metadata_manager {
-                                                    mm.register_settings_metadata(metadata);
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            error!("Failed to bind to settings metadata: {e}")
-                                        }
-                                    }
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
-
-                    let result = match store_rc.try_borrow_mut() {
-                        Ok(mut store) => store.add_object(&registry, global, &store_rc, &graph_tx),
-                        Err(e) => {
-                            error!("Failed to borrow store: {e}");
-                            Ok(false)
-                        }
-                    };
-
-                    match result {
-                        Ok(added) => {
-                            if added {
-                                update_graph(&store_rc, &graph_tx);
-                            }
-
-                            if global.type_ == ObjectType::Client {
-                                let should_refresh = {
-                                    let store = store_rc.borrow();
-                                    !store.refresh_pending
-                                };
-
-                                if should_refresh {
-                                    store_rc.borrow_mut().refresh_pending = true;
-                                    refresh_route_capable_devices(&store_rc);
-                                    store_rc.borrow_mut().refresh_pending = false;
-                                }
-                            }
-                        }
-                        Err(e) => error!("Error adding object {}: {:?}", global.id, e),
-                    }
+                    event_queue
+                        .borrow_mut()
+                        .push_back(QueuedEvent::GlobalAdded(global.to_owned()));
                 }
             })
-            .global_remove({
-                let store_rc = store_clone.clone();
-                let graph_tx = graph_tx_clone.clone();
-
-                move |id| {
-                    debug!("Registry: Global remove event: id {id}");
-                    if let Ok(mut store) = store_rc.try_borrow_mut() {
-                        store.remove_object(id);
-                    }
-                    update_graph(&store_rc, &graph_tx);
-                }
+            .global_remove(move |id| {
+                debug!("Registry: Global remove event: id {id}");
+                event_queue
+                    .borrow_mut()
+                    .push_back(QueuedEvent::GlobalRemoved(id));
             })
             .register()
     };
 
+    let connection_lost = Rc::new(Cell::new(false));
+
     let _core_listener = {
         let store_clone = store.clone();
-        let graph_tx_clone = graph_tx.clone();
-        let mainloop_clone_err = mainloop.clone();
+        let event_queue = store.borrow().event_queue.clone();
+        let connection_lost = connection_lost.clone();
         core.add_listener_local()
             .info({
                 let store = store_clone.clone();
@@ -373,29 +644,23 @@ fn run_pipewire_loop(
                     debug!("Core: Info event received for client ID: {}", info.id());
                 }
             })
-            .error({
-                let store = store_clone.clone();
-                let graph_tx = graph_tx_clone.clone();
-                move |id, seq, res, message| {
-                    if res == -2 && message.contains("enum params") {
-                        debug!("PipeWire internal parameter enumeration failed: id {id}, seq {seq}: {message}");
-                        return;
-                    }
-                    error!("PipeWire Core Error: id {id}, seq {seq}, res {res}: {message}");
-                    store.borrow_mut().connection_status = ConnectionStatus::Error;
-                    update_graph(&store, &graph_tx);
-                    mainloop_clone_err.quit();
+            .error(move |id, seq, res, message| {
+                if res == -2 && message.contains("enum params") {
+                    debug!("PipeWire internal parameter enumeration failed: id {id}, seq {seq}: {message}");
+                    return;
                 }
-            })
-            .done({
-                let store = store_clone;
-                let graph_tx = graph_tx_clone;
-                move |_id, seq| {
-                    let seq_num = seq.seq();
-                    store.borrow_mut().handle_sync_done(seq_num);
-                    update_graph(&store, &graph_tx);
+                error!("PipeWire Core Error: id {id}, seq {seq}, res {res}: {message}");
+                // Errors on the core itself mean the daemon went away
+                if id == PW_ID_CORE {
+                    connection_lost.set(true);
                 }
             })
+            // Queued behind the globals and params it was issued after
+            .done(move |_id, seq| {
+                event_queue
+                    .borrow_mut()
+                    .push_back(QueuedEvent::SyncDone(seq.seq()));
+            })
             .register()
     };
 
@@ -404,11 +669,17 @@ fn run_pipewire_loop(
     store.borrow_mut().initial_sync_seq = Some(initial_sync_seq);
 
     debug!("Starting PipeWire event loop...");
-    let mainloop_clone = mainloop.clone();
     let loop_ref = mainloop.loop_();
 
-    loop {
-        let timeout = std::time::Duration::from_millis(100);
+    let session_end = 'pipewire: loop {
+        // Wake up in time to send coalesced graph changes and retry restorations
+        let timeout = [
+            pending_graph_delay(&store),
+            pending_restoration_delay(&store),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(LOOP_TIMEOUT, Duration::min);
         match loop_ref.iterate(timeout) {
             res if res < 0 => {
                 let err_code = nix::errno::Errno::last_raw();
@@ -418,149 +689,327 @@ fn run_pipewire_loop(
                     nix::errno::Errno::from_raw(err_code)
                 );
                 store.borrow_mut().connection_status = ConnectionStatus::Error;
-                update_graph(&store, &graph_tx);
-                mainloop_clone.quit();
-                break;
+                flush_graph(&store, &graph_tx);
+                break SessionEnd::Exit;
             }
             _ => {}
         }
 
-        match cmd_rx.try_recv() {
-            Ok(cmd) => {
-                debug!("Received command: {cmd:?}");
+        if connection_lost.get() {
+            break SessionEnd::ConnectionLost;
+        }
+
+        if apply_queued_events(&store) {
+            update_graph(&store, &graph_tx);
+        }
+        flush_pending_graph(&store, &graph_tx);
 
-                if matches!(cmd, PwCommand::Exit) {
-                    debug!("Exit command received. Quitting PipeWire loop.");
-                    mainloop_clone.quit();
+        if pending_restoration_delay(&store).is_some_and(|delay| delay.is_zero())
+            && run_due_restorations(&store)
+        {
+            update_graph(&store, &graph_tx);
+        }
+
+        store.borrow_mut().expire_test_tone();
+
+        // Drain everything queued since the last wakeup
+        let mut commands = Vec::new();
+        let mut channel_closed = false;
+        loop {
+            match cmd_rx.try_recv() {
+                Ok(cmd) => {
+                    debug!("Received command: {cmd:?}");
+                    if cmd.is_cancelled() {
+                        debug!("Command cancelled by its caller, skipping.");
+                    } else {
+                        commands.push(cmd);
+                    }
+                }
+                Err(mpsc::error::TryRecvError::Empty) => break,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    channel_closed = true;
                     break;
                 }
+            }
+        }
 
-                let (cmd_processing_result, should_update_graph) = match cmd {
-                    PwCommand::SetNodeVolume {
-                        node_id,
-                        volume,
-                        result_sender,
-                    } => (
-                        result_sender.send(store.borrow_mut().set_node_volume(node_id, volume)),
-                        true,
-                    ),
-                    PwCommand::SetNodeMute {
-                        node_id,
-                        mute,
-                        result_sender,
-                    } => (
-                        result_sender.send(store.borrow_mut().set_node_mute(node_id, mute)),
-                        true,
-                    ),
-                    PwCommand::CreateLink {
-                        output_node,
-                        input_node,
+        for cmd in coalesce_volume_commands(commands) {
+            if matches!(cmd, PwCommand::Exit) {
+                debug!("Exit command received. Quitting PipeWire loop.");
+                break 'pipewire SessionEnd::Exit;
+            }
+
+            // Logs of the loop while it runs the command, graph flush included
+            let _span = debug_span!("pw_command", command = cmd.name()).entered();
+
+            let (cmd_processing_result, should_update_graph) = match cmd {
+                PwCommand::SetNodeVolume {
+                    node_id,
+                    volume,
+                    result_sender,
+                    merged,
+                } => (
+                    send_volume_result(
                         result_sender,
-                    } => (
-                        result_sender.send(store.borrow_mut().create_link(output_node, input_node)),
-                        true,
+                        merged,
+                        store.borrow_mut().set_node_volume(node_id, volume),
                     ),
-                    PwCommand::RemoveLink {
+                    true,
+                ),
+                PwCommand::SetNodeMute {
+                    node_id,
+                    mute,
+                    result_sender,
+                } => (
+                    result_sender.send(store.borrow_mut().set_node_mute(node_id, mute)),
+                    true,
+                ),
+                PwCommand::CreateLink {
+                    output_node,
+                    input_node,
+                    mapping,
+                    result_sender,
+                } => (
+                    result_sender.send(store.borrow_mut().create_link(
                         output_node,
                         input_node,
-                        result_sender,
-                    } => (
-                        result_sender.send(store.borrow_mut().remove_link(output_node, input_node)),
-                        true,
-                    ),
-                    PwCommand::SetDefaultSink {
-                        node_id,
-                        result_sender,
-                    } => (
-                        result_sender.send(store.borrow_mut().set_default_sink(node_id)),
-                        false,
-                    ),
-                    PwCommand::SetDefaultSource {
-                        node_id,
-                        result_sender,
-                    } => (
-                        result_sender.send(store.borrow_mut().set_default_source(node_id)),
-                        false,
-                    ),
-                    PwCommand::SwitchDeviceProfile {
-                        device_id,
-                        profile_index,
-                        result_sender,
-                    } => (
-                        result_sender.send(
-                            store
-                                .borrow_mut()
-                                .switch_device_profile(device_id, profile_index),
-                        ),
-                        true,
+                        mapping,
+                    )),
+                    true,
+                ),
+                PwCommand::RemoveLink {
+                    output_node,
+                    input_node,
+                    result_sender,
+                } => (
+                    result_sender.send(store.borrow_mut().remove_link(output_node, input_node)),
+                    true,
+                ),
+                PwCommand::SetDefaultSink {
+                    node_id,
+                    result_sender,
+                } => (
+                    result_sender.send(store.borrow_mut().set_default_sink(node_id)),
+                    false,
+                ),
+                PwCommand::SetDefaultSource {
+                    node_id,
+                    result_sender,
+                } => (
+                    result_sender.send(store.borrow_mut().set_default_source(node_id)),
+                    false,
+                ),
+                PwCommand::MoveStreams {
+                    node_id,
+                    result_sender,
+                } => (
+                    result_sender
+                        .send(store.borrow().move_streams_to(node_id))
+                        .map_err(|unsent| unsent.map(|_| ())),
+                    false,
+                ),
+                PwCommand::SetStreamTarget {
+                    stream_id,
+                    target_id,
+                    result_sender,
+                } => (
+                    result_sender.send(store.borrow().set_stream_target(stream_id, target_id)),
+                    false,
+                ),
+                PwCommand::SetNodeLatency {
+                    node_id,
+                    latency,
+                    result_sender,
+                } => (
+                    result_sender.send(store.borrow_mut().set_node_latency(node_id, &latency)),
+                    false,
+                ),
+                PwCommand::SwitchDeviceProfile {
+                    device_id,
+                    profile_index,
+                    result_sender,
+                } => (
+                    result_sender.send(
+                        store
+                            .borrow_mut()
+                            .switch_device_profile(device_id, profile_index),
                     ),
-                    PwCommand::SwitchDeviceProfileWithRestoration {
-                        device_id,
-                        profile_index,
-                        result_sender,
-                    } => (
-                        result_sender.send(
-                            store
-                                .borrow_mut()
-                                .switch_device_profile_with_restoration(device_id, profile_index),
-                        ),
-                        true,
+                    true,
+                ),
+                PwCommand::SwitchDeviceProfileWithRestoration {
+                    device_id,
+                    profile_index,
+                    result_sender,
+                } => (
+                    result_sender.send(
+                        store
+                            .borrow_mut()
+                            .switch_device_profile_with_restoration(device_id, profile_index),
                     ),
-                    PwCommand::SetDeviceVolume {
-                        device_id,
-                        volume,
-                        direction,
+                    true,
+                ),
+                PwCommand::CancelProfileRestoration {
+                    device_id,
+                    result_sender,
+                } => (
+                    result_sender.send(store.borrow_mut().cancel_profile_restoration(device_id)),
+                    false,
+                ),
+                PwCommand::ConfigureRestoration {
+                    settings,
+                    result_sender,
+                } => {
+                    store.borrow_mut().restoration_manager.configure(settings);
+                    (result_sender.send(Ok(())), false)
+                }
+                PwCommand::SetPreferredNodes {
+                    names,
+                    result_sender,
+                } => {
+                    store.borrow_mut().preferred_nodes = names;
+                    (result_sender.send(Ok(())), false)
+                }
+                PwCommand::SetVolumeCurve { curve } => {
+                    *volume_curve = curve;
+                    store.borrow_mut().set_volume_curve(curve);
+                    (Ok(()), true)
+                }
+                PwCommand::SetDeviceVolume {
+                    device_id,
+                    volume,
+                    direction,
+                    card_device,
+                    result_sender,
+                    merged,
+                } => (
+                    send_volume_result(
                         result_sender,
-                    } => (
-                        result_sender.send(
-                            store
-                                .borrow_mut()
-                                .set_device_volume(device_id, volume, direction),
+                        merged,
+                        store.borrow_mut().set_device_volume(
+                            device_id,
+                            volume,
+                            direction,
+                            card_device,
                         ),
-                        true,
                     ),
-                    PwCommand::SetDeviceMute {
+                    true,
+                ),
+                PwCommand::SetDeviceMute {
+                    device_id,
+                    mute,
+                    direction,
+                    card_device,
+                    result_sender,
+                } => (
+                    result_sender.send(store.borrow_mut().set_device_mute(
                         device_id,
                         mute,
                         direction,
-                        result_sender,
-                    } => (
-                        result_sender.send(
-                            store
-                                .borrow_mut()
-                                .set_device_mute(device_id, mute, direction),
-                        ),
-                        true,
-                    ),
-                    PwCommand::SetSampleRate {
-                        sample_rate,
-                        result_sender,
-                    } => (
-                        result_sender.send(store.borrow_mut().set_sample_rate(sample_rate)),
-                        true,
+                        card_device,
+                    )),
+                    true,
+                ),
+                PwCommand::SetSampleRate {
+                    sample_rate,
+                    result_sender,
+                } => (
+                    result_sender.send(store.borrow_mut().set_sample_rate(sample_rate)),
+                    true,
+                ),
+                PwCommand::CreateVirtualSink {
+                    description,
+                    result_sender,
+                } => (
+                    result_sender.send(store.borrow_mut().create_virtual_sink(&description)),
+                    false,
+                ),
+                PwCommand::DestroyManagedNode {
+                    node_id,
+                    result_sender,
+                } => (
+                    result_sender.send(store.borrow_mut().destroy_managed_node(node_id)),
+                    true,
+                ),
+                PwCommand::RenameManagedNode {
+                    node_id,
+                    description,
+                    result_sender,
+                } => (
+                    result_sender.send(
+                        store
+                            .borrow_mut()
+                            .rename_managed_node(node_id, &description),
                     ),
+                    true,
+                ),
+                PwCommand::DestroyAllManaged { result_sender } => (
+                    result_sender
+                        .send(store.borrow_mut().destroy_all_managed())
+                        .map_err(|unsent| unsent.map(|_| ())),
+                    true,
+                ),
+                PwCommand::PlayTestTone {
+                    node_id,
+                    result_sender,
+                } => (
+                    result_sender.send(store.borrow_mut().play_test_tone(node_id)),
+                    false,
+                ),
+                PwCommand::StartLevelMeter {
+                    node_id,
+                    result_sender,
+                } => (
+                    result_sender
+                        .send(store.borrow_mut().start_level_meter(node_id))
+                        .map_err(|unsent| unsent.map(|_| ())),
+                    false,
+                ),
+                PwCommand::StopLevelMeter { result_sender } => (
+                    result_sender.send(store.borrow_mut().stop_level_meter()),
+                    false,
+                ),
+                PwCommand::SyncStreamMeters {
+                    node_ids,
+                    result_sender,
+                } => (
+                    result_sender
+                        .send(store.borrow_mut().sync_stream_meters(&node_ids))
+                        .map_err(|unsent| unsent.map(|_| ())),
+                    false,
+                ),
+                PwCommand::StartProfiler { result_sender } => (
+                    result_sender
+                        .send(store.borrow_mut().start_profiler(&registry))
+                        .map_err(|unsent| unsent.map(|_| ())),
+                    false,
+                ),
+                PwCommand::StopProfiler { result_sender } => (
+                    result_sender.send(store.borrow_mut().stop_profiler()),
+                    false,
+                ),
 
-                    PwCommand::Exit => unreachable!("Exit handled above"),
-                };
+                PwCommand::Batch {
+                    steps,
+                    result_sender,
+                } => (result_sender.send(run_batch(&store, steps)), true),
 
-                if cmd_processing_result.is_err() {
-                    debug!("Command result receiver dropped.");
-                }
+                PwCommand::Exit => unreachable!("Exit handled above"),
+            };
 
-                if should_update_graph {
-                    update_graph(&store, &graph_tx);
-                }
+            if cmd_processing_result.is_err() {
+                debug!("Command result receiver dropped.");
             }
-            Err(mpsc::error::TryRecvError::Empty) => {}
-            Err(mpsc::error::TryRecvError::Disconnected) => {
-                debug!("Command channel closed. Quitting PipeWire loop.");
-                mainloop_clone.quit();
-                break;
+
+            if should_update_graph {
+                flush_graph(&store, &graph_tx);
             }
         }
-    }
 
-    mainloop.quit();
+        if channel_closed {
+            debug!("Command channel closed. Quitting PipeWire loop.");
+            break 'pipewire SessionEnd::Exit;
+        }
+    };
 
     // Drop resources in reverse init order
     drop(_registry_listener);
@@ -568,6 +1017,10 @@ fn run_pipewire_loop(
 
     {
         let mut store_mut = store.borrow_mut();
+        store_mut.test_tone.take();
+        store_mut.level_meter.take();
+        store_mut.stream_meters.clear();
+        store_mut.profiler.take();
         store_mut.nodes.clear();
         store_mut.devices.clear();
         store_mut.ports.clear();
@@ -577,9 +1030,102 @@ fn run_pipewire_loop(
     drop(store);
     drop(registry);
     drop(core);
-    drop(mainloop);
+    drop(context);
 
-    Ok(())
+    Ok(session_end)
+}
+
+/// Runs every step even after a failure, reporting all failures together.
+fn run_batch(store: &Rc<RefCell<Store>>, steps: Vec<BatchStep>) -> Result<()> {
+    let failures: Vec<String> = steps
+        .into_iter()
+        .filter_map(|step| {
+            let result = match &step {
+                BatchStep::SetNodeVolume { node_id, volume } => {
+                    store.borrow_mut().set_node_volume(*node_id, *volume)
+                }
+                BatchStep::SetNodeMute { node_id, mute } => {
+                    store.borrow_mut().set_node_mute(*node_id, *mute)
+                }
+                BatchStep::SetDefaultSink { node_id } => {
+                    store.borrow_mut().set_default_sink(*node_id)
+                }
+                BatchStep::SetDefaultSource { node_id } => {
+                    store.borrow_mut().set_default_source(*node_id)
+                }
+                BatchStep::MoveStreams { node_id } => {
+                    store.borrow().move_streams_to(*node_id).map(|_| ())
+                }
+            };
+            result.err().map(|e| format!("{step:?}: {e}"))
+        })
+        .collect();
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(failures.join("; ")))
+    }
+}
+
+/// Adds a global taken from the event queue, binding the metadata objects pwmenu follows.
+/// Returns whether the graph changed.
+pub fn add_global(store_rc: &Rc<RefCell<Store>>, global: &GlobalObject<PropertiesBox>) -> bool {
+    let registry = store_rc.borrow().registry.clone();
+    let global: GlobalObject<&DictRef> = GlobalObject {
+        id: global.id,
+        permissions: global.permissions,
+        type_: global.type_.clone(),
+        version: global.version,
+        props: global.props.as_ref().map(|props| props.as_ref()),
+    };
+
+    if global.type_ == ObjectType::Metadata {
+        match global.props.and_then(|props| props.get("metadata.name")) {
+            Some("default") => {
+                match registry.bind::<pipewire::metadata::Metadata, &DictRef>(&global) {
+                    Ok(metadata) => {
+                        debug!("Found and bound to default metadata object");
+                        let mut store = store_rc.borrow_mut();
+                        store.default_metadata_id = Some(global.id);
+                        if let Some(mm) = &mut store.metadata_manager {
+                            mm.register_default_metadata(metadata);
+                        }
+                    }
+                    Err(e) => error!("Failed to bind to default metadata: {e}"),
+                }
+            }
+            Some("settings") => {
+                match registry.bind::<pipewire::metadata::Metadata, &DictRef>(&global) {
+                    Ok(metadata) => {
+                        debug!("Found and bound to settings metadata object");
+                        if let Some(mm) = &mut store_rc.borrow_mut().metadata_manager {
+                            mm.register_settings_metadata(metadata);
+                        }
+                    }
+                    Err(e) => error!("Failed to bind to settings metadata: {e}"),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let result = store_rc.borrow_mut().add_object(&registry, &global);
+    let added = match result {
+        Ok(added) => added,
+        Err(e) => {
+            error!("Error adding object {}: {:?}", global.id, e);
+            return false;
+        }
+    };
+
+    if global.type_ == ObjectType::Client && !store_rc.borrow().refresh_pending {
+        store_rc.borrow_mut().refresh_pending = true;
+        refresh_route_capable_devices(store_rc);
+        store_rc.borrow_mut().refresh_pending = false;
+    }
+
+    added
 }
 
 fn refresh_route_capable_devices(store_rc: &Rc<RefCell<Store>>) {
@@ -615,15 +1161,13 @@ impl Store {
         &mut self,
         registry: &Rc<pipewire::registry::RegistryRc>,
         global: &GlobalObject<&DictRef>,
-        store_rc: &Rc<RefCell<Store>>,
-        graph_tx: &watch::Sender<AudioGraph>,
     ) -> Result<bool> {
         match global.type_ {
             ObjectType::Device => {
-                self.add_device(registry, global, store_rc, graph_tx)?;
+                self.add_device(registry, global)?;
             }
             ObjectType::Node => {
-                self.add_node(registry, global, store_rc, graph_tx)?;
+                self.add_node(registry, global)?;
             }
             ObjectType::Port => {
                 self.add_port(registry, global)?;
@@ -631,28 +1175,47 @@ impl Store {
             ObjectType::Link => {
                 self.add_link(registry, global)?;
             }
+            ObjectType::Profiler => {
+                // Only bound on demand, as the server collects timings while a profiler is bound
+                self.profiler_global = Some(global.id);
+                return Ok(false);
+            }
             _ => return Ok(false),
         }
         Ok(true)
     }
 
     pub fn remove_object(&mut self, id: u32) {
-        if self.devices.remove(&id).is_some() {
+        if self.profiler_global == Some(id) {
+            self.profiler_global = None;
+            self.profiler = None;
+        }
+        if self.default_metadata_id == Some(id) {
+            self.default_metadata_id = None;
+        }
+
+        if let Some(mut device) = self.devices.remove(&id) {
+            device.teardown();
+            self.discard_queued_events(id);
             debug!("Removed device {id}");
-        } else if let Some(node) = self.nodes.remove(&id) {
+        } else if let Some(mut node) = self.nodes.remove(&id) {
+            node.teardown();
+            self.discard_queued_events(id);
             debug!("Removed node {}: '{}'", id, node.name);
+            if let Some(device_id) = node.device_id {
+                if let Some(device) = self.devices.get_mut(&device_id) {
+                    device.nodes.retain(|&n_id| n_id != id);
+                }
+            }
             if self.default_sink == Some(id) {
                 self.default_sink = None;
                 debug!("Removed default sink (node was removed)");
+                self.promote_fallback_default(NodeType::AudioSink);
             }
             if self.default_source == Some(id) {
                 self.default_source = None;
                 debug!("Removed default source (node was removed)");
-            }
-            if let Some(device_id) = node.device_id {
-                if let Some(device) = self.devices.get_mut(&device_id) {
-                    device.nodes.retain(|&n_id| n_id != id);
-                }
+                self.promote_fallback_default(NodeType::AudioSource);
             }
         } else if let Some(port) = self.ports.remove(&id) {
             debug!("Removed port {}: '{}'", id, port.name);
@@ -671,11 +1234,13 @@ impl Store {
                         other_port.links.retain(|&l_id| l_id != link_id);
                     }
                 }
-                if self.links.remove(&link_id).is_some() {
+                if let Some(mut link) = self.links.remove(&link_id) {
+                    link.teardown();
                     debug!("Cascaded removal of link {link_id} due to port removal");
                 }
             }
-        } else if let Some(removed_link) = self.links.remove(&id) {
+        } else if let Some(mut removed_link) = self.links.remove(&id) {
+            removed_link.teardown();
             debug!("Removed link {id}");
             if let Some(port) = self.ports.get_mut(&removed_link.output_port) {
                 port.links.retain(|&l_id| l_id != id);
@@ -685,4 +1250,51 @@ impl Store {
             }
         }
     }
+
+    /// Picks the next-best node after the default one disappeared, preferring the nodes set
+    /// with [`PwEngine::set_preferred_nodes`] and then the same form factor and bus ordering
+    /// as the menus. The new default reaches graph receivers like any other default change.
+    fn promote_fallback_default(&mut self, node_type: NodeType) {
+        let Some(node) = self
+            .nodes
+            .values()
+            .filter(|n| n.node_type == node_type)
+            .min_by_key(|n| {
+                let device = n.device_id.and_then(|id| self.devices.get(&id));
+                (
+                    self.preferred_nodes
+                        .iter()
+                        .position(|name| *name == n.name)
+                        .unwrap_or(usize::MAX),
+                    FormFactorPriority::from_form_factor(
+                        device.and_then(|d| d.form_factor.as_deref()),
+                    ),
+                    BusPriority::from_bus(device.and_then(|d| d.bus.as_deref())),
+                    n.id,
+                )
+            })
+        else {
+            debug!("No fallback available for default {node_type:?}");
+            return;
+        };
+
+        let (node_id, node_name) = (node.id, node.name.clone());
+
+        let Some(metadata_manager) = &self.metadata_manager else {
+            return;
+        };
+        if let Err(e) = metadata_manager.set_fallback_default(&node_name, node_type) {
+            warn!("Failed to set fallback default {node_name}: {e}");
+            return;
+        }
+
+        let device_type = if node_type == NodeType::AudioSink {
+            self.default_sink = Some(node_id);
+            "output"
+        } else {
+            self.default_source = Some(node_id);
+            "input"
+        };
+        debug!("Promoted {node_name} to default {device_type} after removal");
+    }
 }
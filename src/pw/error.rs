@@ -0,0 +1,97 @@
+use std::fmt;
+
+use crate::pw::commands::CommandError;
+
+/// Kind of graph object a [`PwError::NotFound`] refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Node,
+    Device,
+    Port,
+    Link,
+}
+
+impl fmt::Display for ObjectKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Node => "Node",
+            Self::Device => "Device",
+            Self::Port => "Port",
+            Self::Link => "Link",
+        })
+    }
+}
+
+/// Failures of PipeWire operations. Functions in [`crate::pw`] return them inside
+/// `anyhow::Error`; use [`PwError::find`] to get them back.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PwError {
+    /// The object is not in the graph, or is no longer.
+    NotFound { kind: ObjectKind, id: u32 },
+    /// No device matches a name, description or alias given by the user.
+    DeviceNotFound(String),
+    /// The device has not reported a route for this direction, so its volume cannot be set.
+    NoRouteInfo { device_id: u32 },
+    /// The `default` or `settings` metadata object has not been announced.
+    MetadataUnavailable(&'static str),
+    /// No session manager published default devices, so they cannot be changed.
+    NoSessionManager,
+    /// A parameter pod could not be built or parsed.
+    PodBuild(String),
+    /// PipeWire refused to create, bind or destroy an object.
+    ProxyError(String),
+    /// The operation does not apply to this object, such as a stream as default device.
+    Unsupported(String),
+    /// The device did not report the requested profile as active in time.
+    ProfileNotApplied { device_id: u32, profile_index: u32 },
+    /// The command got no result from the PipeWire thread.
+    Command(CommandError),
+    /// The PipeWire daemon could not be reached.
+    Unavailable,
+}
+
+impl PwError {
+    /// The `PwError` behind an error, looking through added context.
+    pub fn find(error: &anyhow::Error) -> Option<&PwError> {
+        error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<PwError>())
+    }
+}
+
+impl fmt::Display for PwError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound { kind, id } => write!(f, "{kind} {id} not found"),
+            Self::DeviceNotFound(key) => write!(f, "Device \"{key}\" not found"),
+            Self::NoRouteInfo { device_id } => {
+                write!(f, "Device {device_id} has no route for this direction")
+            }
+            Self::MetadataUnavailable(name) => write!(f, "No {name} metadata object available"),
+            Self::NoSessionManager => write!(
+                f,
+                "No session manager is running, default devices cannot be changed"
+            ),
+            Self::PodBuild(message) | Self::ProxyError(message) | Self::Unsupported(message) => {
+                f.write_str(message)
+            }
+            Self::ProfileNotApplied {
+                device_id,
+                profile_index,
+            } => write!(
+                f,
+                "Device {device_id} did not switch to profile {profile_index}"
+            ),
+            Self::Command(error) => error.fmt(f),
+            Self::Unavailable => write!(f, "Could not connect to PipeWire"),
+        }
+    }
+}
+
+impl std::error::Error for PwError {}
+
+impl From<CommandError> for PwError {
+    fn from(error: CommandError) -> Self {
+        Self::Command(error)
+    }
+}
@@ -0,0 +1,260 @@
+use futures_core::Stream;
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::sync::watch;
+
+use crate::pw::{
+    graph::AudioGraph,
+    nodes::{Node, Volume},
+};
+
+// Ignore float noise from volume round-trips
+const VOLUME_EPSILON: f32 = 0.001;
+
+/// A change between two graph states, for consumers that do not want to diff whole graphs.
+#[derive(Debug, Clone)]
+pub enum GraphEvent {
+    NodeAdded(Node),
+    NodeRemoved {
+        id: u32,
+        name: String,
+    },
+    DefaultSinkChanged {
+        old: Option<u32>,
+        new: Option<u32>,
+    },
+    DefaultSourceChanged {
+        old: Option<u32>,
+        new: Option<u32>,
+    },
+    /// Volume or mute state of a node changed.
+    VolumeChanged {
+        node: u32,
+        old: Volume,
+        new: Volume,
+    },
+}
+
+/// A default device switch. `node` is the new default when it is in the graph.
+#[derive(Debug, Clone)]
+pub struct DefaultChange {
+    pub old: Option<u32>,
+    pub new: Option<u32>,
+    pub node: Option<Node>,
+}
+
+/// A node whose volume or mute state changed. `node` holds the new volume.
+#[derive(Debug, Clone)]
+pub struct VolumeChange {
+    pub node: Node,
+    pub old: Volume,
+}
+
+impl VolumeChange {
+    pub fn volume_changed(&self) -> bool {
+        // A switch of the volume curve moves `linear` but leaves the amplitude alone
+        self.old.raw() != self.node.volume.raw()
+            && (self.old.linear - self.node.volume.linear).abs() > VOLUME_EPSILON
+    }
+
+    pub fn mute_toggled(&self) -> bool {
+        self.old.muted != self.node.volume.muted
+    }
+}
+
+/// Summary of what changed between two graph states, with nodes sorted by id.
+#[derive(Debug, Clone, Default)]
+pub struct GraphDiff {
+    pub added: Vec<Node>,
+    pub removed: Vec<Node>,
+    pub default_sink: Option<DefaultChange>,
+    pub default_source: Option<DefaultChange>,
+    pub volume_changes: Vec<VolumeChange>,
+}
+
+impl GraphDiff {
+    pub fn between(old: &AudioGraph, new: &AudioGraph) -> Self {
+        let mut removed: Vec<Node> = old
+            .nodes
+            .values()
+            .filter(|node| !new.nodes.contains_key(&node.id))
+            .cloned()
+            .collect();
+        removed.sort_by_key(|node| node.id);
+
+        let mut added: Vec<Node> = new
+            .nodes
+            .values()
+            .filter(|node| !old.nodes.contains_key(&node.id))
+            .cloned()
+            .collect();
+        added.sort_by_key(|node| node.id);
+
+        let default_change = |old_id: Option<u32>, new_id: Option<u32>| {
+            (old_id != new_id).then(|| DefaultChange {
+                old: old_id,
+                new: new_id,
+                node: new_id.and_then(|id| new.nodes.get(&id)).cloned(),
+            })
+        };
+
+        let mut volume_changes: Vec<VolumeChange> = new
+            .nodes
+            .values()
+            .filter_map(|node| {
+                let before = old.nodes.get(&node.id)?;
+                Some(VolumeChange {
+                    node: node.clone(),
+                    old: before.volume.clone(),
+                })
+            })
+            .filter(|change| change.volume_changed() || change.mute_toggled())
+            .collect();
+        volume_changes.sort_by_key(|change| change.node.id);
+
+        Self {
+            added,
+            removed,
+            default_sink: default_change(old.default_sink, new.default_sink),
+            default_source: default_change(old.default_source, new.default_source),
+            volume_changes,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.default_sink.is_none()
+            && self.default_source.is_none()
+            && self.volume_changes.is_empty()
+    }
+
+    /// Keeps only the nodes matching `keep`, e.g. to ignore streams. Default changes are
+    /// kept as they are.
+    pub fn retain_nodes(&mut self, keep: impl Fn(&Node) -> bool) {
+        self.added.retain(&keep);
+        self.removed.retain(&keep);
+        self.volume_changes.retain(|change| keep(&change.node));
+    }
+
+    /// The same changes as events: removals first, then additions, defaults and volumes.
+    pub fn into_events(self) -> Vec<GraphEvent> {
+        let mut events = Vec::new();
+
+        events.extend(
+            self.removed
+                .into_iter()
+                .map(|node| GraphEvent::NodeRemoved {
+                    id: node.id,
+                    name: node.name,
+                }),
+        );
+        events.extend(self.added.into_iter().map(GraphEvent::NodeAdded));
+        if let Some(change) = self.default_sink {
+            events.push(GraphEvent::DefaultSinkChanged {
+                old: change.old,
+                new: change.new,
+            });
+        }
+        if let Some(change) = self.default_source {
+            events.push(GraphEvent::DefaultSourceChanged {
+                old: change.old,
+                new: change.new,
+            });
+        }
+        events.extend(
+            self.volume_changes
+                .into_iter()
+                .map(|change| GraphEvent::VolumeChanged {
+                    node: change.node.id,
+                    old: change.old,
+                    new: change.node.volume,
+                }),
+        );
+
+        events
+    }
+}
+
+/// Events turning `old` into `new`: removals first, then additions, defaults and volumes.
+pub fn diff_graphs(old: &AudioGraph, new: &AudioGraph) -> Vec<GraphEvent> {
+    GraphDiff::between(old, new).into_events()
+}
+
+type ChangedFuture = Pin<
+    Box<
+        dyn Future<
+                Output = (
+                    Result<(), watch::error::RecvError>,
+                    watch::Receiver<Arc<AudioGraph>>,
+                ),
+            > + Send,
+    >,
+>;
+
+/// Stream of [`GraphEvent`]s computed from successive graph updates. It ends when the
+/// engine shuts down.
+pub struct GraphEvents {
+    previous: Arc<AudioGraph>,
+    pending: VecDeque<GraphEvent>,
+    changed: Option<ChangedFuture>,
+}
+
+impl GraphEvents {
+    pub(crate) fn new(mut graph_rx: watch::Receiver<Arc<AudioGraph>>) -> Self {
+        let previous = graph_rx.borrow_and_update().clone();
+        Self {
+            previous,
+            pending: VecDeque::new(),
+            changed: Some(wait_for_change(graph_rx)),
+        }
+    }
+
+    /// Waits for the next event, `None` once the engine is gone.
+    pub async fn next(&mut self) -> Option<GraphEvent> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+}
+
+fn wait_for_change(mut graph_rx: watch::Receiver<Arc<AudioGraph>>) -> ChangedFuture {
+    Box::pin(async move {
+        let result = graph_rx.changed().await;
+        (result, graph_rx)
+    })
+}
+
+impl Stream for GraphEvents {
+    type Item = GraphEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<GraphEvent>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+
+            let Some(changed) = self.changed.as_mut() else {
+                return Poll::Ready(None);
+            };
+            let (result, mut graph_rx) = match changed.as_mut().poll(cx) {
+                Poll::Ready(output) => output,
+                Poll::Pending => return Poll::Pending,
+            };
+
+            if result.is_err() {
+                self.changed = None;
+                continue;
+            }
+
+            let current = graph_rx.borrow_and_update().clone();
+            let events = diff_graphs(&self.previous, &current);
+            self.pending.extend(events);
+            self.previous = current;
+            self.changed = Some(wait_for_change(graph_rx));
+        }
+    }
+}
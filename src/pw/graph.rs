@@ -1,16 +1,119 @@
 use crate::pw::{
-    devices::{Device, DeviceInternal},
+    devices::{Device, DeviceInfoProps, DeviceInternal},
+    engine::add_global,
+    error::PwError,
     links::{Link, LinkInternal, Port, PortInternal},
     metadata::MetadataManager,
-    nodes::{Node, NodeInternal},
+    meter::LevelMeter,
+    nodes::{Node, NodeInfoProps, NodeInternal},
+    profiler::Profiler,
     restoration::RestorationManager,
+    tone::TestTone,
+    volume::{VolumeCurve, VolumeResolver},
     DeviceType, NodeType,
 };
-use anyhow::anyhow;
 use anyhow::Result;
-use log::{debug, error, warn};
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use pipewire::{
+    properties::PropertiesBox,
+    registry::{GlobalObject, RegistryRc},
+    spa::{param::ParamType, pod::Pod},
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, VecDeque},
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tokio::sync::watch;
+use tracing::{debug, error, warn};
+
+/// Param and info events arrive in bursts, so graph sends are coalesced over this window.
+pub const GRAPH_UPDATE_COALESCE: Duration = Duration::from_millis(20);
+
+/// Copy of a param pod, kept 8-byte aligned like the pods PipeWire hands to listeners.
+pub struct OwnedPod {
+    words: Vec<u64>,
+}
+
+impl OwnedPod {
+    pub fn new(pod: &Pod) -> Self {
+        let words = pod
+            .as_bytes()
+            .chunks(8)
+            .map(|chunk| {
+                let mut word = [0; 8];
+                word[..chunk.len()].copy_from_slice(chunk);
+                u64::from_ne_bytes(word)
+            })
+            .collect();
+        Self { words }
+    }
+
+    pub fn as_pod(&self) -> Option<&Pod> {
+        // SAFETY: the bytes of `words` are initialized and live as long as `self`
+        let bytes = unsafe {
+            std::slice::from_raw_parts(self.words.as_ptr().cast::<u8>(), self.words.len() * 8)
+        };
+        Pod::from_bytes(bytes)
+    }
+}
+
+/// An event received by a registry or object listener, waiting for [`apply_queued_events`].
+pub enum QueuedEvent {
+    NodeParam {
+        node_id: u32,
+        generation: u64,
+        param_type: ParamType,
+        pod: OwnedPod,
+    },
+    DeviceParam {
+        device_id: u32,
+        generation: u64,
+        param_type: ParamType,
+        pod: OwnedPod,
+    },
+    NodeInfo {
+        node_id: u32,
+        generation: u64,
+        props: NodeInfoProps,
+    },
+    DeviceInfo {
+        device_id: u32,
+        generation: u64,
+        props: DeviceInfoProps,
+    },
+    LinkInfo {
+        link_id: u32,
+        generation: u64,
+        is_managed: bool,
+    },
+    GlobalAdded(GlobalObject<PropertiesBox>),
+    GlobalRemoved(u32),
+    SyncDone(i32),
+}
+
+impl QueuedEvent {
+    /// The object an event is about, `None` for registry and core events which must never be
+    /// dropped.
+    fn object_id(&self) -> Option<u32> {
+        match self {
+            QueuedEvent::NodeParam { node_id, .. } | QueuedEvent::NodeInfo { node_id, .. } => {
+                Some(*node_id)
+            }
+            QueuedEvent::DeviceParam { device_id, .. }
+            | QueuedEvent::DeviceInfo { device_id, .. } => Some(*device_id),
+            QueuedEvent::LinkInfo { link_id, .. } => Some(*link_id),
+            QueuedEvent::GlobalAdded(_)
+            | QueuedEvent::GlobalRemoved(_)
+            | QueuedEvent::SyncDone(_) => None,
+        }
+    }
+}
+
+/// Listeners push events here instead of borrowing the store, which may already be borrowed
+/// when they run, so no update is dropped.
+pub type EventQueue = Rc<RefCell<VecDeque<QueuedEvent>>>;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, Default)]
 pub enum ConnectionStatus {
@@ -18,6 +121,19 @@ pub enum ConnectionStatus {
     #[default]
     Disconnected,
     Error,
+    /// The daemon went away and the engine is waiting to connect again.
+    Reconnecting,
+}
+
+impl ConnectionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionStatus::Connected => "connected",
+            ConnectionStatus::Disconnected => "disconnected",
+            ConnectionStatus::Error => "error",
+            ConnectionStatus::Reconnecting => "reconnecting",
+        }
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Default)]
@@ -33,6 +149,38 @@ pub struct AudioGraph {
     pub params_sync_complete: bool,
     pub data_complete: bool,
     pub default_clock_rate: u32,
+    /// Whether a session manager such as WirePlumber published the default metadata. Without
+    /// one, default devices cannot be changed.
+    pub session_manager: bool,
+    /// Global id of the `default` metadata object, if one was announced.
+    pub default_metadata_id: Option<u32>,
+}
+
+impl AudioGraph {
+    /// Whether the daemon is reachable and the registry has been listed at least once.
+    pub fn is_connected(&self) -> bool {
+        self.connection_status == ConnectionStatus::Connected && self.initial_sync_complete
+    }
+
+    /// Puts volumes on `curve` from their raw amplitudes, for graphs read back from a file such
+    /// as the cache or a recording. Routes without `channelVolumes` are left as they are.
+    pub fn apply_volume_curve(&mut self, curve: VolumeCurve) {
+        for node in self.nodes.values_mut() {
+            node.volume = node.volume.with_curve(curve);
+        }
+
+        for device in self.devices.values_mut() {
+            let routes = [&mut device.output_route, &mut device.input_route]
+                .into_iter()
+                .chain(device.output_routes.values_mut())
+                .chain(device.input_routes.values_mut());
+            for route in routes {
+                if let Some(&raw) = route.channel_volumes.first() {
+                    route.volume = Some(VolumeResolver::apply_curve(curve, raw));
+                }
+            }
+        }
+    }
 }
 
 pub struct Store {
@@ -45,19 +193,36 @@ pub struct Store {
     pub connection_status: ConnectionStatus,
     pub pwmenu_client_id: Option<u32>,
     pub core: Rc<pipewire::core::CoreRc>,
+    pub registry: Rc<RegistryRc>,
     pub metadata_manager: Option<MetadataManager>,
     pub restoration_manager: RestorationManager,
     pub initial_sync_complete: bool,
     pub initial_sync_seq: Option<i32>,
     pub params_sync_complete: bool,
-    pub params_sync_seq: Option<i32>,
+    /// Round trips issued after subscribing to an object's params or info, by sequence number.
+    pub pending_param_syncs: HashMap<i32, u32>,
     pub data_complete: bool,
     pub refresh_pending: bool,
     pub default_clock_rate: u32,
+    pub test_tone: Option<TestTone>,
+    pub level_meter: Option<LevelMeter>,
+    pub stream_meters: HashMap<u32, LevelMeter>,
+    pub profiler_global: Option<u32>,
+    pub profiler: Option<Profiler>,
+    pub default_metadata_id: Option<u32>,
+    /// When the first change not yet sent to graph receivers was made.
+    pub graph_dirty_since: Cell<Option<Instant>>,
+    pub event_queue: EventQueue,
+    /// Last generation handed to a node or device.
+    pub generation: u64,
+    /// Node names promoted first when a default node goes away, most preferred first.
+    pub preferred_nodes: Vec<String>,
+    /// Curve volumes are read and written on, see [`Store::set_volume_curve`].
+    pub volume_curve: VolumeCurve,
 }
 
 impl Store {
-    pub fn new(core: Rc<pipewire::core::CoreRc>) -> Self {
+    pub fn new(core: Rc<pipewire::core::CoreRc>, registry: Rc<RegistryRc>) -> Self {
         Self {
             nodes: HashMap::new(),
             devices: HashMap::new(),
@@ -68,15 +233,46 @@ impl Store {
             connection_status: ConnectionStatus::Connected,
             pwmenu_client_id: None,
             core,
+            registry,
             metadata_manager: None,
             restoration_manager: RestorationManager::new(),
             initial_sync_complete: false,
             initial_sync_seq: None,
             params_sync_complete: false,
-            params_sync_seq: None,
+            pending_param_syncs: HashMap::new(),
             data_complete: false,
             refresh_pending: false,
             default_clock_rate: 48000,
+            test_tone: None,
+            level_meter: None,
+            stream_meters: HashMap::new(),
+            profiler_global: None,
+            profiler: None,
+            default_metadata_id: None,
+            graph_dirty_since: Cell::new(None),
+            event_queue: EventQueue::default(),
+            generation: 0,
+            preferred_nodes: Vec::new(),
+            volume_curve: VolumeCurve::default(),
+        }
+    }
+
+    /// Reads and writes volumes on `curve` from now on. Nodes keep their raw amplitude, so
+    /// only the route volumes read on the previous curve are moved over.
+    pub fn set_volume_curve(&mut self, curve: VolumeCurve) {
+        let previous = std::mem::replace(&mut self.volume_curve, curve);
+
+        for device in self.devices.values_mut() {
+            let routes = [&mut device.output_route, &mut device.input_route]
+                .into_iter()
+                .chain(device.output_routes.values_mut())
+                .chain(device.input_routes.values_mut());
+            for route in routes {
+                route.volume = route.volume.map(|volume| {
+                    let raw = VolumeResolver::apply_inverse_curve(previous, volume);
+                    VolumeResolver::apply_curve(curve, raw)
+                });
+            }
         }
     }
 
@@ -85,7 +281,14 @@ impl Store {
             nodes: self
                 .nodes
                 .iter()
-                .map(|(&id, node)| (id, node.to_node()))
+                .map(|(&id, node)| {
+                    let mut node = node.to_node(self.volume_curve);
+                    node.target_object = self
+                        .metadata_manager
+                        .as_ref()
+                        .and_then(|mm| mm.get_stream_target(id));
+                    (id, node)
+                })
                 .collect(),
             devices: self
                 .devices
@@ -109,38 +312,150 @@ impl Store {
             params_sync_complete: self.params_sync_complete,
             data_complete: self.data_complete,
             default_clock_rate: self.default_clock_rate,
+            session_manager: self.has_session_manager(),
+            default_metadata_id: self.default_metadata_id,
         }
     }
 
+    /// Ids are reused once objects are gone, so listeners and queued events carry the
+    /// generation of the object they were made for.
+    pub fn next_generation(&mut self) -> u64 {
+        self.generation += 1;
+        self.generation
+    }
+
+    /// Drops the events still queued for `object_id`, which was just removed.
+    pub fn discard_queued_events(&self, object_id: u32) {
+        self.event_queue
+            .borrow_mut()
+            .retain(|event| event.object_id() != Some(object_id));
+    }
+
+    /// Applies a queued param or info event, returning whether it changed the graph.
+    fn apply_object_event(&mut self, event: QueuedEvent) -> bool {
+        // An event of an object that was removed must not reach a new one with its id
+        match event {
+            QueuedEvent::NodeParam {
+                node_id,
+                generation,
+                param_type,
+                pod,
+            } => {
+                self.nodes
+                    .get(&node_id)
+                    .is_some_and(|node| node.generation == generation)
+                    && pod
+                        .as_pod()
+                        .is_some_and(|pod| self.handle_node_parameter(node_id, param_type, pod))
+            }
+            QueuedEvent::DeviceParam {
+                device_id,
+                generation,
+                param_type,
+                pod,
+            } => {
+                self.devices
+                    .get(&device_id)
+                    .is_some_and(|device| device.generation == generation)
+                    && pod
+                        .as_pod()
+                        .is_some_and(|pod| self.handle_device_parameter(device_id, param_type, pod))
+            }
+            QueuedEvent::NodeInfo {
+                node_id,
+                generation,
+                props,
+            } => self
+                .nodes
+                .get_mut(&node_id)
+                .filter(|node| node.generation == generation)
+                .is_some_and(|node| node.apply_info_props(props)),
+            QueuedEvent::DeviceInfo {
+                device_id,
+                generation,
+                props,
+            } => self
+                .devices
+                .get_mut(&device_id)
+                .filter(|device| device.generation == generation)
+                .is_some_and(|device| device.apply_info_props(props)),
+            QueuedEvent::LinkInfo {
+                link_id,
+                generation,
+                is_managed,
+            } => match self.links.get_mut(&link_id) {
+                Some(link) if link.generation == generation && link.is_managed != is_managed => {
+                    link.is_managed = is_managed;
+                    true
+                }
+                _ => false,
+            },
+            QueuedEvent::GlobalAdded(_)
+            | QueuedEvent::GlobalRemoved(_)
+            | QueuedEvent::SyncDone(_) => false,
+        }
+    }
+
+    pub fn has_session_manager(&self) -> bool {
+        self.metadata_manager
+            .as_ref()
+            .is_some_and(MetadataManager::is_available)
+    }
+
     pub fn handle_sync_done(&mut self, seq: i32) {
         debug!(
-            "Handling sync done: received seq={}, expecting initial={:?}, params={:?}",
-            seq, self.initial_sync_seq, self.params_sync_seq
+            "Handling sync done: received seq={}, expecting initial={:?}, {} param syncs",
+            seq,
+            self.initial_sync_seq,
+            self.pending_param_syncs.len()
         );
 
         if let Some(initial_seq) = self.initial_sync_seq {
             if seq == initial_seq && !self.initial_sync_complete {
                 self.initial_sync_complete = true;
                 debug!("Initial sync complete! (seq: {seq})");
+                self.check_params_sync();
                 return;
             }
         }
 
-        if let Some(params_seq) = self.params_sync_seq {
-            if seq == params_seq && !self.params_sync_complete {
-                self.params_sync_complete = true;
-                debug!("Parameter sync complete! (seq: {seq})");
-                return;
-            }
+        if let Some(object_id) = self.pending_param_syncs.remove(&seq) {
+            debug!("Params of object {object_id} synced (seq: {seq})");
+            self.check_params_sync();
+            return;
         }
 
         debug!("Received sync done for untracked sequence: {seq}");
     }
 
+    /// Asks for a round trip right after subscribing to the params or info of `object_id`. The
+    /// daemon answers in order, so once it is done, what it had for the object has arrived.
+    pub fn track_param_sync(&mut self, object_id: u32) {
+        match self.core.sync(0) {
+            Ok(seq) => {
+                self.pending_param_syncs.insert(seq.seq(), object_id);
+            }
+            Err(e) => warn!("Failed to sync params of object {object_id}: {e}"),
+        }
+    }
+
+    // Objects listed in the initial sync subscribe before it completes, so their round trips
+    // are all pending by then
+    fn check_params_sync(&mut self) {
+        if self.initial_sync_complete
+            && !self.params_sync_complete
+            && self.pending_param_syncs.is_empty()
+        {
+            self.params_sync_complete = true;
+            debug!("Parameter sync complete!");
+            self.log_node_volume_only_devices();
+        }
+    }
+
     pub fn setup_metadata_manager(
         &mut self,
         store_rc: &Rc<RefCell<Store>>,
-        graph_tx: &watch::Sender<AudioGraph>,
+        graph_tx: &watch::Sender<Arc<AudioGraph>>,
     ) {
         let store_weak = Rc::downgrade(store_rc);
         let graph_tx_clone = graph_tx.clone();
@@ -236,24 +551,55 @@ impl Store {
         device_id: u32,
         profile_index: u32,
     ) -> Result<()> {
-        if let Some((device_name, had_default_sink, had_default_source)) =
-            RestorationManager::should_capture_defaults(self, device_id)
+        if let Some(restoration) =
+            RestorationManager::should_capture_defaults(self, device_id, profile_index)
         {
-            self.restoration_manager.capture_defaults(
-                device_id,
-                device_name,
-                had_default_sink,
-                had_default_source,
-                profile_index,
-            );
+            self.restoration_manager.capture_defaults(restoration);
         }
 
-        self.switch_device_profile(device_id, profile_index)
+        let result = self.switch_device_profile(device_id, profile_index);
+        if result.is_err() {
+            self.restoration_manager.cancel(device_id);
+        }
+        result
+    }
+
+    pub fn cancel_profile_restoration(&mut self, device_id: u32) -> Result<()> {
+        self.restoration_manager.cancel(device_id);
+        Ok(())
+    }
+
+    /// Reports cards that sent no Route params by the end of the initial sync, whose volume is
+    /// then controlled through their nodes.
+    fn log_node_volume_only_devices(&self) {
+        for device in self.devices.values() {
+            if device.known_routes.is_empty() && !device.nodes.is_empty() {
+                debug!(
+                    "Device {} ({}) reports no routes, using node volume",
+                    device.id, device.name
+                );
+            }
+        }
     }
 
     fn check_data_completeness(&mut self) -> bool {
+        // Every object answered its round trip, so no more initial params are on the way
+        if self.params_sync_complete {
+            self.update_device_types();
+            self.apply_default_fallbacks();
+            return true;
+        }
+
         if self.devices.is_empty() {
-            return false;
+            // Devices are created by the session manager, so without one only nodes will show up
+            if !self.initial_sync_complete || self.has_session_manager() {
+                return false;
+            }
+            return self
+                .nodes
+                .values()
+                .filter(|n| matches!(n.node_type, NodeType::AudioSink | NodeType::AudioSource))
+                .all(|n| n.has_received_params);
         }
 
         let has_audio_nodes = self
@@ -275,10 +621,7 @@ impl Store {
             return false;
         }
 
-        let device_ids: Vec<u32> = self.devices.keys().copied().collect();
-        for device_id in device_ids {
-            self.update_device_type_from_nodes(device_id);
-        }
+        self.update_device_types();
 
         let mut found_audio_device = false;
         for device in self.devices.values() {
@@ -306,6 +649,13 @@ impl Store {
         true
     }
 
+    fn update_device_types(&mut self) {
+        let device_ids: Vec<u32> = self.devices.keys().copied().collect();
+        for device_id in device_ids {
+            self.update_device_type_from_nodes(device_id);
+        }
+    }
+
     fn apply_default_fallbacks(&mut self) {
         let has_sinks = self
             .nodes
@@ -355,7 +705,7 @@ impl Store {
         if let Some(metadata_manager) = &self.metadata_manager {
             metadata_manager.set_sample_rate(sample_rate)?;
         } else {
-            return Err(anyhow!("Metadata manager not available"));
+            return Err(PwError::MetadataUnavailable("default").into());
         }
 
         debug!("Set global sample rate to {} Hz", sample_rate);
@@ -363,11 +713,74 @@ impl Store {
     }
 }
 
-pub fn update_graph(store_rc: &Rc<RefCell<Store>>, graph_tx: &watch::Sender<AudioGraph>) {
-    let (nodes_to_restore, completed_devices) = {
-        let store = store_rc.borrow();
-        store.restoration_manager.get_pending_restorations(&store)
-    };
+/// Marks the graph as changed. It is sent once the coalescing window has passed, either from
+/// the PipeWire loop or from a later change.
+pub fn update_graph(store_rc: &Rc<RefCell<Store>>, graph_tx: &watch::Sender<Arc<AudioGraph>>) {
+    let dirty_since = store_rc.borrow().graph_dirty_since.get();
+    match dirty_since {
+        None => store_rc
+            .borrow()
+            .graph_dirty_since
+            .set(Some(Instant::now())),
+        Some(since) if since.elapsed() >= GRAPH_UPDATE_COALESCE => flush_graph(store_rc, graph_tx),
+        Some(_) => {}
+    }
+}
+
+/// Applies the events queued by listeners in the order they arrived. Returns whether any of
+/// them changed the graph.
+pub fn apply_queued_events(store_rc: &Rc<RefCell<Store>>) -> bool {
+    let queue = store_rc.borrow().event_queue.clone();
+    let mut updated = false;
+
+    loop {
+        // Only borrowed to pop, so listeners can keep pushing while an event is applied
+        let next = queue.borrow_mut().pop_front();
+        let Some(event) = next else {
+            break;
+        };
+
+        updated |= match event {
+            QueuedEvent::GlobalAdded(global) => add_global(store_rc, &global),
+            QueuedEvent::GlobalRemoved(id) => {
+                store_rc.borrow_mut().remove_object(id);
+                true
+            }
+            QueuedEvent::SyncDone(seq) => {
+                store_rc.borrow_mut().handle_sync_done(seq);
+                true
+            }
+            event => store_rc.borrow_mut().apply_object_event(event),
+        };
+    }
+
+    updated
+}
+
+/// Time left before pending changes are due, `None` when the graph is up to date.
+pub fn pending_graph_delay(store_rc: &Rc<RefCell<Store>>) -> Option<Duration> {
+    let since = store_rc.borrow().graph_dirty_since.get()?;
+    Some(GRAPH_UPDATE_COALESCE.saturating_sub(since.elapsed()))
+}
+
+/// Sends pending changes whose coalescing window has passed.
+pub fn flush_pending_graph(
+    store_rc: &Rc<RefCell<Store>>,
+    graph_tx: &watch::Sender<Arc<AudioGraph>>,
+) {
+    if pending_graph_delay(store_rc).is_some_and(|delay| delay.is_zero()) {
+        flush_graph(store_rc, graph_tx);
+    }
+}
+
+/// Rebuilds and sends the graph right away, for command results and shutdown.
+pub fn flush_graph(store_rc: &Rc<RefCell<Store>>, graph_tx: &watch::Sender<Arc<AudioGraph>>) {
+    store_rc.borrow().graph_dirty_since.set(None);
+
+    // The graph may be flushed from a listener before the loop gets to the queue
+    apply_queued_events(store_rc);
+
+    run_due_restorations(store_rc);
 
     {
         let mut store = store_rc.borrow_mut();
@@ -382,10 +795,33 @@ pub fn update_graph(store_rc: &Rc<RefCell<Store>>, graph_tx: &watch::Sender<Audi
         if !store.data_complete {
             store.data_complete = store.check_data_completeness();
         }
+    }
 
-        store.restoration_manager.update_attempts_and_cleanup();
+    let graph = Arc::new(store_rc.borrow().to_graph());
+    if graph_tx.send(graph).is_err() {
+        error!("Graph receiver dropped, cannot send updates.");
+    }
+}
+
+/// Time left before the next restoration attempt, `None` when no defaults are pending.
+pub fn pending_restoration_delay(store_rc: &Rc<RefCell<Store>>) -> Option<Duration> {
+    store_rc.borrow().restoration_manager.next_attempt_delay()
+}
+
+/// Attempts the restorations that are due, setting the defaults of those whose nodes are
+/// back, and returns whether any were set. Graph updates attempt them too, so calling this
+/// separately only matters when the graph is quiet.
+pub fn run_due_restorations(store_rc: &Rc<RefCell<Store>>) -> bool {
+    let (nodes_to_restore, completed_devices) = {
+        let store = store_rc.borrow();
+        store.restoration_manager.get_pending_restorations(&*store)
+    };
+    let restored = !nodes_to_restore.is_empty();
+
+    {
+        let mut store = store_rc.borrow_mut();
         store.restoration_manager.mark_completed(&completed_devices);
-        store.restoration_manager.cleanup_expired();
+        store.restoration_manager.update_attempts_and_cleanup();
     }
 
     if !nodes_to_restore.is_empty() {
@@ -408,8 +844,5 @@ pub fn update_graph(store_rc: &Rc<RefCell<Store>>, graph_tx: &watch::Sender<Audi
         }
     }
 
-    let graph = store_rc.borrow().to_graph();
-    if graph_tx.send(graph).is_err() {
-        error!("Graph receiver dropped, cannot send updates.");
-    }
+    restored
 }
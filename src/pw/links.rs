@@ -1,12 +1,16 @@
 use anyhow::{anyhow, Context as AnyhowContext, Result};
-use log::{debug, error, warn};
 use pipewire::{
     keys::*, properties::properties, registry::GlobalObject, spa::utils::dict::DictRef,
 };
 use serde::{Deserialize, Serialize};
 use std::{collections::HashSet, rc::Rc};
+use tracing::{debug, error, warn};
 
-use crate::pw::graph::Store;
+use crate::pw::{
+    error::{ObjectKind, PwError},
+    graph::{QueuedEvent, Store},
+    managed::{is_managed_props, MANAGED_PROPERTY},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PortDirection {
@@ -55,6 +59,7 @@ pub struct Link {
     pub output_port: u32,
     pub input_node: u32,
     pub input_port: u32,
+    pub is_managed: bool,
 }
 
 #[derive(Debug)]
@@ -64,10 +69,19 @@ pub struct LinkInternal {
     pub output_port: u32,
     pub input_node: u32,
     pub input_port: u32,
+    pub is_managed: bool,
     pub proxy: pipewire::link::Link,
+    pub info_listener: Option<pipewire::link::LinkListener>,
+    /// Tells this link apart from a later one reusing its id, see [`Store::next_generation`].
+    pub generation: u64,
 }
 
 impl LinkInternal {
+    /// Unregisters the listener while the proxy it listens on is still alive.
+    pub fn teardown(&mut self) {
+        self.info_listener = None;
+    }
+
     pub fn to_link(&self) -> Link {
         Link {
             id: self.id,
@@ -75,6 +89,7 @@ impl LinkInternal {
             output_port: self.output_port,
             input_node: self.input_node,
             input_port: self.input_port,
+            is_managed: self.is_managed,
         }
     }
 }
@@ -169,16 +184,43 @@ impl Store {
         let output_node = parse_u32(*LINK_OUTPUT_NODE)?;
         let input_node = parse_u32(*LINK_INPUT_NODE)?;
 
-        let link = LinkInternal {
+        let mut link = LinkInternal {
             id: global.id,
             output_node,
             output_port,
             input_node,
             input_port,
+            // Known once the info arrives, the global leaves the property out
+            is_managed: false,
             proxy,
+            info_listener: None,
+            generation: self.next_generation(),
         };
 
+        let info_listener = link
+            .proxy
+            .add_listener_local()
+            .info({
+                let event_queue = self.event_queue.clone();
+                let link_id = global.id;
+                let generation = link.generation;
+
+                move |info| {
+                    if let Some(props) = info.props() {
+                        event_queue.borrow_mut().push_back(QueuedEvent::LinkInfo {
+                            link_id,
+                            generation,
+                            is_managed: is_managed_props(props),
+                        });
+                    }
+                }
+            })
+            .register();
+        link.info_listener = Some(info_listener);
+
         self.links.insert(global.id, link);
+        // The info arrives before the round trip completes, so cleanup sees managed links
+        self.track_param_sync(global.id);
         debug!(
             "Added link {} ({}p:{} -> {}p:{})",
             global.id, output_node, output_port, input_node, input_port
@@ -197,38 +239,47 @@ impl Store {
         Ok(())
     }
 
-    pub fn create_link(&mut self, output_node_id: u32, input_node_id: u32) -> Result<()> {
-        let output_node = self
-            .nodes
-            .get(&output_node_id)
-            .ok_or_else(|| anyhow!("Output node {output_node_id} not found for create_link"))?;
-        let input_node = self
-            .nodes
-            .get(&input_node_id)
-            .ok_or_else(|| anyhow!("Input node {input_node_id} not found for create_link"))?;
-
-        let output_ports: Vec<&PortInternal> = output_node
+    pub fn create_link(
+        &mut self,
+        output_node_id: u32,
+        input_node_id: u32,
+        mapping: PortMapping,
+    ) -> Result<()> {
+        let output_node = self.nodes.get(&output_node_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Node,
+            id: output_node_id,
+        })?;
+        let input_node = self.nodes.get(&input_node_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Node,
+            id: input_node_id,
+        })?;
+
+        let output_ports: Vec<Port> = output_node
             .ports
             .iter()
             .filter_map(|port_id| self.ports.get(port_id))
             .filter(|p| p.direction == PortDirection::Output)
+            .map(PortInternal::to_port)
             .collect();
         if output_ports.is_empty() {
             return Err(anyhow!("Output node {output_node_id} has no output ports"));
         }
 
-        let input_ports: Vec<&PortInternal> = input_node
+        let input_ports: Vec<Port> = input_node
             .ports
             .iter()
             .filter_map(|port_id| self.ports.get(port_id))
             .filter(|p| p.direction == PortDirection::Input)
+            .map(PortInternal::to_port)
             .collect();
         if input_ports.is_empty() {
             return Err(anyhow!("Input node {input_node_id} has no input ports"));
         }
 
         let core = self.core.clone();
-        let port_pairs = map_ports(&output_ports, &input_ports);
+        let output_refs: Vec<&Port> = output_ports.iter().collect();
+        let input_refs: Vec<&Port> = input_ports.iter().collect();
+        let port_pairs = map_ports(&output_refs, &input_refs, mapping);
         if port_pairs.is_empty() {
             return Err(anyhow!(
                 "No matching ports found between nodes {output_node_id} and {input_node_id}",
@@ -252,6 +303,7 @@ impl Store {
                 *LINK_OUTPUT_NODE => output_node_id.to_string(), *LINK_OUTPUT_PORT => output_port_id.to_string(),
                 *LINK_INPUT_NODE => input_node_id.to_string(), *LINK_INPUT_PORT => input_port_id.to_string(),
                 *OBJECT_LINGER => "true",
+                MANAGED_PROPERTY => "true",
             };
 
             match core.create_object::<pipewire::link::Link>("link-factory", &props) {
@@ -264,7 +316,7 @@ impl Store {
                         format!("Failed to create link {output_port_id}p -> {input_port_id}p: {e}");
                     error!("{err_msg}");
                     if first_error.is_none() {
-                        first_error = Some(anyhow!(err_msg));
+                        first_error = Some(PwError::ProxyError(err_msg).into());
                     }
                 }
             }
@@ -316,7 +368,8 @@ impl Store {
         let mut first_error: Option<anyhow::Error> = None;
 
         for link_id in links_to_remove_ids {
-            if let Some(link_internal) = self.links.remove(&link_id) {
+            if let Some(mut link_internal) = self.links.remove(&link_id) {
+                link_internal.teardown();
                 if let Some(port) = self.ports.get_mut(&link_internal.output_port) {
                     port.links.retain(|&id| id != link_id);
                 }
@@ -333,7 +386,7 @@ impl Store {
                         let err_msg = format!("Failed to destroy link object {link_id}: {e}");
                         error!("{err_msg}");
                         if first_error.is_none() {
-                            first_error = Some(anyhow!(err_msg));
+                            first_error = Some(PwError::ProxyError(err_msg).into());
                         }
                     }
                 }
@@ -351,20 +404,119 @@ impl Store {
     }
 }
 
-pub fn map_ports<'a>(
-    output_ports: &[&'a PortInternal],
-    input_ports: &[&'a PortInternal],
+/// How output ports are paired with input ports when linking two nodes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PortMapping {
+    /// Match channel names, fan a mono output out to every input, and fall back to
+    /// port order for whatever could not be matched by name.
+    #[default]
+    Auto,
+    /// Only link ports whose channel names are identical.
+    Strict,
+    /// Link ports by their order, ignoring channel names.
+    Positional,
+    /// Link only the front left/right pair, folding it into a mono input when needed.
+    Downmix,
+    /// Link the first output channel to every input port.
+    DuplicateMono,
+}
+
+impl PortMapping {
+    /// Strategies that can be picked explicitly, in the order they are offered.
+    pub const ALL: [PortMapping; 5] = [
+        PortMapping::Auto,
+        PortMapping::Downmix,
+        PortMapping::DuplicateMono,
+        PortMapping::Positional,
+        PortMapping::Strict,
+    ];
+}
+
+/// Pairs output ports with input ports according to `mapping`, returning `(output, input)` port IDs.
+///
+/// Ports are expected in node order. Each input port is used at most once, except when
+/// [`PortMapping::Auto`] fans a single output out to every input.
+pub fn map_ports(
+    output_ports: &[&Port],
+    input_ports: &[&Port],
+    mapping: PortMapping,
 ) -> Vec<(u32, u32)> {
     if output_ports.is_empty() || input_ports.is_empty() {
         return Vec::new();
     }
-    if output_ports.len() == 1 {
-        return input_ports
+
+    match mapping {
+        PortMapping::Auto => {
+            if output_ports.len() == 1 {
+                return input_ports
+                    .iter()
+                    .map(|in_port| (output_ports[0].id, in_port.id))
+                    .collect();
+            }
+
+            let mut pairs = match_by_channel(output_ports, input_ports);
+            let min_len = output_ports.len().min(input_ports.len());
+            if pairs.len() < min_len {
+                warn!("Channel matching incomplete ({} pairs for {}/{} ports), attempting positional fallback.", pairs.len(), output_ports.len(), input_ports.len());
+                let mut used_input_ports: HashSet<u32> =
+                    pairs.iter().map(|(_, in_id)| *in_id).collect();
+                let mut fallback_pairs = Vec::new();
+                for (i, out_port) in output_ports.iter().enumerate() {
+                    if pairs.iter().any(|(out_id, _)| *out_id == out_port.id) {
+                        continue;
+                    }
+                    if let Some(in_port) = input_ports.get(i) {
+                        if !used_input_ports.contains(&in_port.id) {
+                            fallback_pairs.push((out_port.id, in_port.id));
+                            used_input_ports.insert(in_port.id);
+                        }
+                    }
+                }
+                pairs.extend(fallback_pairs);
+            }
+            pairs
+        }
+        PortMapping::Strict => match_by_channel(output_ports, input_ports),
+        PortMapping::Positional => output_ports
             .iter()
-            .map(|in_port| (output_ports[0].id, in_port.id))
-            .collect();
+            .zip(input_ports.iter())
+            .map(|(out_port, in_port)| (out_port.id, in_port.id))
+            .collect(),
+        PortMapping::Downmix => downmix(output_ports, input_ports),
+        PortMapping::DuplicateMono => {
+            let source = output_ports
+                .iter()
+                .find(|p| p.channel == "MONO")
+                .unwrap_or(&output_ports[0]);
+            input_ports
+                .iter()
+                .map(|in_port| (source.id, in_port.id))
+                .collect()
+        }
     }
+}
+
+fn downmix(output_ports: &[&Port], input_ports: &[&Port]) -> Vec<(u32, u32)> {
+    let front_pair = |ports: &[&Port]| -> Vec<u32> {
+        let left = ports.iter().find(|p| p.channel == "FL");
+        let right = ports.iter().find(|p| p.channel == "FR");
+        match (left, right) {
+            (Some(left), Some(right)) => vec![left.id, right.id],
+            _ => ports.iter().take(2).map(|p| p.id).collect(),
+        }
+    };
 
+    let outputs = front_pair(output_ports);
+    let inputs = front_pair(input_ports);
+
+    match (outputs.len(), inputs.len()) {
+        (1, _) => inputs.iter().map(|&in_id| (outputs[0], in_id)).collect(),
+        (_, 1) => outputs.iter().map(|&out_id| (out_id, inputs[0])).collect(),
+        _ => outputs.into_iter().zip(inputs).collect(),
+    }
+}
+
+fn match_by_channel(output_ports: &[&Port], input_ports: &[&Port]) -> Vec<(u32, u32)> {
     let mut pairs = Vec::new();
     let mut used_input_ports = HashSet::new();
 
@@ -380,23 +532,162 @@ pub fn map_ports<'a>(
         }
     }
 
-    let min_len = output_ports.len().min(input_ports.len());
-    if pairs.len() < min_len {
-        warn!("Channel matching incomplete ({} pairs for {}/{} ports), attempting positional fallback.", pairs.len(), output_ports.len(), input_ports.len());
-        let mut fallback_pairs = Vec::new();
-        let mut current_used_inputs = used_input_ports;
-        for (i, out_port) in output_ports.iter().enumerate() {
-            if pairs.iter().any(|(out_id, _)| *out_id == out_port.id) {
-                continue;
-            }
-            if let Some(in_port) = input_ports.get(i) {
-                if !current_used_inputs.contains(&in_port.id) {
-                    fallback_pairs.push((out_port.id, in_port.id));
-                    current_used_inputs.insert(in_port.id);
-                }
-            }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn port(id: u32, direction: PortDirection, channel: &str) -> Port {
+        Port {
+            id,
+            name: format!("port_{id}"),
+            node_id: if direction == PortDirection::Output {
+                1
+            } else {
+                2
+            },
+            direction,
+            channel: channel.to_string(),
+            links: Vec::new(),
         }
-        pairs.extend(fallback_pairs);
     }
-    pairs
+
+    fn outputs(channels: &[&str]) -> Vec<Port> {
+        channels
+            .iter()
+            .enumerate()
+            .map(|(i, channel)| port(10 + i as u32, PortDirection::Output, channel))
+            .collect()
+    }
+
+    fn inputs(channels: &[&str]) -> Vec<Port> {
+        channels
+            .iter()
+            .enumerate()
+            .map(|(i, channel)| port(20 + i as u32, PortDirection::Input, channel))
+            .collect()
+    }
+
+    fn map(out: &[Port], inp: &[Port], mapping: PortMapping) -> Vec<(u32, u32)> {
+        let out: Vec<&Port> = out.iter().collect();
+        let inp: Vec<&Port> = inp.iter().collect();
+        map_ports(&out, &inp, mapping)
+    }
+
+    #[test]
+    fn empty_ports_produce_no_pairs() {
+        let out = outputs(&["FL", "FR"]);
+        assert!(map(&out, &[], PortMapping::Auto).is_empty());
+        assert!(map(&[], &out, PortMapping::Positional).is_empty());
+    }
+
+    #[test]
+    fn stereo_matches_by_channel_name() {
+        let out = outputs(&["FL", "FR"]);
+        let inp = inputs(&["FR", "FL"]);
+        assert_eq!(map(&out, &inp, PortMapping::Auto), vec![(10, 21), (11, 20)]);
+        assert_eq!(
+            map(&out, &inp, PortMapping::Strict),
+            vec![(10, 21), (11, 20)]
+        );
+    }
+
+    #[test]
+    fn mono_output_fans_out_to_stereo() {
+        let out = outputs(&["MONO"]);
+        let inp = inputs(&["FL", "FR"]);
+        assert_eq!(map(&out, &inp, PortMapping::Auto), vec![(10, 20), (10, 21)]);
+        assert!(map(&out, &inp, PortMapping::Strict).is_empty());
+        assert_eq!(map(&out, &inp, PortMapping::Positional), vec![(10, 20)]);
+    }
+
+    #[test]
+    fn mismatched_channel_names_fall_back_to_position() {
+        let out = outputs(&["FL", "FR"]);
+        let inp = inputs(&["RL", "RR"]);
+        assert_eq!(map(&out, &inp, PortMapping::Auto), vec![(10, 20), (11, 21)]);
+        assert!(map(&out, &inp, PortMapping::Strict).is_empty());
+    }
+
+    #[test]
+    fn partial_match_fills_remaining_ports_by_position() {
+        let out = outputs(&["FL", "FR"]);
+        let inp = inputs(&["FL", "AUX1"]);
+        assert_eq!(map(&out, &inp, PortMapping::Auto), vec![(10, 20), (11, 21)]);
+        assert_eq!(map(&out, &inp, PortMapping::Strict), vec![(10, 20)]);
+    }
+
+    #[test]
+    fn aux_ports_link_positionally() {
+        let out = outputs(&["FL", "FR"]);
+        let inp = inputs(&["AUX0", "AUX1", "AUX2", "AUX3"]);
+        assert_eq!(map(&out, &inp, PortMapping::Auto), vec![(10, 20), (11, 21)]);
+        assert_eq!(
+            map(&out, &inp, PortMapping::Positional),
+            vec![(10, 20), (11, 21)]
+        );
+    }
+
+    #[test]
+    fn unknown_channels_are_never_matched_by_name() {
+        let out = outputs(&["unknown", "unknown"]);
+        let inp = inputs(&["unknown", "unknown"]);
+        assert!(map(&out, &inp, PortMapping::Strict).is_empty());
+        assert_eq!(map(&out, &inp, PortMapping::Auto), vec![(10, 20), (11, 21)]);
+    }
+
+    #[test]
+    fn downmix_links_only_the_front_pair() {
+        let out = outputs(&["FL", "FR", "FC", "LFE", "RL", "RR"]);
+        let inp = inputs(&["FR", "FL"]);
+        assert_eq!(
+            map(&out, &inp, PortMapping::Downmix),
+            vec![(10, 21), (11, 20)]
+        );
+    }
+
+    #[test]
+    fn downmix_folds_stereo_into_mono() {
+        let out = outputs(&["FL", "FR"]);
+        let inp = inputs(&["MONO"]);
+        assert_eq!(
+            map(&out, &inp, PortMapping::Downmix),
+            vec![(10, 20), (11, 20)]
+        );
+    }
+
+    #[test]
+    fn downmix_falls_back_to_the_first_two_ports() {
+        let out = outputs(&["AUX0", "AUX1", "AUX2"]);
+        let inp = inputs(&["FL", "FR", "FC"]);
+        assert_eq!(
+            map(&out, &inp, PortMapping::Downmix),
+            vec![(10, 20), (11, 21)]
+        );
+    }
+
+    #[test]
+    fn duplicate_mono_fans_first_channel_out() {
+        let out = outputs(&["FL", "FR"]);
+        let inp = inputs(&["FL", "FR", "FC", "LFE"]);
+        assert_eq!(
+            map(&out, &inp, PortMapping::DuplicateMono),
+            vec![(10, 20), (10, 21), (10, 22), (10, 23)]
+        );
+
+        let out = outputs(&["AUX0", "MONO"]);
+        assert_eq!(
+            map(&out, &inp, PortMapping::DuplicateMono),
+            vec![(11, 20), (11, 21), (11, 22), (11, 23)]
+        );
+    }
+
+    #[test]
+    fn surplus_outputs_are_left_unlinked() {
+        let out = outputs(&["FL", "FR", "FC", "LFE"]);
+        let inp = inputs(&["FL", "FR"]);
+        assert_eq!(map(&out, &inp, PortMapping::Auto), vec![(10, 20), (11, 21)]);
+    }
 }
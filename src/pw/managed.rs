@@ -0,0 +1,243 @@
+use anyhow::{anyhow, Result};
+use pipewire::{keys::*, properties::properties};
+use tracing::{debug, error};
+
+use crate::pw::{
+    error::{ObjectKind, PwError},
+    graph::{AudioGraph, Store},
+    nodes::NodeType,
+};
+
+/// Property attached to every object pwmenu creates so it can be found and removed later.
+pub const MANAGED_PROPERTY: &str = "pwmenu.managed";
+
+const VIRTUAL_SINK_FACTORY: &str = "support.null-audio-sink";
+const VIRTUAL_NODE_PREFIX: &str = "pwmenu_virtual_";
+
+pub fn is_managed_props(props: &pipewire::spa::utils::dict::DictRef) -> bool {
+    props.get(MANAGED_PROPERTY) == Some("true")
+}
+
+/// Links and nodes pwmenu created, each sorted by id. Links are removed first so none is left
+/// pointing at a node that is gone.
+pub fn managed_objects(graph: &AudioGraph) -> (Vec<u32>, Vec<u32>) {
+    let mut links: Vec<u32> = graph
+        .links
+        .values()
+        .filter(|l| l.is_managed)
+        .map(|l| l.id)
+        .collect();
+    let mut nodes: Vec<u32> = graph
+        .nodes
+        .values()
+        .filter(|n| n.is_managed)
+        .map(|n| n.id)
+        .collect();
+    links.sort_unstable();
+    nodes.sort_unstable();
+    (links, nodes)
+}
+
+fn virtual_node_name(description: &str) -> String {
+    let sanitized: String = description
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    format!("{VIRTUAL_NODE_PREFIX}{sanitized}")
+}
+
+impl Store {
+    pub fn create_virtual_sink(&mut self, description: &str) -> Result<()> {
+        let description = description.trim();
+        if description.is_empty() {
+            return Err(anyhow!("Virtual sink name cannot be empty"));
+        }
+
+        let node_name = virtual_node_name(description);
+        if self.nodes.values().any(|n| n.name == node_name) {
+            return Err(anyhow!(
+                "A virtual sink named '{description}' already exists"
+            ));
+        }
+
+        self.spawn_virtual_sink(&node_name, description)
+    }
+
+    fn spawn_virtual_sink(&self, node_name: &str, description: &str) -> Result<()> {
+        let props = properties! {
+            *FACTORY_NAME => VIRTUAL_SINK_FACTORY,
+            *NODE_NAME => node_name,
+            *NODE_DESCRIPTION => description,
+            *MEDIA_CLASS => "Audio/Sink",
+            "audio.position" => "FL,FR",
+            *OBJECT_LINGER => "true",
+            MANAGED_PROPERTY => "true",
+        };
+
+        match self
+            .core
+            .create_object::<pipewire::node::Node>("adapter", &props)
+        {
+            Ok(_) => {
+                debug!("Sent command to create virtual sink '{node_name}'");
+                Ok(())
+            }
+            Err(e) => {
+                error!("Failed to create virtual sink '{node_name}': {e}");
+                Err(anyhow!(
+                    "Failed to create virtual sink '{description}': {e}"
+                ))
+            }
+        }
+    }
+
+    pub fn destroy_managed_node(&mut self, node_id: u32) -> Result<()> {
+        let is_managed =
+            self.nodes
+                .get(&node_id)
+                .map(|n| n.is_managed)
+                .ok_or(PwError::NotFound {
+                    kind: ObjectKind::Node,
+                    id: node_id,
+                })?;
+
+        if !is_managed {
+            return Err(
+                PwError::Unsupported(format!("Node {node_id} was not created by pwmenu")).into(),
+            );
+        }
+
+        let mut node = self.nodes.remove(&node_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Node,
+            id: node_id,
+        })?;
+
+        if let Some(device_id) = node.device_id {
+            if let Some(device) = self.devices.get_mut(&device_id) {
+                device.nodes.retain(|&n_id| n_id != node_id);
+            }
+        }
+
+        // Unhook listeners before the proxy goes away
+        node.listener.take();
+        node.info_listener.take();
+
+        self.core
+            .destroy_object(node.proxy)
+            .map_err(|e| PwError::ProxyError(format!("Failed to destroy node {node_id}: {e}")))?;
+
+        debug!("Sent command to destroy managed node {node_id}");
+        Ok(())
+    }
+
+    pub fn rename_managed_node(&mut self, node_id: u32, description: &str) -> Result<()> {
+        let description = description.trim();
+        if description.is_empty() {
+            return Err(anyhow!("Virtual sink name cannot be empty"));
+        }
+
+        let node = self.nodes.get(&node_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Node,
+            id: node_id,
+        })?;
+
+        if !node.is_managed || node.node_type != NodeType::AudioSink {
+            return Err(PwError::Unsupported(
+                "Only virtual sinks created by pwmenu can be renamed".to_string(),
+            )
+            .into());
+        }
+
+        // The adapter updates the property in place, so the sink keeps its id and streams
+        self.set_node_prop_param(node_id, *NODE_DESCRIPTION, description)?;
+        debug!("Sent description '{description}' for managed node {node_id}");
+        Ok(())
+    }
+
+    pub fn destroy_all_managed(&mut self) -> Result<usize> {
+        let (managed_links, managed_nodes) = managed_objects(&self.to_graph());
+
+        let mut destroyed_count = 0;
+        let mut first_error: Option<anyhow::Error> = None;
+
+        for link_id in managed_links {
+            let Some(mut link) = self.links.remove(&link_id) else {
+                continue;
+            };
+            link.teardown();
+
+            if let Some(port) = self.ports.get_mut(&link.output_port) {
+                port.links.retain(|&id| id != link_id);
+            }
+            if let Some(port) = self.ports.get_mut(&link.input_port) {
+                port.links.retain(|&id| id != link_id);
+            }
+
+            match self.core.destroy_object(link.proxy) {
+                Ok(_) => {
+                    debug!("Sent command to destroy managed link {link_id}");
+                    destroyed_count += 1;
+                }
+                Err(e) => {
+                    error!("Failed to destroy managed link {link_id}: {e}");
+                    first_error.get_or_insert(
+                        PwError::ProxyError(format!("Failed to destroy link {link_id}: {e}"))
+                            .into(),
+                    );
+                }
+            }
+        }
+
+        for node_id in managed_nodes {
+            match self.destroy_managed_node(node_id) {
+                Ok(()) => destroyed_count += 1,
+                Err(e) => {
+                    error!("{e}");
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        match first_error {
+            Some(err) => Err(err),
+            None => Ok(destroyed_count),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAPTOP: &str = include_str!("../../tests/fixtures/laptop.json");
+
+    #[test]
+    fn cleanup_selects_only_managed_objects() {
+        let mut graph: AudioGraph = serde_json::from_str(LAPTOP).unwrap();
+        assert_eq!(managed_objects(&graph), (vec![], vec![]));
+
+        // A virtual sink pwmenu created, with Firefox linked into it
+        let mut sink = graph.nodes[&52].clone();
+        sink.id = 120;
+        sink.name = virtual_node_name("Music");
+        sink.device_id = None;
+        sink.is_default = false;
+        sink.is_managed = true;
+        graph.nodes.insert(sink.id, sink);
+
+        let mut link = graph.links[&95].clone();
+        link.id = 121;
+        link.input_node = 120;
+        link.is_managed = true;
+        graph.links.insert(link.id, link);
+
+        assert_eq!(managed_objects(&graph), (vec![121], vec![120]));
+    }
+}
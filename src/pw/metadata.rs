@@ -1,13 +1,16 @@
-use anyhow::{anyhow, Result};
-use log::debug;
+use anyhow::Result;
 use pipewire::metadata::{Metadata, MetadataListener};
 use serde_json::Value;
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use tracing::debug;
+
+use crate::pw::{error::PwError, nodes::NodeType};
 
 pub struct MetadataManager {
     default_metadata: Option<Metadata>,
     settings_metadata: Option<Metadata>,
     properties: Rc<RefCell<HashMap<String, String>>>,
+    stream_targets: Rc<RefCell<HashMap<u32, String>>>,
     settings_properties: Rc<RefCell<HashMap<String, String>>>,
     _default_listener: Option<MetadataListener>,
     _settings_listener: Option<MetadataListener>,
@@ -24,6 +27,7 @@ const GLOBAL_SUBJECT_ID: u32 = 0;
 const SPA_JSON_TYPE: &str = "Spa:String:JSON";
 const DEFAULT_AUDIO_PREFIX: &str = "default.audio.";
 const DEFAULT_CONFIGURED_AUDIO_PREFIX: &str = "default.configured.audio.";
+const STREAM_TARGET_KEY: &str = "target.object";
 
 fn is_default_audio_key(key: &str) -> bool {
     key.starts_with(DEFAULT_AUDIO_PREFIX) || key.starts_with(DEFAULT_CONFIGURED_AUDIO_PREFIX)
@@ -35,6 +39,7 @@ impl MetadataManager {
             default_metadata: None,
             settings_metadata: None,
             properties: Rc::new(RefCell::new(HashMap::new())),
+            stream_targets: Rc::new(RefCell::new(HashMap::new())),
             settings_properties: Rc::new(RefCell::new(HashMap::new())),
             _default_listener: None,
             _settings_listener: None,
@@ -54,6 +59,7 @@ impl MetadataManager {
         debug!("Registered default metadata object");
 
         let properties_clone = self.properties.clone();
+        let stream_targets_clone = self.stream_targets.clone();
         let update_callback = self.update_callback.clone();
 
         let listener = metadata
@@ -63,7 +69,25 @@ impl MetadataManager {
                     "Default metadata property callback - subject: {subject}, key: {key:?}, type: {type_:?}, value: {value:?}",
                 );
 
+                // A property of another subject with no key clears all of them
                 if subject != GLOBAL_SUBJECT_ID {
+                    if key.is_none_or(|key| key == STREAM_TARGET_KEY) {
+                        let changed = match value.filter(|_| key.is_some()) {
+                            Some(target) => {
+                                stream_targets_clone
+                                    .borrow_mut()
+                                    .insert(subject, target.to_string())
+                                    .as_deref()
+                                    != Some(target)
+                            }
+                            None => stream_targets_clone.borrow_mut().remove(&subject).is_some(),
+                        };
+                        if changed {
+                            if let Some(ref callback) = update_callback {
+                                callback();
+                            }
+                        }
+                    }
                     return 0;
                 }
 
@@ -163,11 +187,16 @@ impl MetadataManager {
         self.settings_metadata.is_some()
     }
 
-    fn set_default_audio_device(&self, node_name: &str, device_type: &str) -> Result<()> {
+    fn set_default_audio_device(
+        &self,
+        node_name: &str,
+        device_type: &str,
+        persist: bool,
+    ) -> Result<()> {
         let metadata = self
             .default_metadata
             .as_ref()
-            .ok_or_else(|| anyhow!("Default metadata object not found"))?;
+            .ok_or(PwError::MetadataUnavailable("default"))?;
 
         let value = format!(r#"{{ "name": "{node_name}" }}"#);
         let property_key = format!("default.audio.{device_type}");
@@ -180,30 +209,76 @@ impl MetadataManager {
             Some(SPA_JSON_TYPE),
             Some(&value),
         );
-        metadata.set_property(
-            GLOBAL_SUBJECT_ID,
-            &configured_key,
-            Some(SPA_JSON_TYPE),
-            Some(&value),
-        );
+        if persist {
+            metadata.set_property(
+                GLOBAL_SUBJECT_ID,
+                &configured_key,
+                Some(SPA_JSON_TYPE),
+                Some(&value),
+            );
+        }
 
         debug!("Set default {device_type} to {node_name} in default metadata");
         Ok(())
     }
 
     pub fn set_default_sink(&self, node_name: &str) -> Result<()> {
-        self.set_default_audio_device(node_name, "sink")
+        self.set_default_audio_device(node_name, "sink", true)
     }
 
     pub fn set_default_source(&self, node_name: &str) -> Result<()> {
-        self.set_default_audio_device(node_name, "source")
+        self.set_default_audio_device(node_name, "source", true)
+    }
+
+    /// Points a stream at a specific node; the session manager moves it there right away.
+    pub fn set_stream_target(&self, stream_id: u32, target_name: &str) -> Result<()> {
+        let metadata = self
+            .default_metadata
+            .as_ref()
+            .ok_or(PwError::MetadataUnavailable("default"))?;
+
+        metadata.set_property(stream_id, STREAM_TARGET_KEY, None, Some(target_name));
+
+        debug!("Set target.object of stream {stream_id} to {target_name}");
+        Ok(())
+    }
+
+    /// Drops the target of a stream, which then follows the default device again.
+    pub fn clear_stream_target(&self, stream_id: u32) -> Result<()> {
+        let metadata = self
+            .default_metadata
+            .as_ref()
+            .ok_or(PwError::MetadataUnavailable("default"))?;
+
+        metadata.set_property(stream_id, STREAM_TARGET_KEY, None, None);
+
+        debug!("Cleared target.object of stream {stream_id}");
+        Ok(())
+    }
+
+    /// The `target.object` set for a stream, a node name or serial.
+    pub fn get_stream_target(&self, stream_id: u32) -> Option<String> {
+        self.stream_targets.borrow().get(&stream_id).cloned()
+    }
+
+    /// Sets the current default without touching the configured one, so the session
+    /// manager can still switch back to the user's choice when it reappears.
+    pub fn set_fallback_default(&self, node_name: &str, node_type: NodeType) -> Result<()> {
+        match node_type {
+            NodeType::AudioSink => self.set_default_audio_device(node_name, "sink", false),
+            NodeType::AudioSource => self.set_default_audio_device(node_name, "source", false),
+            _ => Err(PwError::Unsupported(format!(
+                "Node type {node_type:?} cannot be a default device"
+            ))
+            .into()),
+        }
     }
 
     pub fn set_sample_rate(&self, sample_rate: u32) -> Result<()> {
         let metadata = self
             .settings_metadata
             .as_ref()
-            .ok_or_else(|| anyhow!("Settings metadata object not found"))?;
+            .ok_or(PwError::MetadataUnavailable("settings"))?;
 
         // Set the desired rate and enforce it immediately
         metadata.set_property(
@@ -0,0 +1,229 @@
+use anyhow::{Context, Result};
+use libspa::sys::{SPA_PARAM_EnumFormat, SPA_TYPE_OBJECT_Format};
+use pipewire::{
+    keys::*,
+    properties::properties,
+    spa::{
+        param::audio::{AudioFormat, AudioInfoRaw},
+        pod::{serialize::PodSerializer, Object, Pod, Value},
+        utils::Direction,
+    },
+    stream::{StreamFlags, StreamListener, StreamRc},
+};
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+use tracing::{debug, warn};
+
+use crate::pw::{
+    error::{ObjectKind, PwError},
+    graph::Store,
+    nodes::NodeType,
+};
+
+/// Quietest level shown on meters; anything below reads as silence.
+const METER_FLOOR_DB: f32 = -60.0;
+
+/// Latest peak captured by a level meter, shared with the PipeWire thread.
+#[derive(Debug, Clone, Default)]
+pub struct InputLevel {
+    peak_bits: Arc<AtomicU32>,
+}
+
+impl InputLevel {
+    /// Peak sample magnitude of the last captured buffer, from 0.0 to 1.0.
+    pub fn peak(&self) -> f32 {
+        f32::from_bits(self.peak_bits.load(Ordering::Relaxed))
+    }
+
+    pub fn peak_db(&self) -> f32 {
+        let peak = self.peak();
+        if peak > 0.0 {
+            (20.0 * peak.log10()).max(METER_FLOOR_DB)
+        } else {
+            METER_FLOOR_DB
+        }
+    }
+
+    /// Peak mapped from the meter floor to 0 dBFS as a 0 to 100 value.
+    pub fn percent(&self) -> u8 {
+        ((1.0 - self.peak_db() / METER_FLOOR_DB) * 100.0).round() as u8
+    }
+
+    fn store_peak(&self, peak: f32) {
+        self.peak_bits.store(peak.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Temporary capture stream measuring the level of one source or playback stream.
+pub struct LevelMeter {
+    // Declared first so the listener is unregistered before the stream is destroyed
+    _listener: StreamListener<InputLevel>,
+    _stream: StreamRc,
+    level: InputLevel,
+}
+
+fn meter_format_pod() -> Result<Vec<u8>> {
+    // Rate and channels are left out so the source's own layout is used
+    let mut audio_info = AudioInfoRaw::new();
+    audio_info.set_format(AudioFormat::F32LE);
+
+    let (cursor, _) = PodSerializer::serialize(
+        Cursor::new(Vec::new()),
+        &Value::Object(Object {
+            type_: SPA_TYPE_OBJECT_Format,
+            id: SPA_PARAM_EnumFormat,
+            properties: audio_info.into(),
+        }),
+    )
+    .context("Failed to serialize level meter format")?;
+
+    Ok(cursor.into_inner())
+}
+
+impl Store {
+    /// Starts measuring the level of `node_id`, replacing any meter already running.
+    pub fn start_level_meter(&mut self, node_id: u32) -> Result<InputLevel> {
+        let node_name = {
+            let node = self.nodes.get(&node_id).ok_or(PwError::NotFound {
+                kind: ObjectKind::Node,
+                id: node_id,
+            })?;
+
+            if !matches!(
+                node.node_type,
+                NodeType::AudioSource | NodeType::AudioDuplex
+            ) {
+                return Err(PwError::Unsupported(
+                    "Levels can only be measured on input devices".to_string(),
+                )
+                .into());
+            }
+
+            node.name.clone()
+        };
+
+        self.level_meter.take();
+
+        let meter = self.create_level_meter(&node_name, false)?;
+        let level = meter.level.clone();
+        self.level_meter = Some(meter);
+
+        debug!("Measuring input level of node {node_id}");
+        Ok(level)
+    }
+
+    pub fn stop_level_meter(&mut self) -> Result<()> {
+        if self.level_meter.take().is_some() {
+            debug!("Stopped level meter");
+        }
+        Ok(())
+    }
+
+    /// Meters exactly the given playback streams, keeping meters that already run and
+    /// dropping those of streams no longer listed.
+    pub fn sync_stream_meters(&mut self, node_ids: &[u32]) -> Result<HashMap<u32, InputLevel>> {
+        self.stream_meters.retain(|id, _| node_ids.contains(id));
+
+        for &node_id in node_ids {
+            if self.stream_meters.contains_key(&node_id) {
+                continue;
+            }
+
+            // The stream may be gone already, or be something else than a playback stream
+            let Some(node) = self
+                .nodes
+                .get(&node_id)
+                .filter(|node| node.node_type == NodeType::StreamOutputAudio)
+            else {
+                continue;
+            };
+
+            // Stream names are not unique, the serial is
+            let target = node.serial.clone().unwrap_or_else(|| node.name.clone());
+
+            match self.create_level_meter(&target, true) {
+                Ok(meter) => {
+                    self.stream_meters.insert(node_id, meter);
+                    debug!("Measuring playback level of stream {node_id}");
+                }
+                Err(e) => warn!("Failed to meter stream {node_id}: {e}"),
+            }
+        }
+
+        Ok(self
+            .stream_meters
+            .iter()
+            .map(|(&id, meter)| (id, meter.level.clone()))
+            .collect())
+    }
+
+    fn create_level_meter(&self, target: &str, monitor: bool) -> Result<LevelMeter> {
+        let mut props = properties! {
+            *MEDIA_TYPE => "Audio",
+            *MEDIA_CATEGORY => "Capture",
+            *MEDIA_NAME => "Level meter",
+            *TARGET_OBJECT => target,
+            *NODE_DONT_RECONNECT => "true",
+        };
+        if monitor {
+            props.insert(*STREAM_MONITOR, "true");
+        }
+
+        let stream = StreamRc::new((*self.core).clone(), "pwmenu-level-meter", props)
+            .context("Failed to create level meter stream")?;
+
+        let level = InputLevel::default();
+
+        let listener = stream
+            .add_local_listener_with_user_data(level.clone())
+            .process(|stream, level| {
+                let Some(mut buffer) = stream.dequeue_buffer() else {
+                    return;
+                };
+                let data = &mut buffer.datas_mut()[0];
+                let offset = data.chunk().offset() as usize;
+                let size = data.chunk().size() as usize;
+
+                let Some(samples) = data
+                    .data()
+                    .and_then(|bytes| bytes.get(offset..offset + size))
+                else {
+                    return;
+                };
+
+                let peak = samples
+                    .chunks_exact(std::mem::size_of::<f32>())
+                    .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+                level.store_peak(peak.min(1.0));
+            })
+            .register()
+            .context("Failed to register level meter listener")?;
+
+        let format = meter_format_pod()?;
+        let mut params = [Pod::from_bytes(&format).ok_or_else(|| {
+            PwError::PodBuild("Failed to create Pod reference for level meter format".to_string())
+        })?];
+
+        stream
+            .connect(
+                Direction::Input,
+                None,
+                StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS | StreamFlags::RT_PROCESS,
+                &mut params,
+            )
+            .context("Failed to connect level meter stream")?;
+
+        Ok(LevelMeter {
+            _listener: listener,
+            _stream: stream,
+            level,
+        })
+    }
+}
@@ -0,0 +1,994 @@
+use anyhow::{anyhow, Context, Result};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    sync::watch,
+    time::{sleep_until, Duration, Instant},
+};
+
+use crate::pw::{
+    commands::BatchStep,
+    error::{ObjectKind, PwError},
+    graph::AudioGraph,
+    links::{map_ports, Link, Port, PortDirection, PortMapping},
+    managed::managed_objects,
+    meter::InputLevel,
+    nodes::{Node, NodeType, Volume},
+    profiler::ProfilerStats,
+    recording::GraphFrame,
+    restoration::RestorationSettings,
+    source::{GraphSource, SourceFuture},
+    volume::{RouteDirection, VolumeCurve, VolumeResolver},
+};
+
+/// [`GraphSource`] serving a fixed [`AudioGraph`], such as one saved with `pwmenu dump`.
+/// Volume, mute, default device, profile and link operations are applied to the graph and
+/// published like PipeWire would; those needing a real daemon, such as virtual sinks or
+/// level meters, fail with [`PwError::Unsupported`] or do nothing.
+pub struct MockEngine {
+    graph_tx: watch::Sender<Arc<AudioGraph>>,
+    volume_curve: Mutex<VolumeCurve>,
+}
+
+impl MockEngine {
+    pub fn new(graph: AudioGraph) -> Self {
+        let (graph_tx, _) = watch::channel(Arc::new(graph));
+        Self {
+            graph_tx,
+            volume_curve: Mutex::new(VolumeCurve::default()),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let graph = serde_json::from_str(json).context("Failed to parse graph fixture")?;
+        Ok(Self::new(graph))
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read graph fixture {}", path.display()))?;
+        Self::from_json(&json)
+    }
+
+    /// Engine showing the first frame of a recording, to be followed by
+    /// [`replay`](Self::replay).
+    pub fn from_recording(frames: &[GraphFrame]) -> Result<Self> {
+        let first = frames
+            .first()
+            .ok_or_else(|| anyhow!("Graph recording has no frames"))?;
+        Ok(Self::new(first.graph.clone()))
+    }
+
+    /// Publishes the frames at their recorded offsets from now, overriding changes made in
+    /// the meantime.
+    pub async fn replay(&self, frames: Vec<GraphFrame>) {
+        let start = Instant::now();
+        for frame in frames {
+            sleep_until(start + Duration::from_millis(frame.elapsed_ms)).await;
+            let mut graph = frame.graph;
+            graph.apply_volume_curve(self.volume_curve());
+            self.replace_graph(graph);
+        }
+    }
+
+    /// Publishes a new graph to subscribers, as if PipeWire had reported it.
+    pub fn replace_graph(&self, graph: AudioGraph) {
+        self.graph_tx.send_replace(Arc::new(graph));
+    }
+
+    fn update<T>(&self, change: impl FnOnce(&mut AudioGraph) -> Result<T>) -> Result<T> {
+        let mut graph = AudioGraph::clone(&self.graph_tx.borrow());
+        let result = change(&mut graph)?;
+        self.replace_graph(graph);
+        Ok(result)
+    }
+
+    fn volume_curve(&self) -> VolumeCurve {
+        self.volume_curve
+            .lock()
+            .map_or(VolumeCurve::default(), |curve| *curve)
+    }
+
+    fn ready<T: Send + 'static>(result: Result<T>) -> SourceFuture<'static, T> {
+        Box::pin(std::future::ready(result))
+    }
+}
+
+fn node_mut(graph: &mut AudioGraph, node_id: u32) -> Result<&mut Node, PwError> {
+    graph.nodes.get_mut(&node_id).ok_or(PwError::NotFound {
+        kind: ObjectKind::Node,
+        id: node_id,
+    })
+}
+
+fn set_node_volume(
+    graph: &mut AudioGraph,
+    node_id: u32,
+    volume: f32,
+    curve: VolumeCurve,
+) -> Result<()> {
+    let node = node_mut(graph, node_id)?;
+    node.volume = Volume::on_curve(curve, volume, node.volume.muted);
+    let raw = node.volume.raw();
+    node.channel_volumes
+        .iter_mut()
+        .for_each(|channel| *channel = raw);
+    Ok(())
+}
+
+fn set_node_mute(graph: &mut AudioGraph, node_id: u32, mute: bool) -> Result<()> {
+    node_mut(graph, node_id)?.volume.muted = mute;
+    Ok(())
+}
+
+fn set_device_route(
+    graph: &mut AudioGraph,
+    device_id: u32,
+    direction: Option<RouteDirection>,
+    card_device: Option<i32>,
+    volume: Option<f32>,
+    mute: Option<bool>,
+    curve: VolumeCurve,
+) -> Result<()> {
+    let device = graph.devices.get_mut(&device_id).ok_or(PwError::NotFound {
+        kind: ObjectKind::Device,
+        id: device_id,
+    })?;
+    let node_type = match direction {
+        Some(RouteDirection::Output) => NodeType::AudioSink,
+        Some(RouteDirection::Input) => NodeType::AudioSource,
+        None => return Err(PwError::NoRouteInfo { device_id }.into()),
+    };
+    let route_device = direction
+        .map(|direction| device.route(direction, card_device))
+        .filter(|route| route.is_available() && route.has_volume())
+        .and_then(|route| route.device)
+        .ok_or(PwError::NoRouteInfo { device_id })?;
+
+    let (routes, last_route) = match direction {
+        Some(RouteDirection::Output) => (&mut device.output_routes, &mut device.output_route),
+        _ => (&mut device.input_routes, &mut device.input_route),
+    };
+    let routes = routes
+        .get_mut(&route_device)
+        .into_iter()
+        .chain(Some(last_route).filter(|route| route.device == Some(route_device)));
+    for route in routes {
+        if let Some(volume) = volume {
+            route.volume = Some(volume);
+            let raw = VolumeResolver::apply_inverse_curve(curve, volume);
+            route
+                .channel_volumes
+                .iter_mut()
+                .for_each(|channel| *channel = raw);
+        }
+        if let Some(mute) = mute {
+            route.muted = Some(mute);
+        }
+    }
+
+    // PipeWire reports route changes on the nodes the route drives too
+    let node_ids: Vec<u32> = device
+        .nodes
+        .iter()
+        .filter(|id| {
+            graph.nodes.get(*id).is_some_and(|n| {
+                n.node_type == node_type
+                    && n.card_device().is_none_or(|device| device == route_device)
+            })
+        })
+        .copied()
+        .collect();
+    for node in node_ids {
+        if let Some(volume) = volume {
+            set_node_volume(graph, node, volume, curve)?;
+        }
+        if let Some(mute) = mute {
+            set_node_mute(graph, node, mute)?;
+        }
+    }
+    Ok(())
+}
+
+fn set_default(graph: &mut AudioGraph, node_id: u32, is_output: bool) -> Result<()> {
+    let node_type = node_mut(graph, node_id)?.node_type;
+    let expected = if is_output {
+        NodeType::AudioSink
+    } else {
+        NodeType::AudioSource
+    };
+    if node_type != expected && node_type != NodeType::AudioDuplex {
+        return Err(PwError::Unsupported(format!(
+            "Node {node_id} cannot be a default {}",
+            if is_output { "sink" } else { "source" }
+        ))
+        .into());
+    }
+
+    if is_output {
+        graph.default_sink = Some(node_id);
+    } else {
+        graph.default_source = Some(node_id);
+    }
+    let (sink, source) = (graph.default_sink, graph.default_source);
+    for node in graph.nodes.values_mut() {
+        node.is_default = Some(node.id) == sink || Some(node.id) == source;
+    }
+    Ok(())
+}
+
+fn set_stream_target(graph: &mut AudioGraph, stream_id: u32, target_id: Option<u32>) -> Result<()> {
+    let target_type = match node_mut(graph, stream_id)?.node_type {
+        NodeType::StreamOutputAudio => NodeType::AudioSink,
+        NodeType::StreamInputAudio => NodeType::AudioSource,
+        _ => return Err(PwError::Unsupported(format!("Node {stream_id} is not a stream")).into()),
+    };
+    let target_name = match target_id {
+        Some(target_id) => {
+            let target = node_mut(graph, target_id)?;
+            if target.node_type != target_type && target.node_type != NodeType::AudioDuplex {
+                return Err(PwError::Unsupported(format!(
+                    "Node {target_id} cannot be a target of stream {stream_id}"
+                ))
+                .into());
+            }
+            Some(target.name.clone())
+        }
+        None => None,
+    };
+
+    node_mut(graph, stream_id)?.target_object = target_name;
+    Ok(())
+}
+
+fn run_step(graph: &mut AudioGraph, step: &BatchStep, curve: VolumeCurve) -> Result<()> {
+    match *step {
+        BatchStep::SetNodeVolume { node_id, volume } => {
+            set_node_volume(graph, node_id, volume, curve)
+        }
+        BatchStep::SetNodeMute { node_id, mute } => set_node_mute(graph, node_id, mute),
+        BatchStep::SetDefaultSink { node_id } => set_default(graph, node_id, true),
+        BatchStep::SetDefaultSource { node_id } => set_default(graph, node_id, false),
+        // Fixtures do not route streams
+        BatchStep::MoveStreams { .. } => Ok(()),
+    }
+}
+
+fn node_ports(graph: &AudioGraph, node_id: u32, direction: PortDirection) -> Vec<&Port> {
+    let mut ports: Vec<&Port> = graph
+        .ports
+        .values()
+        .filter(|port| port.node_id == node_id && port.direction == direction)
+        .collect();
+    ports.sort_by_key(|port| port.id);
+    ports
+}
+
+fn create_link(
+    graph: &mut AudioGraph,
+    output_node: u32,
+    input_node: u32,
+    mapping: PortMapping,
+) -> Result<()> {
+    node_mut(graph, output_node)?;
+    node_mut(graph, input_node)?;
+
+    let pairs = map_ports(
+        &node_ports(graph, output_node, PortDirection::Output),
+        &node_ports(graph, input_node, PortDirection::Input),
+        mapping,
+    );
+    let mut next_id = graph
+        .nodes
+        .keys()
+        .chain(graph.devices.keys())
+        .chain(graph.ports.keys())
+        .chain(graph.links.keys())
+        .max()
+        .map_or(1, |id| id + 1);
+
+    for (output_port, input_port) in pairs {
+        graph.links.insert(
+            next_id,
+            Link {
+                id: next_id,
+                output_node,
+                output_port,
+                input_node,
+                input_port,
+                is_managed: true,
+            },
+        );
+        for port_id in [output_port, input_port] {
+            if let Some(port) = graph.ports.get_mut(&port_id) {
+                port.links.push(next_id);
+            }
+        }
+        next_id += 1;
+    }
+    Ok(())
+}
+
+fn remove_link(graph: &mut AudioGraph, output_node: u32, input_node: u32) -> Result<()> {
+    graph
+        .links
+        .retain(|_, link| link.output_node != output_node || link.input_node != input_node);
+    let links = &graph.links;
+    for port in graph.ports.values_mut() {
+        port.links.retain(|id| links.contains_key(id));
+    }
+    Ok(())
+}
+
+fn unsupported<T>(operation: &str) -> Result<T> {
+    Err(PwError::Unsupported(format!("{operation} is not supported by the mock engine")).into())
+}
+
+impl GraphSource for MockEngine {
+    fn graph(&self) -> Arc<AudioGraph> {
+        self.graph_tx.borrow().clone()
+    }
+
+    fn subscribe(&self) -> watch::Receiver<Arc<AudioGraph>> {
+        self.graph_tx.subscribe()
+    }
+
+    fn wait_for_initialization(&self) -> SourceFuture<'_, ()> {
+        let result = if self.graph().is_connected() {
+            Ok(())
+        } else {
+            Err(PwError::Unavailable.into())
+        };
+        Self::ready(result)
+    }
+
+    fn shutdown(&self) -> SourceFuture<'_, ()> {
+        Self::ready(Ok(()))
+    }
+
+    fn set_node_volume(&self, node_id: u32, volume: f32) -> SourceFuture<'_, ()> {
+        let curve = self.volume_curve();
+        Self::ready(self.update(|graph| set_node_volume(graph, node_id, volume, curve)))
+    }
+
+    fn set_node_mute(&self, node_id: u32, mute: bool) -> SourceFuture<'_, ()> {
+        Self::ready(self.update(|graph| set_node_mute(graph, node_id, mute)))
+    }
+
+    fn set_device_volume(
+        &self,
+        device_id: u32,
+        volume: f32,
+        direction: Option<RouteDirection>,
+        card_device: Option<i32>,
+    ) -> SourceFuture<'_, ()> {
+        let curve = self.volume_curve();
+        Self::ready(self.update(|graph| {
+            set_device_route(
+                graph,
+                device_id,
+                direction,
+                card_device,
+                Some(volume),
+                None,
+                curve,
+            )
+        }))
+    }
+
+    fn set_device_mute(
+        &self,
+        device_id: u32,
+        mute: bool,
+        direction: Option<RouteDirection>,
+        card_device: Option<i32>,
+    ) -> SourceFuture<'_, ()> {
+        let curve = self.volume_curve();
+        Self::ready(self.update(|graph| {
+            set_device_route(
+                graph,
+                device_id,
+                direction,
+                card_device,
+                None,
+                Some(mute),
+                curve,
+            )
+        }))
+    }
+
+    fn set_default_sink(&self, node_id: u32) -> SourceFuture<'_, ()> {
+        Self::ready(self.update(|graph| set_default(graph, node_id, true)))
+    }
+
+    fn set_default_source(&self, node_id: u32) -> SourceFuture<'_, ()> {
+        Self::ready(self.update(|graph| set_default(graph, node_id, false)))
+    }
+
+    fn move_streams_to(&self, _node_id: u32) -> SourceFuture<'_, usize> {
+        Self::ready(Ok(0))
+    }
+
+    fn set_stream_target(&self, stream_id: u32, target_id: Option<u32>) -> SourceFuture<'_, ()> {
+        Self::ready(self.update(|graph| set_stream_target(graph, stream_id, target_id)))
+    }
+
+    fn set_node_latency(&self, node_id: u32, latency: String) -> SourceFuture<'_, ()> {
+        Self::ready(self.update(|graph| {
+            node_mut(graph, node_id)?.latency = Some(latency);
+            Ok(())
+        }))
+    }
+
+    fn batch(&self, steps: Vec<BatchStep>) -> SourceFuture<'_, ()> {
+        // Like the engine, every step is attempted and failures are reported together
+        let mut failures = Vec::new();
+        let curve = self.volume_curve();
+        let _ = self.update(|graph| {
+            for step in &steps {
+                if let Err(e) = run_step(graph, step, curve) {
+                    failures.push(format!("{step:?}: {e}"));
+                }
+            }
+            Ok(())
+        });
+
+        let result = if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(failures.join("; ")))
+        };
+        Self::ready(result)
+    }
+
+    fn create_link(
+        &self,
+        output_node: u32,
+        input_node: u32,
+        mapping: PortMapping,
+    ) -> SourceFuture<'_, ()> {
+        Self::ready(self.update(|graph| create_link(graph, output_node, input_node, mapping)))
+    }
+
+    fn remove_link(&self, output_node: u32, input_node: u32) -> SourceFuture<'_, ()> {
+        Self::ready(self.update(|graph| remove_link(graph, output_node, input_node)))
+    }
+
+    fn switch_device_profile_with_restoration(
+        &self,
+        device_id: u32,
+        profile_index: u32,
+    ) -> SourceFuture<'_, ()> {
+        Self::ready(self.update(|graph| {
+            let device = graph.devices.get_mut(&device_id).ok_or(PwError::NotFound {
+                kind: ObjectKind::Device,
+                id: device_id,
+            })?;
+            if !device.profiles.iter().any(|p| p.index == profile_index) {
+                return Err(PwError::Unsupported(format!(
+                    "Profile {profile_index} not found for device {device_id}"
+                ))
+                .into());
+            }
+            device.current_profile_index = Some(profile_index);
+            Ok(())
+        }))
+    }
+
+    // Nothing is captured for restoration, as switches apply at once
+    fn cancel_profile_restoration(&self, _device_id: u32) -> SourceFuture<'_, ()> {
+        Self::ready(Ok(()))
+    }
+
+    fn configure_restoration(&self, _settings: RestorationSettings) -> SourceFuture<'_, ()> {
+        Self::ready(Ok(()))
+    }
+
+    fn set_preferred_nodes(&self, _names: Vec<String>) -> SourceFuture<'_, ()> {
+        Self::ready(Ok(()))
+    }
+
+    fn set_volume_curve(&self, curve: VolumeCurve) -> Result<()> {
+        if let Ok(mut volume_curve) = self.volume_curve.lock() {
+            *volume_curve = curve;
+        }
+        self.update(|graph| {
+            graph.apply_volume_curve(curve);
+            Ok(())
+        })
+    }
+
+    fn set_sample_rate(&self, sample_rate: u32) -> SourceFuture<'_, ()> {
+        Self::ready(self.update(|graph| {
+            graph.default_clock_rate = sample_rate;
+            Ok(())
+        }))
+    }
+
+    fn create_virtual_sink<'a>(&'a self, _description: &'a str) -> SourceFuture<'a, ()> {
+        Self::ready(unsupported("Creating virtual sinks"))
+    }
+
+    fn destroy_managed_node(&self, node_id: u32) -> SourceFuture<'_, ()> {
+        Self::ready(self.update(|graph| {
+            if !node_mut(graph, node_id)?.is_managed {
+                return Err(PwError::Unsupported(format!(
+                    "Node {node_id} was not created by pwmenu"
+                ))
+                .into());
+            }
+            graph.nodes.remove(&node_id);
+            Ok(())
+        }))
+    }
+
+    fn rename_managed_node<'a>(
+        &'a self,
+        node_id: u32,
+        description: &'a str,
+    ) -> SourceFuture<'a, ()> {
+        Self::ready(self.update(|graph| {
+            node_mut(graph, node_id)?.description = Some(description.to_string());
+            Ok(())
+        }))
+    }
+
+    fn destroy_all_managed(&self) -> SourceFuture<'_, usize> {
+        Self::ready(self.update(|graph| {
+            let (links, nodes) = managed_objects(graph);
+            for link_id in &links {
+                graph.links.remove(link_id);
+            }
+            for node_id in &nodes {
+                graph.nodes.remove(node_id);
+            }
+            Ok(links.len() + nodes.len())
+        }))
+    }
+
+    fn play_test_tone(&self, node_id: u32) -> SourceFuture<'_, ()> {
+        let result = if self.graph().nodes.contains_key(&node_id) {
+            Ok(())
+        } else {
+            Err(PwError::NotFound {
+                kind: ObjectKind::Node,
+                id: node_id,
+            }
+            .into())
+        };
+        Self::ready(result)
+    }
+
+    fn start_level_meter(&self, _node_id: u32) -> SourceFuture<'_, InputLevel> {
+        Self::ready(Ok(InputLevel::default()))
+    }
+
+    fn stop_level_meter(&self) -> SourceFuture<'_, ()> {
+        Self::ready(Ok(()))
+    }
+
+    fn sync_stream_meters(&self, node_ids: Vec<u32>) -> SourceFuture<'_, HashMap<u32, InputLevel>> {
+        Self::ready(Ok(node_ids
+            .into_iter()
+            .map(|id| (id, InputLevel::default()))
+            .collect()))
+    }
+
+    fn start_profiler(&self) -> SourceFuture<'_, ProfilerStats> {
+        Self::ready(unsupported("Profiling"))
+    }
+
+    fn stop_profiler(&self) -> SourceFuture<'_, ()> {
+        Self::ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pw::{
+        controller::Controller,
+        devices::{RouteInfo, RoutePort},
+        VolumeCurve,
+    };
+
+    const LAPTOP: &str = include_str!("../../tests/fixtures/laptop.json");
+    const USB_AND_BLUETOOTH: &str = include_str!("../../tests/fixtures/usb-and-bluetooth.json");
+    const NO_SESSION_MANAGER: &str = include_str!("../../tests/fixtures/no-session-manager.json");
+
+    /// Controller keeping its state in memory, away from the user's state file.
+    fn isolated(engine: Arc<MockEngine>) -> Controller {
+        Controller::with_state_file(engine, None)
+    }
+
+    fn controller(fixture: &str) -> Controller {
+        isolated(Arc::new(MockEngine::from_json(fixture).unwrap()))
+    }
+
+    #[tokio::test]
+    async fn fixtures_are_complete_graphs() {
+        for fixture in [LAPTOP, USB_AND_BLUETOOTH, NO_SESSION_MANAGER] {
+            let controller = controller(fixture);
+            controller.wait_for_initialization().await.unwrap();
+            assert!(controller.get_graph().is_connected());
+        }
+    }
+
+    #[tokio::test]
+    async fn route_volume_reaches_device_and_node() {
+        let controller = controller(LAPTOP);
+        controller.set_volume(52, 0.3).await.unwrap();
+
+        let graph = controller.get_graph();
+        assert_eq!(graph.devices[&45].output_route.volume, Some(0.3));
+        assert_eq!(graph.nodes[&52].volume.percent(), 30);
+        // The input route is left alone
+        assert_eq!(graph.devices[&45].input_route.volume, Some(0.8));
+    }
+
+    #[tokio::test]
+    async fn each_card_device_keeps_its_route_volume() {
+        let mut graph: AudioGraph = serde_json::from_str(LAPTOP).unwrap();
+        let mut hdmi = graph.nodes[&52].clone();
+        hdmi.id = 60;
+        hdmi.name = "alsa_output.pci-0000_00_1f.3.hdmi-stereo".to_string();
+        hdmi.properties
+            .insert("card.profile.device".to_string(), "3".to_string());
+        graph.nodes.insert(60, hdmi);
+        graph
+            .nodes
+            .get_mut(&52)
+            .unwrap()
+            .properties
+            .insert("card.profile.device".to_string(), "0".to_string());
+
+        let device = graph.devices.get_mut(&45).unwrap();
+        device.nodes.push(60);
+        let analog = device.output_route.clone();
+        device.output_routes.insert(0, analog.clone());
+        device.output_routes.insert(
+            3,
+            RouteInfo {
+                index: Some(5),
+                device: Some(3),
+                volume: Some(1.0),
+                ..analog
+            },
+        );
+        let controller = isolated(Arc::new(MockEngine::new(graph)));
+
+        controller.set_volume(60, 0.4).await.unwrap();
+        assert_eq!(controller.get_node(60).unwrap().volume.percent(), 40);
+        assert_eq!(controller.get_node(52).unwrap().volume.percent(), 60);
+
+        let graph = controller.get_graph();
+        assert_eq!(graph.devices[&45].output_routes[&3].volume, Some(0.4));
+        assert_eq!(graph.devices[&45].output_routes[&0].volume, Some(0.6));
+    }
+
+    #[tokio::test]
+    async fn input_without_route_volume_uses_its_node() {
+        let mut graph: AudioGraph = serde_json::from_str(LAPTOP).unwrap();
+        let device = graph.devices.get_mut(&45).unwrap();
+        device.input_route.volume = None;
+        device.input_route.muted = None;
+        let controller = isolated(Arc::new(MockEngine::new(graph)));
+
+        controller.set_volume(53, 0.5).await.unwrap();
+        controller.set_mute(53, true).await.unwrap();
+        let source = controller.get_node(53).unwrap();
+        assert_eq!(source.volume.percent(), 50);
+        assert!(source.volume.muted);
+
+        // The output route keeps its own state
+        let sink = controller.get_node(52).unwrap();
+        assert_eq!(sink.volume.percent(), 60);
+        assert!(!sink.volume.muted);
+        assert_eq!(controller.get_graph().devices[&45].input_route.volume, None);
+    }
+
+    #[tokio::test]
+    async fn volumes_are_read_and_written_on_the_curve() {
+        let controller = controller(LAPTOP).with_volume_curve(VolumeCurve::Linear);
+
+        controller.set_volume(81, 0.5).await.unwrap();
+        let volume = controller.get_node(81).unwrap().volume;
+        assert_eq!(volume.linear, 0.5);
+        assert_eq!(volume.raw(), 0.5);
+
+        // Switching the curve keeps the amplitude and shows it on the new curve
+        controller.set_volume_curve(VolumeCurve::Cubic);
+        let volume = controller.get_node(81).unwrap().volume;
+        assert!((volume.linear - 0.5_f32.cbrt()).abs() < 1e-6);
+        assert_eq!(volume.raw(), 0.5);
+    }
+
+    #[tokio::test]
+    async fn large_volume_increases_are_capped() {
+        let controller = isolated(Arc::new(MockEngine::from_json(LAPTOP).unwrap()))
+            .with_max_volume_jump(Some(20));
+
+        controller.set_volume(52, 1.5).await.unwrap();
+        assert_eq!(controller.get_node(52).unwrap().volume.percent(), 80);
+
+        controller
+            .batch(vec![BatchStep::SetNodeVolume {
+                node_id: 81,
+                volume: 2.0,
+            }])
+            .await
+            .unwrap();
+        assert_eq!(controller.get_node(81).unwrap().volume.percent(), 120);
+
+        // Lowering is never held back
+        controller.set_volume(52, 0.1).await.unwrap();
+        assert_eq!(controller.get_node(52).unwrap().volume.percent(), 10);
+    }
+
+    #[tokio::test]
+    async fn stream_volume_and_mute_apply_to_node() {
+        let controller = controller(LAPTOP);
+        controller.set_volume(81, 0.5).await.unwrap();
+        controller.set_mute(81, true).await.unwrap();
+
+        let volume = &controller.get_graph().nodes[&81].volume;
+        assert_eq!(volume.percent(), 50);
+        assert!(volume.muted);
+    }
+
+    #[tokio::test]
+    async fn diff_summarizes_volume_and_default_changes() {
+        let controller = controller(USB_AND_BLUETOOTH);
+        let before = controller.get_graph();
+
+        controller.set_volume(110, 0.3).await.unwrap();
+        controller.set_default_sink(130).await.unwrap();
+
+        let diff = controller.diff(&before, &controller.get_graph());
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        let sink = diff.default_sink.as_ref().unwrap();
+        assert_eq!((sink.old, sink.new), (Some(110), Some(130)));
+        assert_eq!(sink.node.as_ref().map(|node| node.id), Some(130));
+        assert!(diff.default_source.is_none());
+
+        let volume = diff
+            .volume_changes
+            .iter()
+            .find(|change| change.node.id == 110)
+            .unwrap();
+        assert!(volume.volume_changed() && !volume.mute_toggled());
+        assert_eq!(volume.node.volume.percent(), 30);
+
+        assert!(controller
+            .diff(&controller.get_graph(), &controller.get_graph())
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn cached_devices_stand_in_until_the_graph_is_listed() {
+        let live = controller(LAPTOP).get_graph();
+        let cached = crate::pw::cache::trim(&live);
+        assert!(cached.nodes.contains_key(&52) && !cached.nodes.contains_key(&81));
+        assert!(cached.links.is_empty() && cached.ports.is_empty());
+
+        let engine = Arc::new(MockEngine::new(AudioGraph::default()));
+        let controller = isolated(engine.clone()).with_cached_graph(cached.clone());
+        assert!(controller.is_showing_cache());
+        assert_eq!(controller.get_default_sink(), Some(52));
+
+        engine.replace_graph((*live).clone());
+        assert!(!controller.is_showing_cache());
+        assert!(controller.get_graph().nodes.contains_key(&81));
+    }
+
+    #[tokio::test]
+    async fn default_sink_moves_between_devices() {
+        let controller = controller(USB_AND_BLUETOOTH);
+        assert_eq!(controller.get_default_sink(), Some(110));
+
+        controller.set_default_sink(130).await.unwrap();
+
+        let graph = controller.get_graph();
+        assert_eq!(graph.default_sink, Some(130));
+        assert!(graph.nodes[&130].is_default);
+        assert!(!graph.nodes[&110].is_default);
+        // The default source is unaffected
+        assert!(graph.nodes[&53].is_default);
+    }
+
+    #[tokio::test]
+    async fn profile_switch_is_confirmed_by_the_device() {
+        let controller = controller(LAPTOP);
+        controller.switch_device_profile(45, 2).await.unwrap();
+        controller.wait_for_profile(45, 2).await.unwrap();
+        assert_eq!(
+            controller.get_device_current_profile(45).map(|p| p.index),
+            Some(2)
+        );
+
+        // Off and unavailable profiles are kept out of the regular list
+        let profiles = controller.get_device_profiles(45);
+        assert!(profiles.iter().all(|p| p.index != 0 && p.index != 4));
+        assert_eq!(
+            controller.get_device_off_profile(45).map(|p| p.index),
+            Some(0)
+        );
+        let unavailable = controller.get_device_unavailable_profiles(45);
+        assert_eq!(unavailable.iter().map(|p| p.index).collect::<Vec<_>>(), [4]);
+
+        controller.switch_device_profile(45, 0).await.unwrap();
+        assert!(controller.is_device_off(45));
+    }
+
+    #[tokio::test]
+    async fn defaults_need_a_session_manager() {
+        let controller = controller(NO_SESSION_MANAGER);
+        let err = controller.set_default_sink(32).await.unwrap_err();
+        assert_eq!(PwError::find(&err), Some(&PwError::NoSessionManager));
+    }
+
+    #[tokio::test]
+    async fn missing_nodes_are_reported() {
+        let controller = controller(LAPTOP);
+        let err = controller.set_mute(999, true).await.unwrap_err();
+        assert_eq!(
+            PwError::find(&err),
+            Some(&PwError::NotFound {
+                kind: ObjectKind::Node,
+                id: 999
+            })
+        );
+    }
+
+    #[test]
+    fn nodes_are_found_by_name_glob_or_substring() {
+        let controller = controller(USB_AND_BLUETOOTH);
+        let find = |pattern| controller.find_node(pattern).map(|node| node.id);
+
+        assert_eq!(find("alsa_input.pci-0000_00_1f.3.analog-stereo"), Some(53));
+        assert_eq!(find("WH-1000XM4"), Some(130));
+        assert_eq!(find("g435*stereo"), Some(110));
+        assert_eq!(find("bluez_?utput.*"), Some(130));
+        assert_eq!(find("firefox"), Some(81));
+        assert_eq!(find("*nothing*"), None);
+    }
+
+    #[test]
+    fn jacks_and_active_ports_come_from_routes() {
+        let controller = controller(LAPTOP);
+
+        let sink = controller.get_node(52).unwrap();
+        let port = controller.get_active_route_port(&sink).unwrap();
+        assert_eq!(port.description, "Speakers");
+        assert_eq!(port.is_plugged(), None);
+
+        // Speakers and the internal microphone cannot be unplugged
+        let jacks = controller.get_jacks();
+        assert_eq!(jacks.len(), 1);
+        assert_eq!(jacks[0].device_id, 45);
+        assert_eq!(jacks[0].port.description, "Headphones");
+        assert_eq!(jacks[0].port.is_plugged(), Some(false));
+    }
+
+    #[test]
+    fn hdmi_outputs_are_named_after_the_display() {
+        let mut graph: AudioGraph = serde_json::from_str(LAPTOP).unwrap();
+        graph
+            .devices
+            .get_mut(&45)
+            .unwrap()
+            .route_ports
+            .push(RoutePort {
+                index: 5,
+                direction: RouteDirection::Output,
+                name: "hdmi-output-0".to_string(),
+                description: "HDMI / DisplayPort".to_string(),
+                available: "yes".to_string(),
+                devices: vec![3],
+                port_type: Some("hdmi".to_string()),
+                monitor_name: Some("DELL U2720Q".to_string()),
+            });
+        let sink = graph.nodes.get_mut(&52).unwrap();
+        sink.properties
+            .insert("card.profile.device".to_string(), "3".to_string());
+        let controller = isolated(Arc::new(MockEngine::new(graph)));
+
+        let sink = controller.get_node(52).unwrap();
+        assert_eq!(controller.get_node_base_name(&sink), "HDMI – DELL U2720Q");
+    }
+
+    #[tokio::test]
+    async fn streams_can_be_pinned_and_released() {
+        let controller = controller(LAPTOP);
+        assert!(controller.get_node(81).unwrap().follows_default());
+
+        controller.set_stream_target(81, Some(52)).await.unwrap();
+        let stream = controller.get_node(81).unwrap();
+        assert!(!stream.follows_default());
+        assert_eq!(
+            controller.get_stream_target(&stream).map(|n| n.id),
+            Some(52)
+        );
+
+        // A playback stream cannot record from a source
+        assert!(controller.set_stream_target(81, Some(53)).await.is_err());
+
+        controller.set_stream_target(81, None).await.unwrap();
+        assert!(controller.get_node(81).unwrap().follows_default());
+    }
+
+    #[tokio::test]
+    async fn input_boost_stops_at_full_volume_first() {
+        let controller = controller(LAPTOP);
+        controller.set_volume(53, 0.9).await.unwrap();
+        let source = controller.get_node(53).unwrap();
+        assert_eq!(source.volume_limit(1.5), 1.0);
+        assert_eq!(source.max_volume(1.5), 1.5);
+
+        controller.set_volume(53, 1.0).await.unwrap();
+        let source = controller.get_node(53).unwrap();
+        assert_eq!(source.volume_limit(1.5), 1.5);
+        assert!(!source.is_boosted());
+
+        // Outputs are not limited by the input maximum
+        assert_eq!(controller.get_node(52).unwrap().volume_limit(1.5), 2.0);
+    }
+
+    #[tokio::test]
+    async fn volume_muted_at_zero_comes_back_when_raised() {
+        // The volume is read back from the state file, so it needs one of its own
+        let state_dir =
+            std::env::temp_dir().join(format!("pwmenu-tests-{}-muted-at-zero", std::process::id()));
+        let controller = Controller::with_state_file(
+            Arc::new(MockEngine::from_json(LAPTOP).unwrap()),
+            Some(state_dir.join("state.json")),
+        );
+        controller.set_volume(52, 0.3).await.unwrap();
+
+        let sink = controller.get_node(52).unwrap();
+        let volume = controller
+            .set_volume_muting_at_zero(&sink, 0.0, false, 1.0)
+            .await
+            .unwrap();
+        assert_eq!(volume, 0.0);
+        let sink = controller.get_node(52).unwrap();
+        assert!(sink.volume.muted);
+
+        let volume = controller
+            .set_volume_muting_at_zero(&sink, 0.05, true, 1.0)
+            .await
+            .unwrap();
+        assert!((volume - 0.3).abs() < 1e-6);
+        let sink = controller.get_node(52).unwrap();
+        assert!(!sink.volume.muted);
+        assert_eq!(sink.volume.percent(), 30);
+
+        // Unmuting any other way forgets the volume
+        controller
+            .set_volume_muting_at_zero(&sink, 0.0, false, 1.0)
+            .await
+            .unwrap();
+        controller.set_mute(52, false).await.unwrap();
+        controller.set_mute(52, true).await.unwrap();
+        let sink = controller.get_node(52).unwrap();
+        assert_eq!(controller.take_muted_volume(&sink), None);
+
+        std::fs::remove_dir_all(state_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn updates_reach_subscribers() {
+        let engine = MockEngine::from_json(LAPTOP).unwrap();
+        let mut graph_rx = engine.subscribe();
+        graph_rx.borrow_and_update();
+
+        GraphSource::set_node_mute(&engine, 52, true).await.unwrap();
+
+        assert!(graph_rx.has_changed().unwrap());
+        assert!(graph_rx.borrow().nodes[&52].volume.muted);
+    }
+}
@@ -1,23 +1,87 @@
 use libspa::{
     pod::builder::Builder,
-    sys::{spa_pod_frame, SPA_PARAM_Props, SPA_PROP_mute},
+    sys::{spa_pod_frame, SPA_PARAM_Props, SPA_PROP_mute, SPA_PROP_params},
 };
 use pipewire::spa::{
-    param::ParamType,
+    param::{
+        audio::AudioInfoRaw,
+        format::{MediaSubtype, MediaType},
+        format_utils::parse_format,
+        ParamType,
+    },
     pod::{deserialize::PodDeserializer, Pod, Value},
 };
 use serde::{Deserialize, Serialize};
-use std::{cell::RefCell, mem::MaybeUninit, rc::Rc};
+use std::{collections::BTreeMap, mem::MaybeUninit, rc::Rc};
 
 use anyhow::{anyhow, Context as AnyhowContext, Result};
-use log::{debug, error, warn};
-use tokio::sync::watch;
+use tracing::{debug, warn};
 
 use crate::pw::{
-    graph::{AudioGraph, Store},
-    volume::VolumeResolver,
+    error::{ObjectKind, PwError},
+    graph::{OwnedPod, QueuedEvent, Store},
+    managed::is_managed_props,
+    volume::{VolumeCurve, VolumeResolver, VolumeScale},
 };
 
+/// Node properties shown in the properties inspector.
+const INSPECTED_NODE_PROPS: &[&str] = &[
+    "object.serial",
+    "factory.name",
+    "api.alsa.path",
+    "api.alsa.pcm.card",
+    "api.alsa.pcm.stream",
+    "card.profile.device",
+    "api.bluez5.codec",
+    "audio.rate",
+    "audio.channels",
+    "audio.position",
+    "audio.format",
+    "node.max-latency",
+    "node.driver",
+    "priority.session",
+    "target.object",
+];
+
+/// Copies the values of the listed keys present in `props`.
+pub(crate) fn collect_props(
+    props: &pipewire::spa::utils::dict::DictRef,
+    keys: &[&str],
+) -> BTreeMap<String, String> {
+    keys.iter()
+        .filter_map(|&key| {
+            props
+                .get(key)
+                .map(|value| (key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Properties read from a node's info. The registry global only carries a whitelist of keys,
+/// which leaves out `pwmenu.managed` among others.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeInfoProps {
+    pub description: Option<String>,
+    pub properties: BTreeMap<String, String>,
+    pub latency: Option<String>,
+    pub media_name: Option<String>,
+    pub is_managed: bool,
+}
+
+impl NodeInfoProps {
+    pub fn from_props(props: &pipewire::spa::utils::dict::DictRef) -> Self {
+        Self {
+            description: props
+                .get(*pipewire::keys::NODE_DESCRIPTION)
+                .map(str::to_string),
+            properties: collect_props(props, INSPECTED_NODE_PROPS),
+            latency: props.get("node.latency").map(str::to_string),
+            media_name: props.get("media.name").map(str::to_string),
+            is_managed: is_managed_props(props),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NodeType {
     AudioSink,
@@ -30,22 +94,95 @@ pub enum NodeType {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "SavedVolume")]
 pub struct Volume {
-    pub linear: f32, // 0.0 - 1.0
+    pub linear: f32, // 0.0 - 1.0, on the volume curve
     pub muted: bool,
+    // Kept with the volume so saved and cached ones do not depend on the curve
+    raw: f32,
+}
+
+/// Volumes saved before the raw amplitude was kept along were all on the cubic curve.
+#[derive(Deserialize)]
+struct SavedVolume {
+    linear: f32,
+    muted: bool,
+    raw: Option<f32>,
+}
+
+impl From<SavedVolume> for Volume {
+    fn from(saved: SavedVolume) -> Self {
+        match saved.raw {
+            Some(raw) => Self {
+                linear: saved.linear.clamp(0.0, 2.0),
+                muted: saved.muted,
+                raw,
+            },
+            None => Self::new(saved.linear, saved.muted),
+        }
+    }
 }
 
 impl Volume {
+    /// Volume on the cubic curve, see [`Volume::on_curve`] for others.
     pub fn new(linear: f32, muted: bool) -> Self {
+        Self::on_curve(VolumeCurve::Cubic, linear, muted)
+    }
+
+    pub fn on_curve(curve: VolumeCurve, linear: f32, muted: bool) -> Self {
+        let linear = linear.clamp(0.0, 2.0);
+        Self {
+            linear,
+            muted,
+            raw: VolumeResolver::apply_inverse_curve(curve, linear),
+        }
+    }
+
+    /// Volume for an amplitude read from PipeWire, on `curve`.
+    pub fn from_raw(curve: VolumeCurve, raw: f32, muted: bool) -> Self {
         Self {
-            linear: linear.clamp(0.0, 2.0),
+            linear: VolumeResolver::apply_curve(curve, raw).clamp(0.0, 2.0),
             muted,
+            raw,
         }
     }
 
+    /// The same amplitude on `curve`, for volumes read back from a file.
+    pub fn with_curve(&self, curve: VolumeCurve) -> Self {
+        Self::from_raw(curve, self.raw, self.muted)
+    }
+
     pub fn percent(&self) -> u8 {
         (self.linear * 100.0).round() as u8
     }
+
+    /// Percentage in the given scale; linear values can go well past 200%.
+    pub fn percent_in(&self, scale: VolumeScale) -> u16 {
+        let value = match scale {
+            VolumeScale::Cubic => self.linear,
+            VolumeScale::Linear => self.raw,
+        };
+        (value * 100.0).round() as u16
+    }
+
+    /// Amplitude factor as stored by PipeWire in `channelVolumes`.
+    pub fn raw(&self) -> f32 {
+        self.raw
+    }
+}
+
+/// Negotiated stream format of a node, read from its `Format` param.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SampleSpec {
+    pub rate: u32,
+    pub channels: u32,
+    pub format: String,
+}
+
+impl std::fmt::Display for SampleSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} Hz {}ch", self.format, self.rate, self.channels)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,17 +199,80 @@ pub struct Node {
     pub device_id: Option<u32>,
     pub ports: Vec<u32>,
     pub media_name: Option<String>,
+    pub is_managed: bool,
+    /// Raw per-channel amplitudes from `channelVolumes`, before the volume curve.
+    pub channel_volumes: Vec<f32>,
+    /// Selected PipeWire properties such as the ALSA path or latency, for inspection.
+    pub properties: BTreeMap<String, String>,
+    /// Only known while the node is running with a negotiated format.
+    pub sample_spec: Option<SampleSpec>,
+    /// Requested latency as `node.latency`, e.g. `1024/48000`.
+    pub latency: Option<String>,
+    /// Node a stream is pinned to through the `target.object` metadata.
+    #[serde(default)]
+    pub target_object: Option<String>,
+}
+
+/// Highest volume pwmenu sets, 200%.
+pub const MAX_VOLUME: f32 = 2.0;
+
+impl Node {
+    pub fn is_input(&self) -> bool {
+        matches!(
+            self.node_type,
+            NodeType::AudioSource | NodeType::StreamInputAudio
+        )
+    }
+
+    /// Highest volume of the node; inputs go past 100% up to `max_input` only.
+    pub fn max_volume(&self, max_input: f32) -> f32 {
+        if self.is_input() {
+            max_input.clamp(1.0, MAX_VOLUME)
+        } else {
+            MAX_VOLUME
+        }
+    }
+
+    /// Highest volume a step can reach from the current one. Inputs stop at 100% on the way
+    /// to their maximum, so a boost is never reached by accident.
+    pub fn volume_limit(&self, max_input: f32) -> f32 {
+        if self.is_input() && self.volume.percent() < 100 {
+            1.0
+        } else {
+            self.max_volume(max_input)
+        }
+    }
+
+    /// Whether the node is an input boosted past 100%.
+    pub fn is_boosted(&self) -> bool {
+        self.is_input() && self.volume.percent() > 100
+    }
+
+    /// Card device the node is driven through, matching the `device` of its route.
+    pub fn card_device(&self) -> Option<i32> {
+        self.properties
+            .get("card.profile.device")
+            .and_then(|device| device.parse().ok())
+    }
+
+    /// Whether the session manager moves the stream along with the default device, which
+    /// is the case until a target is set in the metadata or by the application.
+    pub fn follows_default(&self) -> bool {
+        self.target_object.is_none() && !self.properties.contains_key("target.object")
+    }
 }
 
 pub struct NodeInternal {
     pub id: u32,
+    pub serial: Option<String>,
     pub name: String,
     pub nick: Option<String>,
     pub description: Option<String>,
     pub media_class: Option<String>,
     pub application_name: Option<String>,
     pub node_type: NodeType,
-    pub volume: f32,
+    /// Amplitude as stored by PipeWire, put on the volume curve by [`NodeInternal::to_node`].
+    pub raw_volume: f32,
     pub muted: bool,
     pub is_default: bool,
     pub device_id: Option<u32>,
@@ -80,13 +280,65 @@ pub struct NodeInternal {
     pub proxy: pipewire::node::Node,
     pub listener: Option<pipewire::node::NodeListener>,
     pub info_listener: Option<pipewire::node::NodeListener>,
+    /// Tells this node apart from a later one reusing its id, see [`Store::next_generation`].
+    pub generation: u64,
     pub has_received_params: bool,
     pub media_name: Option<String>,
     pub channel_count: usize,
+    pub channel_volumes: Vec<f32>,
+    pub is_managed: bool,
+    pub properties: BTreeMap<String, String>,
+    pub sample_spec: Option<SampleSpec>,
+    pub latency: Option<String>,
 }
 
 impl NodeInternal {
-    pub fn to_node(&self) -> Node {
+    /// Unregisters the listeners while the proxy they listen on is still alive.
+    pub fn teardown(&mut self) {
+        self.listener = None;
+        self.info_listener = None;
+    }
+
+    /// Applies the properties of the node's info, returning whether any of them changed.
+    pub fn apply_info_props(&mut self, info: NodeInfoProps) -> bool {
+        let mut updated = false;
+
+        // Virtual sinks are renamed in place
+        if info.description.is_some() && self.description != info.description {
+            self.description = info.description;
+            updated = true;
+        }
+
+        if self.properties != info.properties {
+            self.properties = info.properties;
+            updated = true;
+        }
+
+        if self.latency != info.latency {
+            self.latency = info.latency;
+            updated = true;
+        }
+
+        if self.is_managed != info.is_managed {
+            self.is_managed = info.is_managed;
+            updated = true;
+        }
+
+        // Players change the media name of their stream as tracks go by
+        if matches!(
+            self.node_type,
+            NodeType::StreamOutputAudio | NodeType::StreamInputAudio
+        ) && info.media_name.is_some()
+            && self.media_name != info.media_name
+        {
+            self.media_name = info.media_name;
+            updated = true;
+        }
+
+        updated
+    }
+
+    pub fn to_node(&self, curve: VolumeCurve) -> Node {
         Node {
             id: self.id,
             name: self.name.clone(),
@@ -95,11 +347,17 @@ impl NodeInternal {
             media_class: self.media_class.clone(),
             application_name: self.application_name.clone(),
             node_type: self.node_type,
-            volume: Volume::new(self.volume, self.muted),
+            volume: Volume::from_raw(curve, self.raw_volume, self.muted),
             is_default: self.is_default,
             device_id: self.device_id,
             ports: self.ports.clone(),
             media_name: self.media_name.clone(),
+            is_managed: self.is_managed,
+            channel_volumes: self.channel_volumes.clone(),
+            properties: self.properties.clone(),
+            sample_spec: self.sample_spec.clone(),
+            latency: self.latency.clone(),
+            target_object: None,
         }
     }
 }
@@ -109,8 +367,6 @@ impl Store {
         &mut self,
         registry: &Rc<pipewire::registry::RegistryRc>,
         global: &pipewire::registry::GlobalObject<&pipewire::spa::utils::dict::DictRef>,
-        store_rc: &Rc<RefCell<Store>>,
-        graph_tx: &watch::Sender<AudioGraph>,
     ) -> Result<()> {
         let props = global
             .props
@@ -154,13 +410,16 @@ impl Store {
 
         let mut node = NodeInternal {
             id: global.id,
+            serial: props
+                .get(*pipewire::keys::OBJECT_SERIAL)
+                .map(str::to_string),
             name: name.clone(),
             nick,
             description,
             media_class,
             application_name,
             node_type,
-            volume: 1.0,
+            raw_volume: 1.0,
             muted: false,
             is_default: (node_type == NodeType::AudioSink && self.default_sink == Some(global.id))
                 || (node_type == NodeType::AudioSource && self.default_source == Some(global.id)),
@@ -169,101 +428,54 @@ impl Store {
             proxy,
             listener: None,
             info_listener: None,
+            generation: self.next_generation(),
             has_received_params: false,
             media_name,
             channel_count: 0,
+            channel_volumes: Vec::new(),
+            // Known once the info arrives, the global leaves the property out
+            is_managed: false,
+            properties: collect_props(props, INSPECTED_NODE_PROPS),
+            sample_spec: None,
+            latency: props.get("node.latency").map(str::to_string),
         };
 
-        let store_weak = Rc::downgrade(store_rc);
-        let graph_tx_clone = graph_tx.clone();
-
         let listener = node
-           .proxy
-           .add_listener_local()
-           .param({
-               let store_weak = store_weak.clone();
-               let graph_tx = graph_tx_clone.clone();
-               let node_id = global.id;
-
-               move |_seq, _param_type, _index, _next, pod_opt: Option<&pipewire::spa::pod::Pod>| {
-                   if let Some(actual_pod) = pod_opt {
-                       if let Some(upgraded_store_rc) = store_weak.upgrade() {
-                           let updated = {
-                               let mut store_borrow = match upgraded_store_rc.try_borrow_mut() {
-                                   Ok(s) => s,
-                                   Err(e) => {
-                                       error!("Failed to borrow store in node param cb {node_id}: {e}");
-                                       return;
-                                   }
-                               };
-                               let result = store_borrow.update_node_param(node_id, actual_pod);
-
-                               if result {
-                                   if let Some(node) = store_borrow.nodes.get(&node_id) {
-                                       if let Some(device_id) = node.device_id {
-                                           if let Some(device) = store_borrow.devices.get(&device_id) {
-                                               if device.has_route_volume {
-                                                   device.proxy.enum_params(0, Some(ParamType::Route), 0, u32::MAX);
-                                               }
-                                           }
-                                       }
-                                   }
-                               }
-
-                               result
-                           };
-                           if updated {
-                               crate::pw::graph::update_graph(&upgraded_store_rc, &graph_tx);
-                           }
-                       }
-                   }
-               }
-           })
-           .register();
+            .proxy
+            .add_listener_local()
+            .param({
+                let event_queue = self.event_queue.clone();
+                let node_id = global.id;
+                let generation = node.generation;
+
+                move |_seq, param_type, _index, _next, pod_opt: Option<&pipewire::spa::pod::Pod>| {
+                    if let Some(pod) = pod_opt {
+                        event_queue.borrow_mut().push_back(QueuedEvent::NodeParam {
+                            node_id,
+                            generation,
+                            param_type,
+                            pod: OwnedPod::new(pod),
+                        });
+                    }
+                }
+            })
+            .register();
 
         let info_listener = node
             .proxy
             .add_listener_local()
             .info({
-                let store_weak = store_weak.clone();
-                let graph_tx = graph_tx_clone.clone();
+                let event_queue = self.event_queue.clone();
                 let node_id = global.id;
+                let generation = node.generation;
 
                 move |info| {
-                    if let Some(store_rc) = store_weak.upgrade() {
-                        let updated = match store_rc.try_borrow_mut() {
-                            Ok(mut store) => {
-                                if let Some(node) = store.nodes.get_mut(&node_id) {
-                                    let mut node_updated = false;
-
-                                    if let Some(props) = info.props() {
-                                        if matches!(
-                                            node.node_type,
-                                            NodeType::StreamOutputAudio
-                                                | NodeType::StreamInputAudio
-                                        ) {
-                                            if let Some(media_name) =
-                                                props.get("media.name").map(str::to_string)
-                                            {
-                                                if node.media_name != Some(media_name.clone()) {
-                                                    node.media_name = Some(media_name);
-                                                    node_updated = true;
-                                                }
-                                            }
-                                        }
-                                    }
-
-                                    node_updated
-                                } else {
-                                    false
-                                }
-                            }
-                            Err(_) => false,
-                        };
-
-                        if updated {
-                            crate::pw::graph::update_graph(&store_rc, &graph_tx);
-                        }
+                    if let Some(props) = info.props() {
+                        event_queue.borrow_mut().push_back(QueuedEvent::NodeInfo {
+                            node_id,
+                            generation,
+                            props: NodeInfoProps::from_props(props),
+                        });
                     }
                 }
             })
@@ -273,10 +485,11 @@ impl Store {
         node.info_listener = Some(info_listener);
 
         node.proxy
-            .subscribe_params(&[pipewire::spa::param::ParamType::Props]);
+            .subscribe_params(&[ParamType::Props, ParamType::Format]);
 
         self.nodes.insert(global.id, node);
-        log::debug!("Added node {}: '{}'", global.id, name);
+        self.track_param_sync(global.id);
+        tracing::debug!("Added node {}: '{}'", global.id, name);
 
         if let Some(dev_id) = device_id {
             if let Some(device) = self.devices.get_mut(&dev_id) {
@@ -289,6 +502,69 @@ impl Store {
         Ok(())
     }
 
+    /// Applies a Props or Format param of a node. A volume change reported on the node of a
+    /// card with route volumes has the card's routes read again.
+    pub fn handle_node_parameter(
+        &mut self,
+        node_id: u32,
+        param_type: ParamType,
+        pod: &Pod,
+    ) -> bool {
+        if param_type == ParamType::Format {
+            return self.update_node_format(node_id, pod);
+        }
+
+        let updated = self.update_node_param(node_id, pod);
+        if updated {
+            let device = self
+                .nodes
+                .get(&node_id)
+                .and_then(|node| node.device_id)
+                .and_then(|device_id| self.devices.get(&device_id))
+                .filter(|device| device.has_route_volume);
+            if let Some(device) = device {
+                device
+                    .proxy
+                    .enum_params(0, Some(ParamType::Route), 0, u32::MAX);
+            }
+        }
+        updated
+    }
+
+    /// Records the node's negotiated format; only raw audio formats are understood.
+    pub fn update_node_format(&mut self, node_id: u32, pod: &Pod) -> bool {
+        let Some(node) = self.nodes.get_mut(&node_id) else {
+            return false;
+        };
+
+        let sample_spec = match parse_format(pod) {
+            Ok((MediaType::Audio, MediaSubtype::Raw)) => {
+                let mut info = AudioInfoRaw::new();
+                match info.parse(pod) {
+                    Ok(_) => Some(SampleSpec {
+                        rate: info.rate(),
+                        channels: info.channels(),
+                        format: format!("{:?}", info.format())
+                            .trim_start_matches("AudioFormat::")
+                            .to_string(),
+                    }),
+                    Err(e) => {
+                        debug!("Failed to parse format of node {node_id}: {e}");
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        if node.sample_spec != sample_spec {
+            node.sample_spec = sample_spec;
+            true
+        } else {
+            false
+        }
+    }
+
     pub fn update_node_param(&mut self, node_id: u32, pod: &Pod) -> bool {
         let Some(node) = self.nodes.get_mut(&node_id) else {
             return false;
@@ -308,9 +584,8 @@ impl Store {
                         if let Some(raw_volume) =
                             VolumeResolver::extract_channel_volume(&prop.value)
                         {
-                            let scaled_volume = VolumeResolver::apply_cubic_scaling(raw_volume);
-                            if (node.volume - scaled_volume).abs() > 0.001 {
-                                node.volume = scaled_volume;
+                            if node.raw_volume != raw_volume {
+                                node.raw_volume = raw_volume;
                                 updated = true;
                             }
                         }
@@ -322,12 +597,16 @@ impl Store {
                                 node.channel_count = count;
                                 updated = true;
                             }
+                            if node.channel_volumes != *float_vec {
+                                node.channel_volumes = float_vec.clone();
+                                updated = true;
+                            }
                         }
                     }
                     libspa::sys::SPA_PROP_volume => {
-                        if let Value::Float(volume) = prop.value {
-                            if (node.volume - volume).abs() > 0.001 {
-                                node.volume = volume;
+                        if let Value::Float(raw_volume) = prop.value {
+                            if node.raw_volume != raw_volume {
+                                node.raw_volume = raw_volume;
                                 updated = true;
                             }
                         }
@@ -349,17 +628,17 @@ impl Store {
     }
 
     pub fn set_node_volume(&mut self, node_id: u32, volume: f32) -> Result<()> {
-        let node = self
-            .nodes
-            .get_mut(&node_id)
-            .ok_or_else(|| anyhow!("Node {node_id} not found for set_node_volume"))?;
+        let node = self.nodes.get_mut(&node_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Node,
+            id: node_id,
+        })?;
 
         if node.channel_count == 0 {
             return Err(anyhow!("Channel count not yet known for node {node_id}"));
         }
 
         let volume_value = volume.clamp(0.0, 2.0);
-        let raw_volume = VolumeResolver::apply_inverse_cubic_scaling(volume_value);
+        let raw_volume = VolumeResolver::apply_inverse_curve(self.volume_curve, volume_value);
 
         let volumes: Vec<f32> = vec![raw_volume; node.channel_count];
 
@@ -386,11 +665,14 @@ impl Store {
             builder.pop(initialized_frame);
         }
 
-        let pod_ref = Pod::from_bytes(&buffer)
-            .ok_or_else(|| anyhow!("Failed to create Pod reference from built bytes for volume"))?;
+        let pod_ref = Pod::from_bytes(&buffer).ok_or_else(|| {
+            PwError::PodBuild(
+                "Failed to create Pod reference from built bytes for volume".to_string(),
+            )
+        })?;
 
         node.proxy.set_param(ParamType::Props, 0, pod_ref);
-        node.volume = volume_value;
+        node.raw_volume = raw_volume;
 
         debug!(
             "Sent volume command for node {node_id} to {volume_value} ({} channels)",
@@ -400,10 +682,10 @@ impl Store {
     }
 
     pub fn set_node_mute(&mut self, node_id: u32, mute: bool) -> Result<()> {
-        let node = self
-            .nodes
-            .get_mut(&node_id)
-            .ok_or_else(|| anyhow!("Node {node_id} not found for set_node_mute"))?;
+        let node = self.nodes.get_mut(&node_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Node,
+            id: node_id,
+        })?;
 
         let mut buffer: Vec<u8> = Vec::new();
         let mut builder = Builder::new(&mut buffer);
@@ -423,8 +705,11 @@ impl Store {
             builder.pop(initialized_frame);
         }
 
-        let pod_ref = Pod::from_bytes(&buffer)
-            .ok_or_else(|| anyhow!("Failed to create Pod reference from built bytes for mute"))?;
+        let pod_ref = Pod::from_bytes(&buffer).ok_or_else(|| {
+            PwError::PodBuild(
+                "Failed to create Pod reference from built bytes for mute".to_string(),
+            )
+        })?;
 
         node.proxy.set_param(ParamType::Props, 0, pod_ref);
         node.muted = mute;
@@ -433,14 +718,143 @@ impl Store {
         Ok(())
     }
 
+    /// Pins a stream to a sink or source, or lets it follow the default again with `None`.
+    pub fn set_stream_target(&self, stream_id: u32, target_id: Option<u32>) -> Result<()> {
+        let stream = self.nodes.get(&stream_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Node,
+            id: stream_id,
+        })?;
+
+        let metadata_manager = self
+            .metadata_manager
+            .as_ref()
+            .ok_or(PwError::MetadataUnavailable("default"))?;
+
+        let Some(target_id) = target_id else {
+            return metadata_manager.clear_stream_target(stream_id);
+        };
+
+        let target = self.nodes.get(&target_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Node,
+            id: target_id,
+        })?;
+
+        let target_type = match stream.node_type {
+            NodeType::StreamOutputAudio => NodeType::AudioSink,
+            NodeType::StreamInputAudio => NodeType::AudioSource,
+            _ => {
+                return Err(
+                    PwError::Unsupported(format!("Node {stream_id} is not a stream")).into(),
+                )
+            }
+        };
+        if target.node_type != target_type && target.node_type != NodeType::AudioDuplex {
+            return Err(PwError::Unsupported(format!(
+                "Node {target_id} cannot be a target of stream {stream_id}"
+            ))
+            .into());
+        }
+
+        metadata_manager.set_stream_target(stream_id, &target.name)
+    }
+
+    /// Asks a node for a latency such as `256/48000` through the `params` of its `Props`.
+    /// Nodes whose application fixes the latency keep their own.
+    pub fn set_node_latency(&mut self, node_id: u32, latency: &str) -> Result<()> {
+        self.set_node_prop_param(node_id, "node.latency", latency)?;
+        debug!("Sent latency {latency} for node {node_id}");
+        Ok(())
+    }
+
+    /// Sets a node property such as `node.latency` through the `params` of its `Props`.
+    pub fn set_node_prop_param(&self, node_id: u32, key: &str, value: &str) -> Result<()> {
+        let node = self.nodes.get(&node_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Node,
+            id: node_id,
+        })?;
+
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut builder = Builder::new(&mut buffer);
+        let mut object_frame = MaybeUninit::<spa_pod_frame>::uninit();
+        let mut struct_frame = MaybeUninit::<spa_pod_frame>::uninit();
+
+        unsafe {
+            builder
+                .push_object(&mut object_frame, SPA_PARAM_Props, SPA_PARAM_Props)
+                .with_context(|| format!("Builder: failed to push object for {key}"))?;
+            let initialized_object_frame = object_frame.assume_init_mut();
+            builder
+                .add_prop(SPA_PROP_params, 0)
+                .context("Builder: failed to add params property key")?;
+            builder
+                .push_struct(&mut struct_frame)
+                .context("Builder: failed to push params struct")?;
+            let initialized_struct_frame = struct_frame.assume_init_mut();
+            builder
+                .add_string(key)
+                .with_context(|| format!("Builder: failed to add {key} key"))?;
+            builder
+                .add_string(value)
+                .with_context(|| format!("Builder: failed to add {key} value"))?;
+            builder.pop(initialized_struct_frame);
+            builder.pop(initialized_object_frame);
+        }
+
+        let pod_ref = Pod::from_bytes(&buffer).ok_or_else(|| {
+            PwError::PodBuild(format!(
+                "Failed to create Pod reference from built bytes for {key}"
+            ))
+        })?;
+
+        node.proxy.set_param(ParamType::Props, 0, pod_ref);
+        Ok(())
+    }
+
+    /// Re-targets every stream of the matching direction to the given sink or source.
+    pub fn move_streams_to(&self, node_id: u32) -> Result<usize> {
+        let target = self.nodes.get(&node_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Node,
+            id: node_id,
+        })?;
+
+        let stream_type = match target.node_type {
+            NodeType::AudioSink => NodeType::StreamOutputAudio,
+            NodeType::AudioSource => NodeType::StreamInputAudio,
+            _ => {
+                return Err(
+                    PwError::Unsupported(format!("Node {node_id} is not a Sink or Source")).into(),
+                )
+            }
+        };
+
+        let metadata_manager = self
+            .metadata_manager
+            .as_ref()
+            .ok_or(PwError::MetadataUnavailable("default"))?;
+
+        let mut moved_count = 0;
+        for stream in self.nodes.values().filter(|n| n.node_type == stream_type) {
+            match metadata_manager.set_stream_target(stream.id, &target.name) {
+                Ok(()) => moved_count += 1,
+                Err(e) => warn!(
+                    "Failed to move stream {} to {}: {e}",
+                    stream.id, target.name
+                ),
+            }
+        }
+
+        debug!("Moved {moved_count} streams to node {node_id}");
+        Ok(moved_count)
+    }
+
     pub fn set_default_sink(&mut self, node_id: u32) -> Result<()> {
-        let node = self
-            .nodes
-            .get(&node_id)
-            .ok_or_else(|| anyhow!("Node {node_id} not found for set_default_sink"))?;
+        let node = self.nodes.get(&node_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Node,
+            id: node_id,
+        })?;
 
         if node.node_type != NodeType::AudioSink {
-            return Err(anyhow!("Node {node_id} is not a Sink"));
+            return Err(PwError::Unsupported(format!("Node {node_id} is not a Sink")).into());
         }
         if self.default_sink == Some(node_id) {
             return Ok(());
@@ -477,13 +891,13 @@ impl Store {
     }
 
     pub fn set_default_source(&mut self, node_id: u32) -> Result<()> {
-        let node = self
-            .nodes
-            .get(&node_id)
-            .ok_or_else(|| anyhow!("Node {node_id} not found for set_default_source"))?;
+        let node = self.nodes.get(&node_id).ok_or(PwError::NotFound {
+            kind: ObjectKind::Node,
+            id: node_id,
+        })?;
 
         if node.node_type != NodeType::AudioSource {
-            return Err(anyhow!("Node {node_id} is not a Source"));
+            return Err(PwError::Unsupported(format!("Node {node_id} is not a Source")).into());
         }
         if self.default_source == Some(node_id) {
             return Ok(());
@@ -523,7 +937,7 @@ impl Store {
         self.nodes
             .values()
             .filter(|n| matches!(n.node_type, NodeType::AudioSink))
-            .map(|n| n.to_node())
+            .map(|n| n.to_node(self.volume_curve))
             .collect()
     }
 
@@ -531,11 +945,43 @@ impl Store {
         self.nodes
             .values()
             .filter(|n| matches!(n.node_type, NodeType::AudioSource))
-            .map(|n| n.to_node())
+            .map(|n| n.to_node(self.volume_curve))
             .collect()
     }
 
     pub fn get_node(&self, node_id: u32) -> Option<Node> {
-        self.nodes.get(&node_id).map(|n| n.to_node())
+        self.nodes
+            .get(&node_id)
+            .map(|n| n.to_node(self.volume_curve))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pw::managed::MANAGED_PROPERTY;
+    use pipewire::{keys::*, properties::properties};
+
+    #[test]
+    fn managed_flag_comes_from_the_node_info() {
+        // What the registry forwards of a virtual sink pwmenu created
+        let global = properties! {
+            *NODE_NAME => "pwmenu_virtual_music",
+            *NODE_DESCRIPTION => "Music",
+            *MEDIA_CLASS => "Audio/Sink",
+        };
+        let info = properties! {
+            *NODE_NAME => "pwmenu_virtual_music",
+            *NODE_DESCRIPTION => "Music",
+            *MEDIA_CLASS => "Audio/Sink",
+            "node.latency" => "1024/48000",
+            MANAGED_PROPERTY => "true",
+        };
+
+        assert!(!NodeInfoProps::from_props(global.dict()).is_managed);
+
+        let info = NodeInfoProps::from_props(info.dict());
+        assert!(info.is_managed);
+        assert_eq!(info.latency.as_deref(), Some("1024/48000"));
     }
 }
@@ -0,0 +1,299 @@
+use anyhow::{anyhow, Result};
+use libspa::sys::{
+    spa_hook, spa_interface, spa_pod, SPA_PROFILER_clock, SPA_PROFILER_driverBlock,
+    SPA_PROFILER_followerBlock, SPA_PROFILER_info,
+};
+use pipewire::{
+    registry::RegistryRc,
+    spa::pod::{deserialize::PodDeserializer, Pod, Value},
+    sys as pw_sys,
+    types::ObjectType,
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    ffi::{c_void, CString},
+    mem,
+    pin::Pin,
+    ptr::NonNull,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+use crate::pw::{error::PwError, graph::Store};
+
+/// Drivers that stopped reporting for this long are considered idle and left out.
+const DRIVER_IDLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Timing of one node in its driver's last cycle.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeTiming {
+    pub id: u32,
+    pub name: String,
+    /// Nanoseconds between the node being signaled and waking up.
+    pub wait_ns: u64,
+    /// Nanoseconds the node spent processing.
+    pub busy_ns: u64,
+    pub xruns: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DriverStats {
+    #[serde(flatten)]
+    pub timing: NodeTiming,
+    /// Samples per cycle.
+    pub quantum: u64,
+    pub rate: u32,
+    pub followers: Vec<NodeTiming>,
+}
+
+impl DriverStats {
+    /// Length of one cycle, which wait and busy times are compared against.
+    pub fn period_ns(&self) -> u64 {
+        if self.rate == 0 {
+            return 0;
+        }
+        self.quantum * 1_000_000_000 / u64::from(self.rate)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProfilerSample {
+    /// DSP load of the graph averaged over short, medium and long periods, from 0.0 to 1.0.
+    pub cpu_load: [f32; 3],
+    pub xruns: u32,
+    pub drivers: Vec<DriverStats>,
+}
+
+#[derive(Debug, Default)]
+struct ProfilerState {
+    cpu_load: [f32; 3],
+    xruns: u32,
+    drivers: BTreeMap<u32, (Instant, DriverStats)>,
+    /// Last reported xrun count of every driver seen, kept while the driver is idle.
+    xrun_counts: BTreeMap<u32, u32>,
+}
+
+/// Latest profiler data, shared with the PipeWire thread.
+#[derive(Debug, Clone, Default)]
+pub struct ProfilerStats {
+    state: Arc<Mutex<ProfilerState>>,
+}
+
+impl ProfilerStats {
+    /// Current load and the drivers that ran recently, ordered by id.
+    pub fn snapshot(&self) -> ProfilerSample {
+        let Ok(state) = self.state.lock() else {
+            return ProfilerSample::default();
+        };
+
+        ProfilerSample {
+            cpu_load: state.cpu_load,
+            xruns: state.xruns,
+            drivers: state
+                .drivers
+                .values()
+                .filter(|(seen, _)| seen.elapsed() < DRIVER_IDLE_TIMEOUT)
+                .map(|(_, driver)| driver.clone())
+                .collect(),
+        }
+    }
+
+    /// Xruns counted by the server for each driver node since it was created.
+    pub fn xrun_counts(&self) -> BTreeMap<u32, u32> {
+        self.state
+            .lock()
+            .map(|state| state.xrun_counts.clone())
+            .unwrap_or_default()
+    }
+
+    fn record(&self, pod: &Pod) {
+        let Ok((_, Value::Struct(objects))) = PodDeserializer::deserialize_any_from(pod.as_bytes())
+        else {
+            debug!("Ignoring unexpected profiler data");
+            return;
+        };
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+
+        for object in objects {
+            let Value::Object(object) = object else {
+                continue;
+            };
+
+            let mut clock = None;
+            let mut driver = None;
+            let mut followers = Vec::new();
+
+            for prop in &object.properties {
+                let Value::Struct(fields) = &prop.value else {
+                    continue;
+                };
+                match prop.key {
+                    SPA_PROFILER_info => {
+                        if let [Value::Long(_), Value::Float(fast), Value::Float(medium), Value::Float(slow), Value::Int(xruns), ..] =
+                            fields.as_slice()
+                        {
+                            state.cpu_load = [*fast, *medium, *slow];
+                            state.xruns = (*xruns).max(0) as u32;
+                        }
+                    }
+                    SPA_PROFILER_clock => clock = parse_clock(fields),
+                    SPA_PROFILER_driverBlock => driver = parse_block(fields),
+                    SPA_PROFILER_followerBlock => followers.extend(parse_block(fields)),
+                    _ => {}
+                }
+            }
+
+            let Some(timing) = driver else {
+                continue;
+            };
+            let (quantum, rate) = clock.unwrap_or_default();
+            state.xrun_counts.insert(timing.id, timing.xruns);
+            state.drivers.insert(
+                timing.id,
+                (
+                    Instant::now(),
+                    DriverStats {
+                        timing,
+                        quantum,
+                        rate,
+                        followers,
+                    },
+                ),
+            );
+        }
+    }
+}
+
+// Clock struct: flags, id, name, nsec, rate, position, duration, ...
+fn parse_clock(fields: &[Value]) -> Option<(u64, u32)> {
+    match fields {
+        [Value::Int(_), Value::Int(_), Value::String(_), Value::Long(_), Value::Fraction(rate), Value::Long(_), Value::Long(duration), ..] => {
+            Some(((*duration).max(0) as u64, rate.denom))
+        }
+        _ => None,
+    }
+}
+
+// Block struct: id, name, prev_signal, signal, awake, finish, status, latency, then xruns
+// from PipeWire 0.3.77
+fn parse_block(fields: &[Value]) -> Option<NodeTiming> {
+    let [Value::Int(id), Value::String(name), Value::Long(_), Value::Long(signal), Value::Long(awake), Value::Long(finish), Value::Int(_), rest @ ..] =
+        fields
+    else {
+        return None;
+    };
+
+    let xruns = match rest {
+        [_, Value::Int(xruns), ..] => (*xruns).max(0) as u32,
+        _ => 0,
+    };
+
+    Some(NodeTiming {
+        id: (*id).max(0) as u32,
+        name: name.clone(),
+        wait_ns: (awake - signal).max(0) as u64,
+        busy_ns: (finish - awake).max(0) as u64,
+        xruns,
+    })
+}
+
+/// Binding to the PipeWire profiler; the server only collects timings while one is bound.
+pub struct Profiler {
+    proxy: NonNull<pw_sys::pw_proxy>,
+    listener: Pin<Box<spa_hook>>,
+    // Must stay allocated while the listener is registered
+    _events: Pin<Box<pw_sys::pw_profiler_events>>,
+    stats: Box<ProfilerStats>,
+}
+
+impl Profiler {
+    /// pipewire-rs has no profiler proxy, so the global is bound through the raw registry.
+    fn bind(registry: &RegistryRc, global_id: u32, stats: ProfilerStats) -> Result<Self> {
+        unsafe extern "C" fn on_profile(data: *mut c_void, pod: *const spa_pod) {
+            if pod.is_null() {
+                return;
+            }
+            if let Some(stats) = (data as *const ProfilerStats).as_ref() {
+                stats.record(Pod::from_raw(pod));
+            }
+        }
+
+        let type_ = CString::new(ObjectType::Profiler.to_str())?;
+
+        unsafe {
+            let iface: *mut spa_interface = registry.as_raw_ptr().cast();
+            let funcs: *const pw_sys::pw_registry_methods = (*iface).cb.funcs.cast();
+            let bind = (*funcs).bind.ok_or_else(|| {
+                PwError::ProxyError("Registry does not support binding".to_string())
+            })?;
+            let proxy = bind(
+                (*iface).cb.data,
+                global_id,
+                type_.as_ptr(),
+                pw_sys::PW_VERSION_PROFILER,
+                0,
+            );
+            let proxy = NonNull::new(proxy.cast::<pw_sys::pw_proxy>()).ok_or_else(|| {
+                PwError::ProxyError(format!("Failed to bind profiler {global_id}"))
+            })?;
+
+            let mut events: Pin<Box<pw_sys::pw_profiler_events>> = Box::pin(mem::zeroed());
+            events.version = pw_sys::PW_VERSION_PROFILER_EVENTS;
+            events.profile = Some(on_profile);
+
+            let stats = Box::new(stats);
+            let mut listener: Pin<Box<spa_hook>> = Box::pin(mem::zeroed());
+            pw_sys::pw_proxy_add_object_listener(
+                proxy.as_ptr(),
+                listener.as_mut().get_unchecked_mut(),
+                (events.as_ref().get_ref() as *const pw_sys::pw_profiler_events).cast(),
+                (stats.as_ref() as *const ProfilerStats).cast_mut().cast(),
+            );
+
+            Ok(Self {
+                proxy,
+                listener,
+                _events: events,
+                stats,
+            })
+        }
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        libspa::utils::hook::remove(*self.listener);
+        unsafe {
+            pw_sys::pw_proxy_destroy(self.proxy.as_ptr());
+        }
+    }
+}
+
+impl Store {
+    pub fn start_profiler(&mut self, registry: &RegistryRc) -> Result<ProfilerStats> {
+        if let Some(profiler) = &self.profiler {
+            return Ok(profiler.stats.as_ref().clone());
+        }
+
+        let global_id = self.profiler_global.ok_or_else(|| {
+            anyhow!("PipeWire profiler not available, is module-profiler loaded?")
+        })?;
+
+        let stats = ProfilerStats::default();
+        self.profiler = Some(Profiler::bind(registry, global_id, stats.clone())?);
+
+        debug!("Bound profiler {global_id}");
+        Ok(stats)
+    }
+
+    pub fn stop_profiler(&mut self) -> Result<()> {
+        if self.profiler.take().is_some() {
+            debug!("Released profiler");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    time::Instant,
+};
+
+use crate::pw::graph::AudioGraph;
+
+/// One graph update of a recording, `elapsed_ms` after the first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphFrame {
+    pub elapsed_ms: u64,
+    pub graph: AudioGraph,
+}
+
+#[derive(Serialize)]
+struct FrameRef<'a> {
+    elapsed_ms: u64,
+    graph: &'a AudioGraph,
+}
+
+/// Writes graph updates as JSON lines, one frame per line, so a recording cut short by an
+/// interrupt stays readable.
+pub struct GraphRecorder<W: Write> {
+    writer: W,
+    started: Instant,
+    frames: usize,
+}
+
+impl<W: Write> GraphRecorder<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            started: Instant::now(),
+            frames: 0,
+        }
+    }
+
+    pub fn record(&mut self, graph: &AudioGraph) -> Result<()> {
+        let frame = FrameRef {
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            graph,
+        };
+        writeln!(self.writer, "{}", serde_json::to_string(&frame)?)?;
+        self.writer.flush()?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Number of frames written so far.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+}
+
+/// Reads a recording made by [`GraphRecorder`], in order.
+pub fn read_recording(path: impl AsRef<Path>) -> Result<Vec<GraphFrame>> {
+    let path = path.as_ref();
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open graph recording {}", path.display()))?;
+
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+        .map(|(index, line)| {
+            let line = line?;
+            serde_json::from_str(&line).with_context(|| {
+                format!("Invalid frame on line {} of {}", index + 1, path.display())
+            })
+        })
+        .collect()
+}
@@ -1,57 +1,185 @@
-use crate::pw::{devices::DeviceInternal, graph::Store, nodes::NodeType};
-use anyhow::{anyhow, Result};
-use log::debug;
-use std::{collections::HashMap, time::Instant};
+use crate::{
+    pw::{error::PwError, graph::AudioGraph, graph::Store, nodes::NodeType},
+    state::State,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant, SystemTime},
+};
+use tracing::{debug, warn};
+
+pub const DEFAULT_RESTORATION_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_RESTORATION_ATTEMPTS: u8 = 20;
+
+// Delay before the second attempt, doubled after each one up to the maximum
+const RESTORATION_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_RESTORATION_BACKOFF: Duration = Duration::from_secs(2);
+
+// Buses whose profile switches recreate the device nodes, losing the defaults set on them
+const RESTORED_BUSES: &[&str] = &["usb", "bluetooth"];
+
+/// A device as seen by restoration.
+#[derive(Debug, Clone, Copy)]
+pub struct RestorationDevice<'a> {
+    pub id: u32,
+    pub name: &'a str,
+    pub bus: Option<&'a str>,
+    pub current_profile_index: Option<u32>,
+}
+
+/// A node as seen by restoration.
+#[derive(Debug, Clone, Copy)]
+pub struct RestorationNode<'a> {
+    pub id: u32,
+    pub name: &'a str,
+    pub device_id: Option<u32>,
+    pub node_type: NodeType,
+    pub is_default: bool,
+}
+
+/// The parts of the graph restoration looks at, provided by the live [`Store`] and by
+/// [`AudioGraph`] snapshots.
+pub trait RestorationGraph {
+    fn restoration_devices(&self) -> Vec<RestorationDevice<'_>>;
+    fn restoration_nodes(&self) -> Vec<RestorationNode<'_>>;
+}
+
+impl RestorationGraph for Store {
+    fn restoration_devices(&self) -> Vec<RestorationDevice<'_>> {
+        self.devices
+            .values()
+            .map(|device| RestorationDevice {
+                id: device.id,
+                name: &device.name,
+                bus: device.bus.as_deref(),
+                current_profile_index: device.current_profile_index,
+            })
+            .collect()
+    }
+
+    fn restoration_nodes(&self) -> Vec<RestorationNode<'_>> {
+        self.nodes
+            .values()
+            .map(|node| RestorationNode {
+                id: node.id,
+                name: &node.name,
+                device_id: node.device_id,
+                node_type: node.node_type,
+                is_default: node.is_default,
+            })
+            .collect()
+    }
+}
+
+impl RestorationGraph for AudioGraph {
+    fn restoration_devices(&self) -> Vec<RestorationDevice<'_>> {
+        self.devices
+            .values()
+            .map(|device| RestorationDevice {
+                id: device.id,
+                name: &device.name,
+                bus: device.bus.as_deref(),
+                current_profile_index: device.current_profile_index,
+            })
+            .collect()
+    }
 
-const RESTORATION_TIMEOUT_SECS: u64 = 30;
-const MAX_RESTORATION_ATTEMPTS: u8 = 50;
+    fn restoration_nodes(&self) -> Vec<RestorationNode<'_>> {
+        self.nodes
+            .values()
+            .map(|node| RestorationNode {
+                id: node.id,
+                name: &node.name,
+                device_id: node.device_id,
+                node_type: node.node_type,
+                is_default: node.is_default,
+            })
+            .collect()
+    }
+}
+
+/// How long and how many times defaults are waited for after a profile switch, and whether
+/// pending ones survive a restart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestorationSettings {
+    pub timeout: Duration,
+    pub max_attempts: u8,
+    pub persist: bool,
+}
 
-#[derive(Debug, Clone)]
+impl Default for RestorationSettings {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_RESTORATION_TIMEOUT,
+            max_attempts: DEFAULT_RESTORATION_ATTEMPTS,
+            persist: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DefaultRestoration {
     pub device_id: u32,
     pub device_name: String,
-    pub had_default_sink: bool,
-    pub had_default_source: bool,
+    /// Name of the node that was the default sink, preferred when it comes back.
+    pub default_sink: Option<String>,
+    pub default_source: Option<String>,
     pub target_profile_index: u32,
-    timestamp: Instant,
+    // Wall-clock time, so restorations read back after a restart still expire
+    timestamp: SystemTime,
+    #[serde(skip)]
     attempts: u8,
+    #[serde(skip, default = "Instant::now")]
+    next_attempt: Instant,
 }
 
 impl DefaultRestoration {
     pub fn new(
         device_id: u32,
         device_name: String,
-        had_default_sink: bool,
-        had_default_source: bool,
+        default_sink: Option<String>,
+        default_source: Option<String>,
         target_profile_index: u32,
     ) -> Self {
         Self {
             device_id,
             device_name,
-            had_default_sink,
-            had_default_source,
+            default_sink,
+            default_source,
             target_profile_index,
-            timestamp: Instant::now(),
+            timestamp: SystemTime::now(),
             attempts: 0,
+            next_attempt: Instant::now(),
         }
     }
 
-    fn is_expired(&self) -> bool {
-        self.timestamp.elapsed().as_secs() > RESTORATION_TIMEOUT_SECS
+    fn is_expired(&self, timeout: Duration) -> bool {
+        // A clock set backwards reads as not expired, the attempt limit still applies
+        self.timestamp
+            .elapsed()
+            .is_ok_and(|elapsed| elapsed > timeout)
     }
 
-    fn max_attempts_reached(&self) -> bool {
-        self.attempts >= MAX_RESTORATION_ATTEMPTS
+    fn is_due(&self, now: Instant) -> bool {
+        self.next_attempt <= now
     }
 
-    fn increment_attempt(&mut self) {
-        self.attempts += 1;
+    /// Counts a failed attempt and schedules the next one.
+    fn back_off(&mut self, now: Instant) {
+        let backoff = RESTORATION_BACKOFF
+            .saturating_mul(1 << self.attempts.min(16))
+            .min(MAX_RESTORATION_BACKOFF);
+        self.attempts = self.attempts.saturating_add(1);
+        self.next_attempt = now + backoff;
     }
 }
 
 #[derive(Debug, Default)]
 pub struct RestorationManager {
     pending: HashMap<String, DefaultRestoration>,
+    settings: RestorationSettings,
 }
 
 impl RestorationManager {
@@ -59,169 +187,391 @@ impl RestorationManager {
         Self::default()
     }
 
-    pub fn should_capture_defaults(store: &Store, device_id: u32) -> Option<(String, bool, bool)> {
-        let device = store.devices.get(&device_id)?;
+    /// Applies new settings. Enabling persistence picks up the restorations a previous run
+    /// left in the state file, so a restart in the middle of a profile switch still restores
+    /// the defaults.
+    pub fn configure(&mut self, settings: RestorationSettings) {
+        let enable_persistence = settings.persist && !self.settings.persist;
+        let changed_persistence = settings.persist != self.settings.persist;
+        self.settings = settings;
 
-        // Only handle USB devices
-        if !Self::is_usb_device(device) {
-            return None;
+        if enable_persistence {
+            self.restore_persisted(State::load().pending_restorations);
         }
+        if changed_persistence {
+            self.save();
+        }
+    }
 
-        // Check current defaults for this device
-        let had_default_sink = store.nodes.values().any(|n| {
-            n.device_id == Some(device_id)
-                && n.is_default
-                && matches!(n.node_type, NodeType::AudioSink)
-        });
-        let had_default_source = store.nodes.values().any(|n| {
-            n.device_id == Some(device_id)
-                && n.is_default
-                && matches!(n.node_type, NodeType::AudioSource)
-        });
+    fn restore_persisted(&mut self, restorations: Vec<DefaultRestoration>) {
+        for restoration in restorations {
+            if restoration.is_expired(self.settings.timeout) {
+                debug!(
+                    "Dropping expired restoration for device {}",
+                    restoration.device_name
+                );
+                continue;
+            }
+            debug!(
+                "Resuming restoration for device {}",
+                restoration.device_name
+            );
+            self.pending
+                .entry(restoration.device_name.clone())
+                .or_insert(restoration);
+        }
+    }
 
-        if !had_default_sink && !had_default_source {
-            return None;
+    fn save(&self) {
+        if !self.settings.persist {
+            return;
         }
 
-        Some((device.name.clone(), had_default_sink, had_default_source))
+        let pending = self.pending.values().cloned().collect();
+        if let Err(e) = State::update(|state| state.pending_restorations = pending) {
+            warn!("Failed to save pending restorations: {e}");
+        }
     }
 
-    pub fn capture_defaults(
-        &mut self,
+    /// The defaults held by a device about to switch to `target_profile_index`, if it is one
+    /// whose nodes are recreated and it holds any.
+    pub fn should_capture_defaults(
+        graph: &impl RestorationGraph,
         device_id: u32,
-        device_name: String,
-        had_default_sink: bool,
-        had_default_source: bool,
         target_profile_index: u32,
-    ) {
-        let restoration = DefaultRestoration::new(
+    ) -> Option<DefaultRestoration> {
+        let devices = graph.restoration_devices();
+        let device = devices.iter().find(|d| d.id == device_id)?;
+
+        if !device.bus.is_some_and(|bus| RESTORED_BUSES.contains(&bus)) {
+            return None;
+        }
+
+        let nodes = graph.restoration_nodes();
+        let default_of = |node_type: NodeType| {
+            nodes
+                .iter()
+                .find(|n| {
+                    n.device_id == Some(device_id) && n.is_default && n.node_type == node_type
+                })
+                .map(|n| n.name.to_string())
+        };
+        let default_sink = default_of(NodeType::AudioSink);
+        let default_source = default_of(NodeType::AudioSource);
+
+        if default_sink.is_none() && default_source.is_none() {
+            return None;
+        }
+
+        Some(DefaultRestoration::new(
             device_id,
-            device_name.clone(),
-            had_default_sink,
-            had_default_source,
+            device.name.to_string(),
+            default_sink,
+            default_source,
             target_profile_index,
+        ))
+    }
+
+    pub fn capture_defaults(&mut self, restoration: DefaultRestoration) {
+        debug!(
+            "Capturing defaults for {}: sink={:?}, source={:?}",
+            restoration.device_name, restoration.default_sink, restoration.default_source
         );
 
-        debug!("Capturing USB defaults for {device_name}: sink={had_default_sink}, source={had_default_source}");
+        self.pending
+            .insert(restoration.device_name.clone(), restoration);
+        self.save();
+    }
 
-        self.pending.insert(device_name, restoration);
+    /// Time left before the next restoration attempt is due, `None` when none are pending.
+    pub fn next_attempt_delay(&self) -> Option<Duration> {
+        let now = Instant::now();
+        self.pending
+            .values()
+            .map(|restoration| restoration.next_attempt.saturating_duration_since(now))
+            .min()
     }
 
-    pub fn get_pending_restorations(&self, store: &Store) -> (Vec<(u32, u32)>, Vec<String>) {
+    /// Sink and source ids to set as defaults, 0 for none, and the devices they complete,
+    /// among the restorations whose attempt is due.
+    pub fn get_pending_restorations(
+        &self,
+        graph: &impl RestorationGraph,
+    ) -> (Vec<(u32, u32)>, Vec<String>) {
         let mut nodes_to_restore = Vec::new();
         let mut completed_devices = Vec::new();
+        let now = Instant::now();
 
         for (device_name, restoration) in &self.pending {
-            if restoration.is_expired() || restoration.max_attempts_reached() {
+            if !restoration.is_due(now)
+                || restoration.is_expired(self.settings.timeout)
+                || restoration.attempts >= self.settings.max_attempts
+            {
                 continue;
             }
 
-            match Self::attempt_restoration(store, restoration) {
-                Ok(Some((sink_ids, source_ids))) => {
-                    let sink_id = sink_ids.first().copied().unwrap_or(0);
-                    let source_id = source_ids.first().copied().unwrap_or(0);
-                    nodes_to_restore.push((sink_id, source_id));
+            match Self::attempt_restoration(graph, restoration) {
+                Ok(Some((sink_id, source_id))) => {
+                    nodes_to_restore.push((sink_id.unwrap_or(0), source_id.unwrap_or(0)));
                     completed_devices.push(device_name.clone());
                 }
                 Ok(None) => {}
-                Err(_e) => {}
+                Err(e) => debug!("Cannot restore defaults for {device_name}: {e}"),
             }
         }
 
         (nodes_to_restore, completed_devices)
     }
 
+    /// Backs off the restorations that were due and did not complete, dropping those out of
+    /// time or attempts. Call after [`Self::mark_completed`].
     pub fn update_attempts_and_cleanup(&mut self) {
         let mut to_remove = Vec::new();
+        let now = Instant::now();
 
         for (device_name, restoration) in &mut self.pending {
-            if restoration.is_expired() {
+            if restoration.is_expired(self.settings.timeout) {
                 debug!("Restoration expired for device {device_name}");
                 to_remove.push(device_name.clone());
-            } else if restoration.max_attempts_reached() {
-                debug!("Max attempts reached for device {device_name}");
-                to_remove.push(device_name.clone());
-            } else {
-                restoration.increment_attempt();
+            } else if restoration.is_due(now) {
+                restoration.back_off(now);
+                if restoration.attempts >= self.settings.max_attempts {
+                    debug!("Max attempts reached for device {device_name}");
+                    to_remove.push(device_name.clone());
+                }
             }
         }
 
+        if to_remove.is_empty() {
+            return;
+        }
         for device_name in to_remove {
             self.pending.remove(&device_name);
         }
+        self.save();
+    }
+
+    /// Drops the defaults captured for a device whose profile switch did not happen.
+    pub fn cancel(&mut self, device_id: u32) {
+        let count = self.pending.len();
+        self.pending.retain(|device_name, restoration| {
+            if restoration.device_id == device_id {
+                debug!("Cancelled restoration for device {device_name}");
+                false
+            } else {
+                true
+            }
+        });
+        if self.pending.len() != count {
+            self.save();
+        }
     }
 
     pub fn mark_completed(&mut self, device_names: &[String]) {
+        let count = self.pending.len();
         for device_name in device_names {
             if self.pending.remove(device_name).is_some() {
                 debug!("Successfully restored defaults for device {device_name}",);
             }
         }
+        if self.pending.len() != count {
+            self.save();
+        }
     }
 
+    /// Waits for the target profile and the nodes that held the defaults, or nodes of the
+    /// same type when the new profile names them differently.
     fn attempt_restoration(
-        store: &Store,
+        graph: &impl RestorationGraph,
         restoration: &DefaultRestoration,
-    ) -> Result<Option<(Vec<u32>, Vec<u32>)>> {
-        // Find the device and check if profile change is complete
-        let device = store
-            .devices
-            .values()
+    ) -> Result<Option<(Option<u32>, Option<u32>)>> {
+        // Device ids can change when the card is recreated, names do not
+        let devices = graph.restoration_devices();
+        let device = devices
+            .iter()
             .find(|d| d.name == restoration.device_name)
-            .ok_or_else(|| anyhow!("Device {} not found", restoration.device_name))?;
+            .ok_or_else(|| PwError::DeviceNotFound(restoration.device_name.clone()))?;
 
-        // Verify profile change is complete
         if device.current_profile_index != Some(restoration.target_profile_index) {
             return Ok(None);
         }
 
-        // Check if expected nodes are available
-        let has_nodes = store.nodes.values().any(|n| n.device_id == Some(device.id));
-        if !has_nodes {
+        let mut nodes: Vec<RestorationNode> = graph
+            .restoration_nodes()
+            .into_iter()
+            .filter(|n| n.device_id == Some(device.id))
+            .collect();
+        nodes.sort_by_key(|n| n.id);
+
+        let find = |previous: &Option<String>, node_type: NodeType| match previous {
+            None => Some(None),
+            Some(name) => nodes
+                .iter()
+                .filter(|n| n.node_type == node_type)
+                .min_by_key(|n| n.name != name)
+                .map(|n| {
+                    debug!("Found {node_type:?} node to restore: {}", n.name);
+                    Some(n.id)
+                }),
+        };
+
+        let (Some(sink_id), Some(source_id)) = (
+            find(&restoration.default_sink, NodeType::AudioSink),
+            find(&restoration.default_source, NodeType::AudioSource),
+        ) else {
             return Ok(None);
-        }
+        };
 
-        let mut sink_ids = Vec::new();
-        let mut source_ids = Vec::new();
+        Ok(Some((sink_id, source_id)))
+    }
 
-        // Collect sink nodes to restore as default
-        if restoration.had_default_sink {
-            if let Some(sink_node) = store.nodes.values().find(|n| {
-                n.device_id == Some(device.id) && matches!(n.node_type, NodeType::AudioSink)
-            }) {
-                sink_ids.push(sink_node.id);
-                debug!("Found sink node to restore: {}", sink_node.name);
+    pub fn cleanup_expired(&mut self) {
+        let count = self.pending.len();
+        self.pending.retain(|device_name, restoration| {
+            if restoration.is_expired(self.settings.timeout) {
+                debug!("Removing expired restoration for device {device_name}",);
+                false
             } else {
-                return Ok(None);
+                true
             }
+        });
+        if self.pending.len() != count {
+            self.save();
         }
+    }
+}
 
-        if restoration.had_default_source {
-            if let Some(source_node) = store.nodes.values().find(|n| {
-                n.device_id == Some(device.id) && matches!(n.node_type, NodeType::AudioSource)
-            }) {
-                source_ids.push(source_node.id);
-                debug!("Found source node to restore: {}", source_node.name);
-            } else {
-                return Ok(None);
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAPTOP: &str = include_str!("../../tests/fixtures/laptop.json");
+    const USB_AND_BLUETOOTH: &str = include_str!("../../tests/fixtures/usb-and-bluetooth.json");
+
+    fn fixture_graph(fixture: &str) -> AudioGraph {
+        serde_json::from_str(fixture).unwrap()
+    }
+
+    /// Applies a profile switch the way PipeWire does: the old nodes go away and the new
+    /// profile's nodes come back with new ids and no default.
+    fn switch_profile(graph: &mut AudioGraph, device_id: u32, profile_index: u32, new_ids: u32) {
+        graph
+            .devices
+            .get_mut(&device_id)
+            .unwrap()
+            .current_profile_index = Some(profile_index);
+        let old_ids: Vec<u32> = graph
+            .nodes
+            .values()
+            .filter(|n| n.device_id == Some(device_id))
+            .map(|n| n.id)
+            .collect();
+        for old_id in old_ids {
+            let mut node = graph.nodes.remove(&old_id).unwrap();
+            node.id = old_id + new_ids;
+            node.is_default = false;
+            graph.nodes.insert(node.id, node);
         }
+    }
+
+    #[test]
+    fn usb_defaults_are_captured_by_node_name() {
+        let graph = fixture_graph(USB_AND_BLUETOOTH);
+        let restoration = RestorationManager::should_capture_defaults(&graph, 105, 2).unwrap();
 
-        Ok(Some((sink_ids, source_ids)))
+        assert_eq!(
+            restoration.default_sink.as_deref(),
+            Some("alsa_output.usb-Logitech_G435-00.analog-stereo")
+        );
+        assert_eq!(restoration.default_source, None);
     }
 
-    fn is_usb_device(device: &DeviceInternal) -> bool {
-        device.bus.as_deref() == Some("usb")
+    #[test]
+    fn devices_without_defaults_or_on_other_buses_are_skipped() {
+        // The Bluetooth card holds no default
+        let graph = fixture_graph(USB_AND_BLUETOOTH);
+        assert!(RestorationManager::should_capture_defaults(&graph, 125, 2).is_none());
+
+        // PCI cards keep their nodes across profile switches
+        let graph = fixture_graph(LAPTOP);
+        assert!(RestorationManager::should_capture_defaults(&graph, 45, 2).is_none());
     }
 
-    pub fn cleanup_expired(&mut self) {
-        self.pending.retain(|device_name, restoration| {
-            if restoration.is_expired() {
-                debug!("Removing expired restoration for device {device_name}",);
-                false
-            } else {
-                true
-            }
+    #[test]
+    fn defaults_are_restored_on_the_recreated_nodes() {
+        let mut graph = fixture_graph(USB_AND_BLUETOOTH);
+        let mut manager = RestorationManager::new();
+        manager
+            .capture_defaults(RestorationManager::should_capture_defaults(&graph, 105, 2).unwrap());
+
+        // Nothing to do until the device reports the new profile
+        assert!(manager.get_pending_restorations(&graph).0.is_empty());
+
+        switch_profile(&mut graph, 105, 2, 100);
+        let (nodes, completed) = manager.get_pending_restorations(&graph);
+        assert_eq!(nodes, [(210, 0)]);
+        assert_eq!(completed, ["alsa_card.usb-Logitech_G435-00"]);
+
+        manager.mark_completed(&completed);
+        assert!(manager.get_pending_restorations(&graph).0.is_empty());
+    }
+
+    #[test]
+    fn cancelled_restorations_are_dropped() {
+        let mut graph = fixture_graph(USB_AND_BLUETOOTH);
+        let mut manager = RestorationManager::new();
+        manager
+            .capture_defaults(RestorationManager::should_capture_defaults(&graph, 105, 2).unwrap());
+
+        manager.cancel(105);
+        switch_profile(&mut graph, 105, 2, 100);
+        assert!(manager.get_pending_restorations(&graph).0.is_empty());
+    }
+
+    #[test]
+    fn persisted_restorations_resume_unless_expired() {
+        let mut graph = fixture_graph(USB_AND_BLUETOOTH);
+        let restoration = RestorationManager::should_capture_defaults(&graph, 105, 2).unwrap();
+        let json = serde_json::to_string(&[restoration]).unwrap();
+
+        let mut expired: Vec<DefaultRestoration> = serde_json::from_str(&json).unwrap();
+        expired[0].timestamp = SystemTime::UNIX_EPOCH;
+        let mut manager = RestorationManager::new();
+        manager.restore_persisted(expired);
+        switch_profile(&mut graph, 105, 2, 100);
+        assert!(manager.get_pending_restorations(&graph).0.is_empty());
+
+        let mut manager = RestorationManager::new();
+        manager.restore_persisted(serde_json::from_str(&json).unwrap());
+        assert_eq!(manager.get_pending_restorations(&graph).0, [(210, 0)]);
+    }
+
+    #[test]
+    fn failed_attempts_back_off_until_the_limit() {
+        let mut graph = fixture_graph(USB_AND_BLUETOOTH);
+        let mut manager = RestorationManager::new();
+        manager.configure(RestorationSettings {
+            max_attempts: 2,
+            ..Default::default()
         });
+        manager
+            .capture_defaults(RestorationManager::should_capture_defaults(&graph, 105, 2).unwrap());
+
+        // The first attempt finds the old profile
+        assert!(manager.get_pending_restorations(&graph).0.is_empty());
+        manager.update_attempts_and_cleanup();
+        let delay = manager.next_attempt_delay().unwrap();
+        assert!(!delay.is_zero() && delay <= RESTORATION_BACKOFF);
+
+        // The next one waits for its turn even when the nodes are back
+        switch_profile(&mut graph, 105, 2, 100);
+        assert!(manager.get_pending_restorations(&graph).0.is_empty());
+
+        // A due attempt that is not completed uses up the last one
+        for restoration in manager.pending.values_mut() {
+            restoration.next_attempt = Instant::now();
+        }
+        manager.update_attempts_and_cleanup();
+        assert!(manager.next_attempt_delay().is_none());
     }
 }
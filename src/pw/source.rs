@@ -0,0 +1,267 @@
+use anyhow::Result;
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc};
+use tokio::sync::watch;
+
+use crate::pw::{
+    commands::BatchStep,
+    engine::PwEngine,
+    events::GraphEvents,
+    graph::AudioGraph,
+    links::PortMapping,
+    meter::InputLevel,
+    profiler::ProfilerStats,
+    restoration::RestorationSettings,
+    volume::{RouteDirection, VolumeCurve},
+};
+
+/// Future returned by [`GraphSource`] operations.
+pub type SourceFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Where a [`Controller`](crate::pw::controller::Controller) reads the audio graph from and
+/// sends its operations to. [`PwEngine`] talks to PipeWire, while
+/// [`MockEngine`](crate::pw::mock::MockEngine) serves a fixture so menus and controller
+/// logic can be exercised without a daemon.
+pub trait GraphSource: Send + Sync {
+    /// Latest snapshot, shared with other readers rather than copied.
+    fn graph(&self) -> Arc<AudioGraph>;
+    fn subscribe(&self) -> watch::Receiver<Arc<AudioGraph>>;
+
+    /// Semantic changes from now on, such as added nodes or volume changes.
+    fn events(&self) -> GraphEvents {
+        GraphEvents::new(self.subscribe())
+    }
+
+    fn wait_for_initialization(&self) -> SourceFuture<'_, ()>;
+    fn shutdown(&self) -> SourceFuture<'_, ()>;
+
+    fn set_node_volume(&self, node_id: u32, volume: f32) -> SourceFuture<'_, ()>;
+    fn set_node_mute(&self, node_id: u32, mute: bool) -> SourceFuture<'_, ()>;
+    fn set_device_volume(
+        &self,
+        device_id: u32,
+        volume: f32,
+        direction: Option<RouteDirection>,
+        card_device: Option<i32>,
+    ) -> SourceFuture<'_, ()>;
+    fn set_device_mute(
+        &self,
+        device_id: u32,
+        mute: bool,
+        direction: Option<RouteDirection>,
+        card_device: Option<i32>,
+    ) -> SourceFuture<'_, ()>;
+    fn set_default_sink(&self, node_id: u32) -> SourceFuture<'_, ()>;
+    fn set_default_source(&self, node_id: u32) -> SourceFuture<'_, ()>;
+    fn move_streams_to(&self, node_id: u32) -> SourceFuture<'_, usize>;
+    fn set_stream_target(&self, stream_id: u32, target_id: Option<u32>) -> SourceFuture<'_, ()>;
+    fn set_node_latency(&self, node_id: u32, latency: String) -> SourceFuture<'_, ()>;
+    fn batch(&self, steps: Vec<BatchStep>) -> SourceFuture<'_, ()>;
+    fn create_link(
+        &self,
+        output_node: u32,
+        input_node: u32,
+        mapping: PortMapping,
+    ) -> SourceFuture<'_, ()>;
+    fn remove_link(&self, output_node: u32, input_node: u32) -> SourceFuture<'_, ()>;
+    fn switch_device_profile_with_restoration(
+        &self,
+        device_id: u32,
+        profile_index: u32,
+    ) -> SourceFuture<'_, ()>;
+    fn cancel_profile_restoration(&self, device_id: u32) -> SourceFuture<'_, ()>;
+    fn configure_restoration(&self, settings: RestorationSettings) -> SourceFuture<'_, ()>;
+    fn set_preferred_nodes(&self, names: Vec<String>) -> SourceFuture<'_, ()>;
+    /// Curve volumes are read and written on, applied to the graph with the next update.
+    fn set_volume_curve(&self, curve: VolumeCurve) -> Result<()>;
+    fn set_sample_rate(&self, sample_rate: u32) -> SourceFuture<'_, ()>;
+    fn create_virtual_sink<'a>(&'a self, description: &'a str) -> SourceFuture<'a, ()>;
+    fn destroy_managed_node(&self, node_id: u32) -> SourceFuture<'_, ()>;
+    fn rename_managed_node<'a>(
+        &'a self,
+        node_id: u32,
+        description: &'a str,
+    ) -> SourceFuture<'a, ()>;
+    fn destroy_all_managed(&self) -> SourceFuture<'_, usize>;
+    fn play_test_tone(&self, node_id: u32) -> SourceFuture<'_, ()>;
+    fn start_level_meter(&self, node_id: u32) -> SourceFuture<'_, InputLevel>;
+    fn stop_level_meter(&self) -> SourceFuture<'_, ()>;
+    fn sync_stream_meters(&self, node_ids: Vec<u32>) -> SourceFuture<'_, HashMap<u32, InputLevel>>;
+    fn start_profiler(&self) -> SourceFuture<'_, ProfilerStats>;
+    fn stop_profiler(&self) -> SourceFuture<'_, ()>;
+}
+
+impl GraphSource for PwEngine {
+    fn graph(&self) -> Arc<AudioGraph> {
+        PwEngine::graph(self)
+    }
+
+    fn subscribe(&self) -> watch::Receiver<Arc<AudioGraph>> {
+        PwEngine::subscribe(self)
+    }
+
+    fn wait_for_initialization(&self) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::wait_for_initialization(self))
+    }
+
+    fn shutdown(&self) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::shutdown(self))
+    }
+
+    fn set_node_volume(&self, node_id: u32, volume: f32) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::set_node_volume(self, node_id, volume))
+    }
+
+    fn set_node_mute(&self, node_id: u32, mute: bool) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::set_node_mute(self, node_id, mute))
+    }
+
+    fn set_device_volume(
+        &self,
+        device_id: u32,
+        volume: f32,
+        direction: Option<RouteDirection>,
+        card_device: Option<i32>,
+    ) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::set_device_volume(
+            self,
+            device_id,
+            volume,
+            direction,
+            card_device,
+        ))
+    }
+
+    fn set_device_mute(
+        &self,
+        device_id: u32,
+        mute: bool,
+        direction: Option<RouteDirection>,
+        card_device: Option<i32>,
+    ) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::set_device_mute(
+            self,
+            device_id,
+            mute,
+            direction,
+            card_device,
+        ))
+    }
+
+    fn set_default_sink(&self, node_id: u32) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::set_default_sink(self, node_id))
+    }
+
+    fn set_default_source(&self, node_id: u32) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::set_default_source(self, node_id))
+    }
+
+    fn move_streams_to(&self, node_id: u32) -> SourceFuture<'_, usize> {
+        Box::pin(PwEngine::move_streams_to(self, node_id))
+    }
+
+    fn set_stream_target(&self, stream_id: u32, target_id: Option<u32>) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::set_stream_target(self, stream_id, target_id))
+    }
+
+    fn set_node_latency(&self, node_id: u32, latency: String) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::set_node_latency(self, node_id, latency))
+    }
+
+    fn batch(&self, steps: Vec<BatchStep>) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::batch(self, steps))
+    }
+
+    fn create_link(
+        &self,
+        output_node: u32,
+        input_node: u32,
+        mapping: PortMapping,
+    ) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::create_link(
+            self,
+            output_node,
+            input_node,
+            mapping,
+        ))
+    }
+
+    fn remove_link(&self, output_node: u32, input_node: u32) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::remove_link(self, output_node, input_node))
+    }
+
+    fn switch_device_profile_with_restoration(
+        &self,
+        device_id: u32,
+        profile_index: u32,
+    ) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::switch_device_profile_with_restoration(
+            self,
+            device_id,
+            profile_index,
+        ))
+    }
+
+    fn cancel_profile_restoration(&self, device_id: u32) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::cancel_profile_restoration(self, device_id))
+    }
+
+    fn configure_restoration(&self, settings: RestorationSettings) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::configure_restoration(self, settings))
+    }
+
+    fn set_preferred_nodes(&self, names: Vec<String>) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::set_preferred_nodes(self, names))
+    }
+
+    fn set_volume_curve(&self, curve: VolumeCurve) -> Result<()> {
+        PwEngine::set_volume_curve(self, curve)
+    }
+
+    fn set_sample_rate(&self, sample_rate: u32) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::set_sample_rate(self, sample_rate))
+    }
+
+    fn create_virtual_sink<'a>(&'a self, description: &'a str) -> SourceFuture<'a, ()> {
+        Box::pin(PwEngine::create_virtual_sink(self, description))
+    }
+
+    fn destroy_managed_node(&self, node_id: u32) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::destroy_managed_node(self, node_id))
+    }
+
+    fn rename_managed_node<'a>(
+        &'a self,
+        node_id: u32,
+        description: &'a str,
+    ) -> SourceFuture<'a, ()> {
+        Box::pin(PwEngine::rename_managed_node(self, node_id, description))
+    }
+
+    fn destroy_all_managed(&self) -> SourceFuture<'_, usize> {
+        Box::pin(PwEngine::destroy_all_managed(self))
+    }
+
+    fn play_test_tone(&self, node_id: u32) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::play_test_tone(self, node_id))
+    }
+
+    fn start_level_meter(&self, node_id: u32) -> SourceFuture<'_, InputLevel> {
+        Box::pin(PwEngine::start_level_meter(self, node_id))
+    }
+
+    fn stop_level_meter(&self) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::stop_level_meter(self))
+    }
+
+    fn sync_stream_meters(&self, node_ids: Vec<u32>) -> SourceFuture<'_, HashMap<u32, InputLevel>> {
+        Box::pin(PwEngine::sync_stream_meters(self, node_ids))
+    }
+
+    fn start_profiler(&self) -> SourceFuture<'_, ProfilerStats> {
+        Box::pin(PwEngine::start_profiler(self))
+    }
+
+    fn stop_profiler(&self) -> SourceFuture<'_, ()> {
+        Box::pin(PwEngine::stop_profiler(self))
+    }
+}
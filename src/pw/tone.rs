@@ -0,0 +1,209 @@
+use anyhow::{Context, Result};
+use libspa::sys::{
+    SPA_PARAM_EnumFormat, SPA_TYPE_OBJECT_Format, SPA_AUDIO_CHANNEL_FL, SPA_AUDIO_CHANNEL_FR,
+};
+use pipewire::{
+    keys::*,
+    properties::properties,
+    spa::{
+        param::audio::{AudioFormat, AudioInfoRaw, MAX_CHANNELS},
+        pod::{serialize::PodSerializer, Object, Pod, Value},
+        utils::Direction,
+    },
+    stream::{StreamFlags, StreamListener, StreamRc},
+};
+use std::{
+    f64::consts::TAU,
+    io::Cursor,
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+use crate::pw::{
+    error::{ObjectKind, PwError},
+    graph::Store,
+    nodes::NodeType,
+};
+
+const TONE_RATE: u32 = 48000;
+const TONE_CHANNELS: usize = 2;
+const TONE_FREQUENCY: f64 = 440.0;
+const TONE_AMPLITUDE: f64 = 0.25;
+/// Each channel sounds in turn for this long, left then right, like `speaker-test`.
+const TONE_CHANNEL_FRAMES: u64 = TONE_RATE as u64 * 6 / 10;
+/// Fade applied at both ends of each channel's tone to avoid clicks.
+const TONE_FADE_FRAMES: u64 = TONE_RATE as u64 / 100;
+/// Extra time the stream is kept alive so queued buffers reach the device.
+const TONE_TAIL: Duration = Duration::from_millis(300);
+
+struct ToneState {
+    phase: f64,
+    frame: u64,
+}
+
+impl ToneState {
+    /// Next frame of the sweep: the tone on one channel and silence on the others.
+    fn next_frame(&mut self) -> [f32; TONE_CHANNELS] {
+        let mut frame = [0.0; TONE_CHANNELS];
+        let active_channel = (self.frame / TONE_CHANNEL_FRAMES) as usize;
+
+        if let Some(sample) = frame.get_mut(active_channel) {
+            let position = self.frame % TONE_CHANNEL_FRAMES;
+            let fade = position
+                .min(TONE_CHANNEL_FRAMES - position)
+                .min(TONE_FADE_FRAMES) as f64
+                / TONE_FADE_FRAMES as f64;
+
+            *sample = (self.phase.sin() * TONE_AMPLITUDE * fade) as f32;
+            self.phase = (self.phase + TAU * TONE_FREQUENCY / TONE_RATE as f64) % TAU;
+        }
+
+        self.frame += 1;
+        frame
+    }
+}
+
+/// Temporary playback stream sweeping a tone across the channels of one sink.
+pub struct TestTone {
+    // Declared first so the listener is unregistered before the stream is destroyed
+    _listener: StreamListener<ToneState>,
+    _stream: StreamRc,
+    deadline: Instant,
+}
+
+impl TestTone {
+    fn is_finished(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+}
+
+fn tone_format_pod() -> Result<Vec<u8>> {
+    let mut audio_info = AudioInfoRaw::new();
+    audio_info.set_format(AudioFormat::F32LE);
+    audio_info.set_rate(TONE_RATE);
+    audio_info.set_channels(TONE_CHANNELS as u32);
+
+    let mut position = [0; MAX_CHANNELS];
+    position[0] = SPA_AUDIO_CHANNEL_FL;
+    position[1] = SPA_AUDIO_CHANNEL_FR;
+    audio_info.set_position(position);
+
+    let (cursor, _) = PodSerializer::serialize(
+        Cursor::new(Vec::new()),
+        &Value::Object(Object {
+            type_: SPA_TYPE_OBJECT_Format,
+            id: SPA_PARAM_EnumFormat,
+            properties: audio_info.into(),
+        }),
+    )
+    .context("Failed to serialize test tone format")?;
+
+    Ok(cursor.into_inner())
+}
+
+impl Store {
+    /// Plays a short left/right test tone on `node_id`, replacing any tone still playing.
+    pub fn play_test_tone(&mut self, node_id: u32) -> Result<()> {
+        let node_name = {
+            let node = self.nodes.get(&node_id).ok_or(PwError::NotFound {
+                kind: ObjectKind::Node,
+                id: node_id,
+            })?;
+
+            if !matches!(node.node_type, NodeType::AudioSink | NodeType::AudioDuplex) {
+                return Err(PwError::Unsupported(
+                    "Test tones can only be played on output devices".to_string(),
+                )
+                .into());
+            }
+
+            node.name.clone()
+        };
+
+        // Stop the previous tone first so two sweeps never overlap
+        self.test_tone.take();
+
+        let stream = StreamRc::new(
+            (*self.core).clone(),
+            "pwmenu-test-tone",
+            properties! {
+                *MEDIA_TYPE => "Audio",
+                *MEDIA_CATEGORY => "Playback",
+                *MEDIA_NAME => "Test tone",
+                *TARGET_OBJECT => node_name.as_str(),
+                *NODE_DONT_RECONNECT => "true",
+            },
+        )
+        .context("Failed to create test tone stream")?;
+
+        let listener = stream
+            .add_local_listener_with_user_data(ToneState {
+                phase: 0.0,
+                frame: 0,
+            })
+            .process(|stream, state| {
+                let Some(mut buffer) = stream.dequeue_buffer() else {
+                    return;
+                };
+                let data = &mut buffer.datas_mut()[0];
+                let stride = std::mem::size_of::<f32>() * TONE_CHANNELS;
+
+                let frame_count = match data.data() {
+                    Some(slice) => {
+                        let frame_count = slice.len() / stride;
+                        for frame_bytes in slice.chunks_exact_mut(stride) {
+                            let frame = state.next_frame();
+                            for (sample_bytes, sample) in frame_bytes.chunks_exact_mut(4).zip(frame)
+                            {
+                                sample_bytes.copy_from_slice(&sample.to_le_bytes());
+                            }
+                        }
+                        frame_count
+                    }
+                    None => 0,
+                };
+
+                let chunk = data.chunk_mut();
+                *chunk.offset_mut() = 0;
+                *chunk.stride_mut() = stride as _;
+                *chunk.size_mut() = (stride * frame_count) as _;
+            })
+            .register()
+            .context("Failed to register test tone listener")?;
+
+        let format = tone_format_pod()?;
+        let mut params = [Pod::from_bytes(&format).ok_or_else(|| {
+            PwError::PodBuild("Failed to create Pod reference for test tone format".to_string())
+        })?];
+
+        stream
+            .connect(
+                Direction::Output,
+                None,
+                StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS | StreamFlags::RT_PROCESS,
+                &mut params,
+            )
+            .context("Failed to connect test tone stream")?;
+
+        let sweep = Duration::from_secs_f64(
+            (TONE_CHANNEL_FRAMES * TONE_CHANNELS as u64) as f64 / TONE_RATE as f64,
+        );
+
+        self.test_tone = Some(TestTone {
+            _listener: listener,
+            _stream: stream,
+            deadline: Instant::now() + sweep + TONE_TAIL,
+        });
+
+        debug!("Playing test tone on node {node_id}");
+        Ok(())
+    }
+
+    /// Destroys the test tone stream once its sweep has played.
+    pub fn expire_test_tone(&mut self) {
+        if self.test_tone.as_ref().is_some_and(TestTone::is_finished) {
+            self.test_tone = None;
+            debug!("Test tone finished");
+        }
+    }
+}
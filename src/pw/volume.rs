@@ -1,11 +1,114 @@
 use libspa::pod::{Value, ValueArray};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RouteDirection {
     Input,
     Output,
 }
 
+/// Convention used to show and type volumes. PipeWire stores linear amplitudes in
+/// `channelVolumes`; pavucontrol, pactl and wpctl show their cube root, as pwmenu does by
+/// default. The cubic scale shows volumes on the [`VolumeCurve`], which is the cube root
+/// unless another curve is configured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VolumeScale {
+    #[default]
+    Cubic,
+    Linear,
+}
+
+impl VolumeScale {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VolumeScale::Cubic => "cubic",
+            VolumeScale::Linear => "linear",
+        }
+    }
+
+    /// Converts a volume on `curve`, as stored in `Volume::linear`, to this scale.
+    pub fn to_display(self, curve: VolumeCurve, volume: f32) -> f32 {
+        match self {
+            VolumeScale::Cubic => volume,
+            VolumeScale::Linear => VolumeResolver::apply_inverse_curve(curve, volume),
+        }
+    }
+
+    /// Converts a value in this scale back to a volume on `curve`.
+    pub fn from_display(self, curve: VolumeCurve, value: f32) -> f32 {
+        match self {
+            VolumeScale::Cubic => value,
+            VolumeScale::Linear => VolumeResolver::apply_curve(curve, value),
+        }
+    }
+}
+
+/// Curve between the volume pwmenu shows, sets and steps through and the amplitude PipeWire
+/// stores. The cubic one makes steps sound even on most hardware; some cards with their own
+/// mixer curve do better with linear steps or another exponent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(from = "CurveSetting")]
+pub enum VolumeCurve {
+    #[default]
+    Cubic,
+    Linear,
+    Exponent(f32),
+}
+
+/// Curves are set by name or, for a custom one, as a bare exponent.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CurveSetting {
+    Name(CurveName),
+    Exponent(f32),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum CurveName {
+    Cubic,
+    Linear,
+}
+
+impl From<CurveSetting> for VolumeCurve {
+    fn from(setting: CurveSetting) -> Self {
+        match setting {
+            CurveSetting::Name(CurveName::Cubic) => VolumeCurve::Cubic,
+            CurveSetting::Name(CurveName::Linear) => VolumeCurve::Linear,
+            CurveSetting::Exponent(exponent) => VolumeCurve::Exponent(exponent),
+        }
+    }
+}
+
+impl VolumeCurve {
+    pub fn exponent(self) -> f32 {
+        match self {
+            VolumeCurve::Cubic => 3.0,
+            VolumeCurve::Linear => 1.0,
+            VolumeCurve::Exponent(exponent) => exponent,
+        }
+    }
+
+    /// Volume for a raw amplitude; 100% stays at full amplitude whatever the exponent.
+    pub fn to_volume(self, raw_volume: f32) -> f32 {
+        if raw_volume <= 0.0 {
+            0.0
+        } else {
+            raw_volume.powf(1.0 / self.exponent())
+        }
+    }
+
+    /// Raw amplitude for a volume.
+    pub fn to_raw(self, volume: f32) -> f32 {
+        if volume <= 0.0 {
+            0.0
+        } else {
+            volume.powf(self.exponent())
+        }
+    }
+}
+
 pub struct VolumeResolver;
 
 impl VolumeResolver {
@@ -26,19 +129,21 @@ impl VolumeResolver {
     }
 
     pub fn apply_cubic_scaling(raw_volume: f32) -> f32 {
-        if raw_volume <= 0.0 {
-            0.0
-        } else {
-            raw_volume.powf(1.0 / 3.0)
-        }
+        VolumeCurve::Cubic.to_volume(raw_volume)
     }
 
     pub fn apply_inverse_cubic_scaling(volume: f32) -> f32 {
-        if volume <= 0.0 {
-            0.0
-        } else {
-            volume.powf(3.0)
-        }
+        VolumeCurve::Cubic.to_raw(volume)
+    }
+
+    /// Volume on `curve` for a raw amplitude read from PipeWire.
+    pub fn apply_curve(curve: VolumeCurve, raw_volume: f32) -> f32 {
+        curve.to_volume(raw_volume)
+    }
+
+    /// Raw amplitude to write to PipeWire for a volume on `curve`.
+    pub fn apply_inverse_curve(curve: VolumeCurve, volume: f32) -> f32 {
+        curve.to_raw(volume)
     }
 
     pub fn extract_channel_volume(value: &Value) -> Option<f32> {
@@ -55,3 +160,27 @@ impl VolumeResolver {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_curves_meet_at_full_volume() {
+        for curve in [
+            VolumeCurve::Cubic,
+            VolumeCurve::Linear,
+            VolumeCurve::Exponent(2.0),
+        ] {
+            assert_eq!(curve.to_raw(1.0), 1.0);
+            assert_eq!(curve.to_volume(0.0), 0.0);
+        }
+        assert_eq!(VolumeCurve::Cubic.to_raw(0.5), 0.125);
+        assert_eq!(VolumeCurve::Linear.to_raw(0.5), 0.5);
+        assert_eq!(VolumeCurve::Exponent(2.0).to_volume(0.25), 0.5);
+
+        let curves: Vec<VolumeCurve> = serde_json::from_str(r#"["linear", 2.5]"#).unwrap();
+        assert_eq!(curves, [VolumeCurve::Linear, VolumeCurve::Exponent(2.5)]);
+        assert!(serde_json::from_str::<VolumeCurve>(r#""steep""#).is_err());
+    }
+}
@@ -0,0 +1,23 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Devices are referred to by node or card name, description, or the name shown in menus.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Scene {
+    pub default_sink: Option<String>,
+    pub default_source: Option<String>,
+    /// Volume percentages keyed by output or input device.
+    pub volumes: BTreeMap<String, u8>,
+    /// Profile name or description keyed by card.
+    pub profiles: BTreeMap<String, String>,
+}
+
+impl Scene {
+    pub fn is_empty(&self) -> bool {
+        self.default_sink.is_none()
+            && self.default_source.is_none()
+            && self.volumes.is_empty()
+            && self.profiles.is_empty()
+    }
+}
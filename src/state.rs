@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, warn};
+
+use crate::pw::{
+    nodes::{NodeType, Volume},
+    restoration::DefaultRestoration,
+};
+
+const MAX_RECENT_NODES: usize = 16;
+
+/// Data pwmenu remembers between runs, kept apart from the user-edited config.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct State {
+    /// Node names, most recently selected first.
+    #[serde(default)]
+    pub recent_nodes: Vec<String>,
+    /// Defaults waiting for a profile switch to complete, kept when `persist-restorations`
+    /// is set so a restart does not lose them.
+    #[serde(default)]
+    pub pending_restorations: Vec<DefaultRestoration>,
+    /// Device volumes keyed by node name, saved when `save-volumes` is set. They keep their raw
+    /// amplitude, so a change of `volume-curve` brings back the same level.
+    #[serde(default)]
+    pub volumes: BTreeMap<String, Volume>,
+    /// Raw amplitudes devices had before `mute-at-zero` muted them, keyed by node name.
+    #[serde(default)]
+    pub muted_volumes: BTreeMap<String, f32>,
+    /// Applications whose streams are pinned to a device.
+    #[serde(default)]
+    pub stream_locks: Vec<StreamLock>,
+}
+
+/// Streams of `application` in one direction, pinned to the node named `target`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamLock {
+    pub application: String,
+    pub stream_type: NodeType,
+    pub target: String,
+}
+
+impl State {
+    pub fn path() -> Option<PathBuf> {
+        let state_dir = env::var_os("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| {
+                env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+            })?;
+
+        Some(state_dir.join("pwmenu").join("state.json"))
+    }
+
+    pub fn load() -> Self {
+        Self::load_from(Self::path().as_deref())
+    }
+
+    /// Loads the state kept at `path`, or an empty one without a path.
+    pub fn load_from(path: Option<&Path>) -> Self {
+        let Some(path) = path.filter(|p| p.exists()) else {
+            return Self::default();
+        };
+
+        let result = fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| serde_json::from_str(&content).map_err(Into::into));
+
+        match result {
+            Ok(state) => state,
+            Err(e) => {
+                warn!("Ignoring unreadable state file {}: {e}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(Self::path().as_deref())
+    }
+
+    /// Writes the state to `path`, nothing is kept without a path.
+    pub fn save_to(&self, path: Option<&Path>) -> Result<()> {
+        let Some(path) = path else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create state directory {}", dir.display()))?;
+        }
+
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content)
+            .with_context(|| format!("Failed to write state file {}", path.display()))?;
+
+        debug!("Saved state to {}", path.display());
+        Ok(())
+    }
+
+    /// Applies `f` to the state on disk and saves it, so writers of different fields do not
+    /// overwrite each other's changes.
+    pub fn update(f: impl FnOnce(&mut Self)) -> Result<Self> {
+        Self::update_at(Self::path().as_deref(), f)
+    }
+
+    /// [`State::update`] for the state kept at `path`.
+    pub fn update_at(path: Option<&Path>, f: impl FnOnce(&mut Self)) -> Result<Self> {
+        let mut state = Self::load_from(path);
+        f(&mut state);
+        state.save_to(path)?;
+        Ok(state)
+    }
+
+    pub fn record_node(&mut self, node_name: &str) {
+        self.recent_nodes.retain(|name| name != node_name);
+        self.recent_nodes.insert(0, node_name.to_string());
+        self.recent_nodes.truncate(MAX_RECENT_NODES);
+    }
+
+    pub fn recent_rank(&self, node_name: &str) -> Option<usize> {
+        self.recent_nodes.iter().position(|name| name == node_name)
+    }
+
+    pub fn stream_lock(&self, application: &str, stream_type: NodeType) -> Option<&StreamLock> {
+        self.stream_locks
+            .iter()
+            .find(|lock| lock.application == application && lock.stream_type == stream_type)
+    }
+
+    /// Adds `lock`, replacing the previous one of the same application and direction.
+    pub fn lock_stream(&mut self, lock: StreamLock) {
+        self.unlock_stream(&lock.application, lock.stream_type);
+        self.stream_locks.push(lock);
+    }
+
+    pub fn unlock_stream(&mut self, application: &str, stream_type: NodeType) {
+        self.stream_locks
+            .retain(|lock| lock.application != application || lock.stream_type != stream_type);
+    }
+}
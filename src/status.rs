@@ -0,0 +1,179 @@
+use crate::pw::{
+    controller::Controller,
+    meter::InputLevel,
+    nodes::{Node, NodeType},
+    VolumeScale,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Default devices and playback streams as printed by `pwmenu status`, one JSON object per line
+/// so bar widgets can read it continuously.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Status {
+    /// `connected`, `disconnected`, `error` or `reconnecting`; devices are empty unless connected.
+    pub connection: &'static str,
+    pub sink: Option<DeviceStatus>,
+    pub source: Option<DeviceStatus>,
+    pub streams: Vec<StreamStatus>,
+    /// Applications recording from a microphone, only present with `--mic`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mic: Option<Vec<MicUser>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceStatus {
+    pub id: u32,
+    pub name: String,
+    pub description: String,
+    pub volume: u16,
+    pub muted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StreamStatus {
+    pub id: u32,
+    pub application: String,
+    pub media: Option<String>,
+    pub volume: u16,
+    pub muted: bool,
+    /// Peak level from 0 to 100, only present when meters are enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_db: Option<f32>,
+}
+
+/// A device or stream as printed by `pwmenu list`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ListEntry {
+    pub id: u32,
+    pub name: String,
+    /// Name shown in menus, the application name for streams.
+    pub description: String,
+    pub volume: u16,
+    pub muted: bool,
+    pub default: bool,
+}
+
+impl ListEntry {
+    pub fn new(controller: &Controller, node: &Node, scale: VolumeScale) -> Self {
+        let description = match node.node_type {
+            NodeType::StreamOutputAudio | NodeType::StreamInputAudio => {
+                controller.get_application_name(node)
+            }
+            _ => controller.get_node_base_name(node),
+        };
+
+        Self {
+            id: node.id,
+            name: node.name.clone(),
+            description,
+            volume: node.volume.percent_in(scale),
+            muted: node.volume.muted,
+            default: node.is_default,
+        }
+    }
+
+    /// Fields in declaration order, tab separated, with tabs in names replaced by spaces.
+    pub fn to_tsv(&self) -> String {
+        [
+            self.id.to_string(),
+            self.name.replace('\t', " "),
+            self.description.replace('\t', " "),
+            self.volume.to_string(),
+            self.muted.to_string(),
+            self.default.to_string(),
+        ]
+        .join("\t")
+    }
+}
+
+/// An application with an active input stream linked to a source.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MicUser {
+    pub id: u32,
+    pub application: String,
+    pub media: Option<String>,
+    pub source: String,
+}
+
+impl Status {
+    /// Reads the current state, filling stream levels from `levels` when a meter runs on them.
+    pub fn capture(
+        controller: &Controller,
+        scale: VolumeScale,
+        levels: &HashMap<u32, InputLevel>,
+    ) -> Self {
+        let device = |id: Option<u32>| {
+            let node = id.and_then(|id| controller.get_node(id))?;
+            Some(DeviceStatus {
+                id: node.id,
+                description: controller.get_node_base_name(&node),
+                volume: node.volume.percent_in(scale),
+                muted: node.volume.muted,
+                name: node.name,
+            })
+        };
+
+        let mut streams: Vec<StreamStatus> = playback_streams(controller)
+            .into_iter()
+            .map(|node| {
+                let level = levels.get(&node.id);
+                StreamStatus {
+                    id: node.id,
+                    application: controller.get_application_name(&node),
+                    media: controller.get_media_name(&node),
+                    volume: node.volume.percent_in(scale),
+                    muted: node.volume.muted,
+                    level: level.map(InputLevel::percent),
+                    peak_db: level.map(|level| (level.peak_db() * 10.0).round() / 10.0),
+                }
+            })
+            .collect();
+        streams.sort_by_key(|stream| stream.id);
+
+        Self {
+            connection: controller.connection_status().as_str(),
+            sink: device(controller.get_default_sink()),
+            source: device(controller.get_default_source()),
+            streams,
+            mic: None,
+        }
+    }
+
+    pub fn with_mic(mut self, controller: &Controller) -> Self {
+        self.mic = Some(mic_users(controller));
+        self
+    }
+}
+
+pub fn mic_users(controller: &Controller) -> Vec<MicUser> {
+    controller
+        .get_active_capture_streams()
+        .into_iter()
+        .map(|(stream, source)| MicUser {
+            id: stream.id,
+            application: controller.get_application_name(&stream),
+            media: controller.get_media_name(&stream),
+            source: controller.get_node_base_name(&source),
+        })
+        .collect()
+}
+
+/// Ids of the playback streams shown in the status, to attach meters to.
+pub fn playback_stream_ids(controller: &Controller) -> Vec<u32> {
+    playback_streams(controller)
+        .iter()
+        .map(|node| node.id)
+        .collect()
+}
+
+// Leaves out pwmenu's own test tones and meters
+fn playback_streams(controller: &Controller) -> Vec<Node> {
+    controller
+        .get_output_streams()
+        .into_iter()
+        .filter(|node| node.application_name.as_deref() != Some("pwmenu"))
+        .collect()
+}
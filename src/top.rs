@@ -0,0 +1,100 @@
+use crate::pw::{
+    controller::Controller,
+    profiler::{NodeTiming, ProfilerSample},
+};
+use std::fmt::Write;
+
+/// Renders a profiler sample as a `pw-top` like table, drivers followed by their followers.
+pub fn render(sample: &ProfilerSample, controller: &Controller) -> String {
+    let [fast, medium, slow] = sample.cpu_load;
+    let mut output = format!(
+        "DSP load {:.1}% {:.1}% {:.1}%, {} xruns\n\n",
+        fast * 100.0,
+        medium * 100.0,
+        slow * 100.0,
+        sample.xruns
+    );
+    let _ = writeln!(
+        output,
+        "{:>6} {:>6} {:>6} {:>8} {:>8} {:>5} {:>5} {:>4}  NAME",
+        "ID", "QUANT", "RATE", "WAIT", "BUSY", "W/Q", "B/Q", "ERR"
+    );
+
+    if sample.drivers.is_empty() {
+        output.push_str("  (no driver is running)\n");
+        return output;
+    }
+
+    for driver in &sample.drivers {
+        let period = driver.period_ns();
+        let name = display_name(&driver.timing, controller);
+        push_row(
+            &mut output,
+            &driver.timing,
+            driver.quantum,
+            driver.rate,
+            period,
+            &name,
+        );
+
+        for follower in &driver.followers {
+            let name = format!(" + {}", display_name(follower, controller));
+            push_row(
+                &mut output,
+                follower,
+                driver.quantum,
+                driver.rate,
+                period,
+                &name,
+            );
+        }
+    }
+
+    output
+}
+
+fn push_row(
+    output: &mut String,
+    timing: &NodeTiming,
+    quantum: u64,
+    rate: u32,
+    period_ns: u64,
+    name: &str,
+) {
+    let ratio = |ns: u64| {
+        if period_ns == 0 {
+            0.0
+        } else {
+            ns as f64 / period_ns as f64
+        }
+    };
+
+    let _ = writeln!(
+        output,
+        "{:>6} {:>6} {:>6} {:>8} {:>8} {:>5.2} {:>5.2} {:>4}  {}",
+        timing.id,
+        quantum,
+        rate,
+        format_duration(timing.wait_ns),
+        format_duration(timing.busy_ns),
+        ratio(timing.wait_ns),
+        ratio(timing.busy_ns),
+        timing.xruns,
+        name
+    );
+}
+
+fn display_name(timing: &NodeTiming, controller: &Controller) -> String {
+    controller
+        .get_node(timing.id)
+        .map(|node| controller.get_node_base_name(&node))
+        .unwrap_or_else(|| timing.name.clone())
+}
+
+fn format_duration(ns: u64) -> String {
+    if ns >= 1_000_000 {
+        format!("{:.1}ms", ns as f64 / 1_000_000.0)
+    } else {
+        format!("{:.1}us", ns as f64 / 1_000.0)
+    }
+}